@@ -26,6 +26,7 @@ use manticore::protocol::capabilities;
 use manticore::protocol::device_id::DeviceIdentifier;
 use manticore::server;
 use manticore::server::pa_rot::PaRot;
+use manticore::server::policy::Policy;
 
 use crate::tcp;
 use crate::tcp::TcpHostPort;
@@ -262,6 +263,7 @@ pub fn serve(opts: Options) -> ! {
 
     let sha = ring::sha256::Builder::new();
     let mut ciphers = ring::sig::Ciphers::new();
+    let mut aead = ring::aead::Ciphers::new();
     let trust_chain_bytes =
         opts.cert_chain.iter().map(Vec::as_ref).collect::<Vec<_>>();
     let mut signer = opts.alias_keypair.as_ref().map(|kp| match kp {
@@ -279,7 +281,10 @@ pub fn serve(opts: Options) -> ! {
         &trust_chain_bytes,
         opts.cert_format,
         &mut ciphers,
+        &sha,
         signer.as_mut().map(|s| s as _),
+        None,
+        &[],
     )
     .unwrap();
 
@@ -288,11 +293,15 @@ pub fn serve(opts: Options) -> ! {
         reset: &reset,
         sha: &sha,
         ciphers: &mut ciphers,
+        aead: Some(&mut aead),
         trust_chain: &mut trust_chain,
         pmr0: &opts.pmr0,
         device_id: opts.device_id,
         networking,
         timeouts,
+        pending: None,
+        policy: Policy::OPEN,
+        provisioning: None,
     });
 
     let mut host = match TcpHostPort::bind() {
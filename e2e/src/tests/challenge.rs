@@ -100,7 +100,10 @@ fn challenge() {
         &certs,
         CertFormat::RiotX509,
         &mut ciphers,
+        &sha,
         None,
+        None,
+        &[],
     )
     .unwrap();
 
@@ -0,0 +1,194 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Verifier-side caching of trust chains.
+//!
+//! Cerberus allows a verifier to skip `GetCert` entirely when the digests
+//! reported by a device's `GetDigests` response match those of a chain it has
+//! already verified. [`ChainCache`] keeps track of such chains.
+
+use core::fmt;
+
+use arrayvec::ArrayVec;
+
+use crate::cert::SimpleChain;
+use crate::crypto::sha256;
+
+/// A cache of up to `N` previously verified trust chains, each with up to
+/// `LEN` certificates, keyed by the digests of their certificates.
+///
+/// A verifier attesting a device first requests its digests with
+/// `GetDigests`, and looks them up with [`ChainCache::get()`]. On a miss, it
+/// retrieves the chain with `GetCert`, verifies it with
+/// [`SimpleChain::parse()`], checks that its root is trusted, and stores it
+/// with [`ChainCache::insert()`]. On a hit, the cached chain can be used to
+/// check the device's `Challenge` response directly.
+///
+/// Chains are not re-verified once cached; in particular, a cached chain may
+/// contain certificates that have since expired or been revoked. Verifiers
+/// that check for either should [`clear()`](ChainCache::clear) the cache
+/// whenever their clock or CRLs change.
+pub struct ChainCache<'cert, const N: usize, const LEN: usize> {
+    // Ordered from least to most recently inserted.
+    chains: ArrayVec<SimpleChain<'cert, LEN>, N>,
+}
+
+impl<const N: usize, const LEN: usize> fmt::Debug for ChainCache<'_, N, LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.chains.fmt(f)
+    }
+}
+
+impl<const N: usize, const LEN: usize> Default for ChainCache<'_, N, LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'cert, const N: usize, const LEN: usize> ChainCache<'cert, N, LEN> {
+    /// Creates a new, empty `ChainCache`.
+    pub fn new() -> Self {
+        Self {
+            chains: ArrayVec::new(),
+        }
+    }
+
+    /// Looks up the chain whose certificates have exactly the given
+    /// `digests`, in chain order.
+    pub fn get(
+        &self,
+        digests: &[sha256::Digest],
+    ) -> Option<&SimpleChain<'cert, LEN>> {
+        self.chains.iter().find(|c| c.digests() == digests)
+    }
+
+    /// Adds an already-verified `chain` to the cache, returning a reference
+    /// to it.
+    ///
+    /// Any cached chain with the same digests is replaced. If the cache is
+    /// full, the least recently inserted chain is evicted to make room. If
+    /// `N` is zero, `chain` is dropped and `None` is returned.
+    pub fn insert(
+        &mut self,
+        chain: SimpleChain<'cert, LEN>,
+    ) -> Option<&SimpleChain<'cert, LEN>> {
+        if N == 0 {
+            return None;
+        }
+        self.remove(chain.digests());
+        if self.chains.is_full() {
+            self.chains.remove(0);
+        }
+        self.chains.push(chain);
+        self.chains.last()
+    }
+
+    /// Removes the chain with the given `digests` from the cache, if
+    /// present.
+    pub fn remove(
+        &mut self,
+        digests: &[sha256::Digest],
+    ) -> Option<SimpleChain<'cert, LEN>> {
+        let idx = self.chains.iter().position(|c| c.digests() == digests)?;
+        Some(self.chains.remove(idx))
+    }
+
+    /// Removes every chain from the cache.
+    pub fn clear(&mut self) {
+        self.chains.clear();
+    }
+
+    /// Returns the number of chains in the cache.
+    pub fn len(&self) -> usize {
+        self.chains.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+}
+
+#[cfg(all(test, not(miri)))] // TODO(#103)
+mod test {
+    use super::*;
+    use testutil::data::x509;
+
+    use crate::cert::CertFormat;
+    use crate::cert::TrustChain as _;
+    use crate::crypto::ring;
+
+    fn parse<'cert>(raw_chain: &[&'cert [u8]]) -> SimpleChain<'cert, 3> {
+        SimpleChain::parse(
+            raw_chain,
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            None,
+            &[],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn lookup() {
+        let short = parse(&[x509::CHAIN1, x509::CHAIN2]);
+        let long = parse(&[x509::CHAIN1, x509::CHAIN2, x509::CHAIN3]);
+        let short_digests = short.digests().to_vec();
+        let long_digests = long.digests().to_vec();
+
+        let mut cache = ChainCache::<2, 3>::new();
+        assert!(cache.get(&long_digests).is_none());
+        cache.insert(short);
+        cache.insert(long);
+        assert_eq!(cache.len(), 2);
+
+        let hit = cache.get(&long_digests).unwrap();
+        assert_eq!(hit.cert(0, 2).unwrap().raw(), x509::CHAIN3);
+        let hit = cache.get(&short_digests).unwrap();
+        assert_eq!(hit.chain_len(0).unwrap().get(), 2);
+
+        // A prefix of a cached chain is not a hit.
+        assert!(cache.get(&long_digests[..1]).is_none());
+
+        // Reinserting a chain does not duplicate it.
+        cache.insert(parse(&[x509::CHAIN1, x509::CHAIN2]));
+        assert_eq!(cache.len(), 2);
+
+        assert!(cache.remove(&short_digests).is_some());
+        assert!(cache.get(&short_digests).is_none());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let mut cache = ChainCache::<1, 3>::new();
+        let first = cache
+            .insert(parse(&[x509::CHAIN1, x509::CHAIN2]))
+            .unwrap()
+            .digests()
+            .to_vec();
+        let second = cache
+            .insert(parse(&[x509::CHAIN1, x509::CHAIN2, x509::CHAIN3]))
+            .unwrap()
+            .digests()
+            .to_vec();
+
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&first).is_none());
+        assert!(cache.get(&second).is_some());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut cache = ChainCache::<0, 3>::new();
+        let chain = parse(&[x509::CHAIN1, x509::CHAIN2]);
+        let digests = chain.digests().to_vec();
+        assert!(cache.insert(chain).is_none());
+        assert!(cache.is_empty());
+        assert!(cache.get(&digests).is_none());
+    }
+}
@@ -6,13 +6,18 @@
 
 use core::fmt;
 use core::num::NonZeroUsize;
+use core::time::Duration;
 
 use arrayvec::ArrayVec;
 
 use crate::cert::Cert;
 use crate::cert::CertFormat;
+use crate::cert::Crl;
 use crate::cert::Error;
+use crate::crypto::sha256;
 use crate::crypto::sig;
+use crate::hardware::WallClock;
+use crate::mem::Arena;
 
 /// A trust chain collection.
 ///
@@ -35,6 +40,44 @@ pub trait TrustChain {
     /// chain. These cases can be distinguished by calling `chain_len()`.
     fn cert(&self, slot: u8, index: usize) -> Option<&Cert>;
 
+    /// Reads up to `len` bytes of the `index`th cert of the `slot`th chain,
+    /// starting at `offset`, as requested by `GetCert`.
+    ///
+    /// Fewer than `len` bytes are returned if the end of the certificate is
+    /// reached. `arena` may be used to hold the bytes, if the certificate is
+    /// not resident in memory.
+    ///
+    /// Returns `None` if there is no such certificate, as in `cert()`.
+    fn read_cert<'a>(
+        &'a self,
+        slot: u8,
+        index: usize,
+        offset: usize,
+        len: usize,
+        arena: &'a dyn Arena,
+    ) -> Result<Option<&'a [u8]>, Error> {
+        let _ = arena;
+        let raw = match self.cert(slot, index) {
+            Some(cert) => cert.raw(),
+            None => return Ok(None),
+        };
+        let start = raw.len().min(offset);
+        let end = raw.len().min(len.saturating_add(start));
+        Ok(Some(&raw[start..end]))
+    }
+
+    /// Gets the SHA-256 digest of the `index`th cert of the `slot`th chain,
+    /// as reported by `GetDigests`, if it has already been computed.
+    ///
+    /// Implementations should compute digests once, when the chain is
+    /// loaded, rather than on every request. Returns `None` if the digest has
+    /// not been computed, in which case the caller should hash the result of
+    /// `cert()` instead.
+    fn cert_digest(&self, slot: u8, index: usize) -> Option<&sha256::Digest> {
+        let _ = (slot, index);
+        None
+    }
+
     /// Gets the signer for the `slot`th chain.
     ///
     /// This value allows the creation of signatures that assert that we have
@@ -42,11 +85,22 @@ pub trait TrustChain {
     ///
     /// Returns `None` if no such chain is present.
     fn signer(&mut self, slot: u8) -> Option<&mut dyn sig::Sign>;
+
+    /// Returns a bitmask of the populated slots, suitable for the `slot_mask`
+    /// field of a `Challenge` response.
+    ///
+    /// Bit `n` is set if `chain_len(n)` is not `None`.
+    fn slot_mask(&self) -> u8 {
+        (0..8)
+            .filter(|&slot| self.chain_len(slot).is_some())
+            .fold(0, |mask, slot| mask | 1 << slot)
+    }
 }
 
 /// A simple trust chain with only one slot.
 pub struct SimpleChain<'cert, const LEN: usize> {
     chain: ArrayVec<Cert<'cert>, LEN>,
+    digests: ArrayVec<sha256::Digest, LEN>,
     signer: Option<&'cert mut dyn sig::Sign>,
 }
 
@@ -59,49 +113,156 @@ impl<const LEN: usize> fmt::Debug for SimpleChain<'_, LEN> {
 impl<'cert, const LEN: usize> SimpleChain<'cert, LEN> {
     /// Parses and verifies the trust chain described by `raw_chain`,
     /// which starts at the root certificate for the trust chain.
+    ///
+    /// If `clock` is provided, every certificate in the chain must also be
+    /// valid at the current time.
+    ///
+    /// Every certificate is also checked against `crls`: if a CRL was issued
+    /// by a certificate in the chain, the certificate that follows it must
+    /// not be revoked by it, or [`Error::Revoked`] is returned. If `clock`
+    /// is provided, such a CRL must also not be stale, or [`Error::Expired`]
+    /// is returned; see [`Crl::is_stale()`].
+    ///
+    /// A CRL is issued by a certificate if its issuer is that certificate's
+    /// subject and, where both carry key identifiers, its authority key
+    /// identifier matches the certificate's subject key identifier; it must
+    /// then be signed by that certificate. Without key identifiers, the name
+    /// alone is ambiguous, so a CRL whose signature does not verify against
+    /// the certificate is assumed to have been issued by someone else. CRLs
+    /// issued by anyone else are ignored.
+    ///
+    /// Where both adjacent certificates carry key identifiers, the issuer's
+    /// subject key identifier must match the authority key identifier of
+    /// the certificate it signed. The leaf certificate must also be usable
+    /// for attestation; see [`Cert::supports_attestation()`].
+    ///
+    /// Each certificate is also hashed with `sha`, so that its digest need
+    /// not be recomputed for every `GetDigests` request; see
+    /// [`SimpleChain::digests()`].
     pub fn parse(
         raw_chain: &[&'cert [u8]],
         format: CertFormat,
         ciphers: &mut impl sig::Ciphers,
+        sha: &impl sha256::Builder,
         signer: Option<&'cert mut dyn sig::Sign>,
+        clock: Option<&dyn WallClock>,
+        crls: &[Crl],
     ) -> Result<Self, Error> {
         if raw_chain.len() > LEN {
             return Err(Error::ChainTooLong);
         }
+        let now = clock.map(WallClock::unix_time);
 
         let mut chain = ArrayVec::new();
+        let mut digests = ArrayVec::new();
         for (i, &raw_cert) in raw_chain.iter().enumerate() {
             let prev = chain.last();
             let key = prev.map(|cert: &Cert| cert.subject_key());
             let cert = Cert::parse(raw_cert, format, key, ciphers)?;
+            check_link(prev, &cert, i, raw_chain.len(), now, crls, ciphers)?;
+
+            let mut digest = sha256::Digest::default();
+            sha.hash_contiguous(raw_cert, &mut digest)?;
+            chain.push(cert);
+            digests.push(digest);
+        }
+
+        Ok(Self {
+            chain,
+            digests,
+            signer,
+        })
+    }
+
+    /// Returns the SHA-256 digests of the certificates in this chain, in
+    /// chain order.
+    ///
+    /// These are the digests a device reports in response to `GetDigests`.
+    pub fn digests(&self) -> &[sha256::Digest] {
+        &self.digests
+    }
+}
+
+/// Checks that `cert`, the `i`th of `len` certificates in a chain, may be
+/// signed by `prev`; for the root, `prev` is `None`.
+///
+/// `cert` must already have been verified against `prev`'s key. See
+/// [`SimpleChain::parse()`] for the checks performed.
+pub(crate) fn check_link(
+    prev: Option<&Cert>,
+    cert: &Cert,
+    i: usize,
+    len: usize,
+    now: Option<Duration>,
+    crls: &[Crl],
+    ciphers: &mut impl sig::Ciphers,
+) -> Result<(), Error> {
+    if let Some(now) = now {
+        cert.check_validity(now)?;
+    }
+
+    let prev = prev.unwrap_or(cert);
+    if prev.subject() != cert.issuer() {
+        return Err(Error::BadChainLink);
+    }
+    if !prev.supports_cert_signing() {
+        return Err(Error::BadChainLink);
+    }
 
-            let prev = prev.unwrap_or(&cert);
-            if prev.subject() != cert.issuer() {
-                return Err(Error::BadChainLink);
+    // Names alone may be ambiguous, e.g. when an intermediate is reissued
+    // with a new key, so key identifiers must also match when both
+    // certificates specify them.
+    if let (Some(aki), Some(ski)) =
+        (cert.authority_key_id(), prev.subject_key_id())
+    {
+        if aki != ski {
+            return Err(Error::BadChainLink);
+        }
+    }
+
+    // None is also ok; it means the format (e.g. CWT) does not support a CA
+    // bit.
+    if prev.is_ca_cert() == Some(false) {
+        return Err(Error::BadChainLink);
+    }
+
+    // len - i is the number of certificates that follow `cert`; the path
+    // length constraint for `prev` is the number of certs that follow it,
+    // except the leaf; these numbers are the same.
+    if !prev.is_within_path_len_constraint(len - i) {
+        return Err(Error::BadChainLink);
+    }
+
+    // A self-signed root can only be removed as a trust anchor, not revoked.
+    if i > 0 {
+        for crl in crls {
+            if crl.issuer() != prev.subject() || !prev.supports_crl_signing() {
+                continue;
             }
-            if !prev.supports_cert_signing() {
-                return Err(Error::BadChainLink);
+            match (crl.authority_key_id(), prev.subject_key_id()) {
+                (Some(aki), Some(ski)) if aki != ski => continue,
+                (Some(_), Some(_)) => crl.verify(prev, ciphers)?,
+                _ => match crl.verify(prev, ciphers) {
+                    Err(Error::BadSignature) => continue,
+                    result => result?,
+                },
             }
 
-            // None is also ok; it means the format (e.g. CWT) does not support
-            // a CA bit.
-            if prev.is_ca_cert() == Some(false) {
-                return Err(Error::BadChainLink);
+            if now.map(|now| crl.is_stale(now)).unwrap_or(false) {
+                return Err(Error::Expired);
             }
-
-            // raw_chain.len() - i is the number of certificates that follow
-            // `cert`; the path length constraint for `prev` is the number of
-            // certs that follow it, except the leaf; these numbers are the
-            // same.
-            if !prev.is_within_path_len_constraint(raw_chain.len() - i) {
-                return Err(Error::BadChainLink);
+            if crl.revokes(cert)? {
+                return Err(Error::Revoked);
             }
-
-            chain.push(cert);
         }
+    }
 
-        Ok(Self { chain, signer })
+    // The leaf is what challenge responses are signed with.
+    if i == len - 1 && !cert.supports_attestation() {
+        return Err(Error::WrongKeyUsage);
     }
+
+    Ok(())
 }
 
 impl<const LEN: usize> TrustChain for SimpleChain<'_, LEN> {
@@ -119,6 +280,13 @@ impl<const LEN: usize> TrustChain for SimpleChain<'_, LEN> {
         NonZeroUsize::new(self.chain.len())
     }
 
+    fn cert_digest(&self, slot: u8, index: usize) -> Option<&sha256::Digest> {
+        if slot != 0 {
+            return None;
+        }
+        self.digests.get(index)
+    }
+
     fn signer(&mut self, slot: u8) -> Option<&mut dyn sig::Sign> {
         match (&mut self.signer, slot) {
             (Some(signer), 0) => Some(*signer),
@@ -130,12 +298,16 @@ impl<const LEN: usize> TrustChain for SimpleChain<'_, LEN> {
 #[cfg(all(test, not(miri)))] // TODO(#103)
 mod test {
     use super::*;
+    use core::time::Duration;
     use testutil::data::keys;
     use testutil::data::x509;
 
     use crate::cert::cwt::test::TestCwt;
     use crate::crypto::ring;
+    use crate::crypto::sha256::Builder as _;
+    use crate::crypto::sig::Algo;
     use crate::crypto::sig::PublicKeyParams;
+    use crate::hardware::fake;
 
     #[test]
     fn x509_chain_parse() {
@@ -143,7 +315,10 @@ mod test {
             &[x509::CHAIN1, x509::CHAIN2, x509::CHAIN3],
             CertFormat::RiotX509,
             &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
             None,
+            None,
+            &[],
         )
         .unwrap();
 
@@ -153,6 +328,20 @@ mod test {
         assert!(chain.cert(0, 3).is_none());
         assert!(chain.cert(1, 0).is_none());
         assert!(chain.chain_len(2).is_none());
+        assert_eq!(chain.slot_mask(), 0b1);
+
+        let sha = ring::sha256::Builder::new();
+        for (i, raw_cert) in [x509::CHAIN1, x509::CHAIN2, x509::CHAIN3]
+            .iter()
+            .enumerate()
+        {
+            let mut digest = sha256::Digest::default();
+            sha.hash_contiguous(raw_cert, &mut digest).unwrap();
+            assert_eq!(chain.digests()[i], digest);
+            assert_eq!(chain.cert_digest(0, i), Some(&digest));
+        }
+        assert!(chain.cert_digest(0, 3).is_none());
+        assert!(chain.cert_digest(1, 0).is_none());
     }
 
     #[test]
@@ -161,7 +350,10 @@ mod test {
             &[x509::CHAIN1, x509::CHAIN3, x509::CHAIN2],
             CertFormat::RiotX509,
             &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
             None,
+            &[],
         );
         assert!(result.is_err());
 
@@ -169,11 +361,38 @@ mod test {
             &[x509::CHAIN2, x509::CHAIN1, x509::CHAIN3],
             CertFormat::RiotX509,
             &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
             None,
+            None,
+            &[],
         );
         assert!(result.is_err());
     }
 
+    #[test]
+    fn x509_chain_validity() {
+        let parse = |clock: &fake::Clock| {
+            SimpleChain::<1>::parse(
+                &[x509::BOUNDED_VALIDITY],
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                Some(clock as &dyn WallClock),
+                &[],
+            )
+        };
+
+        // The cert is valid from 2021-01-01 through 2030-01-01.
+        let clock = fake::Clock::new();
+        clock.advance(Duration::from_secs(1577836800)); // 2020-01-01.
+        assert!(matches!(parse(&clock), Err(Error::NotYetValid)));
+        clock.advance(Duration::from_secs(5 * 365 * 86400)); // ~2025.
+        assert!(parse(&clock).is_ok());
+        clock.advance(Duration::from_secs(10 * 365 * 86400)); // ~2035.
+        assert!(matches!(parse(&clock), Err(Error::Expired)));
+    }
+
     const CWT_TEST_CHAIN: &[TestCwt] = &[
         TestCwt {
             issuer: "Silicon Owner LLC",
@@ -184,6 +403,8 @@ mod test {
             },
             key_usage: &[0b0010_0000],
             issuer_key: keys::KEY1_RSA_KEYPAIR,
+            issuer_algo: Algo::RsaPkcs1Sha256,
+            dice_claims: None,
         },
         TestCwt {
             issuer: "Silicon Owner LLC",
@@ -194,6 +415,8 @@ mod test {
             },
             key_usage: &[0b0010_0000],
             issuer_key: keys::KEY1_RSA_KEYPAIR,
+            issuer_algo: Algo::RsaPkcs1Sha256,
+            dice_claims: None,
         },
         TestCwt {
             issuer: "Silicon Owner LLC Intermediate",
@@ -204,6 +427,8 @@ mod test {
             },
             key_usage: &[0b0010_0000],
             issuer_key: keys::KEY2_RSA_KEYPAIR,
+            issuer_algo: Algo::RsaPkcs1Sha256,
+            dice_claims: None,
         },
     ];
 
@@ -218,7 +443,10 @@ mod test {
             &data,
             CertFormat::OpenDiceCwt,
             &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
             None,
+            None,
+            &[],
         )
         .unwrap();
 
@@ -242,7 +470,10 @@ mod test {
             &data,
             CertFormat::OpenDiceCwt,
             &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
             None,
+            &[],
         );
         assert!(result.is_err());
 
@@ -256,7 +487,10 @@ mod test {
             &data,
             CertFormat::OpenDiceCwt,
             &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
             None,
+            &[],
         );
         assert!(result.is_err());
     }
@@ -0,0 +1,319 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! CWT certificate construction.
+
+use core::convert::TryFrom as _;
+use core::time::Duration;
+
+use crate::cert::cwt::cbor;
+use crate::cert::cwt::cbor::Int;
+use crate::cert::cwt::label;
+use crate::cert::encode;
+use crate::cert::encode::Sink;
+use crate::cert::CertFormat;
+use crate::cert::Error;
+use crate::cert::Measurements;
+use crate::cert::Name;
+use crate::cert::OperationalFlags;
+use crate::crypto::sig;
+use crate::io;
+use crate::io::Write as _;
+use crate::mem::Arena;
+use crate::mem::ArenaExt as _;
+
+impl<'a> Name<'a> {
+    /// Creates a name for use in a CWT, which is an arbitrary string.
+    pub fn cwt(name: &'a str) -> Self {
+        Name {
+            raw: name.as_bytes(),
+            format: CertFormat::OpenDiceCwt,
+        }
+    }
+}
+
+/// A builder for OpenDICE-profile CWT certificates.
+///
+/// Certificates produced by this builder always carry the OpenDICE key usage
+/// claim: CA certificates may only sign certificates, and all other
+/// certificates may only produce digital signatures.
+///
+/// By default, certificates carry no validity period.
+#[derive(Clone, Debug)]
+pub struct CwtBuilder<'a> {
+    issuer: Name<'a>,
+    subject: Name<'a>,
+    subject_key: &'a sig::PublicKeyParams<'a>,
+    not_before: Option<Duration>,
+    not_after: Option<Duration>,
+    is_ca: bool,
+    measurements: Option<&'a Measurements<'a>>,
+}
+
+impl<'a> CwtBuilder<'a> {
+    /// Creates a new builder for a certificate in which `issuer` certifies
+    /// that `subject_key` belongs to `subject`.
+    ///
+    /// `issuer` and `subject` must be CWT names, such as those of other CWT
+    /// certificates.
+    pub fn new(
+        issuer: Name<'a>,
+        subject: Name<'a>,
+        subject_key: &'a sig::PublicKeyParams<'a>,
+    ) -> Self {
+        Self {
+            issuer,
+            subject,
+            subject_key,
+            not_before: None,
+            not_after: None,
+            is_ca: false,
+            measurements: None,
+        }
+    }
+
+    /// Sets the validity period of the certificate, as times since the Unix
+    /// epoch.
+    ///
+    /// `None` omits the corresponding claim.
+    pub fn validity(
+        mut self,
+        not_before: Option<Duration>,
+        not_after: Option<Duration>,
+    ) -> Self {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    /// Makes the certificate a CA certificate.
+    pub fn ca(mut self) -> Self {
+        self.is_ca = true;
+        self
+    }
+
+    /// Includes `measurements` of the firmware that `subject_key` belongs
+    /// to.
+    ///
+    /// The FWIDs are encoded as the code, configuration and authority hashes,
+    /// in that order, and the flags as the mode; other measurements have no
+    /// OpenDICE equivalent and are dropped. There may be at most three FWIDs,
+    /// and the flags must be empty or correspond to a single OpenDICE mode.
+    pub fn measurements(mut self, measurements: &'a Measurements<'a>) -> Self {
+        self.measurements = Some(measurements);
+        self
+    }
+
+    /// Signs the certificate with `signer`, returning the complete CBOR
+    /// encoding of its `COSE_Sign1` envelope.
+    ///
+    /// `issuer_key` selects the signature algorithm, and must be the public
+    /// half of `signer`'s key. `signer` must produce signatures in the
+    /// encoding COSE uses for that algorithm (e.g., the fixed-width PKCS#11
+    /// encoding for ECDSA).
+    pub fn sign<'out>(
+        &self,
+        issuer_key: &sig::PublicKeyParams,
+        signer: &mut dyn sig::Sign,
+        arena: &'out dyn Arena,
+    ) -> Result<&'out [u8], Error> {
+        let algo = algo_for_key(issuer_key)?;
+        algo_for_key(self.subject_key)?;
+
+        let issuer = core::str::from_utf8(self.issuer.raw)
+            .map_err(|_| Error::BadEncoding)?;
+        let subject = core::str::from_utf8(self.subject.raw)
+            .map_err(|_| Error::BadEncoding)?;
+        let not_before = self.not_before.map(date).transpose()?;
+        let not_after = self.not_after.map(date).transpose()?;
+
+        let mut hashes = [None; 3];
+        let mut mode = None;
+        if let Some(m) = self.measurements {
+            if m.fwids().len() > hashes.len() {
+                return Err(Error::BadEncoding);
+            }
+            for (hash, fwid) in hashes.iter_mut().zip(m.fwids()) {
+                *hash = Some(fwid.digest);
+            }
+            mode = m.flags.map(mode_for_flags).transpose()?;
+        }
+
+        // The key usage claim is a little-endian `KeyUsage` bit string; see
+        // `cert::KeyUsage`. keyCertSign is bit 5, and digitalSignature is
+        // bit 0.
+        let key_usage: u8 = if self.is_ca { 0b0010_0000 } else { 0b0000_0001 };
+
+        let protected = encode::to_arena(
+            &|w| {
+                cbor::write_wrapped(w, &|w| {
+                    cbor::write_map(w, &|m| {
+                        m.entry(label::COSE_ALG, &|w| cbor::write_int(w, algo))
+                    })
+                })
+            },
+            arena,
+        )?;
+
+        let payload = encode::to_arena(
+            &|w| {
+                cbor::write_wrapped(w, &|w| {
+                    cbor::write_map(w, &|m| {
+                        m.entry(label::CWT_ISS, &|w| {
+                            cbor::write_utf8(w, issuer)
+                        })?;
+                        m.entry(label::CWT_SUB, &|w| {
+                            cbor::write_utf8(w, subject)
+                        })?;
+                        if let Some(exp) = not_after {
+                            m.entry(label::CWT_EXP, &|w| {
+                                cbor::write_int(w, exp)
+                            })?;
+                        }
+                        if let Some(nbf) = not_before {
+                            m.entry(label::CWT_NBF, &|w| {
+                                cbor::write_int(w, nbf)
+                            })?;
+                        }
+
+                        let hash_keys = [
+                            label::DICE_CODE_HASH,
+                            label::DICE_CONFIG_HASH,
+                            label::DICE_AUTHZ_HASH,
+                        ];
+                        for (&key, hash) in hash_keys.iter().zip(&hashes) {
+                            if let Some(hash) = hash {
+                                m.entry(key, &|w| cbor::write_bytes(w, hash))?;
+                            }
+                        }
+                        if let Some(mode) = mode {
+                            m.entry(label::DICE_MODE, &|w| {
+                                cbor::write_bytes(w, &[mode])
+                            })?;
+                        }
+
+                        m.entry(label::DICE_SPKI, &|w| {
+                            cbor::write_wrapped(w, &|w| {
+                                write_cose_key(w, self.subject_key)
+                            })
+                        })?;
+                        m.entry(label::DICE_KEY_USAGE, &|w| {
+                            cbor::write_bytes(w, &[key_usage])
+                        })
+                    })
+                })
+            },
+            arena,
+        )?;
+
+        // See `parse()` for the layout of the `Sig_structure`.
+        let signature = arena.alloc_slice::<u8>(signer.sig_bytes())?;
+        let sig_len = signer.sign(
+            &[b"\x84\x6aSignature1", protected, b"\x40", payload],
+            signature,
+        )?;
+        let signature = &signature[..sig_len];
+
+        // COSE_Sign1 = [
+        //   protected   : bstr .cbor header_map,
+        //   unprotected : header_map,
+        //   payload     : bstr,
+        //   signature   : bstr,
+        // ]
+        encode::to_arena(
+            &|w| {
+                cbor::write_array(w, &|a| {
+                    a.item(&|w| w.write_bytes(protected))?;
+                    // No unprotected headers.
+                    a.item(&|w| cbor::write_map(w, &|_| Ok(())))?;
+                    a.item(&|w| w.write_bytes(payload))?;
+                    a.item(&|w| cbor::write_bytes(w, signature))
+                })
+            },
+            arena,
+        )
+    }
+}
+
+/// Converts a time into an RFC8392 `NumericDate`.
+fn date(time: Duration) -> Result<Int, Error> {
+    let secs = u32::try_from(time.as_secs()).map_err(|_| Error::BadEncoding)?;
+    Ok(Int::from_u32(secs))
+}
+
+/// Converts operational flags into an OpenDICE mode.
+fn mode_for_flags(flags: OperationalFlags) -> Result<u8, Error> {
+    if flags.is_empty() {
+        Ok(label::MODE_NORMAL)
+    } else if flags == OperationalFlags::NOT_CONFIGURED {
+        Ok(label::MODE_NOT_CONFIGURED)
+    } else if flags == OperationalFlags::DEBUG {
+        Ok(label::MODE_DEBUG)
+    } else if flags == OperationalFlags::RECOVERY {
+        Ok(label::MODE_RECOVERY)
+    } else {
+        Err(Error::BadEncoding)
+    }
+}
+
+/// Selects the COSE algorithm to use when signing with `key`.
+fn algo_for_key(key: &sig::PublicKeyParams) -> Result<Int, Error> {
+    match key {
+        sig::PublicKeyParams::Rsa { .. } => Ok(label::RSA_PKCS1_SHA256),
+        sig::PublicKeyParams::Ecc {
+            curve: sig::Curve::NistP256,
+            ..
+        } => Ok(label::ECDSA_SHA256),
+        sig::PublicKeyParams::Ecc {
+            curve: sig::Curve::NistP384,
+            ..
+        } => Ok(label::ECDSA_SHA384),
+        sig::PublicKeyParams::Okp {
+            curve: sig::Curve::Ed25519,
+            ..
+        } => Ok(label::EDDSA),
+        _ => Err(Error::UnknownAlgorithm),
+    }
+}
+
+/// Writes a `COSE_Key` for `key`; see `parse_cose_key()`.
+fn write_cose_key(
+    w: &mut Sink,
+    key: &sig::PublicKeyParams,
+) -> Result<(), io::Error> {
+    let algo = algo_for_key(key).map_err(|_| io::Error::Internal)?;
+    cbor::write_map(w, &|m| match key {
+        sig::PublicKeyParams::Rsa { modulus, exponent } => {
+            m.entry(label::KEY_KTY, &|w| {
+                cbor::write_int(w, label::KEY_KTY_RSA)
+            })?;
+            m.entry(label::KEY_ALG, &|w| cbor::write_int(w, algo))?;
+            m.entry(label::RSA_MODULUS, &|w| cbor::write_bytes(w, modulus))?;
+            m.entry(label::RSA_EXPONENT, &|w| cbor::write_bytes(w, exponent))
+        }
+        sig::PublicKeyParams::Ecc { curve, x, y } => {
+            let crv = match curve {
+                sig::Curve::NistP256 => label::CRV_P256,
+                _ => label::CRV_P384,
+            };
+            m.entry(label::KEY_KTY, &|w| {
+                cbor::write_int(w, label::KEY_KTY_EC2)
+            })?;
+            m.entry(label::KEY_ALG, &|w| cbor::write_int(w, algo))?;
+            m.entry(label::EC2_CRV, &|w| cbor::write_int(w, crv))?;
+            m.entry(label::EC2_X, &|w| cbor::write_bytes(w, x))?;
+            m.entry(label::EC2_Y, &|w| cbor::write_bytes(w, y))
+        }
+        sig::PublicKeyParams::Okp { x, .. } => {
+            m.entry(label::KEY_KTY, &|w| {
+                cbor::write_int(w, label::KEY_KTY_OKP)
+            })?;
+            m.entry(label::KEY_ALG, &|w| cbor::write_int(w, algo))?;
+            m.entry(label::OKP_CRV, &|w| {
+                cbor::write_int(w, label::CRV_ED25519)
+            })?;
+            m.entry(label::OKP_X, &|w| cbor::write_bytes(w, x))
+        }
+    })
+}
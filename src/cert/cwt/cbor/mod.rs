@@ -2,7 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-//! CBOR parsing.
+//! CBOR parsing and encoding.
 //!
 //! This is not a complete CBOR parser, since we do not parse any CBOR not
 //! needed for COSE or CWT. We also reject all CBOR that is not encoded
@@ -15,6 +15,9 @@
 //! Currently, this parser rejects 64-bit integers, since there is no reason
 //! to encounter them in our regime, and helps cut down on code size.
 //!
+//! The encoder produces exactly the subset of CBOR that the parser accepts;
+//! in particular, map keys must be written in order.
+//!
 //! See: https://datatracker.ietf.org/doc/html/rfc8949
 
 use core::cmp::Ord;
@@ -22,7 +25,11 @@ use core::cmp::Ordering;
 use core::cmp::PartialOrd;
 use core::convert::TryInto as _;
 
+use crate::cert::encode::Contents;
+use crate::cert::encode::Sink;
 use crate::cert::Error;
+use crate::io;
+use crate::io::Write as _;
 
 #[cfg(test)]
 #[macro_use]
@@ -78,6 +85,14 @@ impl Int {
             Int(true, -(n + 1) as u32)
         }
     }
+
+    pub const fn to_i64(self) -> i64 {
+        if self.0 {
+            -(self.1 as i64) - 1
+        } else {
+            self.1 as i64
+        }
+    }
 }
 
 impl From<u32> for Int {
@@ -441,3 +456,153 @@ impl<'i, 'r> MapWalker<'i, 'r> {
         Ok(())
     }
 }
+
+/// Major types, as defined in RFC8949 S3.1.
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_UTF8: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+
+/// Writes the initial byte and argument of an item, using the minimal
+/// encoding for `arg`.
+fn write_head(
+    w: &mut dyn io::Write,
+    major: u8,
+    arg: u32,
+) -> Result<(), io::Error> {
+    let major = major << 5;
+    match arg {
+        0..=23 => w.write_bytes(&[major | arg as u8]),
+        24..=0xff => w.write_bytes(&[major | 24, arg as u8]),
+        0x100..=0xffff => {
+            w.write_bytes(&[major | 25])?;
+            w.write_bytes(&(arg as u16).to_be_bytes())
+        }
+        _ => {
+            w.write_bytes(&[major | 26])?;
+            w.write_bytes(&arg.to_be_bytes())
+        }
+    }
+}
+
+/// Writes the head of a string-like item of length `len`.
+fn write_len_head(
+    w: &mut dyn io::Write,
+    major: u8,
+    len: usize,
+) -> Result<(), io::Error> {
+    let len = len.try_into().map_err(|_| io::Error::BufferExhausted)?;
+    write_head(w, major, len)
+}
+
+/// Writes an integer.
+pub fn write_int(w: &mut Sink, int: impl Into<Int>) -> Result<(), io::Error> {
+    let Int(sign, n) = int.into();
+    write_head(w, sign as u8, n)
+}
+
+/// Writes a byte string.
+pub fn write_bytes(w: &mut Sink, bytes: &[u8]) -> Result<(), io::Error> {
+    write_len_head(w, MAJOR_BYTES, bytes.len())?;
+    w.write_bytes(bytes)
+}
+
+/// Writes a UTF-8 string.
+pub fn write_utf8(w: &mut Sink, s: &str) -> Result<(), io::Error> {
+    write_len_head(w, MAJOR_UTF8, s.len())?;
+    w.write_bytes(s.as_bytes())
+}
+
+/// Writes a byte string containing the CBOR written by `contents`.
+///
+/// This is the inverse of [`Item::read_all()`].
+pub fn write_wrapped(
+    w: &mut Sink,
+    contents: Contents,
+) -> Result<(), io::Error> {
+    w.prefixed(contents, |len, (), w| write_len_head(w, MAJOR_BYTES, len))
+}
+
+/// Writes an array, whose elements are produced by `items`.
+pub fn write_array(
+    w: &mut Sink,
+    items: &dyn Fn(&mut ArrayWriter) -> Result<(), io::Error>,
+) -> Result<(), io::Error> {
+    w.prefixed(
+        |w| {
+            let mut array = ArrayWriter { w, len: 0 };
+            items(&mut array)?;
+            Ok(array.len)
+        },
+        |_, len, w| write_head(w, MAJOR_ARRAY, len),
+    )
+}
+
+/// A writer for the elements of an [`Array`]; see [`write_array()`].
+pub struct ArrayWriter<'w, 'buf> {
+    w: &'w mut Sink<'buf>,
+    len: u32,
+}
+
+impl ArrayWriter<'_, '_> {
+    /// Writes the next element.
+    ///
+    /// `item` must write exactly one item.
+    pub fn item(&mut self, item: Contents) -> Result<(), io::Error> {
+        self.len += 1;
+        item(self.w)
+    }
+}
+
+/// Writes a map, whose entries are produced by `entries`.
+pub fn write_map(
+    w: &mut Sink,
+    entries: &dyn Fn(&mut MapWriter) -> Result<(), io::Error>,
+) -> Result<(), io::Error> {
+    w.prefixed(
+        |w| {
+            let mut map = MapWriter {
+                w,
+                len: 0,
+                prev_key: None,
+            };
+            entries(&mut map)?;
+            Ok(map.len)
+        },
+        |_, len, w| write_head(w, MAJOR_MAP, len),
+    )
+}
+
+/// A writer for the entries of a [`Map`]; see [`write_map()`].
+///
+/// Only integer keys are supported.
+pub struct MapWriter<'w, 'buf> {
+    w: &'w mut Sink<'buf>,
+    len: u32,
+    prev_key: Option<Int>,
+}
+
+impl MapWriter<'_, '_> {
+    /// Writes the next entry.
+    ///
+    /// Keys must be written in ascending order, without duplicates, since
+    /// that is what deterministic encoding requires; `value` must write
+    /// exactly one item.
+    pub fn entry(
+        &mut self,
+        key: impl Into<Int>,
+        value: Contents,
+    ) -> Result<(), io::Error> {
+        let key = key.into();
+        if let Some(prev) = self.prev_key {
+            if prev >= key {
+                return Err(io::Error::Internal);
+            }
+        }
+        self.prev_key = Some(key);
+        self.len += 1;
+
+        write_int(self.w, key)?;
+        value(self.w)
+    }
+}
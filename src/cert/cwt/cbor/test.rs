@@ -2,15 +2,18 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-//! CBOR parser tests.
+//! CBOR parser and encoder tests.
 //!
 //! These are hung off to the side to avoid cluttering the main der.rs.
 
 // NOTE: This is only for convenience and should be avoided in non-test code.
 use untrusted::Input;
 
+use crate::cert::cwt::cbor;
 use crate::cert::cwt::cbor::Int;
 use crate::cert::cwt::cbor::Item;
+use crate::cert::encode::to_vec;
+use crate::cert::encode::Sink;
 use crate::cert::Error::BadEncoding;
 
 type Result = core::result::Result<(), crate::cert::Error>;
@@ -215,3 +218,81 @@ fn out_of_order_map() -> Result {
         Ok(())
     })
 }
+
+#[test]
+fn write_scalars() {
+    let cbor = to_vec(&|w| {
+        cbor::write_int(w, 0)?;
+        cbor::write_int(w, 25)?;
+        cbor::write_int(w, 255)?;
+        cbor::write_int(w, 2000)?;
+        cbor::write_int(w, 100_000)?;
+        cbor::write_int(w, -1)?;
+        cbor::write_int(w, -2000)?;
+        cbor::write_utf8(w, "Hello, world!")?;
+        cbor::write_bytes(w, b"Behold")
+    });
+    assert_eq!(
+        cbor,
+        raw_cbor! {
+            UINT:0
+            UINT:25
+            UINT:255
+            UINT:2000
+            UINT:100000
+            NINT:0
+            NINT:1999
+            UTF8 {"Hello, world!"}
+            BYTES {"Behold"}
+        }
+    );
+}
+
+#[test]
+fn write_nested() -> Result {
+    let cbor = to_vec(&|w| {
+        cbor::write_map(w, &|m| {
+            m.entry(2, &|w| {
+                cbor::write_array(w, &|a| {
+                    a.item(&|w| cbor::write_int(w, 42))?;
+                    a.item(&|w| cbor::write_map(w, &|_| Ok(())))
+                })
+            })?;
+            m.entry(-5, &|w| {
+                cbor::write_wrapped(w, &|w| cbor::write_utf8(w, "wrapped"))
+            })
+        })
+    });
+    assert_eq!(
+        cbor,
+        raw_cbor!(MAP [
+            UINT:2  ARRAY [UINT:42, MAP []],
+            NINT:4  BYTES { UTF8 {"wrapped"} },
+        ])
+    );
+
+    Input::from(&cbor).read_all(BadEncoding, |buf| {
+        Item::parse(buf)?.into_map()?.walk(|w| {
+            w.must_get(Int::from(2))?
+                .into_array()?
+                .with(|i| i.ignore())?;
+            let s = w
+                .must_get(Int::from(-5))?
+                .read_all(|buf| Item::parse(buf)?.into_utf8())?;
+            assert_eq!(s, "wrapped");
+            Ok(())
+        })
+    })
+}
+
+#[test]
+fn write_out_of_order_map() {
+    for &(k1, k2) in &[(-1, 1000), (5, 5)] {
+        let mut buf = [0; 16];
+        let result = cbor::write_map(&mut Sink::new(&mut buf), &|m| {
+            m.entry(k1, &|w| cbor::write_int(w, 0))?;
+            m.entry(k2, &|w| cbor::write_int(w, 0))
+        });
+        assert!(result.is_err());
+    }
+}
@@ -2,14 +2,16 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-//! COSE and CWT certificate parser.
+//! COSE and CWT certificate parser and builder.
 //!
 //! See https://datatracker.ietf.org/doc/html/rfc8152 and
 //! https://datatracker.ietf.org/doc/html/rfc8392
 
+use core::time::Duration;
+
 use crate::cert;
 use crate::cert::cwt::cbor::Item;
-use crate::cert::x509;
+use crate::cert::cwt::cbor::MapWalker;
 use crate::cert::Cert;
 use crate::cert::Error;
 use crate::cert::Name;
@@ -18,6 +20,9 @@ use crate::crypto::sig;
 #[macro_use]
 mod cbor;
 
+mod builder;
+pub use builder::CwtBuilder;
+
 #[cfg(test)]
 pub(crate) mod test;
 
@@ -46,11 +51,28 @@ mod label {
     //
     // See https://www.iana.org/assignments/cose/cose.xhtml
     pub const RSA_PKCS1_SHA256: Int = Int::from_i32(-257);
+    pub const ECDSA_SHA256: Int = Int::from_i32(-7);
+    pub const ECDSA_SHA384: Int = Int::from_i32(-35);
+    pub const EDDSA: Int = Int::from_i32(-8);
 
     // RSA key fields.
     pub const RSA_MODULUS: Int = Int::from_i32(-1);
     pub const RSA_EXPONENT: Int = Int::from_i32(-2);
 
+    // OKP key fields. See RFC8152 Table 24.
+    pub const OKP_CRV: Int = Int::from_i32(-1);
+    pub const OKP_X: Int = Int::from_i32(-2);
+
+    // EC2 key fields. See RFC8152 Table 23.
+    pub const EC2_CRV: Int = Int::from_i32(-1);
+    pub const EC2_X: Int = Int::from_i32(-2);
+    pub const EC2_Y: Int = Int::from_i32(-3);
+
+    // Elliptic curves. See RFC8152 Table 22.
+    pub const CRV_P256: Int = Int::from_i32(1);
+    pub const CRV_P384: Int = Int::from_i32(2);
+    pub const CRV_ED25519: Int = Int::from_i32(6);
+
     // CWT fields.
     pub const CWT_ISS: Int = Int::from_i32(1);
     pub const CWT_SUB: Int = Int::from_i32(2);
@@ -72,6 +94,12 @@ mod label {
     pub const DICE_MODE: Int = Int::from_i32(-4670551);
     pub const DICE_SPKI: Int = Int::from_i32(-4670552);
     pub const DICE_KEY_USAGE: Int = Int::from_i32(-4670553);
+
+    // OpenDICE mode values.
+    pub const MODE_NOT_CONFIGURED: u8 = 0;
+    pub const MODE_NORMAL: u8 = 1;
+    pub const MODE_DEBUG: u8 = 2;
+    pub const MODE_RECOVERY: u8 = 3;
 }
 
 /// Parses a CWT certificate.
@@ -83,24 +111,30 @@ pub fn parse<'cert>(
     let buf = untrusted::Input::from(cert);
     let cose = buf.read_all(Error::BadEncoding, Cose::parse)?;
 
-    let (issuer, subject, subject_key, ku) =
+    let (issuer, subject, not_before, not_after, measurements, subject_key, ku) =
         cose.payload.read_all(Error::BadEncoding, |buf| {
             Item::parse(buf)?.read_all(|buf| {
                 Item::parse(buf)?.into_map()?.walk(|map| {
-                    let iss = Name(
-                        map.must_get(label::CWT_ISS)?.into_utf8()?.as_bytes(),
-                    );
-                    let sub = Name(
-                        map.must_get(label::CWT_SUB)?.into_utf8()?.as_bytes(),
-                    );
-
-                    let (_algo, params) =
-                        parse_cose_key(map.must_get(label::DICE_SPKI)?)?;
+                    let iss =
+                        Name::cwt(map.must_get(label::CWT_ISS)?.into_utf8()?);
+                    let sub =
+                        Name::cwt(map.must_get(label::CWT_SUB)?.into_utf8()?);
+                    let exp =
+                        map.get(label::CWT_EXP)?.map(parse_date).transpose()?;
+                    let nbf =
+                        map.get(label::CWT_NBF)?.map(parse_date).transpose()?;
+                    let measurements = parse_dice_claims(map)?;
+
+                    // The subject key is an encoded COSE_Key wrapped in a
+                    // bstr, rather than a bare map.
+                    let (_algo, params) = map
+                        .must_get(label::DICE_SPKI)?
+                        .read_all(|buf| parse_cose_key(Item::parse(buf)?))?;
                     let ku = map
                         .get(label::DICE_KEY_USAGE)?
-                        .map(|v| x509::KeyUsage::from_le(v.into_bytes()?))
+                        .map(|v| cert::KeyUsage::from_le(v.into_bytes()?))
                         .transpose()?;
-                    Ok((iss, sub, params, ku))
+                    Ok((iss, sub, nbf, exp, measurements, params, ku))
                 })
             })
         })?;
@@ -134,14 +168,64 @@ pub fn parse<'cert>(
     Ok(Cert {
         raw: cert,
         format: cert::CertFormat::OpenDiceCwt,
+        serial: None,
         issuer,
         subject,
         subject_key,
+        not_before,
+        not_after,
         basic_constraints: None,
-        is_cert_sign: ku.map(|ku| ku.is_cert_sign()).unwrap_or(false),
+        subject_key_id: None,
+        authority_key_id: None,
+        // OpenDICE certificates cannot sign CRLs; see `Crl`.
+        key_usage: ku.map(|ku| ku - cert::KeyUsage::CRL_SIGN),
+        ext_key_usage: None,
+        measurements,
     })
 }
 
+/// Parses the OpenDICE measurement claims, i.e., the code, configuration and
+/// authority hashes, and the mode.
+///
+/// Returns `None` if none of these claims are present. Descriptors are
+/// ignored, since their contents are not standardized.
+fn parse_dice_claims<'cert>(
+    map: &mut MapWalker<'cert, '_>,
+) -> Result<Option<cert::Measurements<'cert>>, Error> {
+    let mut m = cert::Measurements::default();
+    let mut any = false;
+    for &key in &[
+        label::DICE_CODE_HASH,
+        label::DICE_CONFIG_HASH,
+        label::DICE_AUTHZ_HASH,
+    ] {
+        if let Some(hash) = map.get(key)? {
+            let digest = hash.into_bytes()?;
+            m.push_fwid(cert::Fwid {
+                algo: cert::FwidAlgo::from_len(digest.len()),
+                digest,
+            })?;
+            any = true;
+        }
+    }
+
+    if let Some(mode) = map.get(label::DICE_MODE)? {
+        let flags = match mode.into_bytes()? {
+            [label::MODE_NOT_CONFIGURED] => {
+                cert::OperationalFlags::NOT_CONFIGURED
+            }
+            [label::MODE_NORMAL] => cert::OperationalFlags::empty(),
+            [label::MODE_DEBUG] => cert::OperationalFlags::DEBUG,
+            [label::MODE_RECOVERY] => cert::OperationalFlags::RECOVERY,
+            _ => return Err(Error::BadEncoding),
+        };
+        m.flags = Some(flags);
+        any = true;
+    }
+
+    Ok(if any { Some(m) } else { None })
+}
+
 fn parse_cose_key<'cert>(
     key: Item<'cert, '_>,
 ) -> Result<(Option<sig::Algo>, sig::PublicKeyParams<'cert>), Error> {
@@ -159,15 +243,58 @@ fn parse_cose_key<'cert>(
                     map.must_get(label::RSA_EXPONENT)?.into_bytes()?;
                 sig::PublicKeyParams::Rsa { modulus, exponent }
             }
+            label::KEY_KTY_OKP => {
+                let curve = match map.must_get(label::OKP_CRV)?.into_int()? {
+                    label::CRV_ED25519 => sig::Curve::Ed25519,
+                    _ => return Err(Error::UnknownAlgorithm),
+                };
+                let x = map.must_get(label::OKP_X)?.into_bytes()?;
+                if x.len() != 32 {
+                    return Err(Error::BadEncoding);
+                }
+                sig::PublicKeyParams::Okp { curve, x }
+            }
+            label::KEY_KTY_EC2 => {
+                let (curve, coord_len) =
+                    match map.must_get(label::EC2_CRV)?.into_int()? {
+                        label::CRV_P256 => (sig::Curve::NistP256, 32),
+                        label::CRV_P384 => (sig::Curve::NistP384, 48),
+                        _ => return Err(Error::UnknownAlgorithm),
+                    };
+                // Compressed points, where `y` is a boolean, are not
+                // supported.
+                let x = map.must_get(label::EC2_X)?.into_bytes()?;
+                let y = map.must_get(label::EC2_Y)?.into_bytes()?;
+                if x.len() != coord_len || y.len() != coord_len {
+                    return Err(Error::BadEncoding);
+                }
+                sig::PublicKeyParams::Ecc { curve, x, y }
+            }
             _ => return Err(Error::UnknownAlgorithm),
         };
         Ok((algo, params))
     })
 }
 
+/// Parses an RFC8392 `NumericDate`.
+///
+/// Only integer dates are supported; times before the Unix epoch are clamped
+/// to it.
+fn parse_date(v: Item) -> Result<Duration, Error> {
+    let secs = v.into_int()?.to_i64();
+    Ok(Duration::from_secs(secs.max(0) as u64))
+}
+
 fn parse_algo(v: Item) -> Result<sig::Algo, Error> {
     match v.into_int()? {
         label::RSA_PKCS1_SHA256 => Ok(sig::Algo::RsaPkcs1Sha256),
+        // COSE specifies that ECDSA signatures are the fixed-width
+        // concatenation of `r` and `s`, not the DER encoding used by X.509.
+        label::ECDSA_SHA256 => Ok(sig::Algo::EcdsaPkcs11P256),
+        label::ECDSA_SHA384 => Ok(sig::Algo::EcdsaPkcs11P384),
+        // RFC8152 only specifies EdDSA for use with Ed25519 and Ed448, and we
+        // only support the former.
+        label::EDDSA => Ok(sig::Algo::Ed25519),
         _ => Err(Error::UnknownAlgorithm),
     }
 }
@@ -232,6 +359,13 @@ impl<'cert> Cose<'cert> {
             })
         }
 
+        // A COSE_Sign1 is an array of exactly four items: the protected and
+        // unprotected headers, the payload, and the signature. The length
+        // prefix is major 0b100, length 4.
+        if buf.read_byte()? != 0x84 {
+            return Err(Error::BadEncoding);
+        }
+
         // NOTE: the protected portion of the COSE structure is a map wrapped
         // in a bstr, similar to how X.509 extensions are DER wrapped up in
         // an OCTET STRING. The payload below is similar.
@@ -2,17 +2,29 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-//! CWT parser tests.
+//! CWT parser and builder tests.
 //!
 //! These are hung off to the side to avoid cluttering the main cwt.rs.
 
+use core::time::Duration;
+
+use testutil::data;
 use testutil::data::keys;
 
 use crate::cert::Cert;
 use crate::cert::CertFormat;
+use crate::cert::CwtBuilder;
+use crate::cert::Fwid;
+use crate::cert::FwidAlgo;
+use crate::cert::Measurements;
+use crate::cert::Name;
+use crate::cert::OperationalFlags;
 use crate::crypto::ring;
+use crate::crypto::sig::Algo;
+use crate::crypto::sig::Curve;
 use crate::crypto::sig::PublicKeyParams;
-use crate::crypto::sig::Sign as _;
+use crate::crypto::sig::Sign;
+use crate::mem::BumpArena;
 
 const UINT: u8 = 0;
 const NINT: u8 = 1;
@@ -28,13 +40,48 @@ pub struct TestCwt {
     pub spki: PublicKeyParams<'static>,
     pub key_usage: &'static [u8],
     pub issuer_key: &'static [u8],
+    pub issuer_algo: Algo,
+    /// The OpenDICE code hash and mode claims, if any.
+    pub dice_claims: Option<(&'static [u8], u8)>,
 }
 
 impl TestCwt {
     pub fn encode(&self) -> Vec<u8> {
+        // COSE algorithm identifiers, encoded as CBOR negative integers.
+        let (alg, mut signer): (u64, Box<dyn Sign>) = match self.issuer_algo {
+            Algo::RsaPkcs1Sha256 => {
+                (256, Box::new(ring::rsa::from_keypair(self.issuer_key).1))
+            }
+            Algo::EcdsaPkcs11P256 => (
+                6,
+                Box::new(
+                    ring::ecdsa::SignP256::with_pkcs11_encoding_from_pkcs8(
+                        self.issuer_key,
+                    )
+                    .unwrap(),
+                ),
+            ),
+            Algo::EcdsaPkcs11P384 => (
+                34,
+                Box::new(
+                    ring::ecdsa::SignP384::with_pkcs11_encoding_from_pkcs8(
+                        self.issuer_key,
+                    )
+                    .unwrap(),
+                ),
+            ),
+            Algo::Ed25519 => (
+                7,
+                Box::new(
+                    ring::ed25519::Sign::from_pkcs8(self.issuer_key).unwrap(),
+                ),
+            ),
+            a => unimplemented!("{:?}", a),
+        };
+
         let protected = raw_cbor!(MAP [
-            // Algorithm, i.e., RSA.
-            UINT:1  NINT:256,
+            // Algorithm.
+            UINT:1  NINT:alg,
             // Criticalities.
             UINT:2  ARRAY[UINT:1, UINT:2],
         ]);
@@ -50,19 +97,66 @@ impl TestCwt {
                 // Exponent.
                 NINT:1  BYTES {exponent},
             ]),
-            _ => unimplemented!(),
+            PublicKeyParams::Ecc { curve, x, y } => {
+                let (crv, alg) = match curve {
+                    Curve::NistP256 => (1, 6),
+                    Curve::NistP384 => (2, 34),
+                    c => unimplemented!("{:?}", c),
+                };
+                raw_cbor!(MAP [
+                    // Key type, EC2.
+                    UINT:1  UINT:2,
+                    // Algorithm, ECDSA.
+                    UINT:3  NINT:alg,
+                    // Curve.
+                    NINT:0  UINT:crv,
+                    // X coordinate.
+                    NINT:1  BYTES {x},
+                    // Y coordinate.
+                    NINT:2  BYTES {y},
+                ])
+            }
+            PublicKeyParams::Okp { curve, x } => {
+                assert_eq!(*curve, Curve::Ed25519);
+                raw_cbor!(MAP [
+                    // Key type, OKP.
+                    UINT:1  UINT:1,
+                    // Algorithm, EdDSA.
+                    UINT:3  NINT:7,
+                    // Curve, Ed25519.
+                    NINT:0  UINT:6,
+                    // Public key.
+                    NINT:1  BYTES {x},
+                ])
+            }
         };
 
-        let payload = raw_cbor!(MAP [
-            // Issuer.
-            UINT:1          UTF8 {(self.issuer)},
-            // Subject.
-            UINT:2          UTF8 {(self.subject)},
-            // DICE SPKI.
-            NINT:4670551    spki,
-            // DICE KeyUsage
-            NINT:4670552    BYTES {(self.key_usage)},
-        ]);
+        let payload = match self.dice_claims {
+            None => raw_cbor!(MAP [
+                // Issuer.
+                UINT:1          UTF8 {(self.issuer)},
+                // Subject.
+                UINT:2          UTF8 {(self.subject)},
+                // DICE SPKI.
+                NINT:4670551    BYTES {spki},
+                // DICE KeyUsage
+                NINT:4670552    BYTES {(self.key_usage)},
+            ]),
+            Some((code_hash, mode)) => raw_cbor!(MAP [
+                // Issuer.
+                UINT:1          UTF8 {(self.issuer)},
+                // Subject.
+                UINT:2          UTF8 {(self.subject)},
+                // DICE code hash.
+                NINT:4670544    BYTES {code_hash},
+                // DICE mode.
+                NINT:4670550    BYTES {([mode])},
+                // DICE SPKI.
+                NINT:4670551    BYTES {spki},
+                // DICE KeyUsage
+                NINT:4670552    BYTES {(self.key_usage)},
+            ]),
+        };
 
         let tbs = raw_cbor!(ARRAY [
             UTF8 {"Signature1"},
@@ -71,16 +165,15 @@ impl TestCwt {
             BYTES {payload},
         ]);
 
-        let (_, mut signer) = ring::rsa::from_keypair(self.issuer_key);
         let mut sig = vec![0; signer.sig_bytes()];
         let sig_len = signer.sign(&[&tbs], &mut sig).unwrap();
 
-        raw_cbor! {
-            BYTES {protected}
-            MAP []  // No unprotected fields.
-            BYTES {payload}
-            BYTES {(sig[..sig_len])}
-        }
+        raw_cbor!(ARRAY [
+            BYTES {protected},
+            MAP [],  // No unprotected fields.
+            BYTES {payload},
+            BYTES {(sig[..sig_len])},
+        ])
     }
 }
 
@@ -96,6 +189,8 @@ fn self_signed() {
         },
         key_usage: &[0b0010_0000],
         issuer_key: keys::KEY1_RSA_KEYPAIR,
+        issuer_algo: Algo::RsaPkcs1Sha256,
+        dice_claims: None,
     };
 
     let data = cwt.encode();
@@ -110,3 +205,344 @@ fn self_signed() {
     assert_eq!(cert.subject(), cert.issuer());
     assert!(cert.supports_cert_signing());
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn ecdsa_chain() {
+    let root = TestCwt {
+        issuer: "my cool ca",
+        subject: "my cool ca",
+        spki: PublicKeyParams::Ecc {
+            curve: Curve::NistP384,
+            x: keys::KEY1_ECDSA_P384_X,
+            y: keys::KEY1_ECDSA_P384_Y,
+        },
+        key_usage: &[0b0010_0000],
+        issuer_key: keys::KEY1_ECDSA_P384_KEYPAIR,
+        issuer_algo: Algo::EcdsaPkcs11P384,
+        dice_claims: None,
+    }
+    .encode();
+    let leaf = TestCwt {
+        issuer: "my cool ca",
+        subject: "my cool device",
+        spki: PublicKeyParams::Ecc {
+            curve: Curve::NistP256,
+            x: keys::KEY1_ECDSA_P256_X,
+            y: keys::KEY1_ECDSA_P256_Y,
+        },
+        key_usage: &[0b0010_0000],
+        issuer_key: keys::KEY1_ECDSA_P384_KEYPAIR,
+        issuer_algo: Algo::EcdsaPkcs11P384,
+        dice_claims: None,
+    }
+    .encode();
+
+    let root = Cert::parse(
+        &root,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    let leaf = Cert::parse(
+        &leaf,
+        CertFormat::OpenDiceCwt,
+        Some(root.subject_key()),
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert!(matches!(
+        leaf.subject_key(),
+        PublicKeyParams::Ecc {
+            curve: Curve::NistP256,
+            ..
+        }
+    ));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn ed25519_self_signed() {
+    let cwt = TestCwt {
+        issuer: "my cool ca",
+        subject: "my cool ca",
+        spki: PublicKeyParams::Okp {
+            curve: Curve::Ed25519,
+            x: keys::KEY1_ED25519_RAW,
+        },
+        key_usage: &[0b0010_0000],
+        issuer_key: keys::KEY1_ED25519_KEYPAIR,
+        issuer_algo: Algo::Ed25519,
+        dice_claims: None,
+    };
+
+    let mut data = cwt.encode();
+    let cert = Cert::parse(
+        &data,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(cert.subject(), cert.issuer());
+    assert!(matches!(
+        cert.subject_key(),
+        PublicKeyParams::Okp {
+            curve: Curve::Ed25519,
+            ..
+        }
+    ));
+
+    // Corrupt the last byte of the signature.
+    *data.last_mut().unwrap() ^= 1;
+    let result = Cert::parse(
+        &data,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn open_dice_ed25519() {
+    let issuer_key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+    let cert = Cert::parse(
+        data::cwt::OPEN_DICE_ED25519,
+        CertFormat::OpenDiceCwt,
+        Some(&issuer_key),
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_ne!(cert.issuer(), cert.subject());
+    assert!(matches!(
+        cert.subject_key(),
+        PublicKeyParams::Okp {
+            curve: Curve::Ed25519,
+            ..
+        }
+    ));
+    assert!(cert.supports_cert_signing());
+
+    // The certificate is not self-signed.
+    let result = Cert::parse(
+        data::cwt::OPEN_DICE_ED25519,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn dice_measurements() {
+    let code_hash: &[u8] = &[0x5a; 64];
+    let cwt = TestCwt {
+        issuer: "my cool ca",
+        subject: "my cool ca",
+        spki: PublicKeyParams::Rsa {
+            modulus: keys::KEY1_RSA_MOD,
+            exponent: keys::KEY1_RSA_EXP,
+        },
+        key_usage: &[0b0010_0000],
+        issuer_key: keys::KEY1_RSA_KEYPAIR,
+        issuer_algo: Algo::RsaPkcs1Sha256,
+        dice_claims: Some((code_hash, 2)),
+    };
+
+    let data = cwt.encode();
+    let cert = Cert::parse(
+        &data,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+
+    let m = cert.measurements().unwrap();
+    assert_eq!(m.flags, Some(OperationalFlags::DEBUG));
+    assert_eq!(
+        m.fwids(),
+        &[Fwid {
+            algo: Some(FwidAlgo::Sha512),
+            digest: code_hash,
+        }]
+    );
+    assert_eq!(m.svn, None);
+
+    // Modes outside of the OpenDICE specification are rejected.
+    let cwt = TestCwt {
+        dice_claims: Some((code_hash, 4)),
+        ..cwt
+    };
+    let data = cwt.encode();
+    let cert = Cert::parse(
+        &data,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    );
+    assert!(cert.is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_chain() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer = ring::ecdsa::SignP384::with_pkcs11_encoding_from_pkcs8(
+        keys::KEY1_ECDSA_P384_KEYPAIR,
+    )
+    .unwrap();
+    let root_key = PublicKeyParams::Ecc {
+        curve: Curve::NistP384,
+        x: keys::KEY1_ECDSA_P384_X,
+        y: keys::KEY1_ECDSA_P384_Y,
+    };
+    let leaf_key = PublicKeyParams::Ecc {
+        curve: Curve::NistP256,
+        x: keys::KEY1_ECDSA_P256_X,
+        y: keys::KEY1_ECDSA_P256_Y,
+    };
+
+    let root_name = Name::cwt("my cool ca");
+    let root = CwtBuilder::new(root_name, root_name, &root_key)
+        .ca()
+        .sign(&root_key, &mut signer, &arena)
+        .unwrap();
+    let root = Cert::parse(
+        root,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(root.subject(), root_name);
+    assert_eq!(root.issuer(), root_name);
+    assert_eq!(root.subject_key(), &root_key);
+    assert!(root.supports_cert_signing());
+    assert_eq!(root.not_before(), None);
+    assert_eq!(root.not_after(), None);
+    assert!(root.measurements().is_none());
+
+    let code_hash = [0x5a; 64];
+    let config_hash = [0xa5; 32];
+    let mut m = Measurements::default();
+    m.flags = Some(OperationalFlags::DEBUG);
+    for &digest in &[&code_hash[..], &config_hash[..]] {
+        m.push_fwid(Fwid { algo: None, digest }).unwrap();
+    }
+
+    let leaf_name = Name::cwt("my cool device");
+    let leaf = CwtBuilder::new(root_name, leaf_name, &leaf_key)
+        .validity(
+            Some(Duration::from_secs(1609459200)),
+            Some(Duration::from_secs(1893456000)),
+        )
+        .measurements(&m)
+        .sign(&root_key, &mut signer, &arena)
+        .unwrap();
+    let leaf = Cert::parse(
+        leaf,
+        CertFormat::OpenDiceCwt,
+        Some(&root_key),
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(leaf.subject(), leaf_name);
+    assert_eq!(leaf.issuer(), root_name);
+    assert_eq!(leaf.subject_key(), &leaf_key);
+    assert!(!leaf.supports_cert_signing());
+    assert_eq!(leaf.not_before(), Some(Duration::from_secs(1609459200)));
+    assert_eq!(leaf.not_after(), Some(Duration::from_secs(1893456000)));
+
+    let m = leaf.measurements().unwrap();
+    assert_eq!(m.flags, Some(OperationalFlags::DEBUG));
+    assert_eq!(
+        m.fwids(),
+        &[
+            Fwid {
+                algo: Some(FwidAlgo::Sha512),
+                digest: &code_hash,
+            },
+            Fwid {
+                algo: Some(FwidAlgo::Sha256),
+                digest: &config_hash,
+            },
+        ]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_ed25519() {
+    let mut arena = [0; 1024];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+
+    let name = Name::cwt("my cool ca");
+    let cert = CwtBuilder::new(name, name, &key)
+        .ca()
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+    let cert = Cert::parse(
+        cert,
+        CertFormat::OpenDiceCwt,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(cert.subject_key(), &key);
+    assert!(cert.supports_cert_signing());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_bad_measurements() {
+    let mut arena = [0; 1024];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+    let name = Name::cwt("my cool ca");
+
+    // OpenDICE has no single mode for debug recovery.
+    let mut m = Measurements::default();
+    m.flags = Some(OperationalFlags::DEBUG | OperationalFlags::RECOVERY);
+    let cert = CwtBuilder::new(name, name, &key).measurements(&m).sign(
+        &key,
+        &mut signer,
+        &arena,
+    );
+    assert!(cert.is_err());
+
+    // Only three hashes are available.
+    let mut m = Measurements::default();
+    for _ in 0..4 {
+        m.push_fwid(Fwid {
+            algo: None,
+            digest: &[0; 32],
+        })
+        .unwrap();
+    }
+    let cert = CwtBuilder::new(name, name, &key).measurements(&m).sign(
+        &key,
+        &mut signer,
+        &arena,
+    );
+    assert!(cert.is_err());
+}
@@ -0,0 +1,128 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! DICE firmware measurements.
+//!
+//! Certificates issued by a DICE layer describe the firmware that the
+//! subject key was derived for. X.509 certificates do so with the TCG DICE
+//! `TcbInfo` extension, while OpenDICE CWTs use a handful of custom claims;
+//! both are surfaced through the [`Measurements`] type, so that a verifier
+//! can appraise them against reference values regardless of format.
+
+use bitflags::bitflags;
+
+use crate::cert::Error;
+
+/// The maximum number of FWIDs a [`Measurements`] can hold.
+///
+/// Certificates with more FWIDs than this are rejected.
+pub const MAX_FWIDS: usize = 8;
+
+/// A hash algorithm used to compute an [`Fwid`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[allow(missing_docs)]
+pub enum FwidAlgo {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl FwidAlgo {
+    /// Guesses the algorithm that produced a digest of `len` bytes.
+    pub(crate) fn from_len(len: usize) -> Option<Self> {
+        match len {
+            32 => Some(Self::Sha256),
+            48 => Some(Self::Sha384),
+            64 => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// A firmware identifier: a digest of some measured component.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Fwid<'cert> {
+    /// The algorithm used to compute `digest`, if it is known.
+    pub algo: Option<FwidAlgo>,
+    /// The digest itself.
+    pub digest: &'cert [u8],
+}
+
+bitflags! {
+    /// Operational state flags for a measured component.
+    ///
+    /// These correspond to the TCG DICE `OperationalFlags` bits; the OpenDICE
+    /// `mode` claim is mapped onto them.
+    pub struct OperationalFlags: u8 {
+        /// The component has not been configured.
+        const NOT_CONFIGURED = 1 << 0;
+        /// The component is not in a secure state.
+        const NOT_SECURE = 1 << 1;
+        /// The component is in recovery mode.
+        const RECOVERY = 1 << 2;
+        /// The component is in debug mode.
+        const DEBUG = 1 << 3;
+    }
+}
+
+/// Firmware measurements extracted from a certificate.
+///
+/// All fields are optional, since neither format requires any of them.
+/// FWIDs appear in the order they are listed in the certificate; for
+/// OpenDICE CWTs, these are the code, configuration and authority hashes, in
+/// that order, skipping any that are absent.
+#[derive(Clone, Default, Debug)]
+pub struct Measurements<'cert> {
+    /// The entity that created the measured component.
+    pub vendor: Option<&'cert str>,
+    /// The product name of the measured component.
+    pub model: Option<&'cert str>,
+    /// The version of the measured component.
+    pub version: Option<&'cert str>,
+    /// The security version number of the measured component.
+    pub svn: Option<u32>,
+    /// The DICE layer the measured component belongs to.
+    pub layer: Option<u32>,
+    /// An index distinguishing components at the same layer.
+    pub index: Option<u32>,
+    /// The operational state of the measured component.
+    pub flags: Option<OperationalFlags>,
+    // This is a plain array rather than an `ArrayVec`, since `Cert` must not
+    // need drop glue.
+    fwids: [Fwid<'cert>; MAX_FWIDS],
+    fwid_count: usize,
+}
+
+impl<'cert> Measurements<'cert> {
+    /// Returns the FWIDs included in these measurements.
+    pub fn fwids(&self) -> &[Fwid<'cert>] {
+        &self.fwids[..self.fwid_count]
+    }
+
+    /// Appends a new FWID.
+    ///
+    /// Returns an error if there are already [`MAX_FWIDS`] FWIDs.
+    pub fn push_fwid(&mut self, fwid: Fwid<'cert>) -> Result<(), Error> {
+        let slot = self
+            .fwids
+            .get_mut(self.fwid_count)
+            .ok_or(Error::BadEncoding)?;
+        *slot = fwid;
+        self.fwid_count += 1;
+        Ok(())
+    }
+
+    /// Returns whether these measurements contain an FWID equal to `fwid`.
+    ///
+    /// If both FWIDs specify an algorithm, they must match.
+    pub fn contains_fwid(&self, fwid: &Fwid) -> bool {
+        self.fwids().iter().any(|f| {
+            let algo_ok = match (f.algo, fwid.algo) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            };
+            algo_ok && f.digest == fwid.digest
+        })
+    }
+}
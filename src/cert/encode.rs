@@ -0,0 +1,157 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Machinery shared by the certificate encoders.
+//!
+//! Both DER and CBOR prefix an element with its length, which is not known
+//! until the element's contents have been encoded. Rather than encoding the
+//! contents twice, once to measure them and once more to write them out, the
+//! contents are written first, and the prefix is then inserted in front of
+//! them; see [`Sink::prefixed()`].
+//!
+//! Encoding a whole structure still takes two passes: one with a counting
+//! [`Sink`], to learn how large a buffer to allocate, and one that writes
+//! into that buffer. See [`to_arena()`].
+
+use crate::cert::Error;
+use crate::io;
+use crate::mem::Arena;
+use crate::mem::ArenaExt as _;
+
+/// The longest prefix that [`Sink::prefixed()`] supports.
+const MAX_PREFIX_LEN: usize = 8;
+
+/// A function that writes out a sequence of encoded elements.
+pub type Contents<'a> = &'a dyn Fn(&mut Sink) -> Result<(), io::Error>;
+
+/// A buffer that encoders write into.
+///
+/// A `Sink` without a buffer only counts the bytes written to it.
+pub struct Sink<'a> {
+    buf: Option<&'a mut [u8]>,
+    len: usize,
+}
+
+impl<'a> Sink<'a> {
+    /// Creates a new `Sink` that discards its input, counting how many bytes
+    /// were written.
+    pub fn counter() -> Self {
+        Self { buf: None, len: 0 }
+    }
+
+    /// Creates a new `Sink` that writes into `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf: Some(buf),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.len
+    }
+
+    /// Runs `contents`, and then inserts the prefix that `prefix` writes in
+    /// front of whatever `contents` wrote.
+    ///
+    /// `prefix` is called with the number of bytes `contents` wrote and the
+    /// value it returned, and may write at most eight bytes.
+    pub fn prefixed<R>(
+        &mut self,
+        contents: impl FnOnce(&mut Self) -> Result<R, io::Error>,
+        prefix: impl FnOnce(usize, R, &mut dyn io::Write) -> Result<(), io::Error>,
+    ) -> Result<(), io::Error> {
+        let start = self.len;
+        let val = contents(self)?;
+
+        let mut buf = [0; MAX_PREFIX_LEN];
+        let mut cursor = &mut buf[..];
+        prefix(self.len - start, val, &mut cursor)?;
+        let prefix_len = MAX_PREFIX_LEN - cursor.len();
+        self.insert(start, &buf[..prefix_len])
+    }
+
+    /// Inserts `bytes` at offset `pos`, moving everything written after it
+    /// forward to make room.
+    fn insert(&mut self, pos: usize, bytes: &[u8]) -> Result<(), io::Error> {
+        let end = self.len + bytes.len();
+        if let Some(buf) = &mut self.buf {
+            if end > buf.len() {
+                return Err(io::Error::BufferExhausted);
+            }
+            buf.copy_within(pos..self.len, pos + bytes.len());
+            buf[pos..pos + bytes.len()].copy_from_slice(bytes);
+        }
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl io::Write for Sink<'_> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), io::Error> {
+        self.insert(self.len, bytes)
+    }
+}
+
+/// Encodes `contents` into a buffer allocated from `arena`.
+pub fn to_arena<'a>(
+    contents: Contents,
+    arena: &'a dyn Arena,
+) -> Result<&'a [u8], Error> {
+    let mut counter = Sink::counter();
+    contents(&mut counter)?;
+
+    let buf = arena.alloc_slice::<u8>(counter.position())?;
+    contents(&mut Sink::new(buf))?;
+    Ok(buf)
+}
+
+/// Encodes `contents` into a freshly allocated buffer.
+#[cfg(test)]
+pub fn to_vec(contents: Contents) -> Vec<u8> {
+    let mut counter = Sink::counter();
+    contents(&mut counter).unwrap();
+
+    let mut buf = vec![0; counter.position()];
+    contents(&mut Sink::new(&mut buf)).unwrap();
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::Write as _;
+
+    /// Writes whatever `contents` writes, prefixed with its length as a
+    /// single byte.
+    fn write_prefixed(
+        w: &mut Sink,
+        contents: Contents,
+    ) -> Result<(), io::Error> {
+        w.prefixed(contents, |len, (), w| w.write_bytes(&[len as u8]))
+    }
+
+    #[test]
+    fn nested_prefixes() {
+        let contents: Contents = &|w| {
+            w.write_bytes(b"a")?;
+            write_prefixed(w, &|w| {
+                write_prefixed(w, &|w| w.write_bytes(b"bc"))?;
+                w.write_bytes(b"d")
+            })?;
+            write_prefixed(w, &|_| Ok(()))
+        };
+        assert_eq!(to_vec(contents), b"a\x04\x02bcd\x00");
+    }
+
+    #[test]
+    fn too_small() {
+        let mut buf = [0; 3];
+        let result = write_prefixed(&mut Sink::new(&mut buf), &|w| {
+            w.write_bytes(b"abc")
+        });
+        assert!(result.is_err());
+    }
+}
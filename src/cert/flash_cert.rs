@@ -0,0 +1,111 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Flash-backed certificates.
+
+use core::fmt;
+
+use crate::cert::Cert;
+use crate::cert::CertFormat;
+use crate::cert::Error;
+use crate::crypto::sha256;
+use crate::crypto::sig;
+use crate::hardware::flash::Flash;
+use crate::hardware::flash::Region;
+use crate::mem::Arena;
+
+/// A certificate that lives in a [`Flash`] region, rather than in memory.
+///
+/// A `FlashCert` only records where its certificate is stored and the
+/// certificate's SHA-256 digest, which are computed while the certificate is
+/// parsed by [`FlashCert::load()`]. Its bytes are read back out of flash on
+/// demand, such as when serving `GetCert` requests.
+///
+/// A `FlashCert` holds a shared borrow of its `Flash`, so the certificate
+/// cannot be reprogrammed out from under it; it is not re-verified when
+/// read.
+pub struct FlashCert<'f> {
+    flash: &'f dyn Flash,
+    region: Region,
+    digest: sha256::Digest,
+}
+
+impl fmt::Debug for FlashCert<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FlashCert")
+            .field("region", &self.region)
+            .field("digest", &self.digest)
+            .finish()
+    }
+}
+
+impl<'f> FlashCert<'f> {
+    /// Reads the certificate stored in `region` of `flash`, hashing and
+    /// parsing it in a single pass.
+    ///
+    /// The certificate is parsed and verified exactly as in
+    /// [`Cert::parse()`]. Its bytes are read with [`Flash::read_direct()`],
+    /// so `arena` is only used if `flash` cannot be read from directly; the
+    /// returned [`Cert`] borrows from it, and may be dropped once it is no
+    /// longer needed, without affecting the `FlashCert`.
+    pub fn load<'a>(
+        flash: &'f dyn Flash,
+        region: Region,
+        format: CertFormat,
+        key: Option<&sig::PublicKeyParams>,
+        ciphers: &mut impl sig::Ciphers,
+        sha: &impl sha256::Builder,
+        arena: &'a dyn Arena,
+    ) -> Result<(Self, Cert<'a>), Error>
+    where
+        'f: 'a,
+    {
+        let raw = flash.read_direct(region, arena, 1)?;
+        let mut digest = sha256::Digest::default();
+        sha.hash_contiguous(raw, &mut digest)?;
+        let cert = Cert::parse(raw, format, key, ciphers)?;
+        Ok((
+            Self {
+                flash,
+                region,
+                digest,
+            },
+            cert,
+        ))
+    }
+
+    /// Returns the region of flash this certificate is stored in.
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Returns the SHA-256 digest of this certificate, as used by
+    /// `GetDigests`.
+    pub fn digest(&self) -> &sha256::Digest {
+        &self.digest
+    }
+
+    /// Reads up to `len` bytes of this certificate, starting at `offset`.
+    ///
+    /// Fewer than `len` bytes are returned if the end of the certificate is
+    /// reached. `arena` is used as in [`Flash::read_direct()`].
+    pub fn read<'a>(
+        &self,
+        offset: usize,
+        len: usize,
+        arena: &'a dyn Arena,
+    ) -> Result<&'a [u8], Error>
+    where
+        'f: 'a,
+    {
+        let cert_len = self.region.len as usize;
+        let start = cert_len.min(offset);
+        let end = cert_len.min(len.saturating_add(start));
+        let chunk = Region::new(
+            self.region.offset + start as u32,
+            (end - start) as u32,
+        );
+        Ok(self.flash.read_direct(chunk, arena, 1)?)
+    }
+}
@@ -0,0 +1,575 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Flash-backed, multi-slot trust chains.
+//!
+//! Each slot of a [`FlashChain`] is stored in its own [`Flash`] region,
+//! using the following layout:
+//! ```text
+//! struct Slot {
+//!     // The number of certificates in the chain. Zero and 0xff (i.e.,
+//!     // erased flash) indicate an empty slot.
+//!     count: u8,
+//!     certs: [Cert; count],
+//! }
+//!
+//! struct Cert {
+//!     // Little-endian.
+//!     len: u16,
+//!     bytes: [u8; len],
+//! }
+//! ```
+//! Certificates are stored in chain order, starting with the root.
+//!
+//! Every certificate of a loaded chain is kept parsed, but its bytes are only
+//! copied out of flash if the [`Flash`] cannot be read from directly. `GetCert`
+//! is served by streaming each certificate back out of flash through its
+//! [`FlashCert`].
+//!
+//! Flash can only be programmed from its erased state, so a slot must be
+//! erased (i.e., filled with 0xff) before a new chain is provisioned into it.
+
+use core::fmt;
+use core::num::NonZeroUsize;
+
+use arrayvec::ArrayVec;
+
+use crate::cert::chain::check_link;
+use crate::cert::Cert;
+use crate::cert::CertFormat;
+use crate::cert::Crl;
+use crate::cert::Error;
+use crate::cert::FlashCert;
+use crate::cert::SimpleChain;
+use crate::cert::TrustChain;
+use crate::crypto::sha256;
+use crate::crypto::sig;
+use crate::hardware::flash;
+use crate::hardware::flash::Flash;
+use crate::hardware::flash::Region;
+use crate::hardware::WallClock;
+use crate::mem::Arena;
+
+/// The largest number of slots a Cerberus device may expose.
+///
+/// This is the width of the `slot_mask` in a `Challenge` response.
+pub const MAX_SLOTS: usize = 8;
+
+/// A [`TrustChain`] with up to `SLOTS` slots, each holding a chain of up to
+/// `LEN` certificates loaded from flash.
+///
+/// Slots start out empty, and are populated with [`FlashChain::load()`],
+/// which verifies the chain before making it available. For example, a device
+/// might load a manufacturer chain into slot 0 at boot, and later load an
+/// owner chain into slot 1 once it has been [provisioned].
+///
+/// [provisioned]: FlashChain::provision
+pub struct FlashChain<'cert, const SLOTS: usize, const LEN: usize> {
+    slots: ArrayVec<Option<Slot<'cert, LEN>>, SLOTS>,
+}
+
+/// A loaded chain.
+struct Slot<'cert, const LEN: usize> {
+    certs: ArrayVec<FlashCert<'cert>, LEN>,
+    parsed: ArrayVec<Cert<'cert>, LEN>,
+    signer: Option<&'cert mut dyn sig::Sign>,
+}
+
+impl<const LEN: usize> fmt::Debug for Slot<'_, LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Slot")
+            .field("certs", &self.certs)
+            .field("parsed", &self.parsed)
+            .finish()
+    }
+}
+
+impl<const SLOTS: usize, const LEN: usize> fmt::Debug
+    for FlashChain<'_, SLOTS, LEN>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.slots.fmt(f)
+    }
+}
+
+impl<const SLOTS: usize, const LEN: usize> Default
+    for FlashChain<'_, SLOTS, LEN>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'cert, const SLOTS: usize, const LEN: usize>
+    FlashChain<'cert, SLOTS, LEN>
+{
+    /// Creates a new `FlashChain` with every slot empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `SLOTS` is greater than [`MAX_SLOTS`].
+    pub fn new() -> Self {
+        assert!(SLOTS <= MAX_SLOTS, "too many slots: {}", SLOTS);
+        let mut slots = ArrayVec::new();
+        while !slots.is_full() {
+            slots.push(None);
+        }
+        Self { slots }
+    }
+
+    /// Loads and verifies the chain stored in `flash` into `slot`.
+    ///
+    /// Certificates are read out of `flash`, hashed, and parsed one at a time,
+    /// and remain parsed for as long as the chain is loaded. `arena` is used
+    /// for copying certificates out of `flash`, if it cannot be read from
+    /// directly.
+    ///
+    /// If `clock` is provided, every certificate in the chain must also be
+    /// valid at the current time. Certificates are also checked for
+    /// revocation against `crls`, as in [`SimpleChain::parse()`].
+    ///
+    /// Returns `false` if `flash` does not contain a chain, in which case
+    /// `slot` is left empty. If verification fails, `slot` is also left empty.
+    /// Returns [`Error::BadSlot`] if `slot` is out of bounds.
+    #[allow(clippy::too_many_arguments)]
+    pub fn load(
+        &mut self,
+        slot: u8,
+        flash: &'cert impl Flash,
+        arena: &'cert dyn Arena,
+        format: CertFormat,
+        ciphers: &mut impl sig::Ciphers,
+        sha: &impl sha256::Builder,
+        signer: Option<&'cert mut dyn sig::Sign>,
+        clock: Option<&dyn WallClock>,
+        crls: &[Crl],
+    ) -> Result<bool, Error> {
+        let entry = self.slots.get_mut(slot as usize).ok_or(Error::BadSlot)?;
+        *entry = None;
+
+        let mut count = [0];
+        flash.read(0, &mut count)?;
+        let count = match count[0] {
+            0 | 0xff => return Ok(false),
+            n => n as usize,
+        };
+        if count > LEN {
+            return Err(Error::ChainTooLong);
+        }
+        let now = clock.map(WallClock::unix_time);
+
+        let mut offset = 1;
+        let mut next_region = || -> Result<Region, Error> {
+            let mut len = [0; 2];
+            flash.read(offset, &mut len)?;
+            let region =
+                Region::new(offset + 2, u16::from_le_bytes(len) as u32);
+            offset = region.end();
+            Ok(region)
+        };
+
+        let mut certs = ArrayVec::new();
+        let mut parsed = ArrayVec::<Cert, LEN>::new();
+        for i in 0..count {
+            let key = parsed.last().map(Cert::subject_key);
+            let (flash_cert, cert) = FlashCert::load(
+                flash,
+                next_region()?,
+                format,
+                key,
+                ciphers,
+                sha,
+                arena,
+            )?;
+            check_link(parsed.last(), &cert, i, count, now, crls, ciphers)?;
+            certs.push(flash_cert);
+            parsed.push(cert);
+        }
+
+        *entry = Some(Slot {
+            certs,
+            parsed,
+            signer,
+        });
+        Ok(true)
+    }
+
+    /// Empties `slot`.
+    ///
+    /// This does not affect the chain stored in flash, if any. Does nothing if
+    /// `slot` is out of bounds.
+    pub fn unload(&mut self, slot: u8) {
+        if let Some(entry) = self.slots.get_mut(slot as usize) {
+            *entry = None;
+        }
+    }
+
+    /// Verifies `raw_chain` and, if successful, writes it to `flash`, so that
+    /// it may later be loaded with [`FlashChain::load()`].
+    ///
+    /// Verification is performed exactly as in `load()`, so a chain that is
+    /// successfully provisioned will also load successfully, barring a change
+    /// in the current time.
+    ///
+    /// [`Flash`] has no way to erase a device, so the part of `flash` that
+    /// the chain will occupy must already be erased (i.e., filled with 0xff);
+    /// otherwise, [`flash::Error::Locked`] is returned.
+    pub fn provision(
+        flash: &mut impl Flash,
+        raw_chain: &[&[u8]],
+        format: CertFormat,
+        ciphers: &mut impl sig::Ciphers,
+        sha: &impl sha256::Builder,
+        clock: Option<&dyn WallClock>,
+        crls: &[Crl],
+    ) -> Result<(), Error> {
+        SimpleChain::<LEN>::parse(
+            raw_chain, format, ciphers, sha, None, clock, crls,
+        )?;
+        write_chain(flash, raw_chain)
+    }
+
+    fn slot(&self, slot: u8) -> Option<&Slot<'cert, LEN>> {
+        self.slots.get(slot as usize)?.as_ref()
+    }
+}
+
+/// Writes `raw_chain` to `flash` in the format expected by
+/// [`FlashChain::load()`], without verifying it.
+///
+/// [`Flash`] has no way to erase a device, and programming over a previous
+/// chain would corrupt it, so the part of `flash` that the chain would occupy
+/// must be erased (i.e., filled with 0xff) beforehand. If it is not, this
+/// function returns [`flash::Error::Locked`] without writing anything.
+pub(crate) fn write_chain(
+    flash: &mut (impl Flash + ?Sized),
+    raw_chain: &[&[u8]],
+) -> Result<(), Error> {
+    match raw_chain.len() {
+        0 => return Err(Error::BadEncoding),
+        // 0xff is reserved for erased flash.
+        n if n >= 0xff => return Err(Error::ChainTooLong),
+        _ => {}
+    }
+
+    // Make sure every byte the chain will occupy is erased before writing
+    // any of it.
+    let mut len = 1;
+    for raw_cert in raw_chain {
+        if raw_cert.len() > u16::MAX as usize {
+            return Err(Error::BadEncoding);
+        }
+        len += 2 + raw_cert.len() as u32;
+    }
+    let mut buf = [0; 32];
+    let mut checked = 0;
+    while checked < len {
+        let chunk = &mut buf[..(len - checked).min(32) as usize];
+        flash.read(checked, chunk)?;
+        if chunk.iter().any(|&b| b != 0xff) {
+            return Err(Error::Flash(flash::Error::Locked));
+        }
+        checked += chunk.len() as u32;
+    }
+
+    flash.program(0, &[raw_chain.len() as u8])?;
+    let mut offset = 1;
+    for raw_cert in raw_chain {
+        let len = raw_cert.len() as u16;
+        flash.program(offset, &len.to_le_bytes())?;
+        flash.program(offset + 2, raw_cert)?;
+        offset += 2 + len as u32;
+    }
+    flash.flush()?;
+    Ok(())
+}
+
+impl<const SLOTS: usize, const LEN: usize> TrustChain
+    for FlashChain<'_, SLOTS, LEN>
+{
+    fn chain_len(&self, slot: u8) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(self.slot(slot)?.certs.len())
+    }
+
+    fn cert(&self, slot: u8, index: usize) -> Option<&Cert> {
+        self.slot(slot)?.parsed.get(index)
+    }
+
+    fn read_cert<'a>(
+        &'a self,
+        slot: u8,
+        index: usize,
+        offset: usize,
+        len: usize,
+        arena: &'a dyn Arena,
+    ) -> Result<Option<&'a [u8]>, Error> {
+        let cert = match self.slot(slot).and_then(|s| s.certs.get(index)) {
+            Some(cert) => cert,
+            None => return Ok(None),
+        };
+        cert.read(offset, len, arena).map(Some)
+    }
+
+    fn cert_digest(&self, slot: u8, index: usize) -> Option<&sha256::Digest> {
+        Some(self.slot(slot)?.certs.get(index)?.digest())
+    }
+
+    fn signer(&mut self, slot: u8) -> Option<&mut dyn sig::Sign> {
+        let slot = self.slots.get_mut(slot as usize)?.as_mut()?;
+        match &mut slot.signer {
+            Some(signer) => Some(*signer),
+            None => None,
+        }
+    }
+}
+
+#[cfg(all(test, not(miri)))] // TODO(#103)
+mod test {
+    use super::*;
+    use testutil::data::x509;
+
+    use crate::crypto::ring;
+    use crate::crypto::sha256::Builder as _;
+    use crate::hardware::flash::Ram;
+    use crate::hardware::flash::RamMut;
+    use crate::mem::OutOfMemory;
+
+    fn provision(raw_chain: &[&[u8]]) -> Result<Vec<u8>, Error> {
+        let mut flash = RamMut(vec![0xff; 4096]);
+        FlashChain::<2, 3>::provision(
+            &mut flash,
+            raw_chain,
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            &[],
+        )?;
+        Ok(flash.0)
+    }
+
+    #[test]
+    fn load_slots() {
+        let mfg = Ram(provision(&[x509::CHAIN1, x509::CHAIN2]).unwrap());
+        let owner = Ram(
+            provision(&[x509::CHAIN1, x509::CHAIN2, x509::CHAIN3]).unwrap()
+        );
+
+        let mut chain = FlashChain::<2, 3>::new();
+        assert_eq!(chain.slot_mask(), 0);
+
+        let loaded = chain
+            .load(
+                0,
+                &mfg,
+                &OutOfMemory,
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(loaded);
+        assert_eq!(chain.slot_mask(), 0b01);
+        assert_eq!(chain.chain_len(0), NonZeroUsize::new(2));
+        assert!(chain.chain_len(1).is_none());
+
+        let loaded = chain
+            .load(
+                1,
+                &owner,
+                &OutOfMemory,
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(loaded);
+        assert_eq!(chain.slot_mask(), 0b11);
+        assert_eq!(chain.chain_len(1), NonZeroUsize::new(3));
+        assert_eq!(chain.cert(1, 2).unwrap().raw(), x509::CHAIN3);
+        assert!(chain.cert(2, 0).is_none());
+
+        chain.unload(0);
+        assert_eq!(chain.slot_mask(), 0b10);
+    }
+
+    #[test]
+    fn read_certs() {
+        let raw_chain = [x509::CHAIN1, x509::CHAIN2, x509::CHAIN3];
+        let flash = Ram(provision(&raw_chain).unwrap());
+        let sha = ring::sha256::Builder::new();
+
+        let mut chain = FlashChain::<1, 3>::new();
+        let loaded = chain
+            .load(
+                0,
+                &flash,
+                &OutOfMemory,
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &sha,
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(loaded);
+
+        for (i, raw_cert) in raw_chain.iter().enumerate() {
+            assert_eq!(chain.cert(0, i).unwrap().raw(), *raw_cert);
+
+            let mut digest = sha256::Digest::default();
+            sha.hash_contiguous(raw_cert, &mut digest).unwrap();
+            assert_eq!(chain.cert_digest(0, i), Some(&digest));
+
+            let mut read = Vec::new();
+            loop {
+                let chunk = chain
+                    .read_cert(0, i, read.len(), 100, &OutOfMemory)
+                    .unwrap()
+                    .unwrap();
+                if chunk.is_empty() {
+                    break;
+                }
+                read.extend_from_slice(chunk);
+            }
+            assert_eq!(read, *raw_cert);
+        }
+        assert!(chain.cert(0, 3).is_none());
+        assert!(chain.cert_digest(0, 3).is_none());
+        assert!(chain
+            .read_cert(0, 3, 0, 100, &OutOfMemory)
+            .unwrap()
+            .is_none());
+        assert!(chain
+            .read_cert(1, 0, 0, 100, &OutOfMemory)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn load_empty() {
+        let flash = Ram(vec![0xff; 4096]);
+        let mut chain = FlashChain::<1, 3>::new();
+        let loaded = chain
+            .load(
+                0,
+                &flash,
+                &OutOfMemory,
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(!loaded);
+        assert_eq!(chain.slot_mask(), 0);
+    }
+
+    #[test]
+    fn load_tampered() {
+        let mut bytes = provision(&[x509::CHAIN1, x509::CHAIN2]).unwrap();
+        // Flip a bit in the signature of the last cert.
+        let end = 1 + 2 + x509::CHAIN1.len() + 2 + x509::CHAIN2.len();
+        bytes[end - 1] ^= 1;
+        let flash = Ram(bytes);
+
+        let mut chain = FlashChain::<1, 3>::new();
+        let result = chain.load(
+            0,
+            &flash,
+            &OutOfMemory,
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            None,
+            &[],
+        );
+        assert!(result.is_err());
+        assert!(chain.chain_len(0).is_none());
+    }
+
+    #[test]
+    fn load_bad_slot() {
+        let flash = Ram(provision(&[x509::CHAIN1, x509::CHAIN2]).unwrap());
+        let mut chain = FlashChain::<1, 3>::new();
+        let result = chain.load(
+            1,
+            &flash,
+            &OutOfMemory,
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            None,
+            &[],
+        );
+        assert!(matches!(result, Err(Error::BadSlot)));
+        chain.unload(1);
+    }
+
+    #[test]
+    fn reprovision() {
+        let mut flash =
+            RamMut(provision(&[x509::CHAIN1, x509::CHAIN2]).unwrap());
+        let owner = [x509::CHAIN1, x509::CHAIN2, x509::CHAIN3];
+        let reprovision = |flash: &mut RamMut<Vec<u8>>| {
+            FlashChain::<1, 3>::provision(
+                flash,
+                &owner,
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                &[],
+            )
+        };
+
+        // Programming over the old chain must not clobber it.
+        let old = flash.0.clone();
+        assert!(matches!(
+            reprovision(&mut flash),
+            Err(Error::Flash(flash::Error::Locked))
+        ));
+        assert_eq!(flash.0, old);
+
+        for byte in &mut flash.0 {
+            *byte = 0xff;
+        }
+        reprovision(&mut flash).unwrap();
+
+        let flash = Ram(flash.0);
+        let mut chain = FlashChain::<1, 3>::new();
+        let loaded = chain
+            .load(
+                0,
+                &flash,
+                &OutOfMemory,
+                CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(loaded);
+        assert_eq!(chain.chain_len(0), NonZeroUsize::new(3));
+    }
+
+    #[test]
+    fn provision_bad_chain() {
+        assert!(provision(&[x509::CHAIN1, x509::CHAIN3]).is_err());
+        assert!(provision(&[]).is_err());
+    }
+}
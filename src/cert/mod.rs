@@ -8,19 +8,47 @@
 //! authentication portions of the Cerberus protocol, including
 //! certificate parsing and handling.
 
+use core::time::Duration;
+
+use crate::crypto::sha256;
 use crate::crypto::sig;
+use crate::hardware::flash;
 use crate::io;
+use crate::mem::OutOfMemory;
+use crate::protocol;
 
 // Note that all parsers leverage Brian Smith's `untrusted` crate to ensure
 // we don't walk off the end of the buffer. We may wind up building this
 // functionality into `manticore::io` if buffering certificates in memory
 // proves to be a non-starter.
+mod encode;
+
 mod cwt;
+pub use cwt::CwtBuilder;
+
 mod x509;
+pub use x509::CertBuilder;
+pub use x509::Crl;
+pub use x509::UnsignedCsr;
 
 mod chain;
 pub use chain::*;
 
+mod cache;
+pub use cache::*;
+
+mod flash_cert;
+pub use flash_cert::*;
+
+mod flash_chain;
+pub use flash_chain::*;
+
+mod dice;
+pub use dice::*;
+
+mod usage;
+pub use usage::*;
+
 /// A certificate format understood by Manticore.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -45,19 +73,29 @@ pub enum CertFormat {
 pub struct Cert<'cert> {
     raw: &'cert [u8],
     format: CertFormat,
+    // X.509 serial numbers, which CRLs refer to certificates by, with any
+    // leading zero stripped. CWTs do not have these.
+    serial: Option<&'cert [u8]>,
     issuer: Name<'cert>,
     subject: Name<'cert>,
     subject_key: sig::PublicKeyParams<'cert>,
+    not_before: Option<Duration>,
+    not_after: Option<Duration>,
     basic_constraints: Option<BasicConstraints>,
-    // Currently, we drop the authority identifier on the ground, but we may
-    // want to communicate it for verification.
-
-    // For now, we drop all key usage bits except for keyCertSign on the
-    // ground. In the future, will want to retain more usage bits as
-    // necessary.
-    is_cert_sign: bool,
+    subject_key_id: Option<&'cert [u8]>,
+    authority_key_id: Option<&'cert [u8]>,
+    key_usage: Option<KeyUsage>,
+    // Only the attestation-related purposes are retained; see
+    // `ExtendedKeyUsage`.
+    ext_key_usage: Option<ExtendedKeyUsage>,
+    measurements: Option<Measurements<'cert>>,
 }
 
+// Certificates must not need drop glue: otherwise, the buffer a `Cert` was
+// parsed from stays borrowed until the `Cert` goes out of scope, rather than
+// until its last use.
+static_assertions::const_assert!(!core::mem::needs_drop::<Cert>());
+
 /// X.509-specific `basicConstraints` extension.
 #[derive(Debug)]
 struct BasicConstraints {
@@ -79,6 +117,9 @@ pub enum Error {
     BadEncoding,
     /// Indicates that a low-level I/O error occured while parsing a cert.
     Io(io::Error),
+    /// Indicates that an error occured while reading or writing a cert to a
+    /// [`flash`] device.
+    Flash(flash::Error),
     /// An algorithm specified in a certificate was not known to Manticore.
     UnknownAlgorithm,
     /// The signature algorithm in a certificate did not match the key
@@ -90,6 +131,25 @@ pub enum Error {
     BadChainLink,
     /// A certificate chain was longer than it was expected to be.
     ChainTooLong,
+    /// A trust chain slot was out of range.
+    BadSlot,
+    /// A certificate's validity period ended before the current time, or a
+    /// CRL's next update was due before the current time.
+    Expired,
+    /// A certificate's validity period starts after the current time.
+    NotYetValid,
+    /// A certificate in a certificate chain was revoked by its issuer.
+    Revoked,
+    /// A certificate's key usage did not permit the operation it was being
+    /// used for.
+    WrongKeyUsage,
+    /// Indicates that an arena ran out of memory while encoding a
+    /// certificate-related structure.
+    OutOfMemory,
+    /// Indicates that a signing operation failed for some reason.
+    SigError(sig::Error),
+    /// Indicates that a hashing operation failed for some reason.
+    ShaError(sha256::Error),
 }
 
 impl From<io::Error> for Error {
@@ -98,21 +158,82 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<flash::Error> for Error {
+    fn from(e: flash::Error) -> Self {
+        Self::Flash(e)
+    }
+}
+
+impl From<OutOfMemory> for Error {
+    fn from(_: OutOfMemory) -> Self {
+        Self::OutOfMemory
+    }
+}
+
+impl From<sig::Error> for Error {
+    fn from(e: sig::Error) -> Self {
+        Self::SigError(e)
+    }
+}
+
+impl From<sha256::Error> for Error {
+    fn from(e: sha256::Error) -> Self {
+        Self::ShaError(e)
+    }
+}
+
 impl From<untrusted::EndOfInput> for Error {
     fn from(_: untrusted::EndOfInput) -> Self {
         Self::Io(io::Error::BufferExhausted)
     }
 }
 
+impl From<Error> for protocol::Error {
+    fn from(e: Error) -> Self {
+        use protocol::ErrorCode;
+        use protocol::ErrorSubcode;
+        match e {
+            Error::UnsupportedSig | Error::UnknownAlgorithm => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::Unsupported)
+            }
+            Error::BadSignature => {
+                Self::new(ErrorCode::Authentication, ErrorSubcode::BadSignature)
+            }
+            Error::Flash(_) => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::Internal)
+            }
+            Error::BadSlot => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::BadSlot)
+            }
+            Error::OutOfMemory => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::OutOfMemory)
+            }
+            Error::SigError(e) => e.into(),
+            Error::ShaError(e) => e.into(),
+            Error::BadEncoding
+            | Error::Io(_)
+            | Error::WrongAlgorithm
+            | Error::BadChainLink
+            | Error::ChainTooLong
+            | Error::Expired
+            | Error::NotYetValid
+            | Error::Revoked
+            | Error::WrongKeyUsage => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::BadCert)
+            }
+        }
+    }
+}
+
 impl<'cert> Cert<'cert> {
     /// Parses `cert`, producing a parsed certificate in the given format.
     ///
     /// If `key` (the key to verify the certificate with) is not provided, then
     /// the certificate is assumed to be self signed, and will be verified
     /// against its own subject key.
-    // TODO: Perhaps `cert` should be a Flash + Arena, instead?
-    // It may also be useful to use this as an opportunity to perform a SHA
-    // hash, for use in the DIGESTs series of messages.
+    ///
+    /// To parse a certificate directly out of flash, see
+    /// [`FlashCert::load()`].
     pub fn parse(
         cert: &'cert [u8],
         format: CertFormat,
@@ -135,6 +256,14 @@ impl<'cert> Cert<'cert> {
         self.format
     }
 
+    /// Returns this certificate's serial number, as a big-endian unsigned
+    /// integer with no leading zeroes.
+    ///
+    /// Returns `None` if the format does not have serial numbers (e.g. CWT).
+    pub fn serial(&self) -> Option<&'cert [u8]> {
+        self.serial
+    }
+
     /// Returns the name of the certificate issuer (i.e., the subject of the
     /// certificate that signed it).
     pub fn issuer(&self) -> Name<'cert> {
@@ -151,13 +280,111 @@ impl<'cert> Cert<'cert> {
         &self.subject_key
     }
 
+    /// Returns the identifier of this certificate's subject key, from the
+    /// X.509 `subjectKeyIdentifier` extension.
+    ///
+    /// Returns `None` if the certificate does not specify one.
+    pub fn subject_key_id(&self) -> Option<&'cert [u8]> {
+        self.subject_key_id
+    }
+
+    /// Returns the identifier of the key that signed this certificate, from
+    /// the `keyIdentifier` field of the X.509 `authorityKeyIdentifier`
+    /// extension.
+    ///
+    /// Returns `None` if the certificate does not specify one.
+    pub fn authority_key_id(&self) -> Option<&'cert [u8]> {
+        self.authority_key_id
+    }
+
+    /// Returns the start of this certificate's validity period, measured from
+    /// the Unix epoch.
+    ///
+    /// Returns `None` if the certificate does not specify one.
+    pub fn not_before(&self) -> Option<Duration> {
+        self.not_before
+    }
+
+    /// Returns the end of this certificate's validity period, measured from
+    /// the Unix epoch.
+    ///
+    /// Returns `None` if the certificate does not specify one, or explicitly
+    /// specifies that it has no well-defined expiration date.
+    pub fn not_after(&self) -> Option<Duration> {
+        self.not_after
+    }
+
+    /// Checks whether this certificate is valid at `now`, measured from the
+    /// Unix epoch.
+    ///
+    /// Both ends of the validity period are inclusive.
+    pub fn check_validity(&self, now: Duration) -> Result<(), Error> {
+        if self.not_before.map(|t| now < t).unwrap_or(false) {
+            return Err(Error::NotYetValid);
+        }
+        if self.not_after.map(|t| now > t).unwrap_or(false) {
+            return Err(Error::Expired);
+        }
+        Ok(())
+    }
+
+    /// Returns the DICE firmware measurements included in this certificate,
+    /// if any.
+    ///
+    /// For X.509 certificates, these come from the TCG DICE `TcbInfo`
+    /// extension; for CWTs, from the OpenDICE code, configuration, authority
+    /// and mode claims.
+    pub fn measurements(&self) -> Option<&Measurements<'cert>> {
+        self.measurements.as_ref()
+    }
+
     /// Whether this certificate's public key can be used to sign other
     /// certificates.
     ///
     /// As a matter of domain separation, certificates that can be used for
     /// this purpose should not be used for anything else.
     pub fn supports_cert_signing(&self) -> bool {
-        self.is_cert_sign
+        self.key_usage
+            .map(|ku| ku.contains(KeyUsage::KEY_CERT_SIGN))
+            .unwrap_or(false)
+    }
+
+    /// Whether this certificate's public key can be used to sign certificate
+    /// revocation lists.
+    ///
+    /// This is only ever true for X.509 certificates that can also sign
+    /// certificates; see [`Crl`].
+    pub fn supports_crl_signing(&self) -> bool {
+        let ca_usage = KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN;
+        self.key_usage
+            .map(|ku| ku.contains(ca_usage))
+            .unwrap_or(false)
+    }
+
+    /// Whether this certificate's public key can be used for attestation,
+    /// i.e., as the leaf of a trust chain used to sign challenge responses.
+    ///
+    /// This is the case unless the certificate has an extended key usage
+    /// which does not include `anyExtendedKeyUsage` or one of the TCG DICE
+    /// attestation purposes.
+    pub fn supports_attestation(&self) -> bool {
+        self.ext_key_usage
+            .map(ExtendedKeyUsage::allows_attestation)
+            .unwrap_or(true)
+    }
+
+    /// Returns the key usage of this certificate, if it specifies one.
+    pub fn key_usage(&self) -> Option<KeyUsage> {
+        self.key_usage
+    }
+
+    /// Returns the extended key usage of this certificate, if it specifies
+    /// one.
+    ///
+    /// Only purposes relevant to attestation are retained, so this may be
+    /// empty even if the certificate names some purposes.
+    pub fn extended_key_usage(&self) -> Option<ExtendedKeyUsage> {
+        self.ext_key_usage
     }
 
     /// Returns whether this certificate is *explicitly* a CA (i.e., not leaf)
@@ -194,8 +421,27 @@ impl<'cert> Cert<'cert> {
 
 /// A name associated with a certificate.
 ///
-/// Names may only be printed (for debugging purposes) or compared
-/// byte-for-byte. Manticore does not support X.500 distinguished name
-/// comparisons.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Name<'cert>(&'cert [u8]);
+/// X.509 names are distinguished names, which are compared as described in
+/// RFC 5280, Section 7.1: string attributes match regardless of case,
+/// insignificant spaces, or which string type they were encoded as. CWT
+/// names are plain strings, and are compared byte-for-byte. Names of
+/// different formats never match.
+#[derive(Copy, Clone, Debug)]
+pub struct Name<'cert> {
+    raw: &'cert [u8],
+    format: CertFormat,
+}
+
+impl PartialEq for Name<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.format != other.format {
+            return false;
+        }
+        match self.format {
+            CertFormat::RiotX509 => x509::dn_eq(self.raw, other.raw),
+            CertFormat::OpenDiceCwt => self.raw == other.raw,
+        }
+    }
+}
+
+impl Eq for Name<'_> {}
@@ -0,0 +1,110 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Key usage restrictions.
+//!
+//! X.509 certificates restrict what their subject key may be used for with
+//! the `keyUsage` and `extKeyUsage` extensions; OpenDICE CWTs carry a key
+//! usage claim with the same encoding as the former.
+
+use bitflags::bitflags;
+
+use crate::cert::Error;
+
+bitflags! {
+    /// The operations a certificate's subject key may be used for.
+    ///
+    /// These correspond to the bits of the RFC5280 `KeyUsage` type, in the
+    /// same order.
+    pub struct KeyUsage: u16 {
+        #[allow(missing_docs)]
+        const DIGITAL_SIGNATURE = 1 << 0;
+        #[allow(missing_docs)]
+        const NON_REPUDIATION = 1 << 1;
+        #[allow(missing_docs)]
+        const KEY_ENCIPHERMENT = 1 << 2;
+        #[allow(missing_docs)]
+        const DATA_ENCIPHERMENT = 1 << 3;
+        #[allow(missing_docs)]
+        const KEY_AGREEMENT = 1 << 4;
+        #[allow(missing_docs)]
+        const KEY_CERT_SIGN = 1 << 5;
+        #[allow(missing_docs)]
+        const CRL_SIGN = 1 << 6;
+        #[allow(missing_docs)]
+        const ENCIPHER_ONLY = 1 << 7;
+        #[allow(missing_docs)]
+        const DECIPHER_ONLY = 1 << 8;
+    }
+}
+
+impl KeyUsage {
+    /// Parses an X.509 `KeyUsage` `BIT STRING`, in which bit zero is the
+    /// most significant bit of the first byte.
+    pub(crate) fn from_be(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes(bytes, true)
+    }
+
+    /// Parses an OpenDICE key usage claim, in which bit zero is the least
+    /// significant bit of the first byte.
+    pub(crate) fn from_le(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes(bytes, false)
+    }
+
+    fn from_bytes(bytes: &[u8], is_be: bool) -> Result<Self, Error> {
+        let b1 = *bytes.get(0).unwrap_or(&0);
+        let b2 = *bytes.get(1).unwrap_or(&0);
+        let val = if is_be {
+            // Note that ASN.1 BIT STRINGs are actually little-endian bytes;
+            // it is merely the bits within the bytes that are big endian!
+            u16::from_le_bytes([b1.reverse_bits(), b2.reverse_bits()])
+        } else {
+            u16::from_le_bytes([b1, b2])
+        };
+
+        // For domain separation reasons, we reject all certificates that mix
+        // certificate signing with any other usage, except for signing CRLs.
+        // This is checked before undefined bits are dropped on the floor.
+        let ca_usage = (Self::KEY_CERT_SIGN | Self::CRL_SIGN).bits();
+        if val & Self::KEY_CERT_SIGN.bits() != 0 && val & !ca_usage != 0 {
+            return Err(Error::BadEncoding);
+        }
+
+        Ok(Self::from_bits_truncate(val))
+    }
+}
+
+bitflags! {
+    /// The purposes a certificate's subject key may be used for.
+    ///
+    /// These correspond to RFC5280 `KeyPurposeId`s. Only the purposes that
+    /// are relevant to attestation, defined by the TCG DICE Attestation
+    /// Architecture, are retained; all others are ignored.
+    pub struct ExtendedKeyUsage: u8 {
+        /// Any purpose, i.e., `anyExtendedKeyUsage`.
+        const ANY = 1 << 0;
+        /// Initial identity, i.e., `tcg-dice-kp-identityInit`.
+        const IDENTITY_INIT = 1 << 1;
+        /// Local identity, i.e., `tcg-dice-kp-identityLoc`.
+        const IDENTITY_LOC = 1 << 2;
+        /// Initial attestation, i.e., `tcg-dice-kp-attestInit`.
+        const ATTEST_INIT = 1 << 3;
+        /// Local attestation, i.e., `tcg-dice-kp-attestLoc`.
+        const ATTEST_LOC = 1 << 4;
+        /// Initial assertion, i.e., `tcg-dice-kp-assertInit`.
+        const ASSERT_INIT = 1 << 5;
+        /// Local assertion, i.e., `tcg-dice-kp-assertLoc`.
+        const ASSERT_LOC = 1 << 6;
+        /// Embedded certificate authority, i.e., `tcg-dice-kp-eca`.
+        const ECA = 1 << 7;
+    }
+}
+
+impl ExtendedKeyUsage {
+    /// Returns whether these purposes include attestation, i.e., signing
+    /// Cerberus challenge responses.
+    pub fn allows_attestation(self) -> bool {
+        self.intersects(Self::ANY | Self::ATTEST_INIT | Self::ATTEST_LOC)
+    }
+}
@@ -0,0 +1,376 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! X.509 certificate construction.
+//!
+//! See RFC 5280 for the certificate format, and the TCG DICE Attestation
+//! Architecture for the `TcbInfo` extension.
+
+use core::time::Duration;
+
+use crate::cert::encode;
+use crate::cert::encode::Contents;
+use crate::cert::encode::Sink;
+use crate::cert::x509;
+use crate::cert::x509::der;
+use crate::cert::x509::der::Oid;
+use crate::cert::x509::der::Tag;
+use crate::cert::x509::oid;
+use crate::cert::x509::SigAlgo;
+use crate::cert::Error;
+use crate::cert::ExtendedKeyUsage;
+use crate::cert::FwidAlgo;
+use crate::cert::Measurements;
+use crate::cert::Name;
+use crate::crypto::sig;
+use crate::io;
+use crate::io::Write as _;
+use crate::mem::Arena;
+
+/// The largest serial number permitted by RFC 5280, in bytes.
+const MAX_SERIAL_LEN: usize = 20;
+
+impl<'a> Name<'a> {
+    /// Encodes an X.509 name consisting only of a `commonName`, into
+    /// `arena`.
+    ///
+    /// This is the kind of name RIoT uses for its certificates.
+    pub fn x509_common_name(
+        cn: &str,
+        arena: &'a dyn Arena,
+    ) -> Result<Self, Error> {
+        // RDNSequence ::= SEQUENCE OF RelativeDistinguishedName
+        // RelativeDistinguishedName ::= SET OF AttributeTypeAndValue
+        // AttributeTypeAndValue ::= SEQUENCE {
+        //   type  OBJECT IDENTIFIER,
+        //   value ANY DEFINED BY type
+        // }
+        //
+        // `Name` holds the contents of the outer `SEQUENCE`.
+        let name = encode::to_arena(
+            &|w| {
+                der::write(w, Tag::SET, &|w| {
+                    der::write(w, Tag::SEQUENCE, &|w| {
+                        der::write_oid(w, &oid::COMMON_NAME)?;
+                        der::write_bytes(w, Tag::UTF8_STRING, cn.as_bytes())
+                    })
+                })
+            },
+            arena,
+        )?;
+        Ok(Name::from_der(name))
+    }
+}
+
+/// A builder for RIoT-profile X.509 certificates.
+///
+/// Certificates produced by this builder always carry a `keyUsage`
+/// extension: CA certificates may only sign certificates and CRLs, and all
+/// other certificates may only produce digital signatures. CA certificates also
+/// carry a `basicConstraints` extension, and any [`Measurements`] are
+/// encoded as a TCG DICE `TcbInfo` extension. Key identifiers and extended
+/// key usages are only included if requested.
+///
+/// By default, certificates are valid from the Unix epoch onwards, with no
+/// well-defined expiration date.
+#[derive(Clone, Debug)]
+pub struct CertBuilder<'a> {
+    serial: &'a [u8],
+    issuer: Name<'a>,
+    subject: Name<'a>,
+    subject_key: &'a sig::PublicKeyParams<'a>,
+    not_before: Option<Duration>,
+    not_after: Option<Duration>,
+    is_ca: bool,
+    path_len_constraint: Option<u32>,
+    subject_key_id: Option<&'a [u8]>,
+    authority_key_id: Option<&'a [u8]>,
+    ext_key_usage: Option<ExtendedKeyUsage>,
+    measurements: Option<&'a Measurements<'a>>,
+}
+
+impl<'a> CertBuilder<'a> {
+    /// Creates a new builder for a certificate in which `issuer` certifies
+    /// that `subject_key` belongs to `subject`.
+    ///
+    /// `serial` is a big-endian, unsigned integer. `issuer` and `subject`
+    /// must be X.509 names, such as those of other X.509 certificates.
+    pub fn new(
+        serial: &'a [u8],
+        issuer: Name<'a>,
+        subject: Name<'a>,
+        subject_key: &'a sig::PublicKeyParams<'a>,
+    ) -> Self {
+        Self {
+            serial,
+            issuer,
+            subject,
+            subject_key,
+            not_before: Some(Duration::from_secs(0)),
+            not_after: None,
+            is_ca: false,
+            path_len_constraint: None,
+            subject_key_id: None,
+            authority_key_id: None,
+            ext_key_usage: None,
+            measurements: None,
+        }
+    }
+
+    /// Sets the validity period of the certificate, as times since the Unix
+    /// epoch.
+    ///
+    /// `None` is encoded as `99991231235959Z`, i.e., "no well-defined
+    /// expiration date".
+    pub fn validity(
+        mut self,
+        not_before: Option<Duration>,
+        not_after: Option<Duration>,
+    ) -> Self {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    /// Makes the certificate a CA certificate, optionally limiting the number
+    /// of intermediate certificates that may follow it in a chain.
+    pub fn ca(mut self, path_len_constraint: Option<u32>) -> Self {
+        self.is_ca = true;
+        self.path_len_constraint = path_len_constraint;
+        self
+    }
+
+    /// Includes `subjectKeyIdentifier` and `authorityKeyIdentifier`
+    /// extensions, identifying `subject_key` and the issuer's key,
+    /// respectively.
+    ///
+    /// The latter should match the former in the issuer's own certificate.
+    pub fn key_identifiers(
+        mut self,
+        subject: Option<&'a [u8]>,
+        authority: Option<&'a [u8]>,
+    ) -> Self {
+        self.subject_key_id = subject;
+        self.authority_key_id = authority;
+        self
+    }
+
+    /// Restricts the purposes `subject_key` may be used for to `eku`, with
+    /// an `extKeyUsage` extension.
+    ///
+    /// `eku` must not be empty.
+    pub fn extended_key_usage(mut self, eku: ExtendedKeyUsage) -> Self {
+        self.ext_key_usage = Some(eku);
+        self
+    }
+
+    /// Includes `measurements` of the firmware that `subject_key` belongs
+    /// to.
+    ///
+    /// Every FWID must specify an algorithm.
+    pub fn measurements(mut self, measurements: &'a Measurements<'a>) -> Self {
+        self.measurements = Some(measurements);
+        self
+    }
+
+    /// Signs the certificate with `signer`, returning the complete,
+    /// DER-encoded certificate.
+    ///
+    /// `issuer_key` selects the signature algorithm, and must be the public
+    /// half of `signer`'s key. `signer` must produce signatures in the
+    /// encoding X.509 uses for that algorithm (e.g., ASN.1 DER for ECDSA).
+    pub fn sign<'out>(
+        &self,
+        issuer_key: &sig::PublicKeyParams,
+        signer: &mut dyn sig::Sign,
+        arena: &'out dyn Arena,
+    ) -> Result<&'out [u8], Error> {
+        let algo = SigAlgo::for_signing_key(issuer_key)?;
+        // We can only encode keys that we could also sign with.
+        SigAlgo::for_signing_key(self.subject_key)?;
+
+        // The serial must be positive, and its encoding, including any
+        // leading zero needed to clear the sign bit, must fit in 20 bytes.
+        let skip = self.serial.iter().take_while(|&&b| b == 0).count();
+        let serial = &self.serial[skip..];
+        let encoded_len = match serial.first() {
+            None => return Err(Error::BadEncoding),
+            Some(&b) => serial.len() + (b >= 0x80) as usize,
+        };
+        if encoded_len > MAX_SERIAL_LEN {
+            return Err(Error::BadEncoding);
+        }
+
+        if let Some(m) = self.measurements {
+            if m.fwids().iter().any(|f| f.algo.is_none()) {
+                return Err(Error::UnknownAlgorithm);
+            }
+        }
+        if self.ext_key_usage.map(|e| e.is_empty()).unwrap_or(false) {
+            return Err(Error::BadEncoding);
+        }
+
+        // TBSCertificate ::= SEQUENCE {
+        //   version         [0] EXPLICIT Version DEFAULT v1,
+        //   serialNumber    CertificateSerialNumber,
+        //   signature       AlgorithmIdentifier,
+        //   issuer          Name,
+        //   validity        Validity,
+        //   subject         Name,
+        //   subjectPKInfo   SubjectPublicKeyInfo,
+        //   extensions      [3] EXPLICIT Extensions OPTIONAL
+        // }
+        let tbs = encode::to_arena(
+            &|w| {
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    // `v3` certificates are encoded as an `INTEGER { 2 }`.
+                    der::write(w, Tag::context_specific(0), &|w| {
+                        der::write_u32(w, 2)
+                    })?;
+                    der::write_uint(w, self.serial)?;
+                    x509::write_algo(w, algo)?;
+                    der::write(w, Tag::SEQUENCE, &|w| {
+                        w.write_bytes(self.issuer.raw)
+                    })?;
+                    der::write(w, Tag::SEQUENCE, &|w| {
+                        der::write_time(w, self.not_before)?;
+                        der::write_time(w, self.not_after)
+                    })?;
+                    der::write(w, Tag::SEQUENCE, &|w| {
+                        w.write_bytes(self.subject.raw)
+                    })?;
+                    x509::write_spki(w, self.subject_key)?;
+                    der::write(w, Tag::context_specific(3), &|w| {
+                        der::write(w, Tag::SEQUENCE, &|w| self.write_extns(w))
+                    })
+                })
+            },
+            arena,
+        )?;
+
+        x509::sign(tbs, algo, signer, arena)
+    }
+
+    /// Writes out the contents of the `Extensions` sequence.
+    fn write_extns(&self, w: &mut Sink) -> Result<(), io::Error> {
+        if self.is_ca {
+            // BasicConstraints ::= SEQUENCE {
+            //   cA                BOOLEAN DEFAULT FALSE,
+            //   pathLenConstraint INTEGER (0..MAX) OPTIONAL
+            // }
+            write_extn(w, &oid::BASIC_CONSTRAINTS, true, &|w| {
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    der::write_bool(w, true)?;
+                    match self.path_len_constraint {
+                        Some(len) => der::write_u32(w, len),
+                        None => Ok(()),
+                    }
+                })
+            })?;
+        }
+
+        // See `KeyUsage` for the bit assignments: keyCertSign is bit 5,
+        // cRLSign is bit 6, and digitalSignature is bit 0.
+        let key_usage = if self.is_ca { 0x06 } else { 0x80 };
+        write_extn(w, &oid::KEY_USAGE, true, &|w| {
+            der::write_named_bits(w, Tag::BIT_STRING, &[key_usage])
+        })?;
+
+        if let Some(eku) = self.ext_key_usage {
+            write_extn(w, &oid::EXT_KEY_USAGE, false, &|w| {
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    for (purpose, purpose_oid) in x509::EXT_KEY_USAGES {
+                        if eku.contains(*purpose) {
+                            der::write_oid(w, purpose_oid)?;
+                        }
+                    }
+                    Ok(())
+                })
+            })?;
+        }
+
+        if let Some(id) = self.subject_key_id {
+            write_extn(w, &oid::SUBJECT_KEY_ID, false, &|w| {
+                der::write_bytes(w, Tag::OCTET_STRING, id)
+            })?;
+        }
+
+        if let Some(id) = self.authority_key_id {
+            write_extn(w, &oid::AUTHORITY_KEY_ID, false, &|w| {
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    let key_id = Tag::context_specific_primitive(0);
+                    der::write_bytes(w, key_id, id)
+                })
+            })?;
+        }
+
+        if let Some(m) = self.measurements {
+            write_extn(w, &oid::TCG_DICE_TCB_INFO, true, &|w| {
+                write_tcb_info(w, m)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a single `Extension`, whose `extnValue` is produced by `contents`.
+fn write_extn(
+    w: &mut Sink,
+    oid: &Oid,
+    is_critical: bool,
+    contents: Contents,
+) -> Result<(), io::Error> {
+    der::write(w, Tag::SEQUENCE, &|w| {
+        der::write_oid(w, oid)?;
+        // `critical` is `DEFAULT FALSE`, so DER requires that it be omitted
+        // when false.
+        if is_critical {
+            der::write_bool(w, true)?;
+        }
+        der::write(w, Tag::OCTET_STRING, contents)
+    })
+}
+
+/// Writes a TCG DICE `TcbInfo` extension; see `parse_tcb_info()` for the
+/// ASN.1 definition.
+fn write_tcb_info(w: &mut Sink, m: &Measurements) -> Result<(), io::Error> {
+    der::write(w, Tag::SEQUENCE, &|w| {
+        let implicit = Tag::context_specific_primitive;
+        for (i, s) in (0..).zip(&[m.vendor, m.model, m.version]) {
+            if let Some(s) = s {
+                der::write_bytes(w, implicit(i), s.as_bytes())?;
+            }
+        }
+        for (i, n) in (3..).zip(&[m.svn, m.layer, m.index]) {
+            if let Some(n) = n {
+                der::write_implicit_u32(w, implicit(i), *n)?;
+            }
+        }
+
+        if !m.fwids().is_empty() {
+            der::write(w, Tag::context_specific(6), &|w| {
+                for fwid in m.fwids() {
+                    let algo = match fwid.algo {
+                        Some(FwidAlgo::Sha256) => &oid::SHA256,
+                        Some(FwidAlgo::Sha384) => &oid::SHA384,
+                        Some(FwidAlgo::Sha512) => &oid::SHA512,
+                        None => return Err(io::Error::Internal),
+                    };
+                    der::write(w, Tag::SEQUENCE, &|w| {
+                        der::write_oid(w, algo)?;
+                        der::write_bytes(w, Tag::OCTET_STRING, fwid.digest)
+                    })?;
+                }
+                Ok(())
+            })?;
+        }
+
+        if let Some(flags) = m.flags {
+            // Bit zero is the most significant bit of the first byte.
+            let bits = flags.bits().reverse_bits();
+            der::write_named_bits(w, implicit(7), &[bits])?;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,318 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! X.509 certificate revocation lists.
+//!
+//! See RFC 5280, Section 5.
+
+use core::time::Duration;
+
+use crate::cert::x509;
+use crate::cert::x509::der;
+use crate::cert::x509::der::Tag;
+use crate::cert::x509::oid;
+use crate::cert::x509::SigAlgo;
+use crate::cert::Cert;
+use crate::cert::Error;
+use crate::cert::Name;
+use crate::crypto::sig;
+
+/// A parsed X.509 certificate revocation list (CRL).
+///
+/// A CRL names certificates that its issuer has revoked by serial number. It
+/// is signed by the same key as the certificates it revokes, so it can only
+/// be trusted once its signature has been checked against its issuer's
+/// certificate, using [`Crl::verify()`]; chain verification does this
+/// automatically for any CRLs it is given.
+///
+/// Only a subset of RFC 5280 CRLs is supported: CRLs must be v2 if they
+/// specify a version, and must not carry any critical extensions, either for
+/// the whole list or for individual entries. In particular, indirect CRLs
+/// and delta CRLs are not supported.
+///
+/// CWTs do not have serial numbers, so they cannot be revoked.
+#[derive(Clone, Debug)]
+pub struct Crl<'crl> {
+    raw: &'crl [u8],
+    tbs: &'crl [u8],
+    sig_algo: SigAlgo,
+    sig: &'crl [u8],
+    issuer: Name<'crl>,
+    authority_key_id: Option<&'crl [u8]>,
+    this_update: Option<Duration>,
+    next_update: Option<Duration>,
+    // The contents of the `revokedCertificates` sequence, which is checked
+    // for well-formedness on parse, and walked again on every lookup.
+    revoked: &'crl [u8],
+}
+
+impl<'crl> Crl<'crl> {
+    /// Parses a DER-encoded CRL.
+    ///
+    /// This does not verify the CRL's signature.
+    pub fn parse(raw: &'crl [u8]) -> Result<Self, Error> {
+        // CertificateList ::= SEQUENCE {
+        //   tbsCertList        TBSCertList,
+        //   signatureAlgorithm AlgorithmIdentifier,
+        //   signatureValue     BIT STRING
+        // }
+        let buf = untrusted::Input::from(raw);
+        buf.read_all(Error::BadEncoding, |buf| {
+            der::tagged(Tag::SEQUENCE, buf, |buf| {
+                let mark = buf.mark();
+                let tbs = der::parse(Tag::SEQUENCE, buf)?;
+                let tbs_bytes =
+                    buf.get_input_between_marks(mark, buf.mark())?;
+                let sig_algo_bytes = der::parse(Tag::SEQUENCE, buf)?;
+                let sig_algo = sig_algo_bytes
+                    .read_all(Error::BadEncoding, x509::parse_algo)?;
+                let sig = der::bits_total(buf)?;
+
+                let fields = tbs.read_all(Error::BadEncoding, |buf| {
+                    parse_tbs(sig_algo_bytes, buf)
+                })?;
+
+                Ok(Self {
+                    raw,
+                    tbs: tbs_bytes.as_slice_less_safe(),
+                    sig_algo,
+                    sig: sig.as_slice_less_safe(),
+                    issuer: fields.issuer,
+                    authority_key_id: fields.authority_key_id,
+                    this_update: fields.this_update,
+                    next_update: fields.next_update,
+                    revoked: fields.revoked.as_slice_less_safe(),
+                })
+            })
+        })
+    }
+
+    /// Returns the slice this CRL was parsed from.
+    pub fn raw(&self) -> &'crl [u8] {
+        self.raw
+    }
+
+    /// Returns the name of the issuer of this CRL.
+    ///
+    /// This is the subject of the certificate whose key signed it.
+    pub fn issuer(&self) -> Name<'crl> {
+        self.issuer
+    }
+
+    /// Returns the key identifier from this CRL's `authorityKeyIdentifier`
+    /// extension, if it has one.
+    ///
+    /// This should match the subject key identifier of the certificate whose
+    /// key signed it.
+    pub fn authority_key_id(&self) -> Option<&'crl [u8]> {
+        self.authority_key_id
+    }
+
+    /// Returns the time at which this CRL was issued, as a duration since the
+    /// Unix epoch.
+    ///
+    /// Callers that keep track of previously seen CRLs can use this to
+    /// reject a CRL that is older than one they have already accepted.
+    pub fn this_update(&self) -> Option<Duration> {
+        self.this_update
+    }
+
+    /// Returns the time by which the next CRL will be issued, as a duration
+    /// since the Unix epoch, if specified.
+    ///
+    /// Past this time, this CRL is stale and should no longer be relied on.
+    pub fn next_update(&self) -> Option<Duration> {
+        self.next_update
+    }
+
+    /// Returns whether this CRL is stale at time `now`, i.e., whether its
+    /// `nextUpdate` has passed.
+    pub fn is_stale(&self, now: Duration) -> bool {
+        self.next_update.map(|t| now > t).unwrap_or(false)
+    }
+
+    /// Verifies that this CRL was signed by `issuer`.
+    ///
+    /// `issuer` must be an X.509 certificate whose subject is this CRL's
+    /// issuer, and which may sign CRLs; otherwise, this function returns
+    /// [`Error::BadChainLink`].
+    pub fn verify(
+        &self,
+        issuer: &Cert,
+        ciphers: &mut impl sig::Ciphers,
+    ) -> Result<(), Error> {
+        if issuer.subject() != self.issuer || !issuer.supports_crl_signing() {
+            return Err(Error::BadChainLink);
+        }
+        x509::verify(
+            self.tbs,
+            self.sig_algo,
+            self.sig,
+            issuer.subject_key(),
+            ciphers,
+        )
+    }
+
+    /// Returns whether this CRL revokes `cert`.
+    ///
+    /// This only compares issuers and serial numbers; the caller is
+    /// responsible for checking that this CRL is authentic, using
+    /// [`Crl::verify()`].
+    ///
+    /// The revoked serial numbers are re-parsed on every call; this only
+    /// fails if they are somehow malformed, which [`Crl::parse()`] would
+    /// already have caught.
+    pub fn revokes(&self, cert: &Cert) -> Result<bool, Error> {
+        let serial = match cert.serial {
+            Some(serial) => trim(serial),
+            None => return Ok(false),
+        };
+        if cert.issuer() != self.issuer {
+            return Ok(false);
+        }
+
+        untrusted::Input::from(self.revoked).read_all(
+            Error::BadEncoding,
+            |buf| {
+                let mut found = false;
+                while !buf.at_end() {
+                    found |= trim(parse_entry(buf)?) == serial;
+                }
+                Ok(found)
+            },
+        )
+    }
+}
+
+/// The fields of a `TBSCertList` that a [`Crl`] keeps.
+struct Tbs<'crl> {
+    issuer: Name<'crl>,
+    authority_key_id: Option<&'crl [u8]>,
+    this_update: Option<Duration>,
+    next_update: Option<Duration>,
+    // The contents of the `revokedCertificates` sequence.
+    revoked: untrusted::Input<'crl>,
+}
+
+/// Parses a `TBSCertList`.
+fn parse_tbs<'crl>(
+    sig_algo_bytes: untrusted::Input,
+    buf: &mut untrusted::Reader<'crl>,
+) -> Result<Tbs<'crl>, Error> {
+    // TBSCertList ::= SEQUENCE {
+    //   version             Version OPTIONAL, -- if present, MUST be v2
+    //   signature           AlgorithmIdentifier,
+    //   issuer              Name,
+    //   thisUpdate          Time,
+    //   nextUpdate          Time OPTIONAL,
+    //   revokedCertificates SEQUENCE OF SEQUENCE { ... } OPTIONAL,
+    //   crlExtensions       [0] EXPLICIT Extensions OPTIONAL
+    // }
+    //
+    // `v2` CRLs are encoded as an `INTEGER { 1 }`.
+    if let Some(version) = der::opt(Tag::INTEGER, buf)? {
+        if version.as_slice_less_safe() != [1] {
+            return Err(Error::BadEncoding);
+        }
+    }
+
+    // As with certificates, the inner and outer signature algorithm
+    // identifiers must match byte-for-byte.
+    if der::parse(Tag::SEQUENCE, buf)? != sig_algo_bytes {
+        return Err(Error::BadEncoding);
+    }
+
+    let issuer =
+        Name::from_der(der::parse(Tag::SEQUENCE, buf)?.as_slice_less_safe());
+
+    let this_update = der::time(buf)?;
+    let next_update = if der::peek_time(buf) {
+        der::time(buf)?
+    } else {
+        None
+    };
+
+    let revoked = der::opt(Tag::SEQUENCE, buf)?
+        .unwrap_or_else(|| untrusted::Input::from(&[][..]));
+    revoked.read_all(Error::BadEncoding, |buf| {
+        while !buf.at_end() {
+            parse_entry(buf)?;
+        }
+        Ok(())
+    })?;
+
+    let authority_key_id = match der::opt(Tag::context_specific(0), buf)? {
+        Some(extns) => extns.read_all(Error::BadEncoding, parse_extns)?,
+        None => None,
+    };
+
+    Ok(Tbs {
+        issuer,
+        authority_key_id,
+        this_update,
+        next_update,
+        revoked,
+    })
+}
+
+/// Parses a single entry of `revokedCertificates`, returning the serial
+/// number.
+fn parse_entry<'crl>(
+    buf: &mut untrusted::Reader<'crl>,
+) -> Result<&'crl [u8], Error> {
+    // SEQUENCE {
+    //   userCertificate    CertificateSerialNumber,
+    //   revocationDate     Time,
+    //   crlEntryExtensions Extensions OPTIONAL
+    // }
+    der::tagged(Tag::SEQUENCE, buf, |buf| {
+        let serial = der::uint(buf)?.as_slice_less_safe();
+        let _revocation_date = der::time(buf)?;
+        if !buf.at_end() {
+            let _ = parse_extns(buf)?;
+        }
+        Ok(serial)
+    })
+}
+
+/// Parses an `Extensions` sequence, rejecting any critical extensions.
+///
+/// The only extension understood is `authorityKeyIdentifier`, whose key
+/// identifier is returned, if present.
+fn parse_extns<'crl>(
+    buf: &mut untrusted::Reader<'crl>,
+) -> Result<Option<&'crl [u8]>, Error> {
+    der::tagged(Tag::SEQUENCE, buf, |buf| {
+        let mut authority_key_id = None;
+        while !buf.at_end() {
+            der::tagged(Tag::SEQUENCE, buf, |buf| {
+                let oid = der::oid(buf)?;
+                if der::opt_bool(buf)?.unwrap_or(false) {
+                    return Err(Error::BadEncoding);
+                }
+                der::tagged(Tag::OCTET_STRING, buf, |buf| match oid {
+                    oid::AUTHORITY_KEY_ID => {
+                        if authority_key_id.is_some() {
+                            return Err(Error::BadEncoding);
+                        }
+                        authority_key_id =
+                            Some(x509::parse_authority_key_id(buf)?);
+                        Ok(())
+                    }
+                    _ => {
+                        let _ = buf.read_bytes_to_end();
+                        Ok(())
+                    }
+                })
+            })?;
+        }
+        Ok(authority_key_id.flatten())
+    })
+}
+
+/// Strips the leading zeroes from a big-endian integer.
+fn trim(int: &[u8]) -> &[u8] {
+    let zeroes = int.iter().take_while(|&&b| b == 0).count();
+    &int[zeroes..]
+}
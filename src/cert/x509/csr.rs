@@ -0,0 +1,161 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! PKCS#10 certificate signing requests.
+//!
+//! See RFC 2986.
+
+use crate::cert::encode;
+use crate::cert::encode::Contents;
+use crate::cert::x509;
+use crate::cert::x509::der;
+use crate::cert::x509::der::Tag;
+use crate::cert::x509::SigAlgo;
+use crate::cert::Error;
+use crate::cert::Name;
+use crate::crypto::sig;
+use crate::io::Write as _;
+use crate::mem::Arena;
+
+/// A certificate signing request that has yet to be signed.
+///
+/// Building a CSR is split into two steps, so that the key being certified
+/// need not be borrowed while its signer is in use. This is necessary when
+/// both come from the same [`TrustChain`](crate::cert::TrustChain).
+#[derive(Copy, Clone, Debug)]
+pub struct UnsignedCsr<'a> {
+    info: &'a [u8],
+    algo: SigAlgo,
+}
+
+impl<'a> UnsignedCsr<'a> {
+    /// Encodes a `CertificationRequestInfo` for `key`, naming `subject` as
+    /// its owner, into `arena`.
+    ///
+    /// `subject` must have come from an X.509 certificate.
+    pub fn new(
+        subject: Name,
+        key: &sig::PublicKeyParams,
+        arena: &'a dyn Arena,
+    ) -> Result<Self, Error> {
+        let algo = SigAlgo::for_signing_key(key)?;
+
+        // CertificationRequestInfo ::= SEQUENCE {
+        //   version       INTEGER { v1(0) },
+        //   subject       Name,
+        //   subjectPKInfo SubjectPublicKeyInfo,
+        //   attributes    [0] Attributes
+        // }
+        let info: Contents = &|w| {
+            der::write(w, Tag::SEQUENCE, &|w| {
+                der::write_uint(w, &[0])?;
+                der::write(w, Tag::SEQUENCE, &|w| w.write_bytes(subject.raw))?;
+                x509::write_spki(w, key)?;
+                der::write(w, Tag::context_specific(0), &|_| Ok(()))
+            })
+        };
+
+        let info = encode::to_arena(info, arena)?;
+        Ok(Self { info, algo })
+    }
+
+    /// Returns the encoded `CertificationRequestInfo`, i.e., the bytes that
+    /// will be signed.
+    pub fn info(&self) -> &'a [u8] {
+        self.info
+    }
+
+    /// Signs this request with `signer`, returning the complete, DER-encoded
+    /// `CertificationRequest`.
+    ///
+    /// `signer` must use the key this request was built for, and must produce
+    /// signatures in the encoding X.509 uses for the corresponding algorithm
+    /// (e.g., ASN.1 DER for ECDSA).
+    pub fn sign(
+        self,
+        signer: &mut dyn sig::Sign,
+        arena: &'a dyn Arena,
+    ) -> Result<&'a [u8], Error> {
+        // CertificationRequest ::= SEQUENCE {
+        //   certificationRequestInfo CertificationRequestInfo,
+        //   signatureAlgorithm       AlgorithmIdentifier,
+        //   signature                BIT STRING
+        // }
+        x509::sign(self.info, self.algo, signer, arena)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testutil::data::keys;
+    use testutil::data::x509;
+
+    use crate::cert::x509::oid;
+    use crate::cert::Cert;
+    use crate::cert::CertFormat;
+    use crate::crypto::ring;
+    use crate::crypto::sig::Verify as _;
+    use crate::mem::BumpArena;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn rsa_csr() {
+        let cert = Cert::parse(
+            x509::CHAIN1,
+            CertFormat::RiotX509,
+            None,
+            &mut ring::sig::Ciphers::new(),
+        )
+        .unwrap();
+        let (mut verifier, mut signer) =
+            ring::rsa::from_keypair(keys::KEY1_RSA_KEYPAIR);
+
+        let mut arena = [0; 2048];
+        let arena = BumpArena::new(&mut arena);
+        let csr = UnsignedCsr::new(cert.subject(), cert.subject_key(), &arena)
+            .unwrap();
+        let info = csr.info();
+        let csr = csr.sign(&mut signer, &arena).unwrap();
+
+        untrusted::Input::from(csr)
+            .read_all(Error::BadEncoding, |buf| {
+                der::tagged(Tag::SEQUENCE, buf, |buf| {
+                    der::parse(Tag::SEQUENCE, buf)?;
+                    der::tagged(Tag::SEQUENCE, buf, |buf| {
+                        assert_eq!(der::oid(buf)?, oid::RSA_PKCS1_SHA256);
+                        der::null(buf)
+                    })?;
+                    let sig = der::bits_total(buf)?.as_slice_less_safe();
+                    verifier.verify(&[info], sig).unwrap();
+                    Ok(())
+                })
+            })
+            .unwrap();
+
+        // Reparse the info to check that the key and subject round-trip.
+        untrusted::Input::from(info)
+            .read_all(Error::BadEncoding, |buf| {
+                der::tagged(Tag::SEQUENCE, buf, |buf| {
+                    assert_eq!(der::u32(buf)?, 0);
+                    let subject = der::parse(Tag::SEQUENCE, buf)?;
+                    assert_eq!(
+                        Name::from_der(subject.as_slice_less_safe()),
+                        cert.subject()
+                    );
+                    der::tagged(Tag::SEQUENCE, buf, |buf| {
+                        der::tagged(Tag::SEQUENCE, buf, |buf| {
+                            assert_eq!(der::oid(buf)?, oid::RSA_ENCRYPTION);
+                            der::null(buf)
+                        })?;
+                        der::bits_total(buf)?;
+                        Ok(())
+                    })?;
+                    der::parse(Tag::context_specific(0), buf)?;
+                    Ok(())
+                })
+            })
+            .unwrap();
+    }
+}
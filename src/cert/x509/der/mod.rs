@@ -2,7 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-//! DER parsing.
+//! DER parsing and encoding.
 //!
 //! Based on BoringSSL's DER parser. See
 //! https://boringssl.googlesource.com/boringssl/+/refs/heads/master/crypto/bytestring/cbs.c
@@ -15,10 +15,16 @@
 
 #![allow(unused)]
 
+use core::convert::TryFrom as _;
+use core::time::Duration;
+
 use crate::cert;
+use crate::cert::encode::Contents;
+use crate::cert::encode::Sink;
 use crate::cert::Error;
 use crate::io;
 use crate::io::Read as _;
+use crate::io::Write as _;
 
 #[cfg(test)]
 mod test;
@@ -65,13 +71,26 @@ impl Tag {
     pub const OCTET_STRING: Tag = Tag(0x04);
     pub const NULL: Tag = Tag(0x05);
     pub const OID: Tag = Tag(0x06);
+    pub const UTF8_STRING: Tag = Tag(0x0c);
+    pub const PRINTABLE_STRING: Tag = Tag(0x13);
+    pub const IA5_STRING: Tag = Tag(0x16);
+    pub const UTC_TIME: Tag = Tag(0x17);
+    pub const GENERALIZED_TIME: Tag = Tag(0x18);
     pub const SEQUENCE: Tag = Tag(0x30); // Constructed bit set.
+    pub const SET: Tag = Tag(0x31); // Constructed bit set.
 
     /// Returns a context-specific, constructed tag.
     #[allow(clippy::unusual_byte_groupings)]
     pub const fn context_specific(number: u8) -> Self {
         Self((number & 0b11111) | 0b10_1_00000)
     }
+
+    /// Returns a context-specific, primitive tag, such as those used for
+    /// `IMPLICIT` tagging of non-constructed types.
+    #[allow(clippy::unusual_byte_groupings)]
+    pub const fn context_specific_primitive(number: u8) -> Self {
+        Self((number & 0b11111) | 0b10_0_00000)
+    }
 }
 
 /// Parse a single element of a `SEQUENCE`, returning its tag and contents.
@@ -148,7 +167,7 @@ pub fn tagged<'cert, T>(
 pub fn bits_total<'cert>(
     buf: &mut untrusted::Reader<'cert>,
 ) -> Result<untrusted::Input<'cert>, Error> {
-    bits_checked(buf, true)
+    bits_checked(Tag::BIT_STRING, buf, true)
 }
 
 /// Parses a `BIT STRING`, ensuring that its trailing bits are zero.
@@ -156,20 +175,35 @@ pub fn bits_total<'cert>(
 pub fn bits_partial<'cert>(
     buf: &mut untrusted::Reader<'cert>,
 ) -> Result<untrusted::Input<'cert>, Error> {
-    bits_checked(buf, false)
+    bits_checked(Tag::BIT_STRING, buf, false)
+}
+
+/// Parses an optional, implicitly tagged `BIT STRING`, ensuring that its
+/// trailing bits are zero.
+pub fn opt_bits_partial<'cert>(
+    tag: Tag,
+    buf: &mut untrusted::Reader<'cert>,
+) -> Result<Option<untrusted::Input<'cert>>, Error> {
+    if !buf.peek(tag.0) {
+        return Ok(None);
+    }
+    bits_checked(tag, buf, false).map(Some)
 }
 
 fn bits_checked<'cert>(
+    tag: Tag,
     buf: &mut untrusted::Reader<'cert>,
     ensure_octets: bool,
 ) -> Result<untrusted::Input<'cert>, Error> {
-    tagged(Tag::BIT_STRING, buf, |buf| {
+    tagged(tag, buf, |buf| {
         // A bit string is lead by a bit specifying how much padding the
         // string has.
         match buf.read_byte()? {
-            // Zero is always ok.
+            // Zero is always ok. An empty bit string is only meaningful as a
+            // named bit list with no bits set, such as `write_named_bits()`
+            // produces.
             0 => {
-                if buf.at_end() {
+                if ensure_octets && buf.at_end() {
                     return Err(Error::BadEncoding);
                 }
                 Ok(buf.read_bytes_to_end())
@@ -205,9 +239,16 @@ fn bits_checked<'cert>(
 /// We reject all integers with the sign bit set.
 pub fn uint<'cert>(
     buf: &mut untrusted::Reader<'cert>,
+) -> Result<untrusted::Input<'cert>, Error> {
+    uint_tagged(Tag::INTEGER, buf)
+}
+
+fn uint_tagged<'cert>(
+    expected: Tag,
+    buf: &mut untrusted::Reader<'cert>,
 ) -> Result<untrusted::Input<'cert>, Error> {
     let (tag, data) = any(buf)?;
-    if tag != Tag::INTEGER {
+    if tag != expected {
         return Err(Error::BadEncoding);
     }
     data.read_all(Error::BadEncoding, |buf| {
@@ -239,7 +280,11 @@ pub fn uint<'cert>(
 
 /// Parses an non-negative `INTEGER`, up to four bytes.
 pub fn u32(buf: &mut untrusted::Reader) -> Result<u32, Error> {
-    uint(buf)?.read_all(Error::BadEncoding, |buf| {
+    u32_tagged(Tag::INTEGER, buf)
+}
+
+fn u32_tagged(tag: Tag, buf: &mut untrusted::Reader) -> Result<u32, Error> {
+    uint_tagged(tag, buf)?.read_all(Error::BadEncoding, |buf| {
         let mut v: u32 = 0;
         let mut octets = 0;
         while let Ok(b) = buf.read_byte() {
@@ -262,6 +307,31 @@ pub fn opt_u32(buf: &mut untrusted::Reader) -> Result<Option<u32>, Error> {
     u32(buf).map(Some)
 }
 
+/// Parses an optional, implicitly tagged, non-negative `INTEGER`, up to four
+/// bytes.
+pub fn opt_implicit_u32(
+    tag: Tag,
+    buf: &mut untrusted::Reader,
+) -> Result<Option<u32>, Error> {
+    if !buf.peek(tag.0) {
+        return Ok(None);
+    }
+    u32_tagged(tag, buf).map(Some)
+}
+
+/// Parses an optional, implicitly tagged `UTF8String`.
+pub fn opt_implicit_utf8<'cert>(
+    tag: Tag,
+    buf: &mut untrusted::Reader<'cert>,
+) -> Result<Option<&'cert str>, Error> {
+    opt(tag, buf)?
+        .map(|s| {
+            core::str::from_utf8(s.as_slice_less_safe())
+                .map_err(|_| Error::BadEncoding)
+        })
+        .transpose()
+}
+
 /// Parses a `NULL`.
 pub fn null(buf: &mut untrusted::Reader) -> Result<(), Error> {
     tagged(Tag::NULL, buf, |mut buf| {
@@ -290,6 +360,260 @@ pub fn opt_bool(buf: &mut untrusted::Reader) -> Result<Option<bool>, Error> {
     }
 }
 
+/// Returns whether the next element is an RFC5280 `Time`; useful for parsing
+/// optional times.
+pub fn peek_time(buf: &untrusted::Reader) -> bool {
+    buf.peek(Tag::UTC_TIME.0) || buf.peek(Tag::GENERALIZED_TIME.0)
+}
+
+/// Parses an RFC5280 `Time`, which is either a `UTCTime` or a
+/// `GeneralizedTime`, returning the time since the Unix epoch.
+///
+/// RFC5280 requires that times be expressed in UTC, including seconds but
+/// not fractional seconds; anything else is rejected. The special value
+/// `99991231235959Z`, which indicates a "no well-defined expiration date",
+/// is returned as `None`.
+pub fn time(buf: &mut untrusted::Reader) -> Result<Option<Duration>, Error> {
+    fn digits(bytes: &[u8]) -> Result<u32, Error> {
+        bytes.iter().try_fold(0, |acc, &b| match b {
+            b'0'..=b'9' => Ok(acc * 10 + (b - b'0') as u32),
+            _ => Err(Error::BadEncoding),
+        })
+    }
+
+    let (tag, body) = any(buf)?;
+    let body = body.as_slice_less_safe();
+    let (year, rest) = match tag {
+        Tag::UTC_TIME if body.len() == 13 => {
+            // RFC5280 specifies that two-digit years are in [1950, 2049].
+            let yy = digits(&body[..2])?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &body[2..])
+        }
+        Tag::GENERALIZED_TIME if body.len() == 15 => {
+            (digits(&body[..4])?, &body[4..])
+        }
+        _ => return Err(Error::BadEncoding),
+    };
+    if rest[10] != b'Z' {
+        return Err(Error::BadEncoding);
+    }
+
+    let month = digits(&rest[0..2])?;
+    let day = digits(&rest[2..4])?;
+    let hour = digits(&rest[4..6])?;
+    let min = digits(&rest[6..8])?;
+    let sec = digits(&rest[8..10])?;
+    if (year, month, day, hour, min, sec) == (9999, 12, 31, 23, 59, 59) {
+        return Ok(None);
+    }
+
+    let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_len = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap => 29,
+        2 => 28,
+        _ => return Err(Error::BadEncoding),
+    };
+    if day == 0 || day > month_len || hour > 23 || min > 59 || sec > 59 {
+        return Err(Error::BadEncoding);
+    }
+
+    // Compute the number of days since 1970-01-01, using the algorithm from
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil.
+    // Because `year` is at most 9999, none of this can overflow.
+    let (year, month, day) = (year as i64, month as i64, day as i64);
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = days * 86400 + hour as i64 * 3600 + min as i64 * 60 + sec as i64;
+    // Times before the epoch are all equally far in the past, as far as
+    // we're concerned.
+    Ok(Some(Duration::from_secs(secs.max(0) as u64)))
+}
+
+/// Writes a single element with the given tag, whose contents are produced
+/// by `contents`.
+pub fn write(
+    w: &mut Sink,
+    tag: Tag,
+    contents: Contents,
+) -> Result<(), io::Error> {
+    w.prefixed(contents, |len, (), w| {
+        w.write_bytes(&[tag.0])?;
+        if len < 0x80 {
+            return w.write_bytes(&[len as u8]);
+        }
+        let len = u32::try_from(len).map_err(|_| io::Error::BufferExhausted)?;
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        w.write_bytes(&[0x80 | (4 - skip) as u8])?;
+        w.write_bytes(&bytes[skip..])
+    })
+}
+
+/// Writes a non-negative big-endian `INTEGER`.
+///
+/// Redundant leading zeroes are removed, and a zero is added if necessary to
+/// keep the sign bit clear.
+pub fn write_uint(w: &mut Sink, be: &[u8]) -> Result<(), io::Error> {
+    write_uint_tagged(w, Tag::INTEGER, be)
+}
+
+fn write_uint_tagged(
+    w: &mut Sink,
+    tag: Tag,
+    be: &[u8],
+) -> Result<(), io::Error> {
+    let skip = be.iter().take_while(|&&b| b == 0).count();
+    let be = &be[skip..];
+    write(w, tag, &|w| match be.first() {
+        None => w.write_bytes(&[0]),
+        Some(&b) if b & 0x80 != 0 => {
+            w.write_bytes(&[0])?;
+            w.write_bytes(be)
+        }
+        Some(_) => w.write_bytes(be),
+    })
+}
+
+/// Writes a `BIT STRING` with no unused bits.
+pub fn write_bits(w: &mut Sink, contents: Contents) -> Result<(), io::Error> {
+    write(w, Tag::BIT_STRING, &|w| {
+        w.write_bytes(&[0])?;
+        contents(w)
+    })
+}
+
+/// Writes a `NULL`.
+pub fn write_null(w: &mut Sink) -> Result<(), io::Error> {
+    w.write_bytes(&[Tag::NULL.0, 0])
+}
+
+/// Writes an `OBJECT IDENTIFIER`.
+pub fn write_oid(w: &mut Sink, oid: &Oid) -> Result<(), io::Error> {
+    write(w, Tag::OID, &|w| w.write_bytes(oid.0))
+}
+
+/// Writes a `BOOLEAN`.
+pub fn write_bool(w: &mut Sink, val: bool) -> Result<(), io::Error> {
+    let val = if val { TRUE } else { FALSE };
+    write(w, Tag::BOOLEAN, &|w| w.write_bytes(val))
+}
+
+/// Writes a non-negative `INTEGER`, up to four bytes.
+pub fn write_u32(w: &mut Sink, val: u32) -> Result<(), io::Error> {
+    write_uint(w, &val.to_be_bytes())
+}
+
+/// Writes an implicitly tagged non-negative `INTEGER`, up to four bytes.
+pub fn write_implicit_u32(
+    w: &mut Sink,
+    tag: Tag,
+    val: u32,
+) -> Result<(), io::Error> {
+    write_uint_tagged(w, tag, &val.to_be_bytes())
+}
+
+/// Writes an element with the given tag, whose contents are exactly `bytes`.
+///
+/// This is suitable for `OCTET STRING`s and character strings, as well as
+/// implicitly-tagged versions thereof.
+pub fn write_bytes(
+    w: &mut Sink,
+    tag: Tag,
+    bytes: &[u8],
+) -> Result<(), io::Error> {
+    write(w, tag, &|w| w.write_bytes(bytes))
+}
+
+/// Writes a `BIT STRING` representing a named bit list, such as a `KeyUsage`,
+/// with the given tag.
+///
+/// `bits` is given in `BIT STRING` order, i.e., bit zero is the most
+/// significant bit of the first byte. DER requires that trailing zero bits be
+/// removed, which this function takes care of.
+pub fn write_named_bits(
+    w: &mut Sink,
+    tag: Tag,
+    bits: &[u8],
+) -> Result<(), io::Error> {
+    let len = bits.len() - bits.iter().rev().take_while(|&&b| b == 0).count();
+    let bits = &bits[..len];
+    let unused = bits.last().map(|b| b.trailing_zeros()).unwrap_or(0);
+    write(w, tag, &|w| {
+        w.write_bytes(&[unused as u8])?;
+        w.write_bytes(bits)
+    })
+}
+
+/// Writes a time, as seconds since the Unix epoch.
+///
+/// As RFC5280 requires, times in the years 1950 through 2049 are written as
+/// `UTCTime`, and all others as `GeneralizedTime`. `None` is written as the
+/// special value `99991231235959Z`; see [`time()`].
+pub fn write_time(
+    w: &mut Sink,
+    time: Option<Duration>,
+) -> Result<(), io::Error> {
+    let (year, month, day, hour, min, sec) = match time {
+        None => (9999, 12, 31, 23, 59, 59),
+        Some(time) => {
+            let secs = time.as_secs();
+            let (days, secs) = (secs / 86400, secs % 86400);
+
+            // Compute the calendar date, using the algorithm from
+            // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+            // This is the inverse of the computation in `time()`.
+            let z = days + 719468;
+            let era = z / 146097;
+            let doe = z - era * 146097;
+            let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+            let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+            let mp = (5 * doy + 2) / 153;
+            let day = doy - (153 * mp + 2) / 5 + 1;
+            let month = if mp < 10 { mp + 3 } else { mp - 9 };
+            let year = yoe + era * 400 + (month <= 2) as u64;
+            if year > 9999 {
+                return Err(io::Error::Internal);
+            }
+
+            (year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+        }
+    };
+
+    let mut buf = [0; 15];
+    for (i, &(val, width)) in [
+        (year, 4),
+        (month, 2),
+        (day, 2),
+        (hour, 2),
+        (min, 2),
+        (sec, 2),
+    ]
+    .iter()
+    .enumerate()
+    {
+        let end = 4 + 2 * i;
+        let mut val = val;
+        for b in buf[end - width..end].iter_mut().rev() {
+            *b = b'0' + (val % 10) as u8;
+            val /= 10;
+        }
+    }
+    buf[14] = b'Z';
+
+    if (1950..2050).contains(&year) {
+        write_bytes(w, Tag::UTC_TIME, &buf[2..])
+    } else {
+        write_bytes(w, Tag::GENERALIZED_TIME, &buf)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 pub struct Oid<'cert>(&'cert [u8]);
 
@@ -325,9 +649,12 @@ macro_rules! oid {
                 // Encode the component in base 128, but in
                 // little-endian order because that's easier.
                 // We then reverse it after the fact below.
+                //
+                // This must emit at least one byte, since a zero component is
+                // encoded as a single 0x00 rather than omitted.
                 let mark = len;
                 let mut first = true;
-                while c != 0 {
+                loop {
                     buf[len] = (c & 0x7f) as u8;
                     if !first {
                         buf[len] |= 0x80;
@@ -336,6 +663,9 @@ macro_rules! oid {
                     c >>= 7;
                     first = false;
                     len += 1;
+                    if c == 0 {
+                        break;
+                    }
                 }
 
                 // No `const` reverse() yet, so we have to do it by hand.
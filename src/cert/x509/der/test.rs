@@ -6,14 +6,20 @@
 //!
 //! These are hung off to the side to avoid cluttering the main der.rs.
 
+use core::cell::Cell;
+
 // NOTE: This is only for convenience and should be avoided in non-test code.
 use untrusted::{Input, Reader};
 
 use testutil::data;
 
+use crate::cert::encode::to_vec;
+use crate::cert::encode::Sink;
 use crate::cert::x509::der;
 use crate::cert::x509::der::Tag;
+use crate::cert::x509::oid;
 use crate::cert::Error::BadEncoding;
+use crate::io;
 
 type Result = core::result::Result<(), crate::cert::Error>;
 
@@ -332,3 +338,162 @@ fn opt_bool_bad() {
     let mut reader = Reader::new(Input::from(data::der::BAD_BOOL));
     assert!(der::opt_bool(&mut reader).is_err());
 }
+
+#[test]
+fn time_utc() -> Result {
+    Input::from(data::der::UTC_TIME).read_all(BadEncoding, |buf| {
+        let t = der::time(buf)?.unwrap();
+        assert_eq!(t.as_secs(), 1631277296);
+        Ok(())
+    })
+}
+
+#[test]
+fn time_generalized() -> Result {
+    Input::from(data::der::GENERALIZED_TIME).read_all(BadEncoding, |buf| {
+        let t = der::time(buf)?.unwrap();
+        assert_eq!(t.as_secs(), 1631277296);
+        Ok(())
+    })
+}
+
+#[test]
+fn time_no_well_defined() -> Result {
+    Input::from(data::der::NO_WELL_DEFINED_TIME).read_all(BadEncoding, |buf| {
+        assert!(der::time(buf)?.is_none());
+        Ok(())
+    })
+}
+
+#[test]
+fn time_bad() {
+    for &bytes in &[
+        data::der::BAD_TIME_MONTH,
+        data::der::BAD_TIME_FRACTION,
+        data::der::BAD_TIME_OFFSET,
+        data::der::FORTY_TWO,
+    ] {
+        let mut reader = Reader::new(Input::from(bytes));
+        assert!(der::time(&mut reader).is_err());
+    }
+}
+
+#[test]
+fn write_uint() {
+    assert_eq!(to_vec(&|w| der::write_uint(w, &[])), data::der::ZERO);
+    assert_eq!(to_vec(&|w| der::write_uint(w, &[0, 0])), data::der::ZERO);
+    assert_eq!(to_vec(&|w| der::write_u32(w, 42)), data::der::FORTY_TWO);
+    assert_eq!(
+        to_vec(&|w| der::write_u32(w, 128)),
+        data::der::ONE_TWENTY_EIGHT
+    );
+    assert_eq!(
+        to_vec(&|w| der::write_u32(w, 9000)),
+        data::der::NINE_THOUSAND
+    );
+}
+
+#[test]
+fn write_long_form() -> Result {
+    let bytes = [0x55; 300];
+    let encoded =
+        to_vec(&|w| der::write_bytes(w, Tag::OCTET_STRING, &bytes[..]));
+    assert_eq!(encoded[..4], [0x04, 0x82, 0x01, 0x2c]);
+    Input::from(&encoded[..]).read_all(BadEncoding, |buf| {
+        let body = der::parse(Tag::OCTET_STRING, buf)?;
+        assert_eq!(body.as_slice_less_safe(), &bytes[..]);
+        Ok(())
+    })
+}
+
+#[test]
+fn write_nested() {
+    fn nest(
+        w: &mut Sink,
+        depth: usize,
+        calls: &Cell<usize>,
+    ) -> core::result::Result<(), io::Error> {
+        if depth == 0 {
+            calls.set(calls.get() + 1);
+            return der::write_bool(w, true);
+        }
+        der::write(w, Tag::SEQUENCE, &|w| nest(w, depth - 1, calls))
+    }
+
+    // The innermost element is written once to measure it, and once more to
+    // write it out, no matter how deeply it is nested.
+    let calls = Cell::new(0);
+    let encoded = to_vec(&|w| nest(w, 32, &calls));
+    assert_eq!(calls.get(), 2);
+    assert_eq!(encoded.len(), 32 * 2 + 3);
+    assert_eq!(encoded[..4], [0x30, 65, 0x30, 63]);
+    assert_eq!(encoded[64..], [0x01, 0x01, 0xff]);
+}
+
+#[test]
+fn write_named_bits() -> Result {
+    let encoded = to_vec(&|w| der::write_named_bits(w, Tag::BIT_STRING, &[]));
+    assert_eq!(encoded, [0x03, 0x01, 0x00]);
+    Input::from(&encoded[..]).read_all(BadEncoding, |buf| {
+        let body = der::bits_partial(buf)?;
+        assert!(body.is_empty());
+        Ok(())
+    })?;
+    // `bits_total()` still requires at least one octet.
+    let mut reader = Reader::new(Input::from(&encoded[..]));
+    assert!(der::bits_total(&mut reader).is_err());
+
+    let encoded =
+        to_vec(&|w| der::write_named_bits(w, Tag::BIT_STRING, &[0x04, 0]));
+    assert_eq!(encoded, [0x03, 0x02, 0x02, 0x04]);
+    Input::from(&encoded[..]).read_all(BadEncoding, |buf| {
+        let body = der::bits_partial(buf)?;
+        assert_eq!(body.as_slice_less_safe(), [0x04]);
+        Ok(())
+    })
+}
+
+#[test]
+fn write_time() {
+    let t = core::time::Duration::from_secs(1631277296);
+    assert_eq!(
+        to_vec(&|w| der::write_time(w, Some(t))),
+        data::der::UTC_TIME
+    );
+    assert_eq!(
+        to_vec(&|w| der::write_time(w, None)),
+        data::der::NO_WELL_DEFINED_TIME
+    );
+
+    // 2050-01-01T00:00:00Z must be a `GeneralizedTime`.
+    let t = core::time::Duration::from_secs(2524608000);
+    let encoded = to_vec(&|w| der::write_time(w, Some(t)));
+    assert_eq!(encoded, b"\x18\x0f20500101000000Z");
+    Input::from(&encoded[..])
+        .read_all(BadEncoding, |buf| {
+            assert_eq!(der::time(buf)?, Some(t));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn write_oid_zero_arc() -> Result {
+    // 1.3.132.0.34, i.e., secp384r1.
+    let encoded = to_vec(&|w| der::write_oid(w, &oid::SECP384R1));
+    assert_eq!(encoded, [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22]);
+    Input::from(&encoded[..]).read_all(BadEncoding, |buf| {
+        assert_eq!(der::oid(buf)?, oid::SECP384R1);
+        Ok(())
+    })?;
+
+    // A trailing zero arc must not be dropped either: 2.5.29.37.0, i.e.,
+    // anyExtendedKeyUsage, is distinct from 2.5.29.37.
+    assert_ne!(oid::ANY_EXT_KEY_USAGE, oid::EXT_KEY_USAGE);
+    let encoded = to_vec(&|w| der::write_oid(w, &oid::ANY_EXT_KEY_USAGE));
+    assert_eq!(encoded, [0x06, 0x04, 0x55, 0x1d, 0x25, 0x00]);
+    Input::from(&encoded[..]).read_all(BadEncoding, |buf| {
+        assert_eq!(der::oid(buf)?, oid::ANY_EXT_KEY_USAGE);
+        Ok(())
+    })
+}
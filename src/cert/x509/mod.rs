@@ -2,22 +2,40 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-//! X.509 parsing.
+//! X.509 parsing and encoding.
 
 use crate::cert;
+use crate::cert::encode;
+use crate::cert::encode::Sink;
 use crate::cert::x509::der::Tag;
 use crate::cert::Cert;
 use crate::cert::Error;
 use crate::cert::Name;
 use crate::crypto::sig;
+use crate::io;
+use crate::io::Write as _;
+use crate::mem::Arena;
+use crate::mem::ArenaExt as _;
 
 #[macro_use]
 mod der;
 
+mod builder;
+pub use builder::CertBuilder;
+
+mod crl;
+pub use crl::Crl;
+
+mod csr;
+pub use csr::UnsignedCsr;
+
+mod name;
+pub use name::dn_eq;
+
 #[cfg(test)]
 mod test;
 
-/// OIDs used by the parser.
+/// OIDs used by the parser and encoders.
 #[allow(unused)]
 mod oid {
     use crate::cert::x509::der::Oid;
@@ -25,22 +43,146 @@ mod oid {
     pub const RSA_ENCRYPTION: Oid = oid!(1, 2, 840, 113549, 1, 1, 1);
     pub const RSA_PKCS1_SHA256: Oid = oid!(1, 2, 840, 113549, 1, 1, 11);
 
+    pub const EC_PUBLIC_KEY: Oid = oid!(1, 2, 840, 10045, 2, 1);
+    pub const SECP256R1: Oid = oid!(1, 2, 840, 10045, 3, 1, 7);
+    pub const SECP384R1: Oid = oid!(1, 3, 132, 0, 34);
+    pub const ECDSA_SHA256: Oid = oid!(1, 2, 840, 10045, 4, 3, 2);
+    pub const ECDSA_SHA384: Oid = oid!(1, 2, 840, 10045, 4, 3, 3);
+    pub const ED25519: Oid = oid!(1, 3, 101, 112);
+
+    pub const COMMON_NAME: Oid = oid!(2, 5, 4, 3);
+    pub const SERIAL_NUMBER: Oid = oid!(2, 5, 4, 5);
+    pub const ORGANIZATION_NAME: Oid = oid!(2, 5, 4, 10);
+
+    pub const SUBJECT_KEY_ID: Oid = oid!(2, 5, 29, 14);
     pub const KEY_USAGE: Oid = oid!(2, 5, 29, 15);
     pub const BASIC_CONSTRAINTS: Oid = oid!(2, 5, 29, 19);
-    pub const TCG_DICE_FWID: Oid = oid!(2, 23, 133, 5, 4, 1);
+    pub const CRL_NUMBER: Oid = oid!(2, 5, 29, 20);
+    pub const AUTHORITY_KEY_ID: Oid = oid!(2, 5, 29, 35);
+    pub const EXT_KEY_USAGE: Oid = oid!(2, 5, 29, 37);
+    pub const TCG_DICE_TCB_INFO: Oid = oid!(2, 23, 133, 5, 4, 1);
+
+    pub const ANY_EXT_KEY_USAGE: Oid = oid!(2, 5, 29, 37, 0);
+    pub const TCG_DICE_KP_IDENTITY_INIT: Oid = oid!(2, 23, 133, 5, 4, 100, 6);
+    pub const TCG_DICE_KP_IDENTITY_LOC: Oid = oid!(2, 23, 133, 5, 4, 100, 7);
+    pub const TCG_DICE_KP_ATTEST_INIT: Oid = oid!(2, 23, 133, 5, 4, 100, 8);
+    pub const TCG_DICE_KP_ATTEST_LOC: Oid = oid!(2, 23, 133, 5, 4, 100, 9);
+    pub const TCG_DICE_KP_ASSERT_INIT: Oid = oid!(2, 23, 133, 5, 4, 100, 10);
+    pub const TCG_DICE_KP_ASSERT_LOC: Oid = oid!(2, 23, 133, 5, 4, 100, 11);
+    pub const TCG_DICE_KP_ECA: Oid = oid!(2, 23, 133, 5, 4, 100, 12);
+
+    pub const SHA256: Oid = oid!(2, 16, 840, 1, 101, 3, 4, 2, 1);
+    pub const SHA384: Oid = oid!(2, 16, 840, 1, 101, 3, 4, 2, 2);
+    pub const SHA512: Oid = oid!(2, 16, 840, 1, 101, 3, 4, 2, 3);
+}
+
+/// A signature algorithm, as named by an RFC3279 algorithm identifier.
+///
+/// Unlike [`sig::Algo`], this does not include the curve for ECDSA, which is
+/// a property of the signing key rather than of the algorithm identifier.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum SigAlgo {
+    RsaPkcs1Sha256,
+    EcdsaSha256,
+    EcdsaSha384,
+    Ed25519,
+}
+
+impl SigAlgo {
+    /// Resolves this algorithm into a [`sig::Algo`], given the key the
+    /// signature will be verified with.
+    fn for_key(self, key: &sig::PublicKeyParams) -> Result<sig::Algo, Error> {
+        let curve = match key {
+            sig::PublicKeyParams::Rsa { .. } => {
+                return match self {
+                    Self::RsaPkcs1Sha256 => Ok(sig::Algo::RsaPkcs1Sha256),
+                    _ => Err(Error::WrongAlgorithm),
+                };
+            }
+            sig::PublicKeyParams::Ecc { curve, .. } => *curve,
+            sig::PublicKeyParams::Okp { curve, .. } => {
+                return match (self, curve) {
+                    (Self::Ed25519, sig::Curve::Ed25519) => {
+                        Ok(sig::Algo::Ed25519)
+                    }
+                    _ => Err(Error::WrongAlgorithm),
+                };
+            }
+        };
+
+        match (self, curve) {
+            (Self::RsaPkcs1Sha256, _) => Err(Error::WrongAlgorithm),
+            (Self::EcdsaSha256, sig::Curve::NistP256) => {
+                Ok(sig::Algo::EcdsaDerP256)
+            }
+            (Self::EcdsaSha384, sig::Curve::NistP384) => {
+                Ok(sig::Algo::EcdsaDerP384)
+            }
+            // ECDSA with a hash that does not match the curve's strength is
+            // legal, but not something we support.
+            _ => Err(Error::UnknownAlgorithm),
+        }
+    }
+
+    /// Selects the algorithm to use when signing with `key`.
+    fn for_signing_key(key: &sig::PublicKeyParams) -> Result<Self, Error> {
+        match key {
+            sig::PublicKeyParams::Rsa { .. } => Ok(Self::RsaPkcs1Sha256),
+            sig::PublicKeyParams::Ecc {
+                curve: sig::Curve::NistP256,
+                ..
+            } => Ok(Self::EcdsaSha256),
+            sig::PublicKeyParams::Ecc {
+                curve: sig::Curve::NistP384,
+                ..
+            } => Ok(Self::EcdsaSha384),
+            sig::PublicKeyParams::Okp {
+                curve: sig::Curve::Ed25519,
+                ..
+            } => Ok(Self::Ed25519),
+            _ => Err(Error::UnknownAlgorithm),
+        }
+    }
 }
 
 /// Parses an RFC3279 algorithm identifier.
-fn parse_algo(buf: &mut untrusted::Reader) -> Result<sig::Algo, Error> {
+fn parse_algo(buf: &mut untrusted::Reader) -> Result<SigAlgo, Error> {
     match der::oid(buf)? {
         oid::RSA_PKCS1_SHA256 => {
             der::null(buf)?;
-            Ok(sig::Algo::RsaPkcs1Sha256)
+            Ok(SigAlgo::RsaPkcs1Sha256)
         }
+        // RFC5758 requires that the parameters be absent for ECDSA.
+        oid::ECDSA_SHA256 => Ok(SigAlgo::EcdsaSha256),
+        oid::ECDSA_SHA384 => Ok(SigAlgo::EcdsaSha384),
+        // RFC8410 also requires that the parameters be absent.
+        oid::ED25519 => Ok(SigAlgo::Ed25519),
         _ => Err(Error::UnknownAlgorithm),
     }
 }
 
+/// Parses an RFC5480 elliptic curve public key, given the `namedCurve`
+/// algorithm parameters and the contents of the `subjectPublicKey` field.
+fn parse_ec_key<'cert>(
+    aparams: untrusted::Input<'cert>,
+    buf: &mut untrusted::Reader<'cert>,
+) -> Result<sig::PublicKeyParams<'cert>, Error> {
+    let (curve, coord_len) =
+        aparams.read_all(Error::BadEncoding, |buf| match der::oid(buf)? {
+            oid::SECP256R1 => Ok((sig::Curve::NistP256, 32)),
+            oid::SECP384R1 => Ok((sig::Curve::NistP384, 48)),
+            _ => Err(Error::UnknownAlgorithm),
+        })?;
+
+    // Only uncompressed points, which start with a 0x04 byte, are supported.
+    if buf.read_byte()? != 0x04 {
+        return Err(Error::BadEncoding);
+    }
+    let x = buf.read_bytes(coord_len)?.as_slice_less_safe();
+    let y = buf.read_bytes(coord_len)?.as_slice_less_safe();
+    Ok(sig::PublicKeyParams::Ecc { curve, x, y })
+}
+
 /// Parses an X.509 certificate.
 ///
 /// This function performs several aggressive checks to reject any and all
@@ -87,6 +229,19 @@ pub fn parse<'cert>(
     cert.raw = raw_cert;
 
     let key = key.unwrap_or_else(|| cert.subject_key());
+    verify(tbs, sig_algo, sig, key, ciphers)?;
+    Ok(cert)
+}
+
+/// Verifies that `sig` is a signature of `tbs` by `key`, using `sig_algo`.
+fn verify(
+    tbs: &[u8],
+    sig_algo: SigAlgo,
+    sig: &[u8],
+    key: &sig::PublicKeyParams,
+    ciphers: &mut impl sig::Ciphers,
+) -> Result<(), Error> {
+    let sig_algo = sig_algo.for_key(key)?;
     if !key.is_params_for(sig_algo) {
         return Err(Error::WrongAlgorithm);
     }
@@ -96,9 +251,7 @@ pub fn parse<'cert>(
         .ok_or(Error::UnknownAlgorithm)?;
     verifier
         .verify(&[tbs], sig)
-        .map_err(|_| Error::BadSignature)?;
-
-    Ok(cert)
+        .map_err(|_| Error::BadSignature)
 }
 
 fn parse_tbs<'cert>(
@@ -121,12 +274,15 @@ fn parse_tbs<'cert>(
     // The certificate serial number must be a positive `INTEGER` consisting
     // of at most 20 octets.
     //
-    // Like with the version, this is a syntactic error. The value itself
-    // is discarded.
-    let serial = der::uint(buf)?.as_slice_less_safe();
-    if serial[0] == 0 || serial.len() > 20 {
+    // Like with the version, this is a syntactic error.
+    let mut serial = der::uint(buf)?.as_slice_less_safe();
+    if serial == [0] || serial.len() > 20 {
         return Err(Error::BadEncoding);
     }
+    // As with RSA keys, drop the leading zero DER may have inserted.
+    if serial[0] == 0 {
+        serial = &serial[1..];
+    }
 
     // A mismatch between the inner and outer signature algorithm
     // identifiers (byte-for-byte) is a syntax error.
@@ -136,15 +292,16 @@ fn parse_tbs<'cert>(
     }
 
     // The issuer is an opaque name.
-    let issuer = Name(der::parse(Tag::SEQUENCE, buf)?.as_slice_less_safe());
+    let issuer =
+        Name::from_der(der::parse(Tag::SEQUENCE, buf)?.as_slice_less_safe());
 
-    // TODO: Provide some mechanism for the user to pass in a clock, if
-    // available. X.509 time parsing incurs significant complexity, so
-    // it probably shouldn't be implemented until it's absolutely necessary.
-    let _validity = der::parse(Tag::SEQUENCE, buf)?;
+    let (not_before, not_after) = der::tagged(Tag::SEQUENCE, buf, |buf| {
+        Ok((der::time(buf)?, der::time(buf)?))
+    })?;
 
     // The subject is also opaque
-    let subject = Name(der::parse(Tag::SEQUENCE, buf)?.as_slice_less_safe());
+    let subject =
+        Name::from_der(der::parse(Tag::SEQUENCE, buf)?.as_slice_less_safe());
 
     let subject_key = der::tagged(Tag::SEQUENCE, buf, |buf| {
         let (algo, aparams) = der::tagged(Tag::SEQUENCE, buf, |buf| {
@@ -170,6 +327,21 @@ fn parse_tbs<'cert>(
                     Ok(sig::PublicKeyParams::Rsa { modulus, exponent })
                 })
             }
+            oid::EC_PUBLIC_KEY => parse_ec_key(aparams, buf),
+            // RFC8410 keys are the raw RFC8032 encoding, with no parameters.
+            oid::ED25519 => {
+                if !aparams.is_empty() {
+                    return Err(Error::BadEncoding);
+                }
+                let x = buf.read_bytes_to_end().as_slice_less_safe();
+                if x.len() != 32 {
+                    return Err(Error::BadEncoding);
+                }
+                Ok(sig::PublicKeyParams::Okp {
+                    curve: sig::Curve::Ed25519,
+                    x,
+                })
+            }
             _ => Err(Error::UnknownAlgorithm),
         })
     })?;
@@ -190,7 +362,7 @@ fn parse_tbs<'cert>(
     })?;
 
     let is_cert_sign = match extns.key_usage {
-        Some(b) => b.is_cert_sign(),
+        Some(ku) => ku.contains(cert::KeyUsage::KEY_CERT_SIGN),
         _ => return Err(Error::BadEncoding),
     };
 
@@ -208,88 +380,36 @@ fn parse_tbs<'cert>(
     Ok(Cert {
         raw: &[],
         format,
+        serial: Some(serial),
         issuer,
         subject,
         subject_key,
+        not_before,
+        not_after,
         basic_constraints: extns.basic_constraints,
-        is_cert_sign,
+        subject_key_id: extns.subject_key_id,
+        authority_key_id: extns.authority_key_id.flatten(),
+        key_usage: extns.key_usage,
+        ext_key_usage: extns.ext_key_usage,
+        measurements: extns.tcb_info,
     })
 }
 
-/// An X.509 `KeyUsage` value, representing the valid usages of a subject
-/// public key.
-///
-/// The memory representation is little-endian, meaning that for X.509, we
-/// need to reverse the bytes and the bits within, but not for CBOR.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub struct KeyUsage(u16);
-
-impl KeyUsage {
-    pub fn from_be(bytes: &[u8]) -> Result<Self, Error> {
-        Self::from_bits(bytes, true)
-    }
-
-    pub fn from_le(bytes: &[u8]) -> Result<Self, Error> {
-        Self::from_bits(bytes, false)
-    }
-
-    fn from_bits(bytes: &[u8], is_be: bool) -> Result<Self, Error> {
-        let b1 = *bytes.get(0).unwrap_or(&0);
-        let b2 = *bytes.get(1).unwrap_or(&0);
-        let val = if is_be {
-            // Note that ASN.1 BIT STRINGs are actually little-endian bytes;
-            // it is merely the bits within the bytes that are big endian!
-            u16::from_le_bytes([b1.reverse_bits(), b2.reverse_bits()])
-        } else {
-            u16::from_le_bytes([b1, b2])
-        };
-
-        // NOTE: This technically drops any "domain-specific"
-        // bits on the ground, but we have zero interest in retaining
-        // those.
-        let bits = Self(val);
-
-        // For domain separation reasons, we reject all
-        // certificates that mix certificate signing with any
-        // other usage.
-        if bits.is_cert_sign() && bits.0 != Self::CERT_SIGN_MASK {
-            return Err(Error::BadEncoding);
-        }
-
-        Ok(bits)
-    }
-
-    // Constants from RFC5280 S4.2.1.3. I.e.,
-    // ```asn1
-    // KeyUsage ::= BIT STRING {
-    //   digitalSignature        (0),
-    //   nonRepudiation          (1),
-    //   keyEncipherment         (2),
-    //   dataEncipherment        (3),
-    //   keyAgreement            (4),
-    //   keyCertSign             (5),
-    //   cRLSign                 (6),
-    //   encipherOnly            (7),
-    //   decipherOnly            (8),
-    // }
-    // ```
-    // Add constants as needed.
-    const CERT_SIGN_MASK: u16 = 1 << 5;
-
-    pub fn is_cert_sign(self) -> bool {
-        self.0 & Self::CERT_SIGN_MASK != 0
-    }
-}
-
 #[derive(Default)]
-struct Extensions {
+struct Extensions<'cert> {
     basic_constraints: Option<cert::BasicConstraints>,
-    key_usage: Option<KeyUsage>,
+    subject_key_id: Option<&'cert [u8]>,
+    // The inner `Option` is the `keyIdentifier` field, which may be absent
+    // even if the extension is present.
+    authority_key_id: Option<Option<&'cert [u8]>>,
+    key_usage: Option<cert::KeyUsage>,
+    ext_key_usage: Option<cert::ExtendedKeyUsage>,
+    tcb_info: Option<cert::Measurements<'cert>>,
 }
 
-fn parse_extn(
-    buf: &mut untrusted::Reader,
-    extns: &mut Extensions,
+fn parse_extn<'cert>(
+    buf: &mut untrusted::Reader<'cert>,
+    extns: &mut Extensions<'cert>,
 ) -> Result<(), Error> {
     der::tagged(Tag::SEQUENCE, buf, |buf| {
         let oid = der::oid(buf)?;
@@ -302,10 +422,32 @@ fn parse_extn(
 
                 der::bits_partial(buf)?.read_all(Error::BadEncoding, |buf| {
                     let bytes = buf.read_bytes_to_end().as_slice_less_safe();
-                    extns.key_usage = Some(KeyUsage::from_be(bytes)?);
+                    extns.key_usage = Some(cert::KeyUsage::from_be(bytes)?);
                     Ok(())
                 })
             }
+            oid::EXT_KEY_USAGE => {
+                if extns.ext_key_usage.is_some() {
+                    return Err(Error::BadEncoding);
+                }
+                extns.ext_key_usage = Some(parse_ext_key_usage(buf)?);
+                Ok(())
+            }
+            oid::SUBJECT_KEY_ID => {
+                if extns.subject_key_id.is_some() {
+                    return Err(Error::BadEncoding);
+                }
+                let id = der::parse(Tag::OCTET_STRING, buf)?;
+                extns.subject_key_id = Some(id.as_slice_less_safe());
+                Ok(())
+            }
+            oid::AUTHORITY_KEY_ID => {
+                if extns.authority_key_id.is_some() {
+                    return Err(Error::BadEncoding);
+                }
+                extns.authority_key_id = Some(parse_authority_key_id(buf)?);
+                Ok(())
+            }
             oid::BASIC_CONSTRAINTS => {
                 if extns.basic_constraints.is_some() {
                     return Err(Error::BadEncoding);
@@ -320,6 +462,13 @@ fn parse_extn(
                     Ok(())
                 })
             }
+            oid::TCG_DICE_TCB_INFO => {
+                if extns.tcb_info.is_some() {
+                    return Err(Error::BadEncoding);
+                }
+                extns.tcb_info = Some(parse_tcb_info(buf)?);
+                Ok(())
+            }
             _ if is_critical => Err(Error::BadEncoding),
             _ => {
                 // Drop the rest of the bytes on the floor. `untrusted` requires
@@ -331,3 +480,252 @@ fn parse_extn(
         })
     })
 }
+
+/// Parses an `authorityKeyIdentifier` extension, returning the key
+/// identifier, if present.
+fn parse_authority_key_id<'cert>(
+    buf: &mut untrusted::Reader<'cert>,
+) -> Result<Option<&'cert [u8]>, Error> {
+    // AuthorityKeyIdentifier ::= SEQUENCE {
+    //   keyIdentifier             [0] KeyIdentifier OPTIONAL,
+    //   authorityCertIssuer       [1] GeneralNames OPTIONAL,
+    //   authorityCertSerialNumber [2] INTEGER OPTIONAL
+    // }
+    //
+    // We only care about the key identifier; the issuer and serial are
+    // redundant with the issuer's own certificate.
+    der::tagged(Tag::SEQUENCE, buf, |buf| {
+        let implicit = Tag::context_specific_primitive;
+        let id = der::opt(implicit(0), buf)?;
+        let _issuer = der::opt(Tag::context_specific(1), buf)?;
+        let _serial = der::opt(implicit(2), buf)?;
+        Ok(id.map(|id| id.as_slice_less_safe()))
+    })
+}
+
+/// Parses an `extKeyUsage` extension.
+///
+/// ```asn1
+/// ExtKeyUsageSyntax ::= SEQUENCE SIZE (1..MAX) OF KeyPurposeId
+/// KeyPurposeId ::= OBJECT IDENTIFIER
+/// ```
+///
+/// Purposes which are not relevant to attestation are ignored.
+fn parse_ext_key_usage(
+    buf: &mut untrusted::Reader,
+) -> Result<cert::ExtendedKeyUsage, Error> {
+    der::tagged(Tag::SEQUENCE, buf, |buf| {
+        if buf.at_end() {
+            return Err(Error::BadEncoding);
+        }
+        let mut eku = cert::ExtendedKeyUsage::empty();
+        while !buf.at_end() {
+            let oid = der::oid(buf)?;
+            for (purpose, purpose_oid) in EXT_KEY_USAGES {
+                if oid == *purpose_oid {
+                    eku |= *purpose;
+                }
+            }
+        }
+        Ok(eku)
+    })
+}
+
+/// The `KeyPurposeId`s for each [`cert::ExtendedKeyUsage`].
+const EXT_KEY_USAGES: &[(cert::ExtendedKeyUsage, der::Oid)] = &[
+    (cert::ExtendedKeyUsage::ANY, oid::ANY_EXT_KEY_USAGE),
+    (
+        cert::ExtendedKeyUsage::IDENTITY_INIT,
+        oid::TCG_DICE_KP_IDENTITY_INIT,
+    ),
+    (
+        cert::ExtendedKeyUsage::IDENTITY_LOC,
+        oid::TCG_DICE_KP_IDENTITY_LOC,
+    ),
+    (
+        cert::ExtendedKeyUsage::ATTEST_INIT,
+        oid::TCG_DICE_KP_ATTEST_INIT,
+    ),
+    (
+        cert::ExtendedKeyUsage::ATTEST_LOC,
+        oid::TCG_DICE_KP_ATTEST_LOC,
+    ),
+    (
+        cert::ExtendedKeyUsage::ASSERT_INIT,
+        oid::TCG_DICE_KP_ASSERT_INIT,
+    ),
+    (
+        cert::ExtendedKeyUsage::ASSERT_LOC,
+        oid::TCG_DICE_KP_ASSERT_LOC,
+    ),
+    (cert::ExtendedKeyUsage::ECA, oid::TCG_DICE_KP_ECA),
+];
+
+/// Parses a TCG DICE `TcbInfo` extension.
+///
+/// ```asn1
+/// DiceTcbInfo ::= SEQUENCE {
+///   vendor     [0] IMPLICIT UTF8String OPTIONAL,
+///   model      [1] IMPLICIT UTF8String OPTIONAL,
+///   version    [2] IMPLICIT UTF8String OPTIONAL,
+///   svn        [3] IMPLICIT INTEGER OPTIONAL,
+///   layer      [4] IMPLICIT INTEGER OPTIONAL,
+///   index      [5] IMPLICIT INTEGER OPTIONAL,
+///   fwids      [6] IMPLICIT FWIDLIST OPTIONAL,
+///   flags      [7] IMPLICIT OperationalFlags OPTIONAL,
+///   vendorInfo [8] IMPLICIT OCTET STRING OPTIONAL,
+///   type       [9] IMPLICIT OCTET STRING OPTIONAL,
+/// }
+/// FWIDLIST ::= SEQUENCE SIZE (1..MAX) OF FWID
+/// FWID ::= SEQUENCE {
+///   hashAlg OBJECT IDENTIFIER,
+///   digest  OCTET STRING,
+/// }
+/// ```
+fn parse_tcb_info<'cert>(
+    buf: &mut untrusted::Reader<'cert>,
+) -> Result<cert::Measurements<'cert>, Error> {
+    der::tagged(Tag::SEQUENCE, buf, |buf| {
+        let implicit = Tag::context_specific_primitive;
+        let mut m = cert::Measurements::default();
+        m.vendor = der::opt_implicit_utf8(implicit(0), buf)?;
+        m.model = der::opt_implicit_utf8(implicit(1), buf)?;
+        m.version = der::opt_implicit_utf8(implicit(2), buf)?;
+        m.svn = der::opt_implicit_u32(implicit(3), buf)?;
+        m.layer = der::opt_implicit_u32(implicit(4), buf)?;
+        m.index = der::opt_implicit_u32(implicit(5), buf)?;
+
+        if let Some(fwids) = der::opt(Tag::context_specific(6), buf)? {
+            fwids.read_all(Error::BadEncoding, |buf| {
+                if buf.at_end() {
+                    return Err(Error::BadEncoding);
+                }
+                while !buf.at_end() {
+                    let fwid = der::tagged(Tag::SEQUENCE, buf, |buf| {
+                        let algo = match der::oid(buf)? {
+                            oid::SHA256 => Some(cert::FwidAlgo::Sha256),
+                            oid::SHA384 => Some(cert::FwidAlgo::Sha384),
+                            oid::SHA512 => Some(cert::FwidAlgo::Sha512),
+                            _ => None,
+                        };
+                        let digest = der::parse(Tag::OCTET_STRING, buf)?
+                            .as_slice_less_safe();
+                        Ok(cert::Fwid { algo, digest })
+                    })?;
+                    m.push_fwid(fwid)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        if let Some(flags) = der::opt_bits_partial(implicit(7), buf)? {
+            // As with `KeyUsage`, bit zero is the most significant bit of the
+            // first byte. Undefined flags are ignored.
+            let bytes = flags.as_slice_less_safe();
+            let bits = bytes.get(0).map(|b| b.reverse_bits()).unwrap_or(0);
+            m.flags = Some(cert::OperationalFlags::from_bits_truncate(bits));
+        }
+
+        // Neither of these have a well-defined meaning we could appraise.
+        let _vendor_info = der::opt(implicit(8), buf)?;
+        let _type = der::opt(implicit(9), buf)?;
+        Ok(m)
+    })
+}
+
+/// Writes an RFC3279 algorithm identifier for `algo`.
+fn write_algo(w: &mut Sink, algo: SigAlgo) -> Result<(), io::Error> {
+    der::write(w, Tag::SEQUENCE, &|w| match algo {
+        SigAlgo::RsaPkcs1Sha256 => {
+            der::write_oid(w, &oid::RSA_PKCS1_SHA256)?;
+            der::write_null(w)
+        }
+        SigAlgo::EcdsaSha256 => der::write_oid(w, &oid::ECDSA_SHA256),
+        SigAlgo::EcdsaSha384 => der::write_oid(w, &oid::ECDSA_SHA384),
+        SigAlgo::Ed25519 => der::write_oid(w, &oid::ED25519),
+    })
+}
+
+/// Writes a `SubjectPublicKeyInfo` for `key`.
+///
+/// Only RSA, NIST curve and Ed25519 keys are supported.
+fn write_spki(
+    w: &mut Sink,
+    key: &sig::PublicKeyParams,
+) -> Result<(), io::Error> {
+    der::write(w, Tag::SEQUENCE, &|w| match key {
+        sig::PublicKeyParams::Rsa { modulus, exponent } => {
+            der::write(w, Tag::SEQUENCE, &|w| {
+                der::write_oid(w, &oid::RSA_ENCRYPTION)?;
+                der::write_null(w)
+            })?;
+            der::write_bits(w, &|w| {
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    der::write_uint(w, modulus)?;
+                    der::write_uint(w, exponent)
+                })
+            })
+        }
+        sig::PublicKeyParams::Ecc { curve, x, y } => {
+            let curve = match curve {
+                sig::Curve::NistP256 => &oid::SECP256R1,
+                sig::Curve::NistP384 => &oid::SECP384R1,
+                _ => return Err(io::Error::Internal),
+            };
+            der::write(w, Tag::SEQUENCE, &|w| {
+                der::write_oid(w, &oid::EC_PUBLIC_KEY)?;
+                der::write_oid(w, curve)
+            })?;
+            // An uncompressed SEC1 point.
+            der::write_bits(w, &|w| {
+                w.write_bytes(&[0x04])?;
+                w.write_bytes(x)?;
+                w.write_bytes(y)
+            })
+        }
+        sig::PublicKeyParams::Okp { curve, x } => {
+            if *curve != sig::Curve::Ed25519 {
+                return Err(io::Error::Internal);
+            }
+            der::write(w, Tag::SEQUENCE, &|w| {
+                der::write_oid(w, &oid::ED25519)
+            })?;
+            der::write_bits(w, &|w| w.write_bytes(x))
+        }
+    })
+}
+
+/// Signs the encoded `tbs` with `signer`, producing the signed structure
+/// shared by certificates and CSRs:
+///
+/// ```asn1
+/// Signed ::= SEQUENCE {
+///   tbs                ToBeSigned,
+///   signatureAlgorithm AlgorithmIdentifier,
+///   signature          BIT STRING,
+/// }
+/// ```
+///
+/// `signer` must produce signatures in the encoding X.509 uses for `algo`
+/// (e.g., ASN.1 DER for ECDSA).
+fn sign<'a>(
+    tbs: &[u8],
+    algo: SigAlgo,
+    signer: &mut dyn sig::Sign,
+    arena: &'a dyn Arena,
+) -> Result<&'a [u8], Error> {
+    let signature = arena.alloc_slice::<u8>(signer.sig_bytes())?;
+    let sig_len = signer.sign(&[tbs], signature)?;
+    let signature = &signature[..sig_len];
+
+    encode::to_arena(
+        &|w| {
+            der::write(w, Tag::SEQUENCE, &|w| {
+                w.write_bytes(tbs)?;
+                write_algo(w, algo)?;
+                der::write_bits(w, &|w| w.write_bytes(signature))
+            })
+        },
+        arena,
+    )
+}
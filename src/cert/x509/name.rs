@@ -0,0 +1,201 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! X.509 distinguished names.
+//!
+//! See RFC 5280, Sections 4.1.2.4 and 7.1.
+
+use crate::cert::x509::der;
+use crate::cert::x509::der::Oid;
+use crate::cert::x509::der::Tag;
+use crate::cert::x509::oid;
+use crate::cert::CertFormat;
+use crate::cert::Error;
+use crate::cert::Name;
+
+impl<'cert> Name<'cert> {
+    /// Wraps the contents of a DER-encoded `RDNSequence`.
+    pub(crate) fn from_der(raw: &'cert [u8]) -> Self {
+        Self {
+            raw,
+            format: CertFormat::RiotX509,
+        }
+    }
+
+    /// Returns the `commonName` attribute of this name.
+    ///
+    /// Returns `None` if this is not an X.509 name, or if it has no such
+    /// attribute; if it has several, the last, which is conventionally the
+    /// most specific, is returned. This, and the other attribute accessors,
+    /// are intended for diagnostics only.
+    pub fn common_name(&self) -> Option<&'cert str> {
+        self.find_attr(&oid::COMMON_NAME)
+    }
+
+    /// Returns the `organizationName` attribute of this name.
+    ///
+    /// See [`Name::common_name()`].
+    pub fn organization(&self) -> Option<&'cert str> {
+        self.find_attr(&oid::ORGANIZATION_NAME)
+    }
+
+    /// Returns the `serialNumber` attribute of this name.
+    ///
+    /// This is unrelated to the serial number of the certificate the name
+    /// came from. See [`Name::common_name()`].
+    pub fn serial_number(&self) -> Option<&'cert str> {
+        self.find_attr(&oid::SERIAL_NUMBER)
+    }
+
+    /// Finds the last string-valued attribute of type `oid`.
+    fn find_attr(&self, oid: &Oid) -> Option<&'cert str> {
+        if self.format != CertFormat::RiotX509 {
+            return None;
+        }
+
+        let value = untrusted::Input::from(self.raw)
+            .read_all(Error::BadEncoding, |buf| {
+                let mut value = None;
+                while !buf.at_end() {
+                    der::tagged(Tag::SET, buf, |buf| {
+                        while !buf.at_end() {
+                            let attr = Attr::parse(buf)?;
+                            if attr.oid == *oid && attr.is_string() {
+                                value = Some(attr.value);
+                            }
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(value)
+            })
+            .ok()
+            .flatten()?;
+        core::str::from_utf8(value).ok()
+    }
+}
+
+/// Returns whether two DER-encoded `RDNSequence`s (without their outer
+/// `SEQUENCE` headers) name the same entity.
+///
+/// Names match if they have the same number of RDNs, and each pair of RDNs
+/// contains the same attributes, in any order. Attribute values that are
+/// `PrintableString`s, `UTF8String`s or `IA5String`s are compared with
+/// [`strings_eq()`], regardless of which of these types they are encoded
+/// as; all other values are compared byte-for-byte.
+///
+/// Malformed names only match names that are byte-for-byte identical.
+pub fn dn_eq(a: &[u8], b: &[u8]) -> bool {
+    if a == b {
+        return true;
+    }
+
+    untrusted::Input::from(a)
+        .read_all(Error::BadEncoding, |a| {
+            untrusted::Input::from(b).read_all(Error::BadEncoding, |b| {
+                let mut eq = true;
+                while !a.at_end() && !b.at_end() {
+                    let rdn_a = der::parse(Tag::SET, a)?;
+                    let rdn_b = der::parse(Tag::SET, b)?;
+                    eq &=
+                        rdn_subset(rdn_a, rdn_b)? && rdn_subset(rdn_b, rdn_a)?;
+                }
+                // If one name has more RDNs than the other, this fails with
+                // an incomplete read.
+                Ok(eq)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Returns whether every attribute in the RDN `a` matches some attribute in
+/// the RDN `b`.
+fn rdn_subset(a: untrusted::Input, b: untrusted::Input) -> Result<bool, Error> {
+    a.read_all(Error::BadEncoding, |a| {
+        // RDNs must contain at least one attribute.
+        if a.at_end() {
+            return Err(Error::BadEncoding);
+        }
+
+        let mut subset = true;
+        while !a.at_end() {
+            let attr = Attr::parse(a)?;
+            subset &= b.read_all(Error::BadEncoding, |b| {
+                let mut found = false;
+                while !b.at_end() {
+                    found |= Attr::parse(b)?.matches(&attr);
+                }
+                Ok(found)
+            })?;
+        }
+        Ok(subset)
+    })
+}
+
+/// Compares two strings using a simplified `caseIgnoreMatch` rule, as
+/// required by RFC 5280.
+///
+/// Leading and trailing spaces are ignored, internal runs of spaces are
+/// treated as a single space, and ASCII letters are compared
+/// case-insensitively. Unlike RFC 4518, no other Unicode normalization or
+/// case folding is performed, so non-ASCII characters must match exactly.
+fn strings_eq(a: &[u8], b: &[u8]) -> bool {
+    fn normalize(s: &[u8]) -> impl Iterator<Item = u8> + '_ {
+        let start = s.iter().take_while(|&&c| c == b' ').count();
+        let end = s.len() - s.iter().rev().take_while(|&&c| c == b' ').count();
+        // If `s` is all spaces, `start` may be past `end`.
+        let s = s.get(start..end).unwrap_or(&[]);
+        s.iter()
+            .enumerate()
+            // Since `s` has no leading spaces, a space is never at index 0.
+            .filter(move |&(i, &c)| c != b' ' || s[i - 1] != b' ')
+            .map(|(_, c)| c.to_ascii_lowercase())
+    }
+    normalize(a).eq(normalize(b))
+}
+
+/// An `AttributeTypeAndValue`.
+///
+/// ```asn1
+/// AttributeTypeAndValue ::= SEQUENCE {
+///   type  OBJECT IDENTIFIER,
+///   value ANY DEFINED BY type
+/// }
+/// ```
+struct Attr<'cert> {
+    oid: Oid<'cert>,
+    tag: Tag,
+    value: &'cert [u8],
+}
+
+impl<'cert> Attr<'cert> {
+    fn parse(buf: &mut untrusted::Reader<'cert>) -> Result<Self, Error> {
+        der::tagged(Tag::SEQUENCE, buf, |buf| {
+            let oid = der::oid(buf)?;
+            let (tag, value) = der::any(buf)?;
+            Ok(Self {
+                oid,
+                tag,
+                value: value.as_slice_less_safe(),
+            })
+        })
+    }
+
+    fn is_string(&self) -> bool {
+        matches!(
+            self.tag,
+            Tag::PRINTABLE_STRING | Tag::UTF8_STRING | Tag::IA5_STRING
+        )
+    }
+
+    fn matches(&self, other: &Attr) -> bool {
+        if self.oid != other.oid {
+            return false;
+        }
+        if self.is_string() && other.is_string() {
+            return strings_eq(self.value, other.value);
+        }
+        self.tag == other.tag && self.value == other.value
+    }
+}
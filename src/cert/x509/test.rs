@@ -6,14 +6,41 @@
 //!
 //! These are hung off to the side to avoid cluttering the main x509.rs.
 
+use core::time::Duration;
+
 use testutil::data;
 use testutil::data::keys;
 
+use crate::cert::encode;
+use crate::cert::x509;
+use crate::cert::x509::der;
+use crate::cert::x509::der::Tag;
+use crate::cert::x509::oid;
+use crate::cert::x509::SigAlgo;
 use crate::cert::Cert;
+use crate::cert::CertBuilder;
 use crate::cert::CertFormat;
+use crate::cert::Crl;
+use crate::cert::Error;
+use crate::cert::ExtendedKeyUsage;
+use crate::cert::Fwid;
+use crate::cert::FwidAlgo;
+use crate::cert::KeyUsage;
+use crate::cert::Measurements;
+use crate::cert::Name;
+use crate::cert::OperationalFlags;
+use crate::cert::SimpleChain;
+use crate::cert::TrustChain;
 use crate::crypto::ring;
+use crate::crypto::sig;
+use crate::crypto::sig::Curve;
 use crate::crypto::sig::NoVerify;
 use crate::crypto::sig::PublicKeyParams;
+use crate::hardware::fake;
+use crate::hardware::WallClock;
+use crate::io::Write as _;
+use crate::mem::Arena;
+use crate::mem::BumpArena;
 
 #[test]
 #[cfg_attr(miri, ignore)]
@@ -52,6 +79,913 @@ fn explicit_key() {
     assert!(!cert.is_within_path_len_constraint(2));
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn self_signed_ecdsa_p256() {
+    let cert = Cert::parse(
+        data::x509::SELF_SIGNED_ECDSA_P256,
+        CertFormat::RiotX509,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+
+    assert_eq!(cert.subject(), cert.issuer());
+    assert!(cert.is_ca_cert().unwrap());
+    match cert.subject_key() {
+        PublicKeyParams::Ecc { curve, x, y } => {
+            assert_eq!(*curve, Curve::NistP256);
+            assert_eq!(*x, &keys::KEY1_ECDSA_P256_X[..]);
+            assert_eq!(*y, &keys::KEY1_ECDSA_P256_Y[..]);
+        }
+        k => panic!("unexpected key: {:?}", k),
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn self_signed_ecdsa_p384() {
+    let cert = Cert::parse(
+        data::x509::SELF_SIGNED_ECDSA_P384,
+        CertFormat::RiotX509,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+
+    match cert.subject_key() {
+        PublicKeyParams::Ecc { curve, x, y } => {
+            assert_eq!(*curve, Curve::NistP384);
+            assert_eq!(*x, &keys::KEY1_ECDSA_P384_X[..]);
+            assert_eq!(*y, &keys::KEY1_ECDSA_P384_Y[..]);
+        }
+        k => panic!("unexpected key: {:?}", k),
+    }
+}
+
+#[test]
+fn ecdsa_wrong_curve_key() {
+    // A P-256 key cannot verify an ecdsa-with-SHA384 signature.
+    let cert = Cert::parse(
+        data::x509::SELF_SIGNED_ECDSA_P384,
+        CertFormat::RiotX509,
+        Some(&PublicKeyParams::Ecc {
+            curve: Curve::NistP256,
+            x: keys::KEY1_ECDSA_P256_X,
+            y: keys::KEY1_ECDSA_P256_Y,
+        }),
+        &mut NoVerify,
+    );
+    assert!(cert.is_err());
+}
+
+#[test]
+fn validity() {
+    let cert = Cert::parse(
+        data::x509::BOUNDED_VALIDITY,
+        CertFormat::RiotX509,
+        None,
+        &mut NoVerify,
+    )
+    .unwrap();
+    // 2021-01-01T00:00:00Z through 2030-01-01T00:00:00Z.
+    assert_eq!(cert.not_before(), Some(Duration::from_secs(1609459200)));
+    assert_eq!(cert.not_after(), Some(Duration::from_secs(1893456000)));
+
+    // This one uses the "no well-defined expiration date" value for both
+    // ends.
+    let cert = Cert::parse(
+        data::x509::SELF_SIGNED,
+        CertFormat::RiotX509,
+        None,
+        &mut NoVerify,
+    )
+    .unwrap();
+    assert_eq!(cert.not_before(), None);
+    assert_eq!(cert.not_after(), None);
+}
+
+#[test]
+fn dice_tcb_info() {
+    let cert = Cert::parse(
+        data::x509::DICE_TCB_INFO,
+        CertFormat::RiotX509,
+        None,
+        &mut NoVerify,
+    )
+    .unwrap();
+    let m = cert.measurements().unwrap();
+    assert_eq!(m.vendor, Some("lowRISC"));
+    assert_eq!(m.model, Some("OpenTitan"));
+    assert_eq!(m.version, Some("1.0"));
+    assert_eq!(m.svn, Some(3));
+    assert_eq!(m.layer, Some(1));
+    assert_eq!(m.index, None);
+    assert_eq!(m.flags, Some(OperationalFlags::DEBUG));
+
+    let digest: Vec<u8> = (0..32).map(|i| (i / 10) << 4 | (i % 10)).collect();
+    assert_eq!(
+        m.fwids(),
+        &[
+            Fwid {
+                algo: Some(FwidAlgo::Sha256),
+                digest: &digest,
+            },
+            Fwid {
+                algo: None,
+                digest: &[0xca, 0xfe, 0xba, 0xbe],
+            },
+        ]
+    );
+    assert!(m.contains_fwid(&Fwid {
+        algo: None,
+        digest: &digest,
+    }));
+    assert!(!m.contains_fwid(&Fwid {
+        algo: Some(FwidAlgo::Sha384),
+        digest: &digest,
+    }));
+
+    let cert = Cert::parse(
+        data::x509::SELF_SIGNED,
+        CertFormat::RiotX509,
+        None,
+        &mut NoVerify,
+    )
+    .unwrap();
+    assert!(cert.measurements().is_none());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_ca_and_leaf() {
+    let mut arena = [0; 8192];
+    let arena = BumpArena::new(&mut arena);
+    let (_, mut signer) = ring::rsa::from_keypair(keys::KEY1_RSA_KEYPAIR);
+    let root_key = PublicKeyParams::Rsa {
+        modulus: keys::KEY1_RSA_MOD,
+        exponent: keys::KEY1_RSA_EXP,
+    };
+    let leaf_key = PublicKeyParams::Ecc {
+        curve: Curve::NistP256,
+        x: keys::KEY1_ECDSA_P256_X,
+        y: keys::KEY1_ECDSA_P256_Y,
+    };
+
+    let root_name = Name::x509_common_name("Root", &arena).unwrap();
+    let root = CertBuilder::new(&[0x80], root_name, root_name, &root_key)
+        .ca(Some(1))
+        .sign(&root_key, &mut signer, &arena)
+        .unwrap();
+    let root = Cert::parse(
+        root,
+        CertFormat::RiotX509,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(root.subject(), root_name);
+    assert_eq!(root.issuer(), root_name);
+    assert_eq!(root.subject_key(), &root_key);
+    assert!(root.supports_cert_signing());
+    assert!(root.is_ca_cert().unwrap());
+    assert!(root.is_within_path_len_constraint(1));
+    assert!(!root.is_within_path_len_constraint(2));
+    assert_eq!(root.not_before(), Some(Duration::from_secs(0)));
+    assert_eq!(root.not_after(), None);
+    assert!(root.measurements().is_none());
+
+    let digest = [0x5a; 32];
+    let mut m = Measurements::default();
+    m.vendor = Some("lowRISC");
+    m.svn = Some(3);
+    m.layer = Some(1);
+    m.flags = Some(OperationalFlags::DEBUG);
+    m.push_fwid(Fwid {
+        algo: Some(FwidAlgo::Sha256),
+        digest: &digest,
+    })
+    .unwrap();
+
+    let leaf_name = Name::x509_common_name("Leaf", &arena).unwrap();
+    let leaf = CertBuilder::new(&[1, 2, 3], root_name, leaf_name, &leaf_key)
+        .validity(
+            Some(Duration::from_secs(1609459200)),
+            Some(Duration::from_secs(2524608000)),
+        )
+        .measurements(&m)
+        .sign(&root_key, &mut signer, &arena)
+        .unwrap();
+    let leaf = Cert::parse(
+        leaf,
+        CertFormat::RiotX509,
+        Some(&root_key),
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(leaf.subject(), leaf_name);
+    assert_eq!(leaf.issuer(), root_name);
+    assert_eq!(leaf.subject_key(), &leaf_key);
+    assert!(!leaf.supports_cert_signing());
+    assert_eq!(leaf.is_ca_cert(), None);
+    assert_eq!(leaf.not_before(), Some(Duration::from_secs(1609459200)));
+    assert_eq!(leaf.not_after(), Some(Duration::from_secs(2524608000)));
+
+    let m2 = leaf.measurements().unwrap();
+    assert_eq!(m2.vendor, Some("lowRISC"));
+    assert_eq!(m2.model, None);
+    assert_eq!(m2.svn, Some(3));
+    assert_eq!(m2.layer, Some(1));
+    assert_eq!(m2.index, None);
+    assert_eq!(m2.flags, Some(OperationalFlags::DEBUG));
+    assert_eq!(m2.fwids(), m.fwids());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_ecdsa() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer = ring::ecdsa::SignP384::with_der_encoding_from_pkcs8(
+        keys::KEY1_ECDSA_P384_KEYPAIR,
+    )
+    .unwrap();
+    let key = PublicKeyParams::Ecc {
+        curve: Curve::NistP384,
+        x: keys::KEY1_ECDSA_P384_X,
+        y: keys::KEY1_ECDSA_P384_Y,
+    };
+
+    let name = Name::x509_common_name("Root", &arena).unwrap();
+    let cert = CertBuilder::new(&[42], name, name, &key)
+        .ca(None)
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+    let cert = Cert::parse(
+        cert,
+        CertFormat::RiotX509,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(cert.subject_key(), &key);
+    assert!(cert.is_ca_cert().unwrap());
+    assert!(cert.is_within_path_len_constraint(100));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_ed25519() {
+    let mut arena = [0; 1024];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+
+    let name = Name::x509_common_name("my cool ca", &arena).unwrap();
+    let cert = CertBuilder::new(&[1], name, name, &key)
+        .ca(None)
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+    let cert = Cert::parse(
+        cert,
+        CertFormat::RiotX509,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert_eq!(cert.subject_key(), &key);
+    assert!(cert.supports_cert_signing());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn build_bad() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let (_, mut signer) = ring::rsa::from_keypair(keys::KEY1_RSA_KEYPAIR);
+    let key = PublicKeyParams::Rsa {
+        modulus: keys::KEY1_RSA_MOD,
+        exponent: keys::KEY1_RSA_EXP,
+    };
+    let name = Name::x509_common_name("Root", &arena).unwrap();
+
+    let serials: &[&[u8]] = &[&[], &[0, 0], &[0x7f; 21], &[0x80; 20]];
+    for &serial in serials {
+        let cert = CertBuilder::new(serial, name, name, &key).sign(
+            &key,
+            &mut signer,
+            &arena,
+        );
+        assert!(cert.is_err());
+    }
+    let cert = CertBuilder::new(&[0x7f; 20], name, name, &key).sign(
+        &key,
+        &mut signer,
+        &arena,
+    );
+    assert!(cert.is_ok());
+
+    let mut m = Measurements::default();
+    m.push_fwid(Fwid {
+        algo: None,
+        digest: &[0xca, 0xfe, 0xba, 0xbe],
+    })
+    .unwrap();
+    let cert = CertBuilder::new(&[1], name, name, &key)
+        .measurements(&m)
+        .sign(&key, &mut signer, &arena);
+    assert!(cert.is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn key_ids_and_ext_key_usage() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let ca_key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+    let leaf_key = PublicKeyParams::Ecc {
+        curve: Curve::NistP256,
+        x: keys::KEY1_ECDSA_P256_X,
+        y: keys::KEY1_ECDSA_P256_Y,
+    };
+
+    let ca_name = Name::x509_common_name("CA", &arena).unwrap();
+    let leaf_name = Name::x509_common_name("Leaf", &arena).unwrap();
+    let ca = CertBuilder::new(&[1], ca_name, ca_name, &ca_key)
+        .ca(None)
+        .key_identifiers(Some(&[0xaa; 20][..]), Some(&[0xaa; 20][..]))
+        .sign(&ca_key, &mut signer, &arena)
+        .unwrap();
+    let mut leaf = |aki: &'static [u8], eku| {
+        CertBuilder::new(&[0x80, 2], ca_name, leaf_name, &leaf_key)
+            .key_identifiers(Some(&[0xbb; 4][..]), Some(aki))
+            .extended_key_usage(eku)
+            .sign(&ca_key, &mut signer, &arena)
+            .unwrap()
+    };
+    let chain = |leaf| {
+        SimpleChain::<2>::parse(
+            &[ca, leaf],
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            None,
+            &[],
+        )
+    };
+
+    let eku = ExtendedKeyUsage::ATTEST_LOC | ExtendedKeyUsage::ECA;
+    let ok = leaf(&[0xaa; 20], eku);
+    let chain_ok = chain(ok).unwrap();
+    let ca_cert = chain_ok.cert(0, 0).unwrap();
+    assert_eq!(ca_cert.serial(), Some(&[1][..]));
+    assert_eq!(ca_cert.subject_key_id(), Some(&[0xaa; 20][..]));
+    assert_eq!(ca_cert.extended_key_usage(), None);
+    assert_eq!(
+        ca_cert.key_usage(),
+        Some(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN)
+    );
+    let leaf_cert = chain_ok.cert(0, 1).unwrap();
+    assert_eq!(leaf_cert.serial(), Some(&[0x80, 2][..]));
+    assert_eq!(leaf_cert.subject_key_id(), Some(&[0xbb; 4][..]));
+    assert_eq!(leaf_cert.authority_key_id(), Some(&[0xaa; 20][..]));
+    assert_eq!(leaf_cert.extended_key_usage(), Some(eku));
+    assert_eq!(leaf_cert.key_usage(), Some(KeyUsage::DIGITAL_SIGNATURE));
+    assert!(leaf_cert.supports_attestation());
+
+    let any = leaf(&[0xaa; 20], ExtendedKeyUsage::ANY);
+    assert!(any.windows(ANY_EKU.len()).any(|w| w == ANY_EKU));
+    assert!(chain(any).is_ok());
+    assert!(matches!(
+        chain(leaf(&[0xcc; 20], eku)),
+        Err(Error::BadChainLink)
+    ));
+    assert!(matches!(
+        chain(leaf(&[0xaa; 20], ExtendedKeyUsage::IDENTITY_LOC)),
+        Err(Error::WrongKeyUsage)
+    ));
+
+    let empty = CertBuilder::new(&[1], ca_name, leaf_name, &leaf_key)
+        .extended_key_usage(ExtendedKeyUsage::empty())
+        .sign(&ca_key, &mut signer, &arena);
+    assert!(empty.is_err());
+}
+
+/// An `ExtKeyUsageSyntax` containing only `anyExtendedKeyUsage`.
+const ANY_EKU: &[u8] = &[0x30, 0x06, 0x06, 0x04, 0x55, 0x1d, 0x25, 0x00];
+
+#[test]
+fn parse_any_ext_key_usage() {
+    let parse = |bytes: &[u8]| {
+        untrusted::Input::from(bytes)
+            .read_all(Error::BadEncoding, x509::parse_ext_key_usage)
+    };
+    let eku = parse(ANY_EKU).unwrap();
+    assert_eq!(eku, ExtendedKeyUsage::ANY);
+    assert!(eku.allows_attestation());
+
+    // The `extKeyUsage` extension's own OID is not a purpose.
+    let eku = parse(&[0x30, 0x05, 0x06, 0x03, 0x55, 0x1d, 0x25]).unwrap();
+    assert_eq!(eku, ExtendedKeyUsage::empty());
+    assert!(!eku.allows_attestation());
+}
+
+/// A relative distinguished name, as accepted by `encode_name()`.
+type Rdn<'a> = &'a [(der::Oid<'a>, Tag, &'a str)];
+
+/// Encodes an X.509 name with the given RDNs, each of which consists of
+/// attribute types, string types and values.
+fn encode_name<'a>(rdns: &[Rdn], arena: &'a dyn Arena) -> Name<'a> {
+    let raw = encode::to_arena(
+        &|w| {
+            for rdn in rdns {
+                der::write(w, Tag::SET, &|w| {
+                    for (oid, tag, value) in rdn.iter() {
+                        der::write(w, Tag::SEQUENCE, &|w| {
+                            der::write_oid(w, oid)?;
+                            der::write_bytes(w, *tag, value.as_bytes())
+                        })?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        },
+        arena,
+    )
+    .unwrap();
+    Name::from_der(raw)
+}
+
+#[test]
+fn name_matching() {
+    let mut arena = [0; 2048];
+    let arena = BumpArena::new(&mut arena);
+    let (utf8, printable) = (Tag::UTF8_STRING, Tag::PRINTABLE_STRING);
+
+    let name = encode_name(
+        &[
+            &[(oid::ORGANIZATION_NAME, utf8, "lowRISC")],
+            &[
+                (oid::COMMON_NAME, utf8, "Root CA"),
+                (oid::SERIAL_NUMBER, printable, "1234"),
+            ],
+        ],
+        &arena,
+    );
+    assert_eq!(name.common_name(), Some("Root CA"));
+    assert_eq!(name.organization(), Some("lowRISC"));
+    assert_eq!(name.serial_number(), Some("1234"));
+
+    // Different string types, case, spacing and attribute order within an
+    // RDN are all insignificant.
+    let same = encode_name(
+        &[
+            &[(oid::ORGANIZATION_NAME, printable, "LOWRISC ")],
+            &[
+                (oid::SERIAL_NUMBER, utf8, "1234"),
+                (oid::COMMON_NAME, printable, "  root   ca"),
+            ],
+        ],
+        &arena,
+    );
+    assert_eq!(name, same);
+    assert_eq!(same.common_name(), Some("  root   ca"));
+
+    let different: &[&[Rdn]] = &[
+        // A different value.
+        &[
+            &[(oid::ORGANIZATION_NAME, utf8, "lowRISC")],
+            &[
+                (oid::COMMON_NAME, utf8, "Root CA 2"),
+                (oid::SERIAL_NUMBER, printable, "1234"),
+            ],
+        ],
+        // Significant spaces.
+        &[
+            &[(oid::ORGANIZATION_NAME, utf8, "low RISC")],
+            &[
+                (oid::COMMON_NAME, utf8, "Root CA"),
+                (oid::SERIAL_NUMBER, printable, "1234"),
+            ],
+        ],
+        // RDNs in a different order.
+        &[
+            &[
+                (oid::COMMON_NAME, utf8, "Root CA"),
+                (oid::SERIAL_NUMBER, printable, "1234"),
+            ],
+            &[(oid::ORGANIZATION_NAME, utf8, "lowRISC")],
+        ],
+        // The same attributes, split across more RDNs.
+        &[
+            &[(oid::ORGANIZATION_NAME, utf8, "lowRISC")],
+            &[(oid::COMMON_NAME, utf8, "Root CA")],
+            &[(oid::SERIAL_NUMBER, printable, "1234")],
+        ],
+        // A missing RDN.
+        &[&[(oid::ORGANIZATION_NAME, utf8, "lowRISC")]],
+        // A value that is not a string.
+        &[
+            &[(oid::ORGANIZATION_NAME, Tag::OCTET_STRING, "lowRISC")],
+            &[
+                (oid::COMMON_NAME, utf8, "Root CA"),
+                (oid::SERIAL_NUMBER, printable, "1234"),
+            ],
+        ],
+    ];
+    for rdns in different {
+        let other = encode_name(rdns, &arena);
+        assert_ne!(name, other);
+        assert_ne!(other, name);
+    }
+
+    assert_ne!(Name::from_der(b"\x31\x00"), Name::from_der(b"\x31\x01\x00"));
+    assert_eq!(Name::from_der(b"\x31\x00"), Name::from_der(b"\x31\x00"));
+    assert_ne!(Name::cwt("Root CA"), Name::cwt("root ca"));
+    assert_ne!(
+        Name::cwt("Root CA"),
+        Name::x509_common_name("Root CA", &arena).unwrap()
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn chain_with_reencoded_names() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+
+    let ca_name = Name::x509_common_name("Root CA", &arena).unwrap();
+    let reencoded = encode_name(
+        &[&[(oid::COMMON_NAME, Tag::PRINTABLE_STRING, "ROOT CA")]],
+        &arena,
+    );
+    let leaf_name = Name::x509_common_name("Leaf", &arena).unwrap();
+    let ca = CertBuilder::new(&[1], ca_name, ca_name, &key)
+        .ca(None)
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+    let leaf = CertBuilder::new(&[2], reencoded, leaf_name, &key)
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+
+    let chain = SimpleChain::<2>::parse(
+        &[ca, leaf],
+        CertFormat::RiotX509,
+        &mut ring::sig::Ciphers::new(),
+        &ring::sha256::Builder::new(),
+        None,
+        None,
+        &[],
+    )
+    .unwrap();
+    let leaf = chain.cert(0, 1).unwrap();
+    assert_eq!(leaf.issuer().common_name(), Some("ROOT CA"));
+    assert_eq!(leaf.issuer(), ca_name);
+}
+
+/// A v2 CRL to encode with [`encode_crl()`].
+#[derive(Clone, Copy)]
+struct TestCrl<'a> {
+    issuer: Name<'a>,
+    serials: &'a [&'a [u8]],
+    authority_key_id: Option<&'a [u8]>,
+    next_update: Option<Duration>,
+    /// Whether the `cRLNumber` extension is marked critical.
+    is_critical: bool,
+}
+
+impl<'a> TestCrl<'a> {
+    fn new(issuer: Name<'a>, serials: &'a [&'a [u8]]) -> Self {
+        Self {
+            issuer,
+            serials,
+            authority_key_id: None,
+            next_update: None,
+            is_critical: false,
+        }
+    }
+}
+
+/// The `thisUpdate` of every [`TestCrl`], as well as the revocation date of
+/// every serial number in it.
+const CRL_THIS_UPDATE: Duration = Duration::from_secs(1609459200);
+
+/// Encodes `crl`, with a `cRLNumber` extension, signed with `key`.
+fn encode_crl<'a>(
+    crl: &TestCrl,
+    key: &PublicKeyParams,
+    signer: &mut dyn sig::Sign,
+    arena: &'a dyn Arena,
+) -> &'a [u8] {
+    let algo = SigAlgo::for_signing_key(key).unwrap();
+    let time = Some(CRL_THIS_UPDATE);
+    let tbs = encode::to_arena(
+        &|w| {
+            der::write(w, Tag::SEQUENCE, &|w| {
+                der::write_u32(w, 1)?;
+                x509::write_algo(w, algo)?;
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    w.write_bytes(crl.issuer.raw)
+                })?;
+                der::write_time(w, time)?;
+                if let Some(next) = crl.next_update {
+                    der::write_time(w, Some(next))?;
+                }
+                der::write(w, Tag::SEQUENCE, &|w| {
+                    for serial in crl.serials {
+                        der::write(w, Tag::SEQUENCE, &|w| {
+                            der::write_uint(w, serial)?;
+                            der::write_time(w, time)
+                        })?;
+                    }
+                    Ok(())
+                })?;
+                der::write(w, Tag::context_specific(0), &|w| {
+                    der::write(w, Tag::SEQUENCE, &|w| {
+                        der::write(w, Tag::SEQUENCE, &|w| {
+                            der::write_oid(w, &oid::CRL_NUMBER)?;
+                            if crl.is_critical {
+                                der::write_bool(w, true)?;
+                            }
+                            der::write(w, Tag::OCTET_STRING, &|w| {
+                                der::write_u32(w, 1)
+                            })
+                        })?;
+                        if let Some(id) = crl.authority_key_id {
+                            der::write(w, Tag::SEQUENCE, &|w| {
+                                der::write_oid(w, &oid::AUTHORITY_KEY_ID)?;
+                                der::write(w, Tag::OCTET_STRING, &|w| {
+                                    der::write(w, Tag::SEQUENCE, &|w| {
+                                        let key_id =
+                                            Tag::context_specific_primitive(0);
+                                        der::write_bytes(w, key_id, id)
+                                    })
+                                })
+                            })?;
+                        }
+                        Ok(())
+                    })
+                })
+            })
+        },
+        arena,
+    )
+    .unwrap();
+    x509::sign(tbs, algo, signer, arena).unwrap()
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn crl() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let ca_key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+    let leaf_key = PublicKeyParams::Ecc {
+        curve: Curve::NistP256,
+        x: keys::KEY1_ECDSA_P256_X,
+        y: keys::KEY1_ECDSA_P256_Y,
+    };
+
+    let ca_name = Name::x509_common_name("CA", &arena).unwrap();
+    let leaf_name = Name::x509_common_name("Leaf", &arena).unwrap();
+    let ca = CertBuilder::new(&[1], ca_name, ca_name, &ca_key)
+        .ca(None)
+        .sign(&ca_key, &mut signer, &arena)
+        .unwrap();
+    let revoked = CertBuilder::new(&[0x80, 2], ca_name, leaf_name, &leaf_key)
+        .sign(&ca_key, &mut signer, &arena)
+        .unwrap();
+    let ok = CertBuilder::new(&[3], ca_name, leaf_name, &leaf_key)
+        .sign(&ca_key, &mut signer, &arena)
+        .unwrap();
+
+    let next_update = CRL_THIS_UPDATE + Duration::from_secs(86400);
+    let test_crl = TestCrl {
+        next_update: Some(next_update),
+        ..TestCrl::new(ca_name, &[&[0x7f], &[0x80, 2]])
+    };
+    let raw_crl = encode_crl(&test_crl, &ca_key, &mut signer, &arena);
+    let crl = Crl::parse(raw_crl).unwrap();
+    assert_eq!(crl.raw(), raw_crl);
+    assert_eq!(crl.issuer(), ca_name);
+    assert_eq!(crl.authority_key_id(), None);
+    assert_eq!(crl.this_update(), Some(CRL_THIS_UPDATE));
+    assert_eq!(crl.next_update(), Some(next_update));
+    assert!(!crl.is_stale(next_update));
+    assert!(crl.is_stale(next_update + Duration::from_secs(1)));
+
+    let mut ciphers = ring::sig::Ciphers::new();
+    let parse = |raw| {
+        Cert::parse(raw, CertFormat::RiotX509, Some(&ca_key), &mut NoVerify)
+            .unwrap()
+    };
+    let ca_cert = parse(ca);
+    assert!(ca_cert.supports_crl_signing());
+    crl.verify(&ca_cert, &mut ciphers).unwrap();
+    assert!(crl.revokes(&parse(revoked)).unwrap());
+    assert!(!crl.revokes(&parse(ok)).unwrap());
+    // Leaves may not sign CRLs.
+    assert!(matches!(
+        crl.verify(&parse(ok), &mut ciphers),
+        Err(Error::BadChainLink)
+    ));
+
+    let chain = |leaf, crl: &Crl| {
+        SimpleChain::<2>::parse(
+            &[ca, leaf],
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            None,
+            &[crl.clone()],
+        )
+    };
+    assert!(matches!(chain(revoked, &crl), Err(Error::Revoked)));
+    assert!(chain(ok, &crl).is_ok());
+
+    // Without key identifiers, a CRL that does not verify may have been
+    // issued by some other CA with the same name, so it is ignored.
+    let mut tampered = raw_crl.to_vec();
+    *tampered.last_mut().unwrap() ^= 1;
+    let tampered = Crl::parse(&tampered).unwrap();
+    assert!(chain(revoked, &tampered).is_ok());
+
+    // CRLs from other issuers are ignored.
+    let other_name = Name::x509_common_name("Other", &arena).unwrap();
+    let other = TestCrl::new(other_name, &[&[3]]);
+    let other = encode_crl(&other, &ca_key, &mut signer, &arena);
+    assert!(chain(ok, &Crl::parse(other).unwrap()).is_ok());
+
+    // Unknown critical extensions make the CRL unusable.
+    let critical = TestCrl {
+        is_critical: true,
+        ..TestCrl::new(ca_name, &[&[3]])
+    };
+    let critical = encode_crl(&critical, &ca_key, &mut signer, &arena);
+    assert!(Crl::parse(critical).is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn crl_key_ids() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+
+    let ca_name = Name::x509_common_name("CA", &arena).unwrap();
+    let leaf_name = Name::x509_common_name("Leaf", &arena).unwrap();
+    let ca = CertBuilder::new(&[1], ca_name, ca_name, &key)
+        .ca(None)
+        .key_identifiers(Some(b"ca"), Some(b"ca"))
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+    let leaf = CertBuilder::new(&[2], ca_name, leaf_name, &key)
+        .key_identifiers(None, Some(b"ca"))
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+
+    let mut chain = |crl: &TestCrl, tamper: bool| {
+        let mut crl = encode_crl(crl, &key, &mut signer, &arena).to_vec();
+        if tamper {
+            *crl.last_mut().unwrap() ^= 1;
+        }
+        SimpleChain::<2>::parse(
+            &[ca, leaf],
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            None,
+            &[Crl::parse(&crl).unwrap()],
+        )
+    };
+
+    let revoking = TestCrl {
+        authority_key_id: Some(b"ca"),
+        ..TestCrl::new(ca_name, &[&[2]])
+    };
+    assert!(matches!(chain(&revoking, false), Err(Error::Revoked)));
+
+    // A CRL for a different key with the same name is ignored.
+    let other_key = TestCrl {
+        authority_key_id: Some(b"other"),
+        ..revoking
+    };
+    assert!(chain(&other_key, false).is_ok());
+
+    // Once the key identifiers match, the CRL is known to come from the CA,
+    // so a bad signature is an error, rather than a reason to ignore it.
+    assert!(matches!(chain(&revoking, true), Err(Error::BadSignature)));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn stale_crl() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let mut signer =
+        ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+    let key = PublicKeyParams::Okp {
+        curve: Curve::Ed25519,
+        x: keys::KEY1_ED25519_RAW,
+    };
+
+    let ca_name = Name::x509_common_name("CA", &arena).unwrap();
+    let leaf_name = Name::x509_common_name("Leaf", &arena).unwrap();
+    let ca = CertBuilder::new(&[1], ca_name, ca_name, &key)
+        .ca(None)
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+    let leaf = CertBuilder::new(&[2], ca_name, leaf_name, &key)
+        .sign(&key, &mut signer, &arena)
+        .unwrap();
+
+    let next_update = CRL_THIS_UPDATE + Duration::from_secs(86400);
+    let crl = TestCrl {
+        next_update: Some(next_update),
+        ..TestCrl::new(ca_name, &[])
+    };
+    let crl = Crl::parse(encode_crl(&crl, &key, &mut signer, &arena)).unwrap();
+
+    let clock = fake::Clock::new();
+    let chain = |clock: Option<&fake::Clock>| {
+        SimpleChain::<2>::parse(
+            &[ca, leaf],
+            CertFormat::RiotX509,
+            &mut ring::sig::Ciphers::new(),
+            &ring::sha256::Builder::new(),
+            None,
+            clock.map(|c| c as &dyn WallClock),
+            &[crl.clone()],
+        )
+    };
+
+    clock.advance(next_update);
+    assert!(chain(Some(&clock)).is_ok());
+    clock.advance(Duration::from_secs(1));
+    assert!(matches!(chain(Some(&clock)), Err(Error::Expired)));
+    // Without a clock, staleness cannot be checked.
+    assert!(chain(None).is_ok());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn crl_without_crl_sign() {
+    let mut arena = [0; 4096];
+    let arena = BumpArena::new(&mut arena);
+    let (_, mut signer) = ring::rsa::from_keypair(keys::KEY1_RSA_KEYPAIR);
+    let key = PublicKeyParams::Rsa {
+        modulus: keys::KEY1_RSA_MOD,
+        exponent: keys::KEY1_RSA_EXP,
+    };
+
+    // This certificate has keyCertSign, but not cRLSign.
+    let ca = Cert::parse(
+        data::x509::SELF_SIGNED,
+        CertFormat::RiotX509,
+        None,
+        &mut ring::sig::Ciphers::new(),
+    )
+    .unwrap();
+    assert!(ca.supports_cert_signing());
+    assert!(!ca.supports_crl_signing());
+
+    let crl = TestCrl::new(ca.subject(), &[]);
+    let crl = Crl::parse(encode_crl(&crl, &key, &mut signer, &arena)).unwrap();
+    assert!(matches!(
+        crl.verify(&ca, &mut ring::sig::Ciphers::new()),
+        Err(Error::BadChainLink)
+    ));
+}
+
 macro_rules! table_test {
     ([$pred:ident] $($test:ident:$data:ident,)*) => {$(
         #[test]
@@ -82,6 +1016,7 @@ table_test! { [is_err]
     serial_too_long: BAD_SERIAL_TOO_LONG,
     missing_sig_alg: BAD_MISSING_SIG_ALG,
     different_sig_alg: BAD_DIFFERENT_SIG_ALG,
+    ecdsa_alg_with_rsa_key: BAD_ECDSA_ALG_WITH_RSA_KEY,
     missing_rsa_null: BAD_MISSING_RSA_NULL,
     missing_issuer: BAD_MISSING_ISSUER,
     missing_validity: BAD_MISSING_VALIDTY,
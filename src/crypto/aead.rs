@@ -0,0 +1,134 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Authenticated encryption with associated data, i.e., AES-GCM.
+
+use static_assertions::assert_obj_safe;
+
+use crate::protocol::capabilities;
+
+/// An error returned by an AEAD operation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Indicates an unspecified, internal error.
+    Unspecified,
+    /// Indicates that a ciphertext, or its associated data, did not match
+    /// its tag.
+    ///
+    /// When this error is returned by [`Aead::open_in_place()`], the
+    /// contents of the buffer are unspecified, and must not be used.
+    WrongTag,
+    /// Indicates that an [`Algo`] is not supported by this implementation.
+    UnsupportedAlgo,
+}
+
+/// The length of an AES-GCM nonce, in bytes.
+pub const NONCE_LEN: usize = 12;
+
+/// The length of an AES-GCM tag, in bytes.
+pub const TAG_LEN: usize = 16;
+
+/// An AES-GCM nonce.
+///
+/// A nonce must never be used twice with the same key.
+pub type Nonce = [u8; NONCE_LEN];
+
+/// An AES-GCM authentication tag.
+pub type Tag = [u8; TAG_LEN];
+
+/// An AEAD algorithm.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Algo {
+    /// AES-GCM with a 128-bit key.
+    Aes128Gcm,
+    /// AES-GCM with a 256-bit key.
+    Aes256Gcm,
+}
+
+impl Algo {
+    /// Returns the length of this algorithm's keys, in bytes.
+    pub fn key_len(self) -> usize {
+        match self {
+            Self::Aes128Gcm => 16,
+            Self::Aes256Gcm => 32,
+        }
+    }
+
+    /// Returns the key strength this algorithm corresponds to in capabilities
+    /// negotiation.
+    pub fn strength(self) -> capabilities::AesKeyStrength {
+        match self {
+            Self::Aes128Gcm => capabilities::AesKeyStrength::BITS_128,
+            Self::Aes256Gcm => capabilities::AesKeyStrength::BITS_256,
+        }
+    }
+}
+
+/// An AEAD engine, already primed with a key.
+///
+/// There is no way to extract the key back out of an `Aead` value.
+pub trait Aead {
+    /// Returns the algorithm this engine was primed for.
+    fn algo(&self) -> Algo;
+
+    /// Encrypts a message in place, appending the tag that authenticates it
+    /// alongside `aad`.
+    ///
+    /// `buf` must consist of the plaintext followed by [`TAG_LEN`] bytes of
+    /// space for the tag; on success, it holds the ciphertext followed by
+    /// the tag.
+    ///
+    /// The associated data is contiguous, rather than an iovec as in
+    /// [`sig::Verify::verify()`], since AES-GCM implementations generally
+    /// cannot process it piecemeal without copying it into a buffer first.
+    ///
+    /// [`sig::Verify::verify()`]: crate::crypto::sig::Verify::verify
+    fn seal_in_place(
+        &mut self,
+        nonce: &Nonce,
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), Error>;
+
+    /// Decrypts a message in place, checking the tag that authenticates it
+    /// alongside `aad`.
+    ///
+    /// `buf` must consist of the ciphertext followed by the tag, as produced
+    /// by `seal_in_place()`; on success, the first `buf.len() - TAG_LEN`
+    /// bytes hold the plaintext. Failures, including tag check failures, are
+    /// included in the `Err` variant.
+    fn open_in_place(
+        &mut self,
+        nonce: &Nonce,
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), Error>;
+}
+assert_obj_safe!(Aead);
+
+/// A collection of AEAD ciphers.
+///
+/// Users are expected to implement this trait to describe to Manticore which
+/// AES key strengths they support and how to access them; compare
+/// [`sig::Ciphers`].
+///
+/// [`sig::Ciphers`]: crate::crypto::sig::Ciphers
+pub trait Ciphers {
+    /// Performs cryptographic capabilities negotiation.
+    ///
+    /// This function populates `caps` with whatever AES key strengths it
+    /// supports.
+    fn negotiate(&self, caps: &mut capabilities::Crypto);
+
+    /// Returns an [`Aead`] primed with `key`, which must be exactly
+    /// `algo.key_len()` bytes long.
+    ///
+    /// Returns [`Error::UnsupportedAlgo`] if `algo` is not supported.
+    fn aead<'a>(
+        &'a mut self,
+        algo: Algo,
+        key: &[u8],
+    ) -> Result<&'a mut dyn Aead, Error>;
+}
+assert_obj_safe!(Ciphers);
@@ -0,0 +1,742 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A deterministic random bit generator, per NIST SP 800-90A.
+//!
+//! [`Drbg`] is an `HMAC_DRBG` that implements [`csrng::Csrng`] on top of an
+//! arbitrary [`hmac::Builder`] and a raw [`Entropy`] source, such as an
+//! on-chip TRNG. Samples from the source are health-tested and conditioned
+//! per SP 800-90B before being used to seed the generator.
+
+use crate::crypto::csrng;
+use crate::crypto::hmac;
+
+/// An error returned by a DRBG operation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Indicates an unspecified, internal error.
+    Unspecified,
+    /// Indicates that the entropy source failed one of its continuous health
+    /// tests.
+    ///
+    /// This error is sticky: once it is returned, the DRBG can no longer be
+    /// used and must be replaced.
+    HealthTestFailed,
+    /// Indicates that the DRBG must be reseeded before generating more
+    /// output, but that it has no entropy source to reseed itself with.
+    ReseedRequired,
+    /// Indicates that the DRBG was asked to reseed itself, but has no entropy
+    /// source.
+    NoEntropySource,
+    /// Indicates that explicitly-provided entropy input or nonce was too
+    /// short for the DRBG's security strength.
+    InsufficientEntropy,
+    /// Indicates that more than [`MAX_REQUEST_LEN`] bytes were requested
+    /// from [`Drbg::generate()`].
+    RequestTooLarge,
+}
+
+impl From<hmac::Error> for Error {
+    fn from(_: hmac::Error) -> Self {
+        Self::Unspecified
+    }
+}
+
+/// The maximum number of bytes a single call to [`Drbg::generate()`] can
+/// produce.
+pub const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// The maximum number of calls to [`Drbg::generate()`] allowed between
+/// reseeds.
+pub const MAX_RESEED_INTERVAL: u64 = 1 << 48;
+
+/// The minimum length of entropy input, in bytes, corresponding to a security
+/// strength of 256 bits.
+const MIN_ENTROPY_LEN: usize = 32;
+
+/// The minimum length of a nonce, in bytes.
+const MIN_NONCE_LEN: usize = MIN_ENTROPY_LEN / 2;
+
+/// The number of samples that are health-tested and discarded when a [`Drbg`]
+/// is first created, per SP 800-90B Section 4.3.
+const STARTUP_SAMPLES: usize = 1024;
+
+/// The window size of the Adaptive Proportion Test, per SP 800-90B Section
+/// 4.4.2.
+const APT_WINDOW: u32 = 512;
+
+/// Repetition Count Test cutoffs, indexed by min-entropy per sample minus
+/// one, computed as `1 + ceil(20 / H)`.
+const RCT_CUTOFFS: [u32; 8] = [21, 11, 8, 6, 5, 5, 4, 4];
+
+/// Adaptive Proportion Test cutoffs, indexed by min-entropy per sample minus
+/// one, computed as `1 + CRITBINOM(512, 2^-H, 1 - 2^-20)`.
+const APT_CUTOFFS: [u32; 8] = [311, 177, 103, 62, 39, 25, 18, 13];
+
+/// A raw, unconditioned entropy source, such as a TRNG.
+pub trait Entropy {
+    /// Returns the assessed min-entropy of each sample produced by this
+    /// source, in bits, as described in SP 800-90B Section 3.1.
+    ///
+    /// This must be between 1 and 8, inclusive; it determines both the
+    /// health-test cutoffs and how many samples are drawn per reseed.
+    fn min_entropy(&self) -> u8;
+
+    /// Fills `buf` with raw samples from the noise source, one per byte.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), Error>;
+}
+
+/// Options for configuring a [`Drbg`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Options {
+    /// The HMAC algorithm underlying the DRBG.
+    pub algo: hmac::Algo,
+    /// The number of calls to [`Drbg::generate()`] allowed before the DRBG
+    /// must be reseeded. Must be between 1 and [`MAX_RESEED_INTERVAL`].
+    pub reseed_interval: u64,
+}
+
+/// An `HMAC_DRBG`, as specified in SP 800-90A Section 10.1.2.
+///
+/// A `Drbg` is usually created with [`Drbg::new()`], which draws its seed
+/// from an [`Entropy`] source and reseeds itself from that source whenever
+/// its reseed interval is exhausted. For certification, a `Drbg` may instead
+/// be created with [`Drbg::new_known_answer()`], which takes all entropy
+/// input explicitly so that its output can be compared against known-answer
+/// test vectors.
+pub struct Drbg<'a> {
+    hmac: &'a mut dyn hmac::Builder,
+    source: Option<Source<'a>>,
+    algo: hmac::Algo,
+    reseed_interval: u64,
+
+    key: [u8; hmac::MAX_TAG_LEN],
+    v: [u8; hmac::MAX_TAG_LEN],
+    reseed_counter: u64,
+    // Set once the DRBG has hit an unrecoverable error.
+    failure: Option<Error>,
+}
+
+impl<'a> Drbg<'a> {
+    /// Creates a new `Drbg`, seeded from `entropy`.
+    ///
+    /// `personalization` is an optional, device-specific string that is mixed
+    /// into the seed; it need not be secret.
+    ///
+    /// Before any samples are used, startup health tests are run over the
+    /// first 1024 samples from `entropy`, which are then discarded.
+    pub fn new(
+        hmac: &'a mut dyn hmac::Builder,
+        entropy: &'a mut dyn Entropy,
+        opts: Options,
+        personalization: &[u8],
+    ) -> Result<Self, Error> {
+        let source = Source::new(entropy, opts.algo)?;
+        let mut drbg = Self::uninit(hmac, Some(source), opts)?;
+
+        let len = drbg.algo.tag_len();
+        let mut entropy = [0; hmac::MAX_TAG_LEN];
+        let mut nonce = [0; hmac::MAX_TAG_LEN];
+        drbg.with_source(|source, hmac| {
+            source.startup()?;
+            source.condition(hmac, &mut entropy[..len])?;
+            source.condition(hmac, &mut nonce[..len])
+        })?;
+        drbg.instantiate(&[&entropy[..len], &nonce[..len], personalization])?;
+        Ok(drbg)
+    }
+
+    /// Creates a new `Drbg` in known-answer test mode, seeded from the given
+    /// `entropy_input`, `nonce`, and `personalization` strings.
+    ///
+    /// A `Drbg` created this way has no entropy source: it must be reseeded
+    /// explicitly with [`Drbg::reseed_with()`], and will return
+    /// [`Error::ReseedRequired`] once its reseed interval is exhausted.
+    ///
+    /// This is intended for running the CAVP test vectors for `HMAC_DRBG`;
+    /// `entropy_input` must be at least 32 bytes and `nonce` at least 16.
+    pub fn new_known_answer(
+        hmac: &'a mut dyn hmac::Builder,
+        opts: Options,
+        entropy_input: &[u8],
+        nonce: &[u8],
+        personalization: &[u8],
+    ) -> Result<Self, Error> {
+        if entropy_input.len() < MIN_ENTROPY_LEN || nonce.len() < MIN_NONCE_LEN
+        {
+            return Err(Error::InsufficientEntropy);
+        }
+        let mut drbg = Self::uninit(hmac, None, opts)?;
+        drbg.instantiate(&[entropy_input, nonce, personalization])?;
+        Ok(drbg)
+    }
+
+    fn uninit(
+        hmac: &'a mut dyn hmac::Builder,
+        source: Option<Source<'a>>,
+        opts: Options,
+    ) -> Result<Self, Error> {
+        if opts.reseed_interval == 0
+            || opts.reseed_interval > MAX_RESEED_INTERVAL
+        {
+            return Err(Error::Unspecified);
+        }
+        Ok(Self {
+            hmac,
+            source,
+            algo: opts.algo,
+            reseed_interval: opts.reseed_interval,
+            key: [0; hmac::MAX_TAG_LEN],
+            v: [0; hmac::MAX_TAG_LEN],
+            reseed_counter: 0,
+            failure: None,
+        })
+    }
+
+    /// Reseeds this `Drbg` from its entropy source, mixing in the optional
+    /// `additional` input.
+    ///
+    /// Returns [`Error::NoEntropySource`] in known-answer test mode.
+    pub fn reseed(&mut self, additional: &[u8]) -> Result<(), Error> {
+        self.check_ok()?;
+        if self.source.is_none() {
+            return Err(Error::NoEntropySource);
+        }
+
+        let len = self.algo.tag_len();
+        let mut entropy = [0; hmac::MAX_TAG_LEN];
+        self.with_source(|source, hmac| {
+            source.condition(hmac, &mut entropy[..len])
+        })?;
+        self.reseed_inner(&[&entropy[..len], additional])
+    }
+
+    /// Reseeds this `Drbg` with an explicit `entropy_input` of at least 32
+    /// bytes, mixing in the optional `additional` input.
+    pub fn reseed_with(
+        &mut self,
+        entropy_input: &[u8],
+        additional: &[u8],
+    ) -> Result<(), Error> {
+        self.check_ok()?;
+        if entropy_input.len() < MIN_ENTROPY_LEN {
+            return Err(Error::InsufficientEntropy);
+        }
+        self.reseed_inner(&[entropy_input, additional])
+    }
+
+    /// Fills `out` with pseudorandom bytes, mixing in the optional
+    /// `additional` input.
+    ///
+    /// If the reseed interval has been exhausted, this function first
+    /// reseeds from the entropy source, or returns [`Error::ReseedRequired`]
+    /// if there is none.
+    pub fn generate(
+        &mut self,
+        out: &mut [u8],
+        additional: &[u8],
+    ) -> Result<(), Error> {
+        self.check_ok()?;
+        if out.len() > MAX_REQUEST_LEN {
+            return Err(Error::RequestTooLarge);
+        }
+
+        let mut additional = additional;
+        if self.reseed_counter > self.reseed_interval {
+            match self.reseed(additional) {
+                Err(Error::NoEntropySource) => {
+                    return Err(Error::ReseedRequired)
+                }
+                result => result?,
+            }
+            additional = &[];
+        }
+
+        let result = self.generate_inner(out, additional);
+        result.map_err(|e| self.poison(e))
+    }
+
+    fn generate_inner(
+        &mut self,
+        out: &mut [u8],
+        additional: &[u8],
+    ) -> Result<(), Error> {
+        if !additional.is_empty() {
+            self.update(&[additional])?;
+        }
+        for chunk in out.chunks_mut(self.algo.tag_len()) {
+            self.next_v()?;
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+        self.update(&[additional])?;
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    fn reseed_inner(&mut self, seed: &[&[u8]]) -> Result<(), Error> {
+        let result = self.update(seed);
+        result.map_err(|e| self.poison(e))?;
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    fn instantiate(&mut self, seed: &[&[u8]]) -> Result<(), Error> {
+        self.key = [0x00; hmac::MAX_TAG_LEN];
+        self.v = [0x01; hmac::MAX_TAG_LEN];
+        self.reseed_inner(seed)
+    }
+
+    /// The `HMAC_DRBG_Update` function, from SP 800-90A Section 10.1.2.2.
+    ///
+    /// `provided` is an iovec-like structure: `provided_data` is the
+    /// concatenation of its buffers.
+    fn update(&mut self, provided: &[&[u8]]) -> Result<(), Error> {
+        let len = self.algo.tag_len();
+        let is_empty = provided.iter().all(|bytes| bytes.is_empty());
+        for &separator in &[0x00, 0x01] {
+            if separator == 0x01 && is_empty {
+                break;
+            }
+
+            let mut key = [0; hmac::MAX_TAG_LEN];
+            let hmac = self.hmac.new_hmac(self.algo, &self.key[..len])?;
+            hmac.write(&self.v[..len])?;
+            hmac.write(&[separator])?;
+            for bytes in provided {
+                hmac.write(bytes)?;
+            }
+            hmac.finish(&mut key[..len])?;
+            self.key = key;
+
+            self.next_v()?;
+        }
+        Ok(())
+    }
+
+    /// Sets `V = HMAC(Key, V)`.
+    fn next_v(&mut self) -> Result<(), Error> {
+        let len = self.algo.tag_len();
+        let mut v = [0; hmac::MAX_TAG_LEN];
+        self.hmac.sign(
+            self.algo,
+            &self.key[..len],
+            &[&self.v[..len]],
+            &mut v[..len],
+        )?;
+        self.v = v;
+        Ok(())
+    }
+
+    /// Calls `f` with this `Drbg`'s entropy source, poisoning the `Drbg` if
+    /// it fails.
+    fn with_source<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Source<'a>, &mut dyn hmac::Builder) -> Result<(), Error>,
+    {
+        let source = self.source.as_mut().ok_or(Error::NoEntropySource)?;
+        let result = f(source, &mut *self.hmac);
+        result.map_err(|e| self.poison(e))
+    }
+
+    fn check_ok(&self) -> Result<(), Error> {
+        match self.failure {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Puts this `Drbg` into a permanent error state, destroying its secret
+    /// state.
+    fn poison(&mut self, e: Error) -> Error {
+        self.failure = Some(e);
+        self.key = [0; hmac::MAX_TAG_LEN];
+        self.v = [0; hmac::MAX_TAG_LEN];
+        e
+    }
+}
+
+impl csrng::Csrng for Drbg<'_> {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), csrng::Error> {
+        for chunk in buf.chunks_mut(MAX_REQUEST_LEN) {
+            self.generate(chunk, &[])
+                .map_err(|_| csrng::Error::Unspecified)?;
+        }
+        Ok(())
+    }
+}
+
+/// An [`Entropy`] source, along with its health-test state.
+struct Source<'a> {
+    entropy: &'a mut dyn Entropy,
+    algo: hmac::Algo,
+    health: Health,
+    // The number of raw samples that are conditioned into a single block of
+    // full-entropy output.
+    samples_per_block: usize,
+}
+
+impl<'a> Source<'a> {
+    fn new(
+        entropy: &'a mut dyn Entropy,
+        algo: hmac::Algo,
+    ) -> Result<Self, Error> {
+        let h = entropy.min_entropy();
+        if !(1..=8).contains(&h) {
+            return Err(Error::Unspecified);
+        }
+
+        // Per SP 800-90B Section 3.1.5.1.2, a vetted conditioning component
+        // produces full-entropy output if it is fed at least 64 bits more
+        // entropy than it outputs.
+        let bits_per_block = 8 * algo.tag_len() + 64;
+        let samples_per_block = (bits_per_block + h as usize - 1) / h as usize;
+
+        Ok(Self {
+            entropy,
+            algo,
+            health: Health::new(h),
+            samples_per_block,
+        })
+    }
+
+    /// Runs the startup health tests, discarding the samples used.
+    fn startup(&mut self) -> Result<(), Error> {
+        let mut buf = [0; 64];
+        for _ in 0..STARTUP_SAMPLES / buf.len() {
+            self.entropy.fill(&mut buf)?;
+            self.health.check(&buf)?;
+        }
+        Ok(())
+    }
+
+    /// Fills `out` with a block of full-entropy bytes, by conditioning raw
+    /// samples with HMAC, a vetted conditioning component.
+    fn condition(
+        &mut self,
+        hmac: &mut dyn hmac::Builder,
+        out: &mut [u8],
+    ) -> Result<(), Error> {
+        // SP 800-90B permits the conditioning key to be fixed.
+        let hmac = hmac.new_hmac(self.algo, &[])?;
+
+        let mut buf = [0; 64];
+        let mut remaining = self.samples_per_block;
+        while remaining > 0 {
+            let len = remaining.min(buf.len());
+            let samples = &mut buf[..len];
+            self.entropy.fill(samples)?;
+            self.health.check(samples)?;
+            hmac.write(samples)?;
+            remaining -= len;
+        }
+        hmac.finish(out)?;
+        Ok(())
+    }
+}
+
+/// State for the continuous health tests from SP 800-90B Section 4.4.
+struct Health {
+    rct_cutoff: u32,
+    rct_last: u8,
+    rct_run: u32,
+
+    apt_cutoff: u32,
+    apt_first: u8,
+    apt_count: u32,
+    apt_seen: u32,
+}
+
+impl Health {
+    fn new(min_entropy: u8) -> Self {
+        let idx = min_entropy as usize - 1;
+        Self {
+            rct_cutoff: RCT_CUTOFFS[idx],
+            rct_last: 0,
+            rct_run: 0,
+            apt_cutoff: APT_CUTOFFS[idx],
+            apt_first: 0,
+            apt_count: 0,
+            apt_seen: 0,
+        }
+    }
+
+    /// Runs both health tests over `samples`.
+    fn check(&mut self, samples: &[u8]) -> Result<(), Error> {
+        for &sample in samples {
+            // Repetition Count Test.
+            if self.rct_run > 0 && sample == self.rct_last {
+                self.rct_run += 1;
+                if self.rct_run >= self.rct_cutoff {
+                    return Err(Error::HealthTestFailed);
+                }
+            } else {
+                self.rct_last = sample;
+                self.rct_run = 1;
+            }
+
+            // Adaptive Proportion Test.
+            if self.apt_seen == 0 {
+                self.apt_first = sample;
+                self.apt_count = 1;
+            } else if sample == self.apt_first {
+                self.apt_count += 1;
+                if self.apt_count >= self.apt_cutoff {
+                    return Err(Error::HealthTestFailed);
+                }
+            }
+            self.apt_seen = (self.apt_seen + 1) % APT_WINDOW;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::crypto::csrng::Csrng as _;
+    use crate::crypto::ring;
+
+    /// A deterministic stand-in for a noise source, with a configurable
+    /// assessed min-entropy.
+    struct FakeTrng {
+        min_entropy: u8,
+        state: u32,
+        samples: usize,
+        // The number of samples after which the source only produces zeroes.
+        stuck_after: usize,
+    }
+
+    impl FakeTrng {
+        fn new(min_entropy: u8) -> Self {
+            Self {
+                min_entropy,
+                state: 0x12345678,
+                samples: 0,
+                stuck_after: usize::MAX,
+            }
+        }
+    }
+
+    impl Entropy for FakeTrng {
+        fn min_entropy(&self) -> u8 {
+            self.min_entropy
+        }
+
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+            for byte in buf.iter_mut() {
+                // xorshift32.
+                self.state ^= self.state << 13;
+                self.state ^= self.state >> 17;
+                self.state ^= self.state << 5;
+                *byte = if self.samples < self.stuck_after {
+                    self.state as u8
+                } else {
+                    0
+                };
+                self.samples += 1;
+            }
+            Ok(())
+        }
+    }
+
+    const OPTS: Options = Options {
+        algo: hmac::Algo::Sha256,
+        reseed_interval: 1 << 20,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn known_answer() {
+        // The first HMAC_DRBG SHA-256 vector from the CAVP, with no
+        // prediction resistance, reseeding, personalization or additional
+        // input. The first block of output is discarded.
+        #[rustfmt::skip]
+        const ENTROPY: &[u8] = &[
+            0xca, 0x85, 0x19, 0x11, 0x34, 0x93, 0x84, 0xbf,
+            0xfe, 0x89, 0xde, 0x1c, 0xbd, 0xc4, 0x6e, 0x68,
+            0x31, 0xe4, 0x4d, 0x34, 0xa4, 0xfb, 0x93, 0x5e,
+            0xe2, 0x85, 0xdd, 0x14, 0xb7, 0x1a, 0x74, 0x88,
+        ];
+        #[rustfmt::skip]
+        const NONCE: &[u8] = &[
+            0x65, 0x9b, 0xa9, 0x6c, 0x60, 0x1d, 0xc6, 0x9f,
+            0xc9, 0x02, 0x94, 0x08, 0x05, 0xec, 0x0c, 0xa8,
+        ];
+        #[rustfmt::skip]
+        const RETURNED: &[u8] = &[
+            0xe5, 0x28, 0xe9, 0xab, 0xf2, 0xde, 0xce, 0x54,
+            0xd4, 0x7c, 0x7e, 0x75, 0xe5, 0xfe, 0x30, 0x21,
+            0x49, 0xf8, 0x17, 0xea, 0x9f, 0xb4, 0xbe, 0xe6,
+            0xf4, 0x19, 0x96, 0x97, 0xd0, 0x4d, 0x5b, 0x89,
+            0xd5, 0x4f, 0xbb, 0x97, 0x8a, 0x15, 0xb5, 0xc4,
+            0x43, 0xc9, 0xec, 0x21, 0x03, 0x6d, 0x24, 0x60,
+            0xb6, 0xf7, 0x3e, 0xba, 0xd0, 0xdc, 0x2a, 0xba,
+            0x6e, 0x62, 0x4a, 0xbf, 0x07, 0x74, 0x5b, 0xc1,
+            0x07, 0x69, 0x4b, 0xb7, 0x54, 0x7b, 0xb0, 0x99,
+            0x5f, 0x70, 0xde, 0x25, 0xd6, 0xb2, 0x9e, 0x2d,
+            0x30, 0x11, 0xbb, 0x19, 0xd2, 0x76, 0x76, 0xc0,
+            0x71, 0x62, 0xc8, 0xb5, 0xcc, 0xde, 0x06, 0x68,
+            0x96, 0x1d, 0xf8, 0x68, 0x03, 0x48, 0x2c, 0xb3,
+            0x7e, 0xd6, 0xd5, 0xc0, 0xbb, 0x8d, 0x50, 0xcf,
+            0x1f, 0x50, 0xd4, 0x76, 0xaa, 0x04, 0x58, 0xbd,
+            0xab, 0xa8, 0x06, 0xf4, 0x8b, 0xe9, 0xdc, 0xb8,
+        ];
+
+        let mut hmac = ring::hmac::Builder::new();
+        let mut drbg =
+            Drbg::new_known_answer(&mut hmac, OPTS, ENTROPY, NONCE, &[])
+                .unwrap();
+        let mut out = [0; 128];
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(&out[..], RETURNED);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn known_answer_reseed() {
+        // Computed with a Python implementation of SP 800-90A, exercising
+        // personalization, reseeding, and additional input.
+        #[rustfmt::skip]
+        const FIRST: &[u8] = &[
+            0x3a, 0x0d, 0xef, 0x48, 0xfc, 0xf3, 0x17, 0x8c,
+            0x21, 0x23, 0xb7, 0x88, 0x71, 0x56, 0x13, 0x81,
+            0x49, 0x9b, 0xe2, 0x2a, 0xd3, 0x54, 0x8b, 0x51,
+            0xa8, 0x19, 0x95, 0xea, 0xdb, 0x74, 0x0f, 0x20,
+        ];
+        #[rustfmt::skip]
+        const SECOND: &[u8] = &[
+            0x3e, 0x29, 0xed, 0x38, 0xbd, 0xaf, 0x5c, 0x0a,
+            0xc2, 0xad, 0xe3, 0xf1, 0xa7, 0x7f, 0x6d, 0x5b,
+            0xa3, 0x83, 0x3c, 0xff, 0xef, 0x63, 0x5a, 0xa9,
+            0x2f, 0x4e, 0x64, 0xce, 0x55, 0x7c, 0x7d, 0x44,
+            0x9e, 0xb7, 0x51, 0xec, 0x2e, 0x10, 0xde, 0xae,
+        ];
+
+        let entropy = (0..80).collect::<Vec<u8>>();
+        let opts = Options {
+            reseed_interval: 1,
+            ..OPTS
+        };
+        let mut hmac = ring::hmac::Builder::new();
+        let mut drbg = Drbg::new_known_answer(
+            &mut hmac,
+            opts,
+            &entropy[..32],
+            &entropy[32..48],
+            b"manticore",
+        )
+        .unwrap();
+
+        let mut out = [0; 40];
+        drbg.generate(&mut out[..32], &[]).unwrap();
+        assert_eq!(&out[..32], FIRST);
+
+        // The reseed interval is exhausted, and there is no entropy source.
+        assert_eq!(drbg.generate(&mut out, &[]), Err(Error::ReseedRequired));
+        assert_eq!(drbg.reseed(&[]), Err(Error::NoEntropySource));
+
+        drbg.reseed_with(&entropy[48..80], b"reseed").unwrap();
+        drbg.generate(&mut out, b"additional").unwrap();
+        assert_eq!(&out[..], SECOND);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn short_inputs() {
+        let mut hmac = ring::hmac::Builder::new();
+        assert!(matches!(
+            Drbg::new_known_answer(&mut hmac, OPTS, &[0; 31], &[0; 16], &[]),
+            Err(Error::InsufficientEntropy)
+        ));
+        assert!(matches!(
+            Drbg::new_known_answer(&mut hmac, OPTS, &[0; 32], &[0; 15], &[]),
+            Err(Error::InsufficientEntropy)
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_source() {
+        let mut hmac = ring::hmac::Builder::new();
+        let mut trng = FakeTrng::new(4);
+        let opts = Options {
+            reseed_interval: 2,
+            ..OPTS
+        };
+        let mut drbg =
+            Drbg::new(&mut hmac, &mut trng, opts, b"device").unwrap();
+
+        let mut a = [0; 48];
+        let mut b = [0; 48];
+        drbg.fill(&mut a).unwrap();
+        drbg.fill(&mut b).unwrap();
+        assert_ne!(a, b);
+
+        // The third call must reseed; each conditioned block takes
+        // (256 + 64) / 4 samples.
+        drbg.fill(&mut b).unwrap();
+        assert_eq!(trng.samples, STARTUP_SAMPLES + 3 * 80);
+
+        // A different personalization string yields different output, even
+        // for the same entropy.
+        let mut trng = FakeTrng::new(4);
+        let mut drbg = Drbg::new(&mut hmac, &mut trng, opts, b"other").unwrap();
+        let mut c = [0; 48];
+        drbg.fill(&mut c).unwrap();
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn stuck_source() {
+        let mut hmac = ring::hmac::Builder::new();
+        let mut trng = FakeTrng::new(8);
+        trng.stuck_after = 0;
+        assert!(matches!(
+            Drbg::new(&mut hmac, &mut trng, OPTS, &[]),
+            Err(Error::HealthTestFailed)
+        ));
+
+        // A source that gets stuck after startup poisons the DRBG for good.
+        let mut trng = FakeTrng::new(8);
+        trng.stuck_after = STARTUP_SAMPLES + 2 * 40;
+        let mut drbg = Drbg::new(&mut hmac, &mut trng, OPTS, &[]).unwrap();
+        let mut out = [0; 32];
+        drbg.generate(&mut out, &[]).unwrap();
+        assert_eq!(drbg.reseed(&[]), Err(Error::HealthTestFailed));
+        assert_eq!(drbg.generate(&mut out, &[]), Err(Error::HealthTestFailed));
+        assert_eq!(
+            drbg.reseed_with(&[0; 32], &[]),
+            Err(Error::HealthTestFailed)
+        );
+    }
+
+    #[test]
+    fn health_tests() {
+        // Repetition Count Test: at 8 bits per sample, four identical
+        // samples in a row is a failure.
+        let mut health = Health::new(8);
+        health.check(&[1, 2, 2, 2]).unwrap();
+        assert_eq!(health.check(&[2]), Err(Error::HealthTestFailed));
+
+        // Adaptive Proportion Test: at 8 bits per sample, 13 copies of the
+        // first sample within a window is a failure, even if they are never
+        // adjacent.
+        let mut health = Health::new(8);
+        let mut samples = [0u8; 26];
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = if i % 2 == 0 { 7 } else { 100 + i as u8 };
+        }
+        assert_eq!(health.check(&samples), Err(Error::HealthTestFailed));
+
+        // ... but the count resets at every window boundary.
+        let mut health = Health::new(8);
+        for i in 0..4 * APT_WINDOW {
+            let sample = if i % 64 == 0 { 7 } else { (i % 64) as u8 + 8 };
+            health.check(&[sample]).unwrap();
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ephemeral elliptic-curve Diffie-Hellman key agreement.
+
+use crate::crypto::csrng;
+
+/// An error returned by an ECDH operation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Indicates an unspecified, internal error.
+    Unspecified,
+    /// Indicates that a peer's public key was malformed, was not on the
+    /// expected curve, or was encoded for a different curve.
+    BadPublicKey,
+    /// Indicates that an output buffer was too small.
+    BufferTooSmall,
+    /// Indicates that a [`Curve`] is not supported by this implementation.
+    UnsupportedCurve,
+}
+
+impl From<csrng::Error> for Error {
+    fn from(_: csrng::Error) -> Self {
+        Self::Unspecified
+    }
+}
+
+/// The largest value [`Curve::public_key_len()`] can return, in bytes.
+pub const MAX_PUBLIC_KEY_LEN: usize = 120;
+
+/// The largest value [`Curve::secret_len()`] can return, in bytes.
+pub const MAX_SECRET_LEN: usize = 48;
+
+/// An elliptic curve suitable for ECDH.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[allow(missing_docs)]
+pub enum Curve {
+    NistP256,
+    NistP384,
+}
+
+impl Curve {
+    /// Returns the length of a shared secret for this curve, in bytes.
+    ///
+    /// This is also the length of a private scalar, and of each coordinate
+    /// of a public point.
+    pub fn secret_len(self) -> usize {
+        match self {
+            Self::NistP256 => 32,
+            Self::NistP384 => 48,
+        }
+    }
+
+    /// Returns the length of a public key for this curve, in bytes, when
+    /// encoded with `format`.
+    pub fn public_key_len(self, format: PublicKeyFormat) -> usize {
+        let point_len = 1 + 2 * self.secret_len();
+        match format {
+            PublicKeyFormat::Uncompressed => point_len,
+            PublicKeyFormat::Der => self.spki_prefix().len() + point_len,
+        }
+    }
+
+    /// Returns the DER-encoded `SubjectPublicKeyInfo` header for this curve,
+    /// which an uncompressed point must follow.
+    ///
+    /// Because the algorithm identifier and point length are fixed for each
+    /// curve, this header is the same for every key.
+    fn spki_prefix(self) -> &'static [u8] {
+        match self {
+            Self::NistP256 => P256_SPKI_PREFIX,
+            Self::NistP384 => P384_SPKI_PREFIX,
+        }
+    }
+}
+
+// SEQUENCE { SEQUENCE { id-ecPublicKey, prime256v1 }, BIT STRING { ... } }
+#[rustfmt::skip]
+const P256_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86,
+    0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03,
+    0x42, 0x00,
+];
+
+// SEQUENCE { SEQUENCE { id-ecPublicKey, secp384r1 }, BIT STRING { ... } }
+#[rustfmt::skip]
+const P384_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x76, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86,
+    0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05, 0x2b,
+    0x81, 0x04, 0x00, 0x22, 0x03, 0x62, 0x00,
+];
+
+/// An encoding for an ECDH public key.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PublicKeyFormat {
+    /// The uncompressed SEC1 point encoding, `0x04 || x || y`.
+    Uncompressed,
+    /// A DER-encoded X.509 `SubjectPublicKeyInfo` wrapping an uncompressed
+    /// point, as carried by [`KeyExchangeRequest::SessionKey`].
+    ///
+    /// [`KeyExchangeRequest::SessionKey`]:
+    ///     crate::protocol::key_exchange::KeyExchangeRequest::SessionKey
+    Der,
+}
+
+/// Encodes the uncompressed point `point` as `format`, writing it to `out`.
+///
+/// Returns the number of bytes written. This is intended for use by
+/// implementations of [`KeyPair`] whose backend only produces uncompressed
+/// points.
+pub fn encode_public_key(
+    curve: Curve,
+    point: &[u8],
+    format: PublicKeyFormat,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    if point.len() != curve.public_key_len(PublicKeyFormat::Uncompressed) {
+        return Err(Error::Unspecified);
+    }
+    let prefix = match format {
+        PublicKeyFormat::Uncompressed => &[][..],
+        PublicKeyFormat::Der => curve.spki_prefix(),
+    };
+
+    let len = prefix.len() + point.len();
+    if out.len() < len {
+        return Err(Error::BufferTooSmall);
+    }
+    out[..prefix.len()].copy_from_slice(prefix);
+    out[prefix.len()..len].copy_from_slice(point);
+    Ok(len)
+}
+
+/// Extracts the uncompressed point out of `key`, which is encoded as
+/// `format`.
+///
+/// Only the encoding is checked; the point itself is not validated. This is
+/// the inverse of [`encode_public_key()`].
+pub fn decode_public_key(
+    curve: Curve,
+    key: &[u8],
+    format: PublicKeyFormat,
+) -> Result<&[u8], Error> {
+    if key.len() != curve.public_key_len(format) {
+        return Err(Error::BadPublicKey);
+    }
+    let prefix = match format {
+        PublicKeyFormat::Uncompressed => &[][..],
+        PublicKeyFormat::Der => curve.spki_prefix(),
+    };
+    if !key.starts_with(prefix) {
+        return Err(Error::BadPublicKey);
+    }
+    Ok(&key[prefix.len()..])
+}
+
+/// A builder for ephemeral ECDH key pairs.
+pub trait Builder {
+    /// The key pair type produced by this builder.
+    type KeyPair: KeyPair;
+
+    /// Generates a fresh key pair on `curve`, drawing its secret from `rng`.
+    ///
+    /// Returns [`Error::UnsupportedCurve`] if `curve` is not supported.
+    fn generate(
+        &self,
+        curve: Curve,
+        rng: &mut dyn csrng::Csrng,
+    ) -> Result<Self::KeyPair, Error>;
+}
+
+/// An ephemeral ECDH key pair.
+///
+/// A key pair may only be used for a single agreement, after which its
+/// private half is destroyed.
+pub trait KeyPair {
+    /// Returns the curve this key pair lives on.
+    fn curve(&self) -> Curve;
+
+    /// Writes the public half of this key pair to `out`, encoded as
+    /// `format`.
+    ///
+    /// Returns the number of bytes written, which is always
+    /// `self.curve().public_key_len(format)`.
+    fn public_key(
+        &self,
+        format: PublicKeyFormat,
+        out: &mut [u8],
+    ) -> Result<usize, Error>;
+
+    /// Computes the secret shared with the holder of `peer_key`, encoded as
+    /// `format`, consuming this key pair.
+    ///
+    /// The secret is the x-coordinate of the shared point, and is written
+    /// to `out`, which must be exactly `self.curve().secret_len()` bytes
+    /// long. It should be passed through a KDF before use, such as one from
+    /// [`crypto::kdf`].
+    ///
+    /// [`crypto::kdf`]: crate::crypto::kdf
+    fn agree(
+        self,
+        peer_key: &[u8],
+        format: PublicKeyFormat,
+        out: &mut [u8],
+    ) -> Result<(), Error>;
+}
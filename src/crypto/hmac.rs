@@ -0,0 +1,145 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! HMAC, a keyed message authentication code (RFC2104).
+
+use static_assertions::assert_obj_safe;
+
+/// An error returned by an HMAC function.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Indicates an unspecified, internal error.
+    Unspecified,
+    /// Indicates that a tag did not match the message it was checked
+    /// against.
+    WrongTag,
+    /// Indicates that an [`Algo`] is not supported by this implementation.
+    UnsupportedAlgo,
+}
+
+/// The longest tag any [`Algo`] produces, in bytes.
+pub const MAX_TAG_LEN: usize = 48;
+
+/// An HMAC algorithm, i.e., the hash function it is built on.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Algo {
+    /// HMAC-SHA256.
+    Sha256,
+    /// HMAC-SHA384.
+    Sha384,
+}
+
+impl Algo {
+    /// Returns the length of the tags this algorithm produces, in bytes.
+    pub fn tag_len(self) -> usize {
+        match self {
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+        }
+    }
+}
+
+/// A builder for creating new [`Hmac`]s.
+///
+/// A value of a type implementing this trait already contains everything it
+/// needs (such as OS handles) to start computing MACs. Unlike
+/// [`sha256::Builder`], this trait is object-safe: the [`Hmac`] it returns
+/// lives inside of the builder, so only one may be in progress at a time.
+///
+/// [`sha256::Builder`]: crate::crypto::sha256::Builder
+pub trait Builder {
+    /// Begins a new HMAC operation keyed with `key`, returning a new
+    /// [`Hmac`] to manage the computation.
+    ///
+    /// Returns [`Error::UnsupportedAlgo`] if `algo` is not supported.
+    fn new_hmac<'a>(
+        &'a mut self,
+        algo: Algo,
+        key: &[u8],
+    ) -> Result<&'a mut dyn Hmac, Error>;
+
+    /// Convenience function for computing the tag of `message_vec` without
+    /// having to deal with an [`Hmac`] directly.
+    ///
+    /// `message_vec` is an iovec-like structure, as in
+    /// [`sig::Sign::sign()`]; `tag` must be exactly `algo.tag_len()` bytes
+    /// long.
+    ///
+    /// [`sig::Sign::sign()`]: crate::crypto::sig::Sign::sign
+    fn sign(
+        &mut self,
+        algo: Algo,
+        key: &[u8],
+        message_vec: &[&[u8]],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        let hmac = self.new_hmac(algo, key)?;
+        for bytes in message_vec {
+            hmac.write(bytes)?;
+        }
+        hmac.finish(tag)
+    }
+
+    /// Convenience function for checking the tag of `message_vec` without
+    /// having to deal with an [`Hmac`] directly.
+    ///
+    /// See [`Hmac::verify()`].
+    fn verify(
+        &mut self,
+        algo: Algo,
+        key: &[u8],
+        message_vec: &[&[u8]],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        let hmac = self.new_hmac(algo, key)?;
+        for bytes in message_vec {
+            hmac.write(bytes)?;
+        }
+        hmac.verify(tag)
+    }
+}
+assert_obj_safe!(Builder);
+
+/// A particular HMAC operation in progress, already primed with a key.
+///
+/// There is no way to extract the key back out of an `Hmac` value.
+pub trait Hmac {
+    /// Returns the algorithm this operation is computing.
+    fn algo(&self) -> Algo;
+
+    /// Feeds more data into the current HMAC operation.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error>;
+
+    /// Finishes the current HMAC operation, writing the tag to `tag`, which
+    /// must be exactly `algo().tag_len()` bytes long.
+    ///
+    /// Once finished, any further calls to this operation will fail.
+    fn finish(&mut self, tag: &mut [u8]) -> Result<(), Error>;
+
+    /// Finishes the current HMAC operation, checking that its tag is equal
+    /// to `tag`.
+    ///
+    /// The comparison is performed in constant time, so that callers do
+    /// not leak how much of a forged tag is correct. Returns
+    /// [`Error::WrongTag`] if the tags differ, including if `tag` has the
+    /// wrong length.
+    fn verify(&mut self, tag: &[u8]) -> Result<(), Error> {
+        let mut expected = [0; MAX_TAG_LEN];
+        let expected = &mut expected[..self.algo().tag_len()];
+        self.finish(expected)?;
+        if !constant_time_eq(expected, tag) {
+            return Err(Error::WrongTag);
+        }
+        Ok(())
+    }
+}
+assert_obj_safe!(Hmac);
+
+/// Compares `a` and `b` in time that depends only on their lengths.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,116 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Key derivation functions built on top of HMAC.
+
+use core::convert::TryFrom as _;
+
+use static_assertions::assert_obj_safe;
+
+use crate::crypto::hmac;
+
+/// An error returned by a key derivation function.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Indicates an unspecified, internal error.
+    Unspecified,
+    /// Indicates that more output was requested than the KDF can produce
+    /// with the chosen [`hmac::Algo`].
+    OutputTooLong,
+    /// Indicates that an [`hmac::Algo`] is not supported by this
+    /// implementation.
+    UnsupportedAlgo,
+}
+
+impl From<hmac::Error> for Error {
+    fn from(e: hmac::Error) -> Self {
+        match e {
+            hmac::Error::UnsupportedAlgo => Self::UnsupportedAlgo,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+/// A key derivation engine.
+///
+/// A `Kdf` stretches secret key material into any number of keys, each
+/// separated by some public context. Both derivations in this trait fill the
+/// entirety of `out` with derived key material.
+pub trait Kdf {
+    /// Derives `out.len()` bytes using HKDF (RFC5869), with the given HMAC
+    /// `algo`.
+    ///
+    /// `ikm` is the input key material; `salt` may be empty. `info_vec` is an
+    /// iovec-like structure, the concatenation of which is HKDF's `info`
+    /// parameter.
+    ///
+    /// Returns [`Error::OutputTooLong`] if `out` is longer than 255 times
+    /// `algo.tag_len()`.
+    fn hkdf(
+        &mut self,
+        algo: hmac::Algo,
+        ikm: &[u8],
+        salt: &[u8],
+        info_vec: &[&[u8]],
+        out: &mut [u8],
+    ) -> Result<(), Error>;
+
+    /// Derives `out.len()` bytes using the SP800-108 KDF in counter mode,
+    /// with the given HMAC `algo` as its PRF.
+    ///
+    /// The counter and the length of `out` in bits are both encoded as
+    /// 32-bit big-endian integers, and the fixed input data to the PRF is
+    /// `label || 0x00 || context || L`, following the recommendations of
+    /// SP800-108 Section 5.1.
+    fn counter_mode(
+        &mut self,
+        algo: hmac::Algo,
+        key: &[u8],
+        label: &[u8],
+        context: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), Error>;
+}
+assert_obj_safe!(Kdf);
+
+/// Implements [`Kdf::counter_mode()`] on top of an arbitrary
+/// [`hmac::Builder`].
+///
+/// This is intended for use by implementations of [`Kdf`] whose backend does
+/// not provide SP800-108 natively.
+pub fn counter_mode(
+    hmac: &mut dyn hmac::Builder,
+    algo: hmac::Algo,
+    key: &[u8],
+    label: &[u8],
+    context: &[u8],
+    out: &mut [u8],
+) -> Result<(), Error> {
+    let bits = out
+        .len()
+        .checked_mul(8)
+        .and_then(|bits| u32::try_from(bits).ok())
+        .ok_or(Error::OutputTooLong)?;
+
+    let mut block = [0; hmac::MAX_TAG_LEN];
+    let block = &mut block[..algo.tag_len()];
+    for (i, chunk) in out.chunks_mut(block.len()).enumerate() {
+        // `i` is bounded by `bits`, which fits in a `u32`.
+        let counter = i as u32 + 1;
+        hmac.sign(
+            algo,
+            key,
+            &[
+                &counter.to_be_bytes()[..],
+                label,
+                &[0],
+                context,
+                &bits.to_be_bytes()[..],
+            ],
+            block,
+        )?;
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    Ok(())
+}
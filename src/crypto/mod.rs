@@ -35,11 +35,24 @@
 //! [`ring` module], based on the [`ring`] crate. Their presence is controlled
 //! by the `ring` feature flag; some opeartions require `std` as well.
 //!
+//! Pure-Rust implementations of a subset of these traits, suitable for
+//! bare-metal targets that `ring` does not support, are provided under the
+//! [`rustcrypto` module], controlled by the `rustcrypto` feature flag.
+//!
 //! [`ring` module]: ring/index.html
+//! [`rustcrypto` module]: rustcrypto/index.html
 
+pub mod aead;
 pub mod csrng;
+pub mod drbg;
+pub mod ecdh;
+pub mod hmac;
+pub mod kdf;
 pub mod sha256;
 pub mod sig;
 
 #[cfg(feature = "ring")]
 pub mod ring;
+
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
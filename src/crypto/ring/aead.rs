@@ -0,0 +1,232 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of [`crypto::aead`] based on `ring`.
+//!
+//! Requires the `std` feature flag to be enabled.
+
+use ring::aead as ring_aead;
+
+use crate::crypto::aead;
+use crate::protocol::capabilities;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// A `ring`-based [`aead::Ciphers`].
+#[derive(Default)]
+pub struct Ciphers {
+    aead: Option<Aead>,
+}
+
+impl Ciphers {
+    /// Creates a new `Ciphers`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl aead::Ciphers for Ciphers {
+    fn negotiate(&self, caps: &mut capabilities::Crypto) {
+        use capabilities::*;
+        *caps = Crypto {
+            has_aes: true,
+            aes_strength: AesKeyStrength::BITS_128 | AesKeyStrength::BITS_256,
+            ..*caps
+        };
+    }
+
+    fn aead<'a>(
+        &'a mut self,
+        algo: aead::Algo,
+        key: &[u8],
+    ) -> Result<&'a mut dyn aead::Aead, aead::Error> {
+        let ring_algo = match algo {
+            aead::Algo::Aes128Gcm => &ring_aead::AES_128_GCM,
+            aead::Algo::Aes256Gcm => &ring_aead::AES_256_GCM,
+        };
+        let key = ring_aead::UnboundKey::new(ring_algo, key)
+            .map_err(|_| aead::Error::Unspecified)?;
+        self.aead = Some(Aead {
+            algo,
+            key: ring_aead::LessSafeKey::new(key),
+        });
+        self.aead
+            .as_mut()
+            .map(|x| x as _)
+            .ok_or(aead::Error::Unspecified)
+    }
+}
+
+/// A `ring`-based [`aead::Aead`].
+///
+/// See [`Ciphers`].
+pub struct Aead {
+    algo: aead::Algo,
+    key: ring_aead::LessSafeKey,
+}
+
+impl aead::Aead for Aead {
+    fn algo(&self) -> aead::Algo {
+        self.algo
+    }
+
+    fn seal_in_place(
+        &mut self,
+        nonce: &aead::Nonce,
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), aead::Error> {
+        let len = buf
+            .len()
+            .checked_sub(aead::TAG_LEN)
+            .ok_or(aead::Error::Unspecified)?;
+        let (msg, tag) = buf.split_at_mut(len);
+        let ring_tag = self
+            .key
+            .seal_in_place_separate_tag(
+                ring_aead::Nonce::assume_unique_for_key(*nonce),
+                ring_aead::Aad::from(aad),
+                msg,
+            )
+            .map_err(|_| aead::Error::Unspecified)?;
+        tag.copy_from_slice(ring_tag.as_ref());
+        Ok(())
+    }
+
+    fn open_in_place(
+        &mut self,
+        nonce: &aead::Nonce,
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), aead::Error> {
+        if buf.len() < aead::TAG_LEN {
+            return Err(aead::Error::Unspecified);
+        }
+        self.key
+            .open_in_place(
+                ring_aead::Nonce::assume_unique_for_key(*nonce),
+                ring_aead::Aad::from(aad),
+                buf,
+            )
+            .map_err(|_| aead::Error::WrongTag)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use testutil::data::misc_crypto;
+
+    use crate::crypto::aead::Ciphers as _;
+
+    // Test cases 2 and 14 from the original GCM specification, which encrypt
+    // a block of zeroes with an all-zero key and nonce.
+    #[rustfmt::skip]
+    const AES128_ZEROES: (&[u8], aead::Tag) = (
+        &[
+            0x03, 0x88, 0xda, 0xce, 0x60, 0xb6, 0xa3, 0x92,
+            0xf3, 0x28, 0xc2, 0xb9, 0x71, 0xb2, 0xfe, 0x78,
+        ],
+        [
+            0xab, 0x6e, 0x47, 0xd4, 0x2c, 0xec, 0x13, 0xbd,
+            0xf5, 0x3a, 0x67, 0xb2, 0x12, 0x57, 0xbd, 0xdf,
+        ],
+    );
+    #[rustfmt::skip]
+    const AES256_ZEROES: (&[u8], aead::Tag) = (
+        &[
+            0xce, 0xa7, 0x40, 0x3d, 0x4d, 0x60, 0x6b, 0x6e,
+            0x07, 0x4e, 0xc5, 0xd3, 0xba, 0xf3, 0x9d, 0x18,
+        ],
+        [
+            0xd0, 0xd1, 0xc8, 0xa7, 0x99, 0x99, 0x6b, 0xf0,
+            0x26, 0x5b, 0x98, 0xb5, 0xd4, 0x8a, 0xb9, 0x19,
+        ],
+    );
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn known_answer() {
+        let mut ciphers = Ciphers::new();
+        for &(algo, (ciphertext, tag)) in &[
+            (aead::Algo::Aes128Gcm, AES128_ZEROES),
+            (aead::Algo::Aes256Gcm, AES256_ZEROES),
+        ] {
+            let key = [0; 32];
+            let aead = ciphers.aead(algo, &key[..algo.key_len()]).unwrap();
+            assert_eq!(aead.algo(), algo);
+
+            let mut buf = [0; 16 + aead::TAG_LEN];
+            aead.seal_in_place(&[0; 12], &[], &mut buf).unwrap();
+            assert_eq!(&buf[..16], ciphertext);
+            assert_eq!(buf[16..], tag);
+
+            aead.open_in_place(&[0; 12], &[], &mut buf).unwrap();
+            assert_eq!(buf[..16], [0; 16]);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn round_trip() {
+        let mut ciphers = Ciphers::new();
+        let aead = ciphers.aead(aead::Algo::Aes256Gcm, &[0x42; 32]).unwrap();
+        let nonce = [7; aead::NONCE_LEN];
+        let len = misc_crypto::PLAIN_TEXT.len();
+
+        let mut buf = misc_crypto::PLAIN_TEXT.to_vec();
+        buf.resize(len + aead::TAG_LEN, 0);
+        aead.seal_in_place(&nonce, b"header", &mut buf).unwrap();
+        assert_ne!(&buf[..len], misc_crypto::PLAIN_TEXT);
+
+        let mut opened = buf.clone();
+        aead.open_in_place(&nonce, b"header", &mut opened).unwrap();
+        assert_eq!(&opened[..len], misc_crypto::PLAIN_TEXT);
+
+        let mut opened = buf.clone();
+        assert_eq!(
+            aead.open_in_place(&nonce, b"other header", &mut opened),
+            Err(aead::Error::WrongTag)
+        );
+
+        buf[len] ^= 1;
+        assert_eq!(
+            aead.open_in_place(&nonce, b"header", &mut buf),
+            Err(aead::Error::WrongTag)
+        );
+
+        // There must be room for a tag.
+        let mut short = [0; aead::TAG_LEN - 1];
+        assert!(aead.seal_in_place(&nonce, &[], &mut short).is_err());
+        assert!(aead.open_in_place(&nonce, &[], &mut short).is_err());
+    }
+
+    #[test]
+    fn negotiate() {
+        let mut caps = capabilities::Crypto {
+            has_ecdsa: true,
+            has_ecc: false,
+            has_rsa: false,
+            has_aes: false,
+            ecc_strength: capabilities::EccKeyStrength::empty(),
+            rsa_strength: capabilities::RsaKeyStrength::empty(),
+            aes_strength: capabilities::AesKeyStrength::empty(),
+        };
+        Ciphers::new().negotiate(&mut caps);
+        assert!(caps.has_aes);
+        assert!(caps.has_ecdsa);
+        assert!(caps.aes_strength.contains(aead::Algo::Aes128Gcm.strength()));
+        assert!(caps.aes_strength.contains(aead::Algo::Aes256Gcm.strength()));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn bad_key_len() {
+        let mut ciphers = Ciphers::new();
+        assert!(ciphers.aead(aead::Algo::Aes128Gcm, &[0; 32]).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of [`crypto::ecdh`] based on `ring`.
+//!
+//! Requires the `std` feature flag to be enabled.
+
+use ring::agreement;
+use ring::test::rand::FixedSliceRandom;
+
+use crate::crypto::csrng;
+use crate::crypto::ecdh;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// The number of times [`Builder::generate()`] will draw a new private scalar
+/// before giving up.
+///
+/// A uniformly random scalar is out of range with probability less than
+/// 2^-32 for both supported curves, so this limit is only ever hit by a
+/// broken [`csrng::Csrng`].
+const MAX_ATTEMPTS: usize = 8;
+
+/// A `ring`-based [`ecdh::Builder`].
+#[derive(Default)]
+pub struct Builder {
+    _priv: (),
+}
+
+impl Builder {
+    /// Creates a new `Builder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ecdh::Builder for Builder {
+    type KeyPair = KeyPair;
+
+    fn generate(
+        &self,
+        curve: ecdh::Curve,
+        rng: &mut dyn csrng::Csrng,
+    ) -> Result<KeyPair, ecdh::Error> {
+        let algo = match curve {
+            ecdh::Curve::NistP256 => &agreement::ECDH_P256,
+            ecdh::Curve::NistP384 => &agreement::ECDH_P384,
+        };
+
+        // `ring` only accepts its own, sealed, random number generators, so
+        // instead the private scalar is drawn from `rng` up front and handed
+        // to `ring` verbatim. `ring` rejects scalars that are out of range,
+        // in which case we simply draw again.
+        let mut seed = [0; ecdh::MAX_SECRET_LEN];
+        let seed = &mut seed[..curve.secret_len()];
+        for _ in 0..MAX_ATTEMPTS {
+            rng.fill(seed)?;
+            let private = match agreement::EphemeralPrivateKey::generate(
+                algo,
+                &FixedSliceRandom { bytes: seed },
+            ) {
+                Ok(private) => private,
+                Err(_) => continue,
+            };
+            let public = private
+                .compute_public_key()
+                .map_err(|_| ecdh::Error::Unspecified)?;
+            return Ok(KeyPair {
+                curve,
+                private,
+                public,
+            });
+        }
+        Err(ecdh::Error::Unspecified)
+    }
+}
+
+/// A `ring`-based [`ecdh::KeyPair`].
+///
+/// See [`Builder`].
+pub struct KeyPair {
+    curve: ecdh::Curve,
+    private: agreement::EphemeralPrivateKey,
+    public: agreement::PublicKey,
+}
+
+impl ecdh::KeyPair for KeyPair {
+    fn curve(&self) -> ecdh::Curve {
+        self.curve
+    }
+
+    fn public_key(
+        &self,
+        format: ecdh::PublicKeyFormat,
+        out: &mut [u8],
+    ) -> Result<usize, ecdh::Error> {
+        ecdh::encode_public_key(self.curve, self.public.as_ref(), format, out)
+    }
+
+    fn agree(
+        self,
+        peer_key: &[u8],
+        format: ecdh::PublicKeyFormat,
+        out: &mut [u8],
+    ) -> Result<(), ecdh::Error> {
+        if out.len() != self.curve.secret_len() {
+            return Err(ecdh::Error::Unspecified);
+        }
+        let point = ecdh::decode_public_key(self.curve, peer_key, format)?;
+        let peer =
+            agreement::UnparsedPublicKey::new(self.private.algorithm(), point);
+        agreement::agree_ephemeral(
+            self.private,
+            &peer,
+            ecdh::Error::BadPublicKey,
+            |secret| {
+                out.copy_from_slice(secret);
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::crypto::ecdh::Builder as _;
+    use crate::crypto::ecdh::KeyPair as _;
+    use crate::crypto::ring::csrng::Csrng;
+
+    /// A "random" number generator that always produces the same bytes.
+    struct Fixed(&'static [u8]);
+    impl csrng::Csrng for Fixed {
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), csrng::Error> {
+            buf.copy_from_slice(self.0);
+            Ok(())
+        }
+    }
+
+    // The P-256 test vector from RFC5903, Section 8.1.
+    #[rustfmt::skip]
+    const P256_I: &[u8] = &[
+        0xc8, 0x8f, 0x01, 0xf5, 0x10, 0xd9, 0xac, 0x3f,
+        0x70, 0xa2, 0x92, 0xda, 0xa2, 0x31, 0x6d, 0xe5,
+        0x44, 0xe9, 0xaa, 0xb8, 0xaf, 0xe8, 0x40, 0x49,
+        0xc6, 0x2a, 0x9c, 0x57, 0x86, 0x2d, 0x14, 0x33,
+    ];
+    #[rustfmt::skip]
+    const P256_GI: &[u8] = &[
+        0x04,
+        0xda, 0xd0, 0xb6, 0x53, 0x94, 0x22, 0x1c, 0xf9,
+        0xb0, 0x51, 0xe1, 0xfe, 0xca, 0x57, 0x87, 0xd0,
+        0x98, 0xdf, 0xe6, 0x37, 0xfc, 0x90, 0xb9, 0xef,
+        0x94, 0x5d, 0x0c, 0x37, 0x72, 0x58, 0x11, 0x80,
+        0x52, 0x71, 0xa0, 0x46, 0x1c, 0xdb, 0x82, 0x52,
+        0xd6, 0x1f, 0x1c, 0x45, 0x6f, 0xa3, 0xe5, 0x9a,
+        0xb1, 0xf4, 0x5b, 0x33, 0xac, 0xcf, 0x5f, 0x58,
+        0x38, 0x9e, 0x05, 0x77, 0xb8, 0x99, 0x0b, 0xb3,
+    ];
+    #[rustfmt::skip]
+    const P256_GR: &[u8] = &[
+        0x04,
+        0xd1, 0x2d, 0xfb, 0x52, 0x89, 0xc8, 0xd4, 0xf8,
+        0x12, 0x08, 0xb7, 0x02, 0x70, 0x39, 0x8c, 0x34,
+        0x22, 0x96, 0x97, 0x0a, 0x0b, 0xcc, 0xb7, 0x4c,
+        0x73, 0x6f, 0xc7, 0x55, 0x44, 0x94, 0xbf, 0x63,
+        0x56, 0xfb, 0xf3, 0xca, 0x36, 0x6c, 0xc2, 0x3e,
+        0x81, 0x57, 0x85, 0x4c, 0x13, 0xc5, 0x8d, 0x6a,
+        0xac, 0x23, 0xf0, 0x46, 0xad, 0xa3, 0x0f, 0x83,
+        0x53, 0xe7, 0x4f, 0x33, 0x03, 0x98, 0x72, 0xab,
+    ];
+    #[rustfmt::skip]
+    const P256_GIR: &[u8] = &[
+        0xd6, 0x84, 0x0f, 0x6b, 0x42, 0xf6, 0xed, 0xaf,
+        0xd1, 0x31, 0x16, 0xe0, 0xe1, 0x25, 0x65, 0x20,
+        0x2f, 0xef, 0x8e, 0x9e, 0xce, 0x7d, 0xce, 0x03,
+        0x81, 0x24, 0x64, 0xd0, 0x4b, 0x94, 0x42, 0xde,
+    ];
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn known_answer() {
+        let builder = Builder::new();
+        let keypair = builder
+            .generate(ecdh::Curve::NistP256, &mut Fixed(P256_I))
+            .unwrap();
+
+        let mut public = [0; ecdh::MAX_PUBLIC_KEY_LEN];
+        let len = keypair
+            .public_key(ecdh::PublicKeyFormat::Uncompressed, &mut public)
+            .unwrap();
+        assert_eq!(&public[..len], P256_GI);
+
+        let mut secret = [0; 32];
+        keypair
+            .agree(P256_GR, ecdh::PublicKeyFormat::Uncompressed, &mut secret)
+            .unwrap();
+        assert_eq!(&secret[..], P256_GIR);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn round_trip() {
+        let builder = Builder::new();
+        let mut rng = Csrng::new();
+        for &curve in &[ecdh::Curve::NistP256, ecdh::Curve::NistP384] {
+            let alice = builder.generate(curve, &mut rng).unwrap();
+            let bob = builder.generate(curve, &mut rng).unwrap();
+
+            let mut alice_der = [0; ecdh::MAX_PUBLIC_KEY_LEN];
+            let alice_der_len = alice
+                .public_key(ecdh::PublicKeyFormat::Der, &mut alice_der)
+                .unwrap();
+            assert_eq!(
+                alice_der_len,
+                curve.public_key_len(ecdh::PublicKeyFormat::Der)
+            );
+            let alice_der = &alice_der[..alice_der_len];
+
+            let mut bob_raw = [0; ecdh::MAX_PUBLIC_KEY_LEN];
+            let bob_raw_len = bob
+                .public_key(ecdh::PublicKeyFormat::Uncompressed, &mut bob_raw)
+                .unwrap();
+            let bob_raw = &bob_raw[..bob_raw_len];
+
+            // Both encodings describe the same point.
+            let mut alice_raw = [0; ecdh::MAX_PUBLIC_KEY_LEN];
+            let alice_raw_len = alice
+                .public_key(ecdh::PublicKeyFormat::Uncompressed, &mut alice_raw)
+                .unwrap();
+            assert_eq!(
+                ecdh::decode_public_key(
+                    curve,
+                    alice_der,
+                    ecdh::PublicKeyFormat::Der
+                ),
+                Ok(&alice_raw[..alice_raw_len]),
+            );
+
+            let mut alice_secret = [0; ecdh::MAX_SECRET_LEN];
+            let alice_secret = &mut alice_secret[..curve.secret_len()];
+            alice
+                .agree(
+                    bob_raw,
+                    ecdh::PublicKeyFormat::Uncompressed,
+                    alice_secret,
+                )
+                .unwrap();
+
+            let mut bob_secret = [0; ecdh::MAX_SECRET_LEN];
+            let bob_secret = &mut bob_secret[..curve.secret_len()];
+            bob.agree(alice_der, ecdh::PublicKeyFormat::Der, bob_secret)
+                .unwrap();
+
+            assert_eq!(alice_secret, bob_secret);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn bad_peer_key() {
+        let builder = Builder::new();
+        let mut rng = Csrng::new();
+        let mut secret = [0; 32];
+
+        // Wrong curve.
+        let keypair =
+            builder.generate(ecdh::Curve::NistP256, &mut rng).unwrap();
+        let mut p384 = [0; ecdh::MAX_PUBLIC_KEY_LEN];
+        let len = builder
+            .generate(ecdh::Curve::NistP384, &mut rng)
+            .unwrap()
+            .public_key(ecdh::PublicKeyFormat::Der, &mut p384)
+            .unwrap();
+        assert_eq!(
+            keypair.agree(
+                &p384[..len],
+                ecdh::PublicKeyFormat::Der,
+                &mut secret
+            ),
+            Err(ecdh::Error::BadPublicKey)
+        );
+
+        // Not on the curve.
+        let keypair =
+            builder.generate(ecdh::Curve::NistP256, &mut rng).unwrap();
+        let mut off_curve = P256_GR.to_vec();
+        off_curve[64] ^= 1;
+        assert_eq!(
+            keypair.agree(
+                &off_curve,
+                ecdh::PublicKeyFormat::Uncompressed,
+                &mut secret
+            ),
+            Err(ecdh::Error::BadPublicKey)
+        );
+    }
+}
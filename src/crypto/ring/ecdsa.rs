@@ -66,7 +66,7 @@ impl sig::Verify for VerifyP256 {
     }
 }
 
-/// A `ring`-based [`sig::Sign`] for PKCS#1.5 RSA using SHA-256.
+/// A `ring`-based [`sig::Sign`] for ECDSA using the P-256 curve.
 pub struct SignP256 {
     keypair: ring::signature::EcdsaKeyPair,
 }
@@ -75,7 +75,7 @@ impl SignP256 {
     /// Creates a new `SignP256` from the given PKCS#8-encoded private key,
     /// using the DER encoding for signatures.
     ///
-    /// Returns `None` if the key fails to parse.
+    /// Returns an error if the key fails to parse.
     pub fn with_der_encoding_from_pkcs8(
         pkcs8: &[u8],
     ) -> Result<Self, sig::Error> {
@@ -90,7 +90,7 @@ impl SignP256 {
     /// Creates a new `SignP256` from the given PKCS#8-encoded private key,
     /// using the PKCS#11-style encoding for signatures.
     ///
-    /// Returns `None` if the key fails to parse.
+    /// Returns an error if the key fails to parse.
     pub fn with_pkcs11_encoding_from_pkcs8(
         pkcs8: &[u8],
     ) -> Result<Self, sig::Error> {
@@ -142,6 +142,129 @@ impl sig::Sign for SignP256 {
     }
 }
 
+/// A `ring`-based [`sig::Verify`] for ECDSA using the P-384 curve.
+pub struct VerifyP384 {
+    key: [u8; 97],
+    algo: &'static EcdsaAlgo,
+}
+
+impl VerifyP384 {
+    /// Creates a new `VerifyP384` using the DER encoding and the given
+    /// public-key coordinates.
+    pub fn with_der_encoding(x: [u8; 48], y: [u8; 48]) -> Self {
+        let mut key = [4u8; 97];
+        key[1..49].copy_from_slice(&x);
+        key[49..97].copy_from_slice(&y);
+
+        Self {
+            key,
+            algo: &ring::signature::ECDSA_P384_SHA384_ASN1,
+        }
+    }
+
+    /// Creates a new `VerifyP384` using the PKCS#11 encoding and the given
+    /// public-key coordinates.
+    pub fn with_pkcs11_encoding(x: [u8; 48], y: [u8; 48]) -> Self {
+        let mut key = [4u8; 97];
+        key[1..49].copy_from_slice(&x);
+        key[49..97].copy_from_slice(&y);
+
+        Self {
+            key,
+            algo: &ring::signature::ECDSA_P384_SHA384_FIXED,
+        }
+    }
+}
+
+impl sig::Verify for VerifyP384 {
+    fn verify(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &[u8],
+    ) -> Result<(), sig::Error> {
+        let mut message = Vec::new();
+        for bytes in message_vec {
+            message.extend_from_slice(bytes);
+        }
+
+        self.algo
+            .verify(
+                (&self.key[..]).into(),
+                message.as_slice().into(),
+                signature.into(),
+            )
+            .map_err(|_| sig::Error::Unspecified)
+    }
+}
+
+/// A `ring`-based [`sig::Sign`] for ECDSA using the P-384 curve.
+pub struct SignP384 {
+    keypair: ring::signature::EcdsaKeyPair,
+}
+
+impl SignP384 {
+    /// Creates a new `SignP384` from the given PKCS#8-encoded private key,
+    /// using the DER encoding for signatures.
+    ///
+    /// Returns an error if the key fails to parse.
+    pub fn with_der_encoding_from_pkcs8(
+        pkcs8: &[u8],
+    ) -> Result<Self, sig::Error> {
+        let keypair = ring::signature::EcdsaKeyPair::from_pkcs8(
+            &ring::signature::ECDSA_P384_SHA384_ASN1_SIGNING,
+            pkcs8,
+        )
+        .map_err(|_| sig::Error::Unspecified)?;
+        Ok(Self { keypair })
+    }
+
+    /// Creates a new `SignP384` from the given PKCS#8-encoded private key,
+    /// using the PKCS#11-style encoding for signatures.
+    ///
+    /// Returns an error if the key fails to parse.
+    pub fn with_pkcs11_encoding_from_pkcs8(
+        pkcs8: &[u8],
+    ) -> Result<Self, sig::Error> {
+        let keypair = ring::signature::EcdsaKeyPair::from_pkcs8(
+            &ring::signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+            pkcs8,
+        )
+        .map_err(|_| sig::Error::Unspecified)?;
+        Ok(Self { keypair })
+    }
+}
+
+impl sig::Sign for SignP384 {
+    fn sig_bytes(&self) -> usize {
+        // See `SignP256::sig_bytes()`; the coordinates are now 48 bytes
+        // each, which still leaves the lengths small enough to fit in a
+        // single byte.
+        96 + 8
+    }
+
+    fn sign(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &mut [u8],
+    ) -> Result<usize, sig::Error> {
+        let mut message = Vec::new();
+        for bytes in message_vec {
+            message.extend_from_slice(bytes);
+        }
+
+        let rng = ring::rand::SystemRandom::new();
+        let sig = self
+            .keypair
+            .sign(&rng, &message)
+            .map_err(|_| sig::Error::Unspecified)?;
+        let signature = signature
+            .get_mut(..sig.as_ref().len())
+            .ok_or(sig::Error::Unspecified)?;
+        signature.copy_from_slice(sig.as_ref());
+        Ok(signature.len())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,4 +316,48 @@ mod tests {
             .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
             .unwrap();
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn p384_der() {
+        let mut signer = SignP384::with_der_encoding_from_pkcs8(
+            keys::KEY1_ECDSA_P384_KEYPAIR,
+        )
+        .unwrap();
+        let mut verifier = VerifyP384::with_der_encoding(
+            *keys::KEY1_ECDSA_P384_X,
+            *keys::KEY1_ECDSA_P384_Y,
+        );
+
+        let mut generated_sig = vec![0; signer.sig_bytes()];
+        let sig_len = signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut generated_sig)
+            .unwrap();
+
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn p384_pkcs11() {
+        let mut signer = SignP384::with_pkcs11_encoding_from_pkcs8(
+            keys::KEY1_ECDSA_P384_KEYPAIR,
+        )
+        .unwrap();
+        let mut verifier = VerifyP384::with_pkcs11_encoding(
+            *keys::KEY1_ECDSA_P384_X,
+            *keys::KEY1_ECDSA_P384_Y,
+        );
+
+        let mut generated_sig = vec![0; signer.sig_bytes()];
+        let sig_len = signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut generated_sig)
+            .unwrap();
+
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .unwrap();
+    }
 }
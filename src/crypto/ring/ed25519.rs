@@ -0,0 +1,190 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of Ed25519 based on `ring`.
+//!
+//! Requires the `std` feature flag to be enabled.
+
+use ring::signature::VerificationAlgorithm as _;
+
+use crate::crypto::sig;
+
+/// A `ring`-based [`sig::Verify`] for Ed25519.
+pub struct Verify {
+    key: [u8; 32],
+}
+
+impl Verify {
+    /// Creates a new `Verify` with the given raw public key.
+    pub fn from_public(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+}
+
+impl sig::Verify for Verify {
+    fn verify(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &[u8],
+    ) -> Result<(), sig::Error> {
+        let mut message = Vec::new();
+        for bytes in message_vec {
+            message.extend_from_slice(bytes);
+        }
+
+        ring::signature::ED25519
+            .verify(
+                (&self.key[..]).into(),
+                message.as_slice().into(),
+                signature.into(),
+            )
+            .map_err(|_| sig::Error::Unspecified)
+    }
+}
+
+/// A `ring`-based [`sig::Sign`] for Ed25519.
+pub struct Sign {
+    keypair: ring::signature::Ed25519KeyPair,
+}
+
+impl Sign {
+    /// Creates a new `Sign` from the given PKCS#8-encoded private key.
+    ///
+    /// Both v1 and v2 PKCS#8 documents are accepted; in the latter case, the
+    /// embedded public key is checked for consistency.
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, sig::Error> {
+        let keypair =
+            ring::signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8)
+                .map_err(|_| sig::Error::Unspecified)?;
+        Ok(Self { keypair })
+    }
+
+    /// Returns the raw public key for this keypair.
+    pub fn public(&self) -> [u8; 32] {
+        use ring::signature::KeyPair as _;
+        let mut key = [0; 32];
+        key.copy_from_slice(self.keypair.public_key().as_ref());
+        key
+    }
+}
+
+impl sig::Sign for Sign {
+    fn sig_bytes(&self) -> usize {
+        64
+    }
+
+    fn sign(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &mut [u8],
+    ) -> Result<usize, sig::Error> {
+        let mut message = Vec::new();
+        for bytes in message_vec {
+            message.extend_from_slice(bytes);
+        }
+
+        let sig = self.keypair.sign(&message);
+        let signature = signature
+            .get_mut(..sig.as_ref().len())
+            .ok_or(sig::Error::Unspecified)?;
+        signature.copy_from_slice(sig.as_ref());
+        Ok(signature.len())
+    }
+}
+
+/// A `ring`-based [`sig::Keygen`] for Ed25519.
+///
+/// Seeds are used directly as RFC8032 private keys.
+pub struct Keygen {
+    _priv: (),
+}
+
+impl Keygen {
+    /// Creates a new `Keygen`.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Default for Keygen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl sig::Keygen for Keygen {
+    type Keypair = Sign;
+
+    fn derive(&mut self, seed: &[u8; 32]) -> Result<Sign, sig::Error> {
+        let keypair =
+            ring::signature::Ed25519KeyPair::from_seed_unchecked(seed)
+                .map_err(|_| sig::Error::Unspecified)?;
+        Ok(Sign { keypair })
+    }
+
+    fn public_key<'k>(&self, keypair: &'k Sign) -> sig::PublicKeyParams<'k> {
+        use ring::signature::KeyPair as _;
+        sig::PublicKeyParams::Okp {
+            curve: sig::Curve::Ed25519,
+            x: keypair.keypair.public_key().as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sig::Sign as _;
+    use crate::crypto::sig::Verify as _;
+    use testutil::data::keys;
+    use testutil::data::misc_crypto;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn ed25519() {
+        let mut signer = Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR).unwrap();
+        assert_eq!(&signer.public(), keys::KEY1_ED25519_RAW);
+        let mut verifier = Verify::from_public(*keys::KEY1_ED25519_RAW);
+
+        let mut generated_sig = vec![0; signer.sig_bytes()];
+        let sig_len = signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut generated_sig)
+            .unwrap();
+
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .unwrap();
+
+        generated_sig[0] ^= 1;
+        assert!(verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn keygen() {
+        use crate::crypto::sig::Keygen as _;
+
+        let mut keygen = Keygen::new();
+        let mut a = keygen.derive(&[0x5a; 32]).unwrap();
+        let b = keygen.derive(&[0x5a; 32]).unwrap();
+        let c = keygen.derive(&[0xa5; 32]).unwrap();
+        assert_eq!(a.public(), b.public());
+        assert_ne!(a.public(), c.public());
+        assert_eq!(
+            keygen.public_key(&a),
+            sig::PublicKeyParams::Okp {
+                curve: sig::Curve::Ed25519,
+                x: &a.public(),
+            }
+        );
+
+        let mut sig = vec![0; a.sig_bytes()];
+        let sig_len = a.sign(&[misc_crypto::PLAIN_TEXT], &mut sig).unwrap();
+        Verify::from_public(b.public())
+            .verify(&[misc_crypto::PLAIN_TEXT], &sig[..sig_len])
+            .unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of [`crypto::hmac`] based on `ring`.
+
+use ring::constant_time;
+use ring::hmac as ring_hmac;
+
+use crate::crypto::hmac;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// Converts an [`hmac::Algo`] into its `ring` equivalent.
+fn ring_algo(algo: hmac::Algo) -> ring_hmac::Algorithm {
+    match algo {
+        hmac::Algo::Sha256 => ring_hmac::HMAC_SHA256,
+        hmac::Algo::Sha384 => ring_hmac::HMAC_SHA384,
+    }
+}
+
+/// A `ring`-based [`hmac::Builder`].
+#[derive(Default)]
+pub struct Builder {
+    hmac: Option<Hmac>,
+}
+
+impl Builder {
+    /// Creates a new `Builder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl hmac::Builder for Builder {
+    fn new_hmac<'a>(
+        &'a mut self,
+        algo: hmac::Algo,
+        key: &[u8],
+    ) -> Result<&'a mut dyn hmac::Hmac, hmac::Error> {
+        let key = ring_hmac::Key::new(ring_algo(algo), key);
+        self.hmac = Some(Hmac {
+            algo,
+            ctx: Some(ring_hmac::Context::with_key(&key)),
+        });
+        self.hmac
+            .as_mut()
+            .map(|x| x as _)
+            .ok_or(hmac::Error::Unspecified)
+    }
+}
+
+/// A `ring`-based [`hmac::Hmac`].
+///
+/// See [`Builder`].
+pub struct Hmac {
+    algo: hmac::Algo,
+    // This is `None` once the operation has been finished.
+    ctx: Option<ring_hmac::Context>,
+}
+
+impl hmac::Hmac for Hmac {
+    fn algo(&self) -> hmac::Algo {
+        self.algo
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), hmac::Error> {
+        let ctx = self.ctx.as_mut().ok_or(hmac::Error::Unspecified)?;
+        ctx.update(bytes);
+        Ok(())
+    }
+
+    fn finish(&mut self, tag: &mut [u8]) -> Result<(), hmac::Error> {
+        if tag.len() != self.algo.tag_len() {
+            return Err(hmac::Error::Unspecified);
+        }
+        let ctx = self.ctx.take().ok_or(hmac::Error::Unspecified)?;
+        tag.copy_from_slice(ctx.sign().as_ref());
+        Ok(())
+    }
+
+    fn verify(&mut self, tag: &[u8]) -> Result<(), hmac::Error> {
+        let ctx = self.ctx.take().ok_or(hmac::Error::Unspecified)?;
+        constant_time::verify_slices_are_equal(ctx.sign().as_ref(), tag)
+            .map_err(|_| hmac::Error::WrongTag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::crypto::hmac::Builder as _;
+
+    // Test case 2 from RFC4231.
+    const KEY: &[u8] = b"Jefe";
+    const DATA: &[u8] = b"what do ya want for nothing?";
+    #[rustfmt::skip]
+    const SHA256_TAG: &[u8] = &[
+        0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e,
+        0x6a, 0x04, 0x24, 0x26, 0x08, 0x95, 0x75, 0xc7,
+        0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83,
+        0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec, 0x38, 0x43,
+    ];
+    #[rustfmt::skip]
+    const SHA384_TAG: &[u8] = &[
+        0xaf, 0x45, 0xd2, 0xe3, 0x76, 0x48, 0x40, 0x31,
+        0x61, 0x7f, 0x78, 0xd2, 0xb5, 0x8a, 0x6b, 0x1b,
+        0x9c, 0x7e, 0xf4, 0x64, 0xf5, 0xa0, 0x1b, 0x47,
+        0xe4, 0x2e, 0xc3, 0x73, 0x63, 0x22, 0x44, 0x5e,
+        0x8e, 0x22, 0x40, 0xca, 0x5e, 0x69, 0xe2, 0xc7,
+        0x8b, 0x32, 0x39, 0xec, 0xfa, 0xb2, 0x16, 0x49,
+    ];
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn hmac() {
+        let mut builder = Builder::new();
+        for &(algo, expected) in &[
+            (hmac::Algo::Sha256, SHA256_TAG),
+            (hmac::Algo::Sha384, SHA384_TAG),
+        ] {
+            let mut tag = [0; hmac::MAX_TAG_LEN];
+            let tag = &mut tag[..algo.tag_len()];
+            builder.sign(algo, KEY, &[DATA], tag).unwrap();
+            assert_eq!(tag, expected);
+
+            let hmac = builder.new_hmac(algo, KEY).unwrap();
+            hmac.write(&DATA[..10]).unwrap();
+            hmac.write(&DATA[10..]).unwrap();
+            hmac.finish(tag).unwrap();
+            assert_eq!(tag, expected);
+            assert!(hmac.finish(tag).is_err());
+
+            builder.verify(algo, KEY, &[DATA], expected).unwrap();
+            let mut wrong = expected.to_vec();
+            wrong[0] ^= 1;
+            assert_eq!(
+                builder.verify(algo, KEY, &[DATA], &wrong),
+                Err(hmac::Error::WrongTag)
+            );
+            assert_eq!(
+                builder.verify(algo, KEY, &[DATA], &expected[1..]),
+                Err(hmac::Error::WrongTag)
+            );
+        }
+    }
+}
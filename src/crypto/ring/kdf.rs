@@ -0,0 +1,150 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of [`crypto::kdf`] based on `ring`.
+
+use ring::hkdf;
+
+use crate::crypto::hmac;
+use crate::crypto::kdf;
+use crate::crypto::ring::hmac as ring_hmac;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// A `ring`-based [`kdf::Kdf`].
+///
+/// HKDF is provided by `ring` directly, while SP800-108 is implemented with
+/// [`kdf::counter_mode()`] on top of a [`ring_hmac::Builder`].
+#[derive(Default)]
+pub struct Kdf {
+    hmac: ring_hmac::Builder,
+}
+
+impl Kdf {
+    /// Creates a new `Kdf`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// An HKDF output length, in bytes.
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+impl kdf::Kdf for Kdf {
+    fn hkdf(
+        &mut self,
+        algo: hmac::Algo,
+        ikm: &[u8],
+        salt: &[u8],
+        info_vec: &[&[u8]],
+        out: &mut [u8],
+    ) -> Result<(), kdf::Error> {
+        let algo = match algo {
+            hmac::Algo::Sha256 => hkdf::HKDF_SHA256,
+            hmac::Algo::Sha384 => hkdf::HKDF_SHA384,
+        };
+        hkdf::Salt::new(algo, salt)
+            .extract(ikm)
+            .expand(info_vec, Len(out.len()))
+            .map_err(|_| kdf::Error::OutputTooLong)?
+            .fill(out)
+            .map_err(|_| kdf::Error::Unspecified)
+    }
+
+    fn counter_mode(
+        &mut self,
+        algo: hmac::Algo,
+        key: &[u8],
+        label: &[u8],
+        context: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), kdf::Error> {
+        kdf::counter_mode(&mut self.hmac, algo, key, label, context, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::crypto::kdf::Kdf as _;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn hkdf() {
+        // Test case 1 from RFC5869.
+        #[rustfmt::skip]
+        const OKM: &[u8] = &[
+            0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a,
+            0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36, 0x2f, 0x2a,
+            0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c,
+            0x5d, 0xb0, 0x2d, 0x56, 0xec, 0xc4, 0xc5, 0xbf,
+            0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18,
+            0x58, 0x65,
+        ];
+        let ikm = [0x0b; 22];
+        let salt = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            0x0b, 0x0c,
+        ];
+        let info = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+
+        let mut kdf = Kdf::new();
+        let mut okm = [0; 42];
+        kdf.hkdf(hmac::Algo::Sha256, &ikm, &salt, &[&info], &mut okm)
+            .unwrap();
+        assert_eq!(&okm[..], OKM);
+
+        // `info` may be split up arbitrarily.
+        let mut okm = [0; 42];
+        kdf.hkdf(
+            hmac::Algo::Sha256,
+            &ikm,
+            &salt,
+            &[&info[..3], &info[3..]],
+            &mut okm,
+        )
+        .unwrap();
+        assert_eq!(&okm[..], OKM);
+
+        let mut too_long = [0; 255 * 32 + 1];
+        assert_eq!(
+            kdf.hkdf(hmac::Algo::Sha256, &ikm, &salt, &[], &mut too_long),
+            Err(kdf::Error::OutputTooLong)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn counter_mode() {
+        // Computed with Python's `hmac` module; this spans two PRF blocks.
+        #[rustfmt::skip]
+        const OUT: &[u8] = &[
+            0xae, 0x4a, 0x7f, 0x15, 0x26, 0xad, 0x9d, 0x51,
+            0x26, 0x84, 0x31, 0xd5, 0xe3, 0x49, 0x83, 0x96,
+            0xd3, 0xd7, 0x1a, 0x41, 0x75, 0xd8, 0xb8, 0x2b,
+            0xa2, 0xef, 0x07, 0x9d, 0xdb, 0x3c, 0x82, 0xea,
+            0xe9, 0xad, 0x40, 0x3a, 0x2d, 0xe3, 0xd6, 0x1c,
+        ];
+
+        let mut kdf = Kdf::new();
+        let mut out = [0; OUT.len()];
+        kdf.counter_mode(
+            hmac::Algo::Sha256,
+            b"key",
+            b"label",
+            b"context",
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(&out[..], OUT);
+    }
+}
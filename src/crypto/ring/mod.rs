@@ -17,11 +17,19 @@
 //!
 //! [`ring` warranty disclaimer]: https://github.com/briansmith/ring/blob/main/README.md
 
+#[cfg(feature = "std")]
+pub mod aead;
 #[cfg(feature = "std")]
 pub mod csrng;
 #[cfg(feature = "std")]
+pub mod ecdh;
+#[cfg(feature = "std")]
 pub mod ecdsa;
 #[cfg(feature = "std")]
+pub mod ed25519;
+pub mod hmac;
+pub mod kdf;
+#[cfg(feature = "std")]
 pub mod rsa;
 pub mod sha256;
 #[cfg(feature = "std")]
@@ -9,6 +9,7 @@
 use core::convert::TryInto as _;
 
 use crate::crypto::ring::ecdsa;
+use crate::crypto::ring::ed25519;
 use crate::crypto::ring::rsa;
 use crate::crypto::sig;
 use crate::crypto::sig::Algo;
@@ -40,7 +41,7 @@ impl sig::Ciphers for Ciphers {
             has_ecc: true,
             has_rsa: true,
 
-            ecc_strength: EccKeyStrength::BITS_256,
+            ecc_strength: EccKeyStrength::BITS_256 | EccKeyStrength::BITS_384,
             rsa_strength: RsaKeyStrength::all(),
             ..*caps
         };
@@ -90,6 +91,45 @@ impl sig::Ciphers for Ciphers {
                     ecdsa::VerifyP256::with_pkcs11_encoding(*x, *y),
                 ));
             }
+            (
+                Algo::EcdsaDerP384,
+                PublicKeyParams::Ecc {
+                    curve: Curve::NistP384,
+                    x,
+                    y,
+                },
+            ) => {
+                let x: &[u8; 48] = (*x).try_into().ok()?;
+                let y: &[u8; 48] = (*y).try_into().ok()?;
+                self.verifier = Some(Box::new(
+                    ecdsa::VerifyP384::with_der_encoding(*x, *y),
+                ));
+            }
+            (
+                Algo::EcdsaPkcs11P384,
+                PublicKeyParams::Ecc {
+                    curve: Curve::NistP384,
+                    x,
+                    y,
+                },
+            ) => {
+                let x: &[u8; 48] = (*x).try_into().ok()?;
+                let y: &[u8; 48] = (*y).try_into().ok()?;
+                self.verifier = Some(Box::new(
+                    ecdsa::VerifyP384::with_pkcs11_encoding(*x, *y),
+                ));
+            }
+            (
+                Algo::Ed25519,
+                PublicKeyParams::Okp {
+                    curve: Curve::Ed25519,
+                    x,
+                },
+            ) => {
+                let x: &[u8; 32] = (*x).try_into().ok()?;
+                self.verifier =
+                    Some(Box::new(ed25519::Verify::from_public(*x)));
+            }
             _ => {}
         }
 
@@ -0,0 +1,251 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of ECDSA based on the `p256` crate.
+
+use core::convert::TryFrom as _;
+
+use p256::ecdsa::signature::DigestSigner as _;
+use p256::ecdsa::signature::DigestVerifier as _;
+use p256::ecdsa::Signature;
+use p256::ecdsa::SigningKey;
+use p256::ecdsa::VerifyingKey;
+use p256::pkcs8::DecodePrivateKey as _;
+
+use crate::crypto::rustcrypto::sha256::digest;
+use crate::crypto::sig;
+
+/// A signature encoding.
+#[derive(Copy, Clone)]
+enum Encoding {
+    Der,
+    Pkcs11,
+}
+
+/// A `p256`-based [`sig::Verify`] for ECDSA using the P-256 curve.
+pub struct VerifyP256 {
+    // This is `None` if the coordinates were not a point on the curve, in
+    // which case all signatures are rejected.
+    key: Option<VerifyingKey>,
+    encoding: Encoding,
+}
+
+impl VerifyP256 {
+    fn new(x: [u8; 32], y: [u8; 32], encoding: Encoding) -> Self {
+        let mut key = [4u8; 65];
+        key[1..33].copy_from_slice(&x);
+        key[33..65].copy_from_slice(&y);
+
+        Self {
+            key: VerifyingKey::from_sec1_bytes(&key).ok(),
+            encoding,
+        }
+    }
+
+    /// Creates a new `VerifyP256` using the DER encoding and the given
+    /// public-key coordinates.
+    pub fn with_der_encoding(x: [u8; 32], y: [u8; 32]) -> Self {
+        Self::new(x, y, Encoding::Der)
+    }
+
+    /// Creates a new `VerifyP256` using the PKCS#11 encoding and the given
+    /// public-key coordinates.
+    pub fn with_pkcs11_encoding(x: [u8; 32], y: [u8; 32]) -> Self {
+        Self::new(x, y, Encoding::Pkcs11)
+    }
+}
+
+impl sig::Verify for VerifyP256 {
+    fn verify(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &[u8],
+    ) -> Result<(), sig::Error> {
+        let key = self.key.as_ref().ok_or(sig::Error::Unspecified)?;
+        let signature = match self.encoding {
+            Encoding::Der => Signature::from_der(signature),
+            Encoding::Pkcs11 => Signature::try_from(signature),
+        }
+        .map_err(|_| sig::Error::Unspecified)?;
+
+        key.verify_digest(digest(message_vec), &signature)
+            .map_err(|_| sig::Error::Unspecified)
+    }
+}
+
+/// A `p256`-based [`sig::Sign`] for ECDSA using the P-256 curve.
+///
+/// Signatures are deterministic, per RFC6979.
+pub struct SignP256 {
+    keypair: SigningKey,
+    encoding: Encoding,
+}
+
+impl SignP256 {
+    /// Creates a new `SignP256` from the given PKCS#8-encoded private key,
+    /// using the DER encoding for signatures.
+    ///
+    /// Returns an error if the key fails to parse.
+    pub fn with_der_encoding_from_pkcs8(
+        pkcs8: &[u8],
+    ) -> Result<Self, sig::Error> {
+        let keypair = p256::SecretKey::from_pkcs8_der(pkcs8)
+            .map_err(|_| sig::Error::Unspecified)?
+            .into();
+        Ok(Self {
+            keypair,
+            encoding: Encoding::Der,
+        })
+    }
+
+    /// Creates a new `SignP256` from the given PKCS#8-encoded private key,
+    /// using the PKCS#11-style encoding for signatures.
+    ///
+    /// Returns an error if the key fails to parse.
+    pub fn with_pkcs11_encoding_from_pkcs8(
+        pkcs8: &[u8],
+    ) -> Result<Self, sig::Error> {
+        let keypair = p256::SecretKey::from_pkcs8_der(pkcs8)
+            .map_err(|_| sig::Error::Unspecified)?
+            .into();
+        Ok(Self {
+            keypair,
+            encoding: Encoding::Pkcs11,
+        })
+    }
+}
+
+impl sig::Sign for SignP256 {
+    fn sig_bytes(&self) -> usize {
+        // See `crypto::ring::ecdsa::SignP256::sig_bytes()`.
+        64 + 8
+    }
+
+    fn sign(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &mut [u8],
+    ) -> Result<usize, sig::Error> {
+        let sig: Signature = self
+            .keypair
+            .try_sign_digest(digest(message_vec))
+            .map_err(|_| sig::Error::Unspecified)?;
+
+        let der;
+        let sig = match self.encoding {
+            Encoding::Der => {
+                der = sig.to_der();
+                der.as_bytes()
+            }
+            Encoding::Pkcs11 => sig.as_ref(),
+        };
+        let signature = signature
+            .get_mut(..sig.len())
+            .ok_or(sig::Error::Unspecified)?;
+        signature.copy_from_slice(sig);
+        Ok(signature.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sig::Sign as _;
+    use crate::crypto::sig::Verify as _;
+    use testutil::data::keys;
+    use testutil::data::misc_crypto;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn p256_der() {
+        let mut signer = SignP256::with_der_encoding_from_pkcs8(
+            keys::KEY1_ECDSA_P256_KEYPAIR,
+        )
+        .unwrap();
+        let mut verifier = VerifyP256::with_der_encoding(
+            *keys::KEY1_ECDSA_P256_X,
+            *keys::KEY1_ECDSA_P256_Y,
+        );
+
+        let mut generated_sig = vec![0; signer.sig_bytes()];
+        let sig_len = signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut generated_sig)
+            .unwrap();
+
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn p256_pkcs11() {
+        let mut signer = SignP256::with_pkcs11_encoding_from_pkcs8(
+            keys::KEY1_ECDSA_P256_KEYPAIR,
+        )
+        .unwrap();
+        let mut verifier = VerifyP256::with_pkcs11_encoding(
+            *keys::KEY1_ECDSA_P256_X,
+            *keys::KEY1_ECDSA_P256_Y,
+        );
+
+        let mut generated_sig = vec![0; signer.sig_bytes()];
+        let sig_len = signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut generated_sig)
+            .unwrap();
+
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(all(feature = "ring", feature = "std"))]
+    fn interop_with_ring() {
+        use crate::crypto::ring::ecdsa as ring_ecdsa;
+
+        // ECDSA signatures are randomized in `ring`, so the best we can do is
+        // check that each backend accepts the other's signatures.
+        let mut ring_signer =
+            ring_ecdsa::SignP256::with_der_encoding_from_pkcs8(
+                keys::KEY1_ECDSA_P256_KEYPAIR,
+            )
+            .unwrap();
+        let mut ring_verifier = ring_ecdsa::VerifyP256::with_der_encoding(
+            *keys::KEY1_ECDSA_P256_X,
+            *keys::KEY1_ECDSA_P256_Y,
+        );
+        let mut signer = SignP256::with_der_encoding_from_pkcs8(
+            keys::KEY1_ECDSA_P256_KEYPAIR,
+        )
+        .unwrap();
+        let mut verifier = VerifyP256::with_der_encoding(
+            *keys::KEY1_ECDSA_P256_X,
+            *keys::KEY1_ECDSA_P256_Y,
+        );
+
+        let mut sig = vec![0; signer.sig_bytes()];
+        let len = ring_signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut sig)
+            .unwrap();
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &sig[..len])
+            .unwrap();
+
+        let len = signer.sign(&[misc_crypto::PLAIN_TEXT], &mut sig).unwrap();
+        ring_verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &sig[..len])
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn not_on_curve() {
+        let mut verifier = VerifyP256::with_pkcs11_encoding([1; 32], [2; 32]);
+        assert!(verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &[1; 64])
+            .is_err());
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of crypto traits, using pure-Rust crates from the
+//! [RustCrypto] project.
+//!
+//! This module provides software implementations of [`crypto`] traits that,
+//! unlike those in [`crypto::ring`], do not depend on `std` or on any
+//! assembly, and so can be built for bare-metal targets. It is intended as a
+//! fallback for integrations without cryptographic hardware.
+//!
+//! Only SHA-256, PKCS#1.5 RSA with SHA-256, and ECDSA on the P-256 curve are
+//! provided. RSA is only available with the `rustcrypto-rsa` feature, since
+//! the underlying crate allocates, and so requires a global allocator; the
+//! rest of this module does not allocate.
+//!
+//! Types in this module, much like those in [`crypto`], should not be imported
+//! directly. Instead, names such as `rustcrypto::rsa::Sign256` should be used
+//! instead.
+//!
+//! [RustCrypto]: https://github.com/RustCrypto
+//! [`crypto::ring`]: crate::crypto::ring
+
+pub mod ecdsa;
+#[cfg(feature = "rustcrypto-rsa")]
+pub mod rsa;
+pub mod sha256;
+pub mod sig;
+
+#[cfg(doc)]
+use crate::crypto;
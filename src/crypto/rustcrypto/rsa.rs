@@ -0,0 +1,176 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of RSA based on the `rsa` crate.
+//!
+//! Requires the `rustcrypto-rsa` feature, and a global allocator.
+
+use rsa::pkcs8::DecodePrivateKey as _;
+use rsa::BigUint;
+use rsa::PaddingScheme;
+use rsa::PublicKey as _;
+use rsa::PublicKeyParts as _;
+use sha2::Digest as _;
+
+use crate::crypto::rustcrypto::sha256::digest;
+use crate::crypto::sig;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// Returns the PKCS#1.5 padding scheme for SHA-256 signatures.
+fn padding() -> PaddingScheme {
+    PaddingScheme::new_pkcs1v15_sign(Some(rsa::Hash::SHA2_256))
+}
+
+/// An RSA public key.
+#[derive(Clone)]
+pub struct PublicKey {
+    key: rsa::RsaPublicKey,
+}
+
+impl PublicKey {
+    /// Creates a new `PublicKey` with the given modulus and exponent, both of
+    /// which should be given in big-endian.
+    ///
+    /// Returns an error if the key is malformed or too large to be supported.
+    pub fn new(modulus: &[u8], exponent: &[u8]) -> Result<Self, sig::Error> {
+        let key = rsa::RsaPublicKey::new(
+            BigUint::from_bytes_be(modulus),
+            BigUint::from_bytes_be(exponent),
+        )
+        .map_err(|_| sig::Error::Unspecified)?;
+        Ok(Self { key })
+    }
+}
+
+/// An `rsa`-based [`sig::Verify`] for PKCS#1.5 RSA using SHA-256.
+pub struct Verify256 {
+    key: PublicKey,
+}
+
+impl Verify256 {
+    /// Creates a new `Verify256` with the given key.
+    pub fn from_public(key: PublicKey) -> Self {
+        Self { key }
+    }
+}
+
+impl sig::Verify for Verify256 {
+    fn verify(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &[u8],
+    ) -> Result<(), sig::Error> {
+        self.key
+            .key
+            .verify(padding(), &digest(message_vec).finalize(), signature)
+            .map_err(|_| sig::Error::Unspecified)
+    }
+}
+
+/// An `rsa`-based [`sig::Sign`] for PKCS#1.5 RSA using SHA-256.
+///
+/// Signing does not use blinding, since [`sig::Sign`] provides no source of
+/// randomness, and is not constant-time.
+pub struct Sign256 {
+    keypair: rsa::RsaPrivateKey,
+}
+
+impl Sign256 {
+    /// Creates a new `Sign256` from the given PKCS#8-encoded private key.
+    ///
+    /// Returns an error if the key fails to parse.
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, sig::Error> {
+        let keypair = rsa::RsaPrivateKey::from_pkcs8_der(pkcs8)
+            .map_err(|_| sig::Error::Unspecified)?;
+        Ok(Self { keypair })
+    }
+
+    /// Creates a `Verify256` using a copy of the corresponding public key.
+    pub fn verifier(&self) -> Verify256 {
+        Verify256 {
+            key: PublicKey {
+                key: self.keypair.to_public_key(),
+            },
+        }
+    }
+}
+
+impl sig::Sign for Sign256 {
+    fn sig_bytes(&self) -> usize {
+        self.keypair.size()
+    }
+
+    fn sign(
+        &mut self,
+        message_vec: &[&[u8]],
+        signature: &mut [u8],
+    ) -> Result<usize, sig::Error> {
+        let sig = self
+            .keypair
+            .sign(padding(), &digest(message_vec).finalize())
+            .map_err(|_| sig::Error::Unspecified)?;
+        let signature = signature
+            .get_mut(..sig.len())
+            .ok_or(sig::Error::Unspecified)?;
+        signature.copy_from_slice(&sig);
+        Ok(signature.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sig::Sign as _;
+    use crate::crypto::sig::Verify as _;
+    use testutil::data::keys;
+    use testutil::data::misc_crypto;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn rsa() {
+        let mut signer = Sign256::from_pkcs8(keys::KEY1_RSA_KEYPAIR).unwrap();
+        let mut verifier = signer.verifier();
+
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], misc_crypto::KEY1_SHA256_SIG)
+            .unwrap();
+
+        let mut generated_sig = vec![0; signer.sig_bytes()];
+        let sig_len = signer
+            .sign(&[misc_crypto::PLAIN_TEXT], &mut generated_sig)
+            .unwrap();
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &generated_sig[..sig_len])
+            .unwrap();
+
+        // PKCS#1.5 signatures are deterministic, so this must agree with the
+        // `ring` backend exactly.
+        assert_eq!(&generated_sig[..sig_len], misc_crypto::KEY1_SHA256_SIG);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_public() {
+        let key =
+            PublicKey::new(keys::KEY1_RSA_MOD, keys::KEY1_RSA_EXP).unwrap();
+        let mut verifier = Verify256::from_public(key);
+        verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], misc_crypto::KEY1_SHA256_SIG)
+            .unwrap();
+
+        // The message may be split up arbitrarily.
+        let (a, b) = misc_crypto::PLAIN_TEXT.split_at(7);
+        verifier
+            .verify(&[a, b], misc_crypto::KEY1_SHA256_SIG)
+            .unwrap();
+
+        let mut bad_sig = misc_crypto::KEY1_SHA256_SIG.to_vec();
+        bad_sig[0] ^= 1;
+        assert!(verifier
+            .verify(&[misc_crypto::PLAIN_TEXT], &bad_sig)
+            .is_err());
+    }
+}
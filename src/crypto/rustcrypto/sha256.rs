@@ -0,0 +1,101 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of [`crypto::sha256`] based on the `sha2` crate.
+
+use sha2::Digest as _;
+
+use crate::crypto::sha256;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// Hashes the concatenation of `message_vec` with SHA-256, returning the
+/// hasher so that callers may either finalize it or hand it to a
+/// [`signature::DigestSigner`].
+///
+/// [`signature::DigestSigner`]: p256::ecdsa::signature::DigestSigner
+pub(crate) fn digest(message_vec: &[&[u8]]) -> sha2::Sha256 {
+    let mut sha = sha2::Sha256::new();
+    for bytes in message_vec {
+        sha.update(bytes);
+    }
+    sha
+}
+
+/// A `sha2`-based [`sha256::Builder`].
+pub struct Builder {
+    _priv: (),
+}
+
+impl Builder {
+    /// Creates a new `Builder`.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl sha256::Builder for Builder {
+    type Hasher = Hasher;
+
+    fn new_hasher(&self) -> Result<Hasher, sha256::Error> {
+        Ok(Hasher {
+            ctx: sha2::Sha256::new(),
+        })
+    }
+}
+
+/// A `sha2`-based [`sha256::Hasher`].
+///
+/// See [`Builder`].
+pub struct Hasher {
+    ctx: sha2::Sha256,
+}
+
+impl sha256::Hasher for Hasher {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), sha256::Error> {
+        self.ctx.update(bytes);
+        Ok(())
+    }
+
+    fn finish(self, out: &mut sha256::Digest) -> Result<(), sha256::Error> {
+        out.copy_from_slice(&self.ctx.finalize());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use testutil::data::misc_crypto;
+
+    use crate::crypto::sha256;
+    use crate::crypto::sha256::Builder as _;
+    use crate::crypto::sha256::Hasher as _;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sha() {
+        let sha = Builder::new();
+        let mut digest = sha256::Digest::default();
+
+        let mut hasher = sha.new_hasher().unwrap();
+        hasher.write(misc_crypto::PLAIN_TEXT).unwrap();
+        hasher.finish(&mut digest).unwrap();
+        assert_eq!(&digest, misc_crypto::PLAIN_SHA256);
+
+        let mut hasher = sha.new_hasher().unwrap();
+        hasher.write(&misc_crypto::PLAIN_TEXT[..16]).unwrap();
+        hasher.write(&misc_crypto::PLAIN_TEXT[16..]).unwrap();
+        hasher.finish(&mut digest).unwrap();
+        assert_eq!(&digest, misc_crypto::PLAIN_SHA256);
+    }
+}
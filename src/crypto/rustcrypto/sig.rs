@@ -0,0 +1,159 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implementations of [`crypto::sig`] based on RustCrypto crates.
+
+use core::convert::TryInto as _;
+
+use crate::crypto::rustcrypto::ecdsa;
+#[cfg(feature = "rustcrypto-rsa")]
+use crate::crypto::rustcrypto::rsa;
+use crate::crypto::sig;
+use crate::crypto::sig::Algo;
+use crate::crypto::sig::Curve;
+use crate::crypto::sig::PublicKeyParams;
+use crate::protocol::capabilities;
+
+#[cfg(doc)]
+use crate::crypto;
+
+/// A [`sig::Ciphers`] built on top of RustCrypto.
+///
+/// Only RSA and P-256 keys are supported; RSA keys only with the
+/// `rustcrypto-rsa` feature.
+#[derive(Default)]
+pub struct Ciphers {
+    // At most one of these is `Some` at a time; keeping each verifier inline
+    // avoids needing to box them.
+    #[cfg(feature = "rustcrypto-rsa")]
+    rsa: Option<rsa::Verify256>,
+    p256: Option<ecdsa::VerifyP256>,
+}
+
+impl Ciphers {
+    /// Returns a new `Ciphers`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl sig::Ciphers for Ciphers {
+    fn negotiate(&self, caps: &mut capabilities::Crypto) {
+        use capabilities::*;
+        let has_rsa = cfg!(feature = "rustcrypto-rsa");
+        *caps = Crypto {
+            has_ecdsa: true,
+            has_ecc: true,
+            has_rsa,
+
+            ecc_strength: EccKeyStrength::BITS_256,
+            rsa_strength: if has_rsa {
+                RsaKeyStrength::all()
+            } else {
+                RsaKeyStrength::empty()
+            },
+            ..*caps
+        };
+    }
+
+    fn verifier<'a>(
+        &'a mut self,
+        algo: sig::Algo,
+        key: &sig::PublicKeyParams,
+    ) -> Option<&'a mut dyn sig::Verify> {
+        #[cfg(feature = "rustcrypto-rsa")]
+        {
+            self.rsa = None;
+        }
+        self.p256 = None;
+        match (algo, key) {
+            #[cfg(feature = "rustcrypto-rsa")]
+            (
+                Algo::RsaPkcs1Sha256,
+                PublicKeyParams::Rsa { modulus, exponent },
+            ) => {
+                let key = rsa::PublicKey::new(modulus, exponent).ok()?;
+                self.rsa = Some(rsa::Verify256::from_public(key));
+                self.rsa.as_mut().map(|x| x as _)
+            }
+            (
+                Algo::EcdsaDerP256,
+                PublicKeyParams::Ecc {
+                    curve: Curve::NistP256,
+                    x,
+                    y,
+                },
+            ) => {
+                let x: &[u8; 32] = (*x).try_into().ok()?;
+                let y: &[u8; 32] = (*y).try_into().ok()?;
+                self.p256 = Some(ecdsa::VerifyP256::with_der_encoding(*x, *y));
+                self.p256.as_mut().map(|x| x as _)
+            }
+            (
+                Algo::EcdsaPkcs11P256,
+                PublicKeyParams::Ecc {
+                    curve: Curve::NistP256,
+                    x,
+                    y,
+                },
+            ) => {
+                let x: &[u8; 32] = (*x).try_into().ok()?;
+                let y: &[u8; 32] = (*y).try_into().ok()?;
+                self.p256 =
+                    Some(ecdsa::VerifyP256::with_pkcs11_encoding(*x, *y));
+                self.p256.as_mut().map(|x| x as _)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use testutil::data::keys;
+    use testutil::data::misc_crypto;
+
+    use crate::crypto::sig::Ciphers as _;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn verifier() {
+        let mut ciphers = Ciphers::new();
+
+        let rsa = PublicKeyParams::Rsa {
+            modulus: keys::KEY1_RSA_MOD,
+            exponent: keys::KEY1_RSA_EXP,
+        };
+        let rsa_verifier = ciphers.verifier(Algo::RsaPkcs1Sha256, &rsa);
+        if cfg!(feature = "rustcrypto-rsa") {
+            rsa_verifier
+                .unwrap()
+                .verify(
+                    &[misc_crypto::PLAIN_TEXT],
+                    misc_crypto::KEY1_SHA256_SIG,
+                )
+                .unwrap();
+        } else {
+            assert!(rsa_verifier.is_none());
+        }
+
+        let p256 = PublicKeyParams::Ecc {
+            curve: Curve::NistP256,
+            x: keys::KEY1_ECDSA_P256_X,
+            y: keys::KEY1_ECDSA_P256_Y,
+        };
+        assert!(ciphers.verifier(Algo::EcdsaDerP256, &p256).is_some());
+        assert!(ciphers.verifier(Algo::EcdsaPkcs11P256, &p256).is_some());
+        assert!(ciphers.verifier(Algo::RsaPkcs1Sha256, &p256).is_none());
+
+        let p384 = PublicKeyParams::Ecc {
+            curve: Curve::NistP384,
+            x: keys::KEY1_ECDSA_P384_X,
+            y: keys::KEY1_ECDSA_P384_Y,
+        };
+        assert!(ciphers.verifier(Algo::EcdsaDerP384, &p384).is_none());
+    }
+}
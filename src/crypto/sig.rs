@@ -56,11 +56,28 @@ pub trait Sign {
     ) -> Result<usize, Error>;
 }
 
+/// A deterministic key-generation engine.
+///
+/// Unlike a [`Sign`], which is primed with an existing keypair, a `Keygen`
+/// derives keypairs from secret seed material, such as a DICE CDI. The same
+/// seed must always produce the same keypair.
+pub trait Keygen {
+    /// The keypair type produced by this engine.
+    type Keypair: Sign;
+
+    /// Deterministically derives a keypair from `seed`.
+    fn derive(&mut self, seed: &[u8; 32]) -> Result<Self::Keypair, Error>;
+
+    /// Returns the public half of `keypair`.
+    fn public_key<'k>(&self, keypair: &'k Self::Keypair)
+        -> PublicKeyParams<'k>;
+}
+
 /// Public key parameters extracted from a certificate.
 ///
 /// This must be paired with a compatible [`Algo`] (which specifies *algorithm*
 /// parameters) to be usable for signature verification.
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum PublicKeyParams<'cert> {
     /// Raw RSA parameters.
     Rsa {
@@ -78,6 +95,14 @@ pub enum PublicKeyParams<'cert> {
         /// The y-coordinate, in big-endian.
         y: &'cert [u8],
     },
+    /// A raw octet key pair public key, i.e., an RFC8032 encoded Edwards
+    /// curve point.
+    Okp {
+        /// The curve the point is from.
+        curve: Curve,
+        /// The encoded point.
+        x: &'cert [u8],
+    },
 }
 
 impl PublicKeyParams<'_> {
@@ -91,19 +116,24 @@ impl PublicKeyParams<'_> {
             (self, algo),
             (Self::Rsa { .. }, Algo::RsaPkcs1Sha256) |
             (Self::Ecc { curve: Curve::NistP256, .. }, Algo::EcdsaDerP256) |
-            (Self::Ecc { curve: Curve::NistP256, .. }, Algo::EcdsaPkcs11P256)
+            (Self::Ecc { curve: Curve::NistP256, .. }, Algo::EcdsaPkcs11P256) |
+            (Self::Ecc { curve: Curve::NistP384, .. }, Algo::EcdsaDerP384) |
+            (Self::Ecc { curve: Curve::NistP384, .. }, Algo::EcdsaPkcs11P384) |
+            (Self::Okp { curve: Curve::Ed25519, .. }, Algo::Ed25519)
         );
         ok
     }
 }
 
-/// An elliptic curve used in e.g. ECDSA.
+/// An elliptic curve used in e.g. ECDSA or EdDSA.
 ///
-/// See [`PublicKeyParams::Ecc`].
+/// See [`PublicKeyParams::Ecc`] and [`PublicKeyParams::Okp`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[allow(missing_docs)]
 pub enum Curve {
     NistP256,
+    NistP384,
+    Ed25519,
 }
 
 /// A signature algorithm for a certificate subject key.
@@ -119,6 +149,14 @@ pub enum Algo {
     /// Fixed-width (PKCS#11-style) ECDSA signatures using the NIST
     /// P-256 curve and SHA-256 for hashing.
     EcdsaPkcs11P256,
+    /// DER-encoded ECDSA signatures using the NIST P-384 curve and
+    /// SHA-384 for hashing.
+    EcdsaDerP384,
+    /// Fixed-width (PKCS#11-style) ECDSA signatures using the NIST
+    /// P-384 curve and SHA-384 for hashing.
+    EcdsaPkcs11P384,
+    /// Pure EdDSA signatures using the Ed25519 curve, as specified in RFC8032.
+    Ed25519,
 }
 
 /// A collection of ciphers that are provided to certificate machinery.
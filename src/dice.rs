@@ -0,0 +1,545 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! DICE layering.
+//!
+//! Under DICE, each boot stage (or *layer*) is given a secret by the layer
+//! before it, the Compound Device Identifier (CDI), which is derived from the
+//! previous layer's own secret and measurements of the new layer. The first
+//! layer receives the hardware's Unique Device Secret (UDS) instead. A
+//! [`Layer`] takes this secret from a [`hardware::Cdi`], and uses it to:
+//! - Derive its own attestation keypair.
+//! - Measure the next layer and derive that layer's CDI.
+//! - Issue a certificate for the next layer's attestation key, signed with
+//!   its own, in either [`CertFormat`].
+//!
+//! This makes it possible for a `manticore` RoT to act as the first DICE
+//! layer, vouching for the firmware it boots.
+//!
+//! # Derivations
+//!
+//! Derivations follow the general shape of OpenDICE, using HKDF-SHA256
+//! (RFC5869) built on top of [`sha256::Builder`]:
+//! ```text
+//! code_hash   = SHA-256(code)
+//! config_hash = SHA-256(config)
+//! inputs      = SHA-256(code_hash || config_hash || mode)
+//! next_cdi    = HKDF(ikm = cdi, salt = inputs, info = "CDI_Attest")
+//! key_seed    = HKDF(ikm = cdi, salt = "", info = "Key Pair")
+//! key_id      = HKDF(ikm = public_key, salt = "", info = "ID")[..20]
+//! ```
+//! where `mode` is the single byte [`OperationalFlags::bits()`], and
+//! `public_key` is the concatenation of the key's raw components (e.g., the
+//! `x` and `y` coordinates of an elliptic curve point). Keypairs are derived
+//! from `key_seed` by a [`sig::Keygen`].
+//!
+//! Certificates name layers by the hex encoding of their `key_id`, as a
+//! `commonName` for X.509 and as a plain string for CWTs; X.509 certificates
+//! additionally use `key_id`, with its top bit cleared, as their serial
+//! number, and carry the subject's and issuer's `key_id`s as their subject
+//! and authority key identifiers. The next layer's code and configuration
+//! hashes are recorded as its first two FWIDs, and `mode` as its flags.
+
+use crate::cert;
+use crate::cert::CertBuilder;
+use crate::cert::CertFormat;
+use crate::cert::CwtBuilder;
+use crate::cert::Fwid;
+use crate::cert::FwidAlgo;
+use crate::cert::Measurements;
+use crate::cert::Name;
+use crate::cert::OperationalFlags;
+use crate::crypto::sha256;
+use crate::crypto::sha256::Hasher as _;
+use crate::crypto::sig;
+use crate::hardware;
+use crate::mem::Arena;
+use crate::mem::ArenaExt as _;
+use crate::mem::OutOfMemory;
+
+/// The length of a key identifier, in bytes.
+const ID_LEN: usize = 20;
+
+/// The SHA-256 block size, in bytes.
+const BLOCK_LEN: usize = 64;
+
+/// HKDF `info` strings used to separate derivations.
+const CDI_INFO: &[u8] = b"CDI_Attest";
+const KEY_INFO: &[u8] = b"Key Pair";
+const ID_INFO: &[u8] = b"ID";
+
+/// A DICE derivation error.
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// Indicates that hashing failed.
+    Sha256(sha256::Error),
+    /// Indicates that deriving or signing with a keypair failed.
+    Sig(sig::Error),
+    /// Indicates that the next layer's certificate could not be issued.
+    Cert(cert::Error),
+}
+
+impl From<sha256::Error> for Error {
+    fn from(e: sha256::Error) -> Self {
+        Self::Sha256(e)
+    }
+}
+
+impl From<sig::Error> for Error {
+    fn from(e: sig::Error) -> Self {
+        Self::Sig(e)
+    }
+}
+
+impl From<cert::Error> for Error {
+    fn from(e: cert::Error) -> Self {
+        Self::Cert(e)
+    }
+}
+
+impl From<OutOfMemory> for Error {
+    fn from(e: OutOfMemory) -> Self {
+        Self::Cert(e.into())
+    }
+}
+
+/// A description of the next DICE layer.
+#[derive(Copy, Clone, Debug)]
+pub struct Inputs<'a> {
+    /// The next layer's code, e.g., its firmware image.
+    pub code: &'a [u8],
+    /// The next layer's security-relevant configuration.
+    pub config: &'a [u8],
+    /// The state the next layer is being booted in.
+    pub flags: OperationalFlags,
+    /// The format of the certificate to issue for the next layer.
+    pub format: CertFormat,
+    /// Whether the next layer will itself issue certificates, i.e., whether
+    /// it is not the last DICE layer.
+    pub is_ca: bool,
+}
+
+/// The outputs of deriving the next DICE layer.
+///
+/// The CDI is secret, and must only be handed to the next layer.
+pub struct NextLayer<'out> {
+    /// The next layer's CDI.
+    pub cdi: sha256::Digest,
+    /// The certificate for the next layer's attestation key.
+    pub cert: &'out [u8],
+}
+
+impl hardware::Cdi for NextLayer<'_> {
+    fn cdi(&self) -> &[u8] {
+        &self.cdi
+    }
+}
+
+/// The currently running DICE layer.
+///
+/// See the module documentation for details.
+pub struct Layer<'a, Sha, Keygen> {
+    cdi: &'a dyn hardware::Cdi,
+    sha: &'a Sha,
+    keygen: &'a mut Keygen,
+}
+
+impl<'a, Sha, Keygen> Layer<'a, Sha, Keygen>
+where
+    Sha: sha256::Builder,
+    Keygen: sig::Keygen,
+{
+    /// Creates a new `Layer` for the layer booted with the secret in `cdi`.
+    pub fn new(
+        cdi: &'a dyn hardware::Cdi,
+        sha: &'a Sha,
+        keygen: &'a mut Keygen,
+    ) -> Self {
+        Self { cdi, sha, keygen }
+    }
+
+    /// Derives this layer's attestation keypair.
+    pub fn keypair(&mut self) -> Result<Keygen::Keypair, Error> {
+        derive_keypair(self.sha, self.keygen, self.cdi.cdi())
+    }
+
+    /// Derives the identifier this layer's certificates are named by.
+    pub fn key_id(&mut self) -> Result<[u8; ID_LEN], Error> {
+        let keypair = self.keypair()?;
+        key_id(self.sha, &self.keygen.public_key(&keypair))
+    }
+
+    /// Measures the next layer described by `inputs`, deriving its CDI and
+    /// issuing a certificate for its attestation key.
+    ///
+    /// The certificate is allocated out of `arena`.
+    pub fn derive_next<'out>(
+        &mut self,
+        inputs: &Inputs,
+        arena: &'out dyn Arena,
+    ) -> Result<NextLayer<'out>, Error> {
+        let mut code_hash = sha256::Digest::default();
+        self.sha.hash_contiguous(inputs.code, &mut code_hash)?;
+        let mut config_hash = sha256::Digest::default();
+        self.sha.hash_contiguous(inputs.config, &mut config_hash)?;
+
+        let mut salt = sha256::Digest::default();
+        let mut hasher = self.sha.new_hasher()?;
+        hasher.write(&code_hash)?;
+        hasher.write(&config_hash)?;
+        hasher.write(&[inputs.flags.bits()])?;
+        hasher.finish(&mut salt)?;
+
+        let mut cdi = sha256::Digest::default();
+        hkdf(self.sha, &salt, &[self.cdi.cdi()], CDI_INFO, &mut cdi)?;
+
+        // The issuer's public key borrows from its keypair, which we need to
+        // sign with, so we copy it out first.
+        let mut issuer_keypair = self.keypair()?;
+        let issuer_key =
+            copy_key(&self.keygen.public_key(&issuer_keypair), arena)?;
+        let subject_keypair = derive_keypair(self.sha, self.keygen, &cdi)?;
+        let subject_key = self.keygen.public_key(&subject_keypair);
+
+        let issuer_id = key_id(self.sha, &issuer_key)?;
+        let subject_id = key_id(self.sha, &subject_key)?;
+        let mut issuer_hex = [0; 2 * ID_LEN];
+        let issuer_name = hex(&issuer_id, &mut issuer_hex);
+        let mut subject_hex = [0; 2 * ID_LEN];
+        let subject_name = hex(&subject_id, &mut subject_hex);
+
+        let mut measurements = Measurements::default();
+        for hash in &[&code_hash, &config_hash] {
+            measurements.push_fwid(Fwid {
+                algo: Some(FwidAlgo::Sha256),
+                digest: *hash,
+            })?;
+        }
+        measurements.flags = Some(inputs.flags);
+
+        let cert = match inputs.format {
+            CertFormat::RiotX509 => {
+                let mut serial = subject_id;
+                serial[0] &= 0x7f;
+
+                let issuer = Name::x509_common_name(issuer_name, arena)?;
+                let subject = Name::x509_common_name(subject_name, arena)?;
+                let mut builder =
+                    CertBuilder::new(&serial, issuer, subject, &subject_key)
+                        .key_identifiers(
+                            Some(&subject_id[..]),
+                            Some(&issuer_id[..]),
+                        )
+                        .measurements(&measurements);
+                if inputs.is_ca {
+                    builder = builder.ca(None);
+                }
+                builder.sign(&issuer_key, &mut issuer_keypair, arena)?
+            }
+            CertFormat::OpenDiceCwt => {
+                let issuer = Name::cwt(issuer_name);
+                let subject = Name::cwt(subject_name);
+                let mut builder =
+                    CwtBuilder::new(issuer, subject, &subject_key)
+                        .measurements(&measurements);
+                if inputs.is_ca {
+                    builder = builder.ca();
+                }
+                builder.sign(&issuer_key, &mut issuer_keypair, arena)?
+            }
+        };
+
+        Ok(NextLayer { cdi, cert })
+    }
+}
+
+/// Derives the attestation keypair for the layer with the given `cdi`.
+fn derive_keypair<Sha: sha256::Builder, Keygen: sig::Keygen>(
+    sha: &Sha,
+    keygen: &mut Keygen,
+    cdi: &[u8],
+) -> Result<Keygen::Keypair, Error> {
+    let mut seed = sha256::Digest::default();
+    hkdf(sha, &[], &[cdi], KEY_INFO, &mut seed)?;
+    Ok(keygen.derive(&seed)?)
+}
+
+/// Derives the identifier for `key`.
+fn key_id<Sha: sha256::Builder>(
+    sha: &Sha,
+    key: &sig::PublicKeyParams,
+) -> Result<[u8; ID_LEN], Error> {
+    let mut okm = sha256::Digest::default();
+    match key {
+        sig::PublicKeyParams::Rsa { modulus, exponent } => {
+            hkdf(sha, &[], &[*modulus, *exponent], ID_INFO, &mut okm)?
+        }
+        sig::PublicKeyParams::Ecc { x, y, .. } => {
+            hkdf(sha, &[], &[*x, *y], ID_INFO, &mut okm)?
+        }
+        sig::PublicKeyParams::Okp { x, .. } => {
+            hkdf(sha, &[], &[*x], ID_INFO, &mut okm)?
+        }
+    }
+    let mut id = [0; ID_LEN];
+    id.copy_from_slice(&okm[..ID_LEN]);
+    Ok(id)
+}
+
+/// Copies `key` into `arena`, so that it no longer borrows from a keypair.
+fn copy_key<'out>(
+    key: &sig::PublicKeyParams,
+    arena: &'out dyn Arena,
+) -> Result<sig::PublicKeyParams<'out>, OutOfMemory> {
+    let copy = |bytes: &[u8]| -> Result<&'out [u8], OutOfMemory> {
+        let buf = arena.alloc_slice::<u8>(bytes.len())?;
+        buf.copy_from_slice(bytes);
+        Ok(buf)
+    };
+
+    Ok(match key {
+        sig::PublicKeyParams::Rsa { modulus, exponent } => {
+            sig::PublicKeyParams::Rsa {
+                modulus: copy(modulus)?,
+                exponent: copy(exponent)?,
+            }
+        }
+        sig::PublicKeyParams::Ecc { curve, x, y } => {
+            sig::PublicKeyParams::Ecc {
+                curve: *curve,
+                x: copy(x)?,
+                y: copy(y)?,
+            }
+        }
+        sig::PublicKeyParams::Okp { curve, x } => sig::PublicKeyParams::Okp {
+            curve: *curve,
+            x: copy(x)?,
+        },
+    })
+}
+
+/// Writes the lowercase hex encoding of `id` into `out`.
+fn hex<'a>(id: &[u8; ID_LEN], out: &'a mut [u8; 2 * ID_LEN]) -> &'a str {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for (byte, digits) in id.iter().zip(out.chunks_mut(2)) {
+        digits[0] = DIGITS[(byte >> 4) as usize];
+        digits[1] = DIGITS[(byte & 0xf) as usize];
+    }
+    core::str::from_utf8(out).expect("hex digits are ASCII")
+}
+
+/// Computes HMAC-SHA256 (RFC2104) of the concatenation of `message`.
+fn hmac<Sha: sha256::Builder>(
+    sha: &Sha,
+    key: &[u8],
+    message: &[&[u8]],
+    out: &mut sha256::Digest,
+) -> Result<(), sha256::Error> {
+    // Keys longer than a block are hashed first; all keys are then padded
+    // out to a block with zeroes.
+    let mut block = [0; BLOCK_LEN];
+    if key.len() > BLOCK_LEN {
+        let mut digest = sha256::Digest::default();
+        sha.hash_contiguous(key, &mut digest)?;
+        block[..digest.len()].copy_from_slice(&digest);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    for b in &mut block {
+        *b ^= 0x36;
+    }
+    let mut inner = sha.new_hasher()?;
+    inner.write(&block)?;
+    for bytes in message {
+        inner.write(bytes)?;
+    }
+    let mut inner_digest = sha256::Digest::default();
+    inner.finish(&mut inner_digest)?;
+
+    for b in &mut block {
+        *b ^= 0x36 ^ 0x5c;
+    }
+    let mut outer = sha.new_hasher()?;
+    outer.write(&block)?;
+    outer.write(&inner_digest)?;
+    outer.finish(out)
+}
+
+/// Computes HKDF-SHA256 (RFC5869) of the concatenation of `ikm`, producing a
+/// single block of output.
+fn hkdf<Sha: sha256::Builder>(
+    sha: &Sha,
+    salt: &[u8],
+    ikm: &[&[u8]],
+    info: &[u8],
+    out: &mut sha256::Digest,
+) -> Result<(), sha256::Error> {
+    let mut prk = sha256::Digest::default();
+    hmac(sha, salt, ikm, &mut prk)?;
+    hmac(sha, &prk, &[info, &[1]], out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::cert::Cert;
+    use crate::crypto::ring;
+    use crate::crypto::sha256::Builder as _;
+    use crate::crypto::sig::Keygen as _;
+    use crate::hardware::fake;
+    use crate::mem::BumpArena;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn rfc5869() {
+        // RFC5869, Appendix A.1, truncated to a single block.
+        let sha = ring::sha256::Builder::new();
+        let mut okm = sha256::Digest::default();
+        hkdf(
+            &sha,
+            &[
+                0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0x8, 0x9, 0xa, 0xb, 0xc,
+            ],
+            &[&[0x0b; 22]],
+            &[0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9],
+            &mut okm,
+        )
+        .unwrap();
+        assert_eq!(
+            okm,
+            [
+                0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43,
+                0x4f, 0x64, 0xd0, 0x36, 0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90,
+                0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56, 0xec, 0xc4,
+                0xc5, 0xbf,
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn hmac_long_key() {
+        let sha = ring::sha256::Builder::new();
+        let mut mac = sha256::Digest::default();
+        hmac(&sha, &[b'k'; 100], &[b"m", b"sg"], &mut mac).unwrap();
+        assert_eq!(
+            mac,
+            [
+                0xbd, 0x56, 0xa1, 0x78, 0x2c, 0x28, 0x30, 0xe8, 0xab, 0xc6,
+                0xed, 0x86, 0x6a, 0x57, 0xa1, 0x23, 0x06, 0x61, 0xe6, 0x50,
+                0xb8, 0x4c, 0x62, 0xf7, 0xee, 0x3a, 0xcc, 0xc5, 0xfa, 0x5a,
+                0xf4, 0x91,
+            ]
+        );
+    }
+
+    fn inputs(format: CertFormat, is_ca: bool) -> Inputs<'static> {
+        Inputs {
+            code: b"next layer firmware",
+            config: b"next layer config",
+            flags: OperationalFlags::empty(),
+            format,
+            is_ca,
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn deterministic() {
+        let mut arena = [0; 4096];
+        let arena = BumpArena::new(&mut arena);
+        let sha = ring::sha256::Builder::new();
+        let mut keygen = ring::ed25519::Keygen::new();
+        let uds = fake::Cdi::new(&[0x42; 32]);
+        let mut layer = Layer::new(&uds, &sha, &mut keygen);
+
+        let inputs = inputs(CertFormat::OpenDiceCwt, true);
+        let a = layer.derive_next(&inputs, &arena).unwrap();
+        let b = layer.derive_next(&inputs, &arena).unwrap();
+        assert_eq!(a.cdi, b.cdi);
+        assert_ne!(&a.cdi[..], &[0x42; 32][..]);
+
+        // Ed25519 signatures are deterministic, too.
+        assert_eq!(a.cert, b.cert);
+
+        // Changing any input changes the CDI.
+        let mut changed = inputs;
+        changed.config = b"other config";
+        let c = layer.derive_next(&changed, &arena).unwrap();
+        assert_ne!(a.cdi, c.cdi);
+
+        let mut changed = inputs;
+        changed.flags = OperationalFlags::DEBUG;
+        let c = layer.derive_next(&changed, &arena).unwrap();
+        assert_ne!(a.cdi, c.cdi);
+
+        let other_uds = fake::Cdi::new(&[0x43; 32]);
+        let mut other = Layer::new(&other_uds, &sha, &mut keygen);
+        let c = other.derive_next(&inputs, &arena).unwrap();
+        assert_ne!(a.cdi, c.cdi);
+    }
+
+    fn check_chain(format: CertFormat) {
+        let mut arena = [0; 8192];
+        let arena = BumpArena::new(&mut arena);
+        let sha = ring::sha256::Builder::new();
+        let mut keygen = ring::ed25519::Keygen::new();
+        let uds = fake::Cdi::new(&[0x42; 32]);
+
+        let mut layer0 = Layer::new(&uds, &sha, &mut keygen);
+        let key0 = layer0.keypair().unwrap();
+        let next1 = layer0.derive_next(&inputs(format, true), &arena).unwrap();
+
+        let mut layer1_keygen = ring::ed25519::Keygen::new();
+        let mut layer1 = Layer::new(&next1, &sha, &mut layer1_keygen);
+        let key1 = layer1.keypair().unwrap();
+        let next2 = layer1.derive_next(&inputs(format, false), &arena).unwrap();
+
+        let cert1 = Cert::parse(
+            next1.cert,
+            format,
+            Some(&keygen.public_key(&key0)),
+            &mut ring::sig::Ciphers::new(),
+        )
+        .unwrap();
+        assert_eq!(cert1.subject_key(), &keygen.public_key(&key1));
+        assert!(cert1.supports_cert_signing());
+
+        let m = cert1.measurements().unwrap();
+        assert_eq!(m.fwids().len(), 2);
+        assert_eq!(m.flags, Some(OperationalFlags::empty()));
+        let mut code_hash = sha256::Digest::default();
+        sha.hash_contiguous(b"next layer firmware", &mut code_hash)
+            .unwrap();
+        assert_eq!(m.fwids()[0].digest, &code_hash);
+
+        let cert2 = Cert::parse(
+            next2.cert,
+            format,
+            Some(cert1.subject_key()),
+            &mut ring::sig::Ciphers::new(),
+        )
+        .unwrap();
+        assert_eq!(cert2.issuer(), cert1.subject());
+        assert!(!cert2.supports_cert_signing());
+        if format == CertFormat::RiotX509 {
+            assert!(cert2.authority_key_id().is_some());
+            assert_eq!(cert2.authority_key_id(), cert1.subject_key_id());
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn chain_x509() {
+        check_chain(CertFormat::RiotX509);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn chain_cwt() {
+        check_chain(CertFormat::OpenDiceCwt);
+    }
+}
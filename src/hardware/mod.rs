@@ -46,8 +46,42 @@ pub trait Reset {
     fn uptime(&self) -> Duration;
 }
 
+/// Provides access to a monotonic clock.
+pub trait Clock {
+    /// Returns the current time, measured from some fixed point in the past.
+    ///
+    /// The value returned by this function must never decrease; it need not
+    /// be related to wall-clock time, and may, for example, be the device's
+    /// uptime.
+    fn now(&self) -> Duration;
+}
+
+/// Provides access to wall-clock time.
+///
+/// Unlike [`Clock`], wall-clock time may not be available on every device,
+/// or may only become available after some other component (such as a BMC)
+/// has provided it.
+pub trait WallClock {
+    /// Returns the current time, measured from the Unix epoch
+    /// (1970-01-01T00:00:00Z) and ignoring leap seconds.
+    fn unix_time(&self) -> Duration;
+}
+
+/// Provides access to the secret a DICE layer was booted with.
+///
+/// For the first DICE layer, this is the hardware's Unique Device Secret
+/// (UDS); for every later layer, it is the Compound Device Identifier (CDI)
+/// derived for it by the previous layer. See [`crate::dice`].
+pub trait Cdi {
+    /// Returns the secret for the currently running DICE layer.
+    ///
+    /// The secret should be at least 32 bytes of uniformly random data.
+    fn cdi(&self) -> &[u8];
+}
+
 #[cfg(test)]
 pub(crate) mod fake {
+    use core::cell::Cell;
     use core::convert::TryInto;
     use core::time::Duration;
     use std::collections::HashMap;
@@ -123,4 +157,52 @@ pub(crate) mod fake {
             self.uptime
         }
     }
+
+    /// A fake `Cdi` that returns a fixed secret.
+    pub struct Cdi {
+        cdi: Vec<u8>,
+    }
+
+    impl Cdi {
+        /// Creates a new `fake::Cdi`.
+        pub fn new(cdi: &[u8]) -> Self {
+            Self { cdi: cdi.to_vec() }
+        }
+    }
+
+    impl super::Cdi for Cdi {
+        fn cdi(&self) -> &[u8] {
+            &self.cdi
+        }
+    }
+
+    /// A fake `Clock` that only moves forward when told to.
+    #[derive(Default)]
+    pub struct Clock {
+        now: Cell<Duration>,
+    }
+
+    impl Clock {
+        /// Creates a new `fake::Clock`, starting at zero.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Moves the clock forward by `by`.
+        pub fn advance(&self, by: Duration) {
+            self.now.set(self.now.get() + by);
+        }
+    }
+
+    impl super::Clock for Clock {
+        fn now(&self) -> Duration {
+            self.now.get()
+        }
+    }
+
+    impl super::WallClock for Clock {
+        fn unix_time(&self) -> Duration {
+            self.now.get()
+        }
+    }
 }
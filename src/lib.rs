@@ -30,6 +30,11 @@
 //! - `ring` (default) enables the [`crypto::ring` module], which provides
 //!   software implementations for cryptography traits used by `manticore`.
 //!   This feature is not intended for on-device use-cases either.
+//! - `rustcrypto` enables the [`crypto::rustcrypto` module], which provides
+//!   pure-Rust software implementations of some cryptography traits. Unlike
+//!   `ring`, this backend builds for bare-metal targets.
+//! - `rustcrypto-rsa` adds RSA to the `crypto::rustcrypto` module. This
+//!   requires a global allocator.
 //! - `serde` enables implementations of `serde`'s (de)serialization traits.
 //! - `inject-alloc` makes it possible to replace borrowed content in some
 //!   structs with allocated content. This is mostly useful for tooling that
@@ -41,6 +46,7 @@
 //!   https://github.com/opencomputeproject/Project_Olympus/tree/master/Project_Cerberus
 //! [`protocol` module]: protocol/index.html
 //! [`crypto::ring` module]: crypto/ring/index.html
+//! [`crypto::rustcrypto` module]: crypto/rustcrypto/index.html
 
 #![allow(clippy::unusual_byte_groupings)]
 #![cfg_attr(not(feature = "std"), no_std)]
@@ -57,6 +63,7 @@ mod serde;
 
 pub mod cert;
 pub mod crypto;
+pub mod dice;
 pub mod hardware;
 pub mod io;
 pub mod manifest;
@@ -78,6 +78,7 @@ use crate::crypto::sig;
 use crate::hardware::flash;
 use crate::io;
 use crate::mem::OutOfMemory;
+use crate::protocol;
 use crate::protocol::wire::WireEnum;
 
 #[cfg(doc)]
@@ -218,6 +219,42 @@ impl From<sha256::Error> for Error {
     }
 }
 
+impl From<Error> for protocol::Error {
+    fn from(e: Error) -> Self {
+        use protocol::ErrorCode;
+        use protocol::ErrorSubcode;
+        match e {
+            Error::OutOfMemory => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::OutOfMemory)
+            }
+            Error::Io(_) | Error::Flash(_) => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::Internal)
+            }
+            Error::HashError(_) => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::Crypto)
+            }
+            Error::UnsupportedHashType(_) => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::Unsupported)
+            }
+            Error::SigError(_)
+            | Error::BadTocHash
+            | Error::BadElementHash { .. } => {
+                Self::new(ErrorCode::Authentication, ErrorSubcode::BadManifest)
+            }
+            Error::OutOfRange
+            | Error::BadMagic(_)
+            | Error::BadParent { .. }
+            | Error::BadHashIndex { .. }
+            | Error::TooShort { .. }
+            | Error::BadRange { .. }
+            | Error::Unaligned
+            | Error::BadSignatureLen => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::BadManifest)
+            }
+        }
+    }
+}
+
 /// A manifest type.
 ///
 /// A type that implements this trait is not itself a "parsed" instance of the
@@ -16,13 +16,18 @@
 #![allow(missing_docs)]
 
 use core::cell::Cell;
+use core::time::Duration;
+
+use bitflags::bitflags;
 use static_assertions::assert_obj_safe;
 
+use crate::hardware::Clock;
 use crate::io;
 use crate::io::Cursor;
 use crate::io::Read;
 use crate::io::ReadZero;
 use crate::io::Write;
+use crate::protocol::capabilities::Timeouts;
 
 pub use crate::protocol::Header;
 
@@ -51,6 +56,22 @@ impl From<io::Error> for Error {
     }
 }
 
+bitflags! {
+    /// The security properties of the session a request arrived over.
+    ///
+    /// A session is negotiated with the `KeyExchange` command, but is
+    /// ultimately enforced by the transport, which is why [`HostRequest`]
+    /// reports it.
+    pub struct Session: u8 {
+        /// A session has been established with the host.
+        const ESTABLISHED = 0b001;
+        /// Messages in this session are encrypted.
+        const ENCRYPTED = 0b010;
+        /// The host has been paired with this device.
+        const PAIRED = 0b100;
+    }
+}
+
 /// Represents a physical port that can be used to interact with host devices.
 ///
 /// This trait provides a generic mechanism for recieving and responding to
@@ -146,6 +167,14 @@ pub trait HostRequest<'req> {
     /// This function should not be called after calling `reply()`.
     fn payload(&mut self) -> Result<&mut dyn ReadZero<'req>, Error>;
 
+    /// Returns the security properties of the session this request arrived
+    /// over.
+    ///
+    /// The default implementation reports that there is no session at all.
+    fn session(&self) -> Session {
+        Session::empty()
+    }
+
     /// Replies to this request..
     ///
     /// Calling this function performs sufficient transport-level operations to
@@ -224,6 +253,115 @@ pub trait DeviceResponse {
     fn payload(&mut self) -> Result<&mut dyn Read, Error>;
 }
 
+/// A [`DevicePort`] wrapper that enforces negotiated [`Timeouts`].
+///
+/// Each request sent through this port is given a deadline, measured with a
+/// [`Clock`], based on whether its command is subject to the "regular" or
+/// "crypto" timeout (see [`Timeouts::for_command()`]). Waiting for a response
+/// never extends past that deadline, so that a silent device results in
+/// [`Error::Timeout`] rather than a hang.
+///
+/// [`TimeoutPort::transact()`] additionally retries requests that time out.
+pub struct TimeoutPort<'a, Clock> {
+    port: &'a mut dyn DevicePort,
+    clock: &'a Clock,
+    timeouts: Timeouts,
+    retries: usize,
+    deadline: Option<Duration>,
+}
+
+impl<'a, C: Clock> TimeoutPort<'a, C> {
+    /// Creates a new `TimeoutPort` wrapping `port`.
+    ///
+    /// `timeouts` should be the timeouts reported by the device on the other
+    /// end of `port`; `retries` is the number of times
+    /// [`TimeoutPort::transact()`] will resend a request that timed out.
+    pub fn new(
+        port: &'a mut dyn DevicePort,
+        clock: &'a C,
+        timeouts: Timeouts,
+        retries: usize,
+    ) -> Self {
+        Self {
+            port,
+            clock,
+            timeouts,
+            retries,
+            deadline: None,
+        }
+    }
+
+    /// Returns the time remaining until the current deadline.
+    ///
+    /// Returns [`Error::Timeout`] if the deadline has already passed, and
+    /// [`Error::OutOfOrder`] if there is no request in flight.
+    fn remaining(&self) -> Result<Duration, Error> {
+        let deadline = self.deadline.ok_or(Error::OutOfOrder)?;
+        match deadline.checked_sub(self.clock.now()) {
+            Some(d) if d > Duration::from_millis(0) => Ok(d),
+            _ => Err(Error::Timeout),
+        }
+    }
+
+    /// Sends a request and waits for its response, retrying if the device
+    /// does not respond in time.
+    ///
+    /// The request is sent at most `retries + 1` times. Errors other than
+    /// [`Error::Timeout`] are returned immediately.
+    pub fn transact(
+        &mut self,
+        dest: u8,
+        header: Header,
+        msg: &[u8],
+    ) -> Result<&mut dyn DeviceResponse, Error> {
+        let mut attempts = 0;
+        loop {
+            let result = self
+                .send(dest, header, msg)
+                .and_then(|_| self.wait_for_response(usize::MAX));
+            match result {
+                Ok(()) => break,
+                Err(Error::Timeout) if attempts < self.retries => attempts += 1,
+                Err(e) => return Err(e),
+            }
+        }
+        self.receive_response()
+    }
+}
+
+impl<C: Clock> DevicePort for TimeoutPort<'_, C> {
+    fn send(
+        &mut self,
+        dest: u8,
+        header: Header,
+        msg: &[u8],
+    ) -> Result<(), Error> {
+        self.deadline =
+            Some(self.clock.now() + self.timeouts.for_command(header.command));
+        // A request that cannot possibly be answered in time is not sent at
+        // all.
+        self.remaining()?;
+        self.port.send(dest, header, msg)
+    }
+
+    fn wait_for_response(&mut self, duration: usize) -> Result<(), Error> {
+        let remaining = self.remaining()?;
+        // Round up, so that we never wait for zero milliseconds while some
+        // time remains.
+        let mut millis = remaining.as_millis();
+        if remaining.subsec_nanos() % 1_000_000 != 0 {
+            millis += 1;
+        }
+        let millis = millis.min(usize::MAX as u128) as usize;
+        self.port.wait_for_response(duration.min(millis))
+    }
+
+    fn receive_response(&mut self) -> Result<&mut dyn DeviceResponse, Error> {
+        self.deadline = None;
+        self.port.receive_response()
+    }
+}
+
 /// A simple in-memory [`HostPort`].
 ///
 /// This type is both useful for testing, and as a demonstration of how to use
@@ -308,6 +446,7 @@ struct InMemInner<'buf> {
     tx_header: Option<Header>,
     tx: Cursor<'buf>,
     finished: bool,
+    session: Session,
 }
 
 impl<'buf> InMemHost<'buf> {
@@ -321,9 +460,15 @@ impl<'buf> InMemHost<'buf> {
             tx_header: None,
             tx: Cursor::new(out),
             finished: false,
+            session: Session::empty(),
         })
     }
 
+    /// Sets the session properties that subsequent requests will report.
+    pub fn set_session(&mut self, session: Session) {
+        self.0.session = session;
+    }
+
     /// Schedules a new request to be recieved, with the given request parts.
     ///
     /// Calling this function will make `recieve()` start working; otherwise,
@@ -366,6 +511,10 @@ impl<'req, 'buf: 'req> HostRequest<'req> for InMemInner<'buf> {
         Ok(&mut self.rx)
     }
 
+    fn session(&self) -> Session {
+        self.session
+    }
+
     fn reply(
         &mut self,
         header: Header,
@@ -465,3 +614,148 @@ impl DeviceResponse for InMemDeviceInner<'_> {
         Ok(&mut self.rx)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hardware::fake;
+    use crate::protocol::CommandType;
+
+    const TIMEOUTS: Timeouts = Timeouts {
+        regular: Duration::from_millis(30),
+        crypto: Duration::from_millis(200),
+    };
+
+    /// A `DevicePort` that only responds after a fixed number of sends, and
+    /// otherwise lets time pass until the requested timeout expires.
+    struct SlowPort<'a> {
+        inner: InMemDevice<'a>,
+        clock: &'a fake::Clock,
+        sends: usize,
+        respond_after: usize,
+        waits: Vec<usize>,
+    }
+
+    impl DevicePort for SlowPort<'_> {
+        fn send(
+            &mut self,
+            dest: u8,
+            header: Header,
+            msg: &[u8],
+        ) -> Result<(), Error> {
+            self.sends += 1;
+            self.inner.send(dest, header, msg)
+        }
+
+        fn wait_for_response(&mut self, duration: usize) -> Result<(), Error> {
+            self.waits.push(duration);
+            if self.sends < self.respond_after {
+                self.clock.advance(Duration::from_millis(duration as u64));
+                return Err(Error::Timeout);
+            }
+            Ok(())
+        }
+
+        fn receive_response(
+            &mut self,
+        ) -> Result<&mut dyn DeviceResponse, Error> {
+            self.inner.receive_response()
+        }
+    }
+
+    fn header(command: CommandType) -> Header {
+        Header {
+            command,
+            is_request: true,
+        }
+    }
+
+    #[test]
+    fn regular_timeout() {
+        let clock = fake::Clock::new();
+        let mut port = SlowPort {
+            inner: InMemDevice::new(),
+            clock: &clock,
+            sends: 0,
+            respond_after: usize::MAX,
+            waits: Vec::new(),
+        };
+        let mut timeout = TimeoutPort::new(&mut port, &clock, TIMEOUTS, 2);
+        let result =
+            timeout.transact(0, header(CommandType::FirmwareVersion), &[]);
+        assert!(matches!(result, Err(Error::Timeout)));
+
+        assert_eq!(port.sends, 3);
+        assert_eq!(port.waits, &[30, 30, 30]);
+        assert_eq!(clock.now(), Duration::from_millis(90));
+    }
+
+    #[test]
+    fn crypto_timeout() {
+        let clock = fake::Clock::new();
+        let mut port = SlowPort {
+            inner: InMemDevice::new(),
+            clock: &clock,
+            sends: 0,
+            respond_after: usize::MAX,
+            waits: Vec::new(),
+        };
+        let mut timeout = TimeoutPort::new(&mut port, &clock, TIMEOUTS, 0);
+        let result = timeout.transact(0, header(CommandType::Challenge), &[]);
+        assert!(matches!(result, Err(Error::Timeout)));
+
+        assert_eq!(port.sends, 1);
+        assert_eq!(port.waits, &[200]);
+    }
+
+    #[test]
+    fn retry_succeeds() {
+        let clock = fake::Clock::new();
+        let mut port = SlowPort {
+            inner: InMemDevice::new(),
+            clock: &clock,
+            sends: 0,
+            respond_after: 2,
+            waits: Vec::new(),
+        };
+        let mut timeout = TimeoutPort::new(&mut port, &clock, TIMEOUTS, 2);
+        let result = timeout.transact(0, header(CommandType::DeviceId), &[]);
+        assert!(result.is_ok());
+        assert_eq!(port.sends, 2);
+    }
+
+    #[test]
+    fn zero_timeout() {
+        let clock = fake::Clock::new();
+        let mut port = SlowPort {
+            inner: InMemDevice::new(),
+            clock: &clock,
+            sends: 0,
+            respond_after: 0,
+            waits: Vec::new(),
+        };
+        let timeouts = Timeouts {
+            regular: Duration::from_millis(0),
+            ..TIMEOUTS
+        };
+        let mut timeout = TimeoutPort::new(&mut port, &clock, timeouts, 2);
+        let result = timeout.transact(0, header(CommandType::DeviceId), &[]);
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert_eq!(port.sends, 0);
+    }
+
+    #[test]
+    fn expired_before_wait() {
+        let clock = fake::Clock::new();
+        let mut device = InMemDevice::new();
+        let mut timeout = TimeoutPort::new(&mut device, &clock, TIMEOUTS, 0);
+        timeout
+            .send(0, header(CommandType::DeviceId), &[])
+            .expect("send failed");
+
+        clock.advance(Duration::from_millis(10));
+        assert!(timeout.wait_for_response(5).is_ok());
+        clock.advance(Duration::from_millis(25));
+        assert!(matches!(timeout.wait_for_response(5), Err(Error::Timeout)));
+    }
+}
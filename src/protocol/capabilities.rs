@@ -185,6 +185,8 @@ bitflags! {
         const BITS_160 = 0b001;
         /// A key strength of 256 bits.
         const BITS_256 = 0b010;
+        /// A key strength of 384 bits.
+        const BITS_384 = 0b100;
     }
 }
 
@@ -458,6 +460,17 @@ pub struct Timeouts {
     pub crypto: Duration,
 }
 
+impl Timeouts {
+    /// Returns the timeout that applies to a request for `command`.
+    pub fn for_command(&self, command: CommandType) -> Duration {
+        if command.is_crypto() {
+            self.crypto
+        } else {
+            self.regular
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -86,7 +86,8 @@ make_fuzz_safe! {
         pub slot: u8,
         /// The "certificate slot mask" (Cerberus does not elaborate further).
         ///
-        /// Manticore ignores this value.
+        /// Manticore sets bit `n` of this mask when slot `n` holds a
+        /// certificate chain.
         pub slot_mask: u8,
         /// The minimum and maximum versions of Cerberus supported.
         ///
@@ -0,0 +1,119 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! `ExportCsr` request and response.
+//!
+//! This module provides a Cerberus command for requesting a certificate
+//! signing request for one of the device's keys.
+
+use crate::io::ReadInt as _;
+use crate::io::ReadZero;
+use crate::io::Write;
+use crate::mem::Arena;
+use crate::mem::ArenaExt as _;
+use crate::protocol::wire;
+use crate::protocol::wire::FromWire;
+use crate::protocol::wire::ToWire;
+use crate::protocol::Command;
+use crate::protocol::CommandType;
+use crate::protocol::Request;
+use crate::protocol::Response;
+
+#[cfg(feature = "arbitrary-derive")]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A command for requesting a PKCS#10 certificate signing request.
+///
+/// Corresponds to [`CommandType::ExportCsr`].
+pub enum ExportCsr {}
+
+impl<'wire> Command<'wire> for ExportCsr {
+    type Req = ExportCsrRequest;
+    type Resp = ExportCsrResponse<'wire>;
+}
+
+/// The [`ExportCsr`] request.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "arbitrary-derive", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExportCsrRequest {
+    /// The slot whose leaf key the request is for.
+    ///
+    /// Cerberus calls this the CSR "index"; index zero is the Device ID key.
+    pub slot: u8,
+}
+make_fuzz_safe!(ExportCsrRequest);
+
+impl Request<'_> for ExportCsrRequest {
+    const TYPE: CommandType = CommandType::ExportCsr;
+}
+
+impl<'wire> FromWire<'wire> for ExportCsrRequest {
+    fn from_wire<R: ReadZero<'wire> + ?Sized, A: Arena>(
+        r: &mut R,
+        _: &'wire A,
+    ) -> Result<Self, wire::Error> {
+        let slot = r.read_le()?;
+        Ok(Self { slot })
+    }
+}
+
+impl ToWire for ExportCsrRequest {
+    fn to_wire<W: Write>(&self, mut w: W) -> Result<(), wire::Error> {
+        w.write_le(self.slot)?;
+        Ok(())
+    }
+}
+
+make_fuzz_safe! {
+    /// The [`ExportCsr`] response.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct ExportCsrResponse<'wire> {
+        /// The DER-encoded certificate signing request.
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        pub csr: &'wire [u8],
+    }
+}
+
+impl<'wire> Response<'wire> for ExportCsrResponse<'wire> {
+    const TYPE: CommandType = CommandType::ExportCsr;
+}
+
+impl<'wire> FromWire<'wire> for ExportCsrResponse<'wire> {
+    fn from_wire<R: ReadZero<'wire> + ?Sized, A: Arena>(
+        r: &mut R,
+        arena: &'wire A,
+    ) -> Result<Self, wire::Error> {
+        let csr_len = r.remaining_data();
+        let csr = arena.alloc_slice::<u8>(csr_len)?;
+        r.read_bytes(csr)?;
+        Ok(Self { csr })
+    }
+}
+
+impl ToWire for ExportCsrResponse<'_> {
+    fn to_wire<W: Write>(&self, mut w: W) -> Result<(), wire::Error> {
+        w.write_bytes(self.csr)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    round_trip_test! {
+        request_round_trip: {
+            bytes: &[0x01],
+            value: ExportCsrRequest { slot: 1 },
+        },
+        response_round_trip: {
+            bytes: &[0x30, 0x03, 0x02, 0x01, 0x00],
+            value: ExportCsrResponse { csr: &[0x30, 0x03, 0x02, 0x01, 0x00] },
+        },
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! `GetCertificateState` request and response.
+//!
+//! This module provides a Cerberus command for checking on the progress of
+//! certificate provisioning.
+
+use crate::io::ReadInt as _;
+use crate::io::ReadZero;
+use crate::io::Write;
+use crate::mem::Arena;
+use crate::protocol::wire;
+use crate::protocol::wire::FromWire;
+use crate::protocol::wire::ToWire;
+use crate::protocol::Command;
+use crate::protocol::CommandType;
+use crate::protocol::Request;
+use crate::protocol::Response;
+
+#[cfg(feature = "arbitrary-derive")]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(doc)]
+use crate::protocol::ImportSignedCert;
+
+/// A command for requesting the provisioning state of a certificate slot.
+///
+/// Corresponds to [`CommandType::GetCertificateState`].
+pub enum GetCertificateState {}
+
+impl<'wire> Command<'wire> for GetCertificateState {
+    type Req = GetCertificateStateRequest;
+    type Resp = GetCertificateStateResponse;
+}
+
+/// The [`GetCertificateState`] request.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "arbitrary-derive", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GetCertificateStateRequest {
+    /// The slot to query.
+    pub slot: u8,
+}
+make_fuzz_safe!(GetCertificateStateRequest);
+
+impl Request<'_> for GetCertificateStateRequest {
+    const TYPE: CommandType = CommandType::GetCertificateState;
+}
+
+impl<'wire> FromWire<'wire> for GetCertificateStateRequest {
+    fn from_wire<R: ReadZero<'wire> + ?Sized, A: Arena>(
+        r: &mut R,
+        _: &'wire A,
+    ) -> Result<Self, wire::Error> {
+        let slot = r.read_le()?;
+        Ok(Self { slot })
+    }
+}
+
+impl ToWire for GetCertificateStateRequest {
+    fn to_wire<W: Write>(&self, mut w: W) -> Result<(), wire::Error> {
+        w.write_le(self.slot)?;
+        Ok(())
+    }
+}
+
+wire_enum! {
+    /// The provisioning state of a certificate slot.
+    #[cfg_attr(feature = "arbitrary-derive", derive(Arbitrary))]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub enum CertState: u8 {
+        /// The slot holds a valid chain.
+        Provisioned = 0x00,
+        /// The slot does not hold a chain, and none is being imported.
+        NotProvisioned = 0x01,
+        /// A chain is being imported with [`ImportSignedCert`], but is not yet
+        /// complete.
+        InProgress = 0x02,
+        /// The most recently imported chain failed validation.
+        Invalid = 0x03,
+    }
+}
+
+/// The [`GetCertificateState`] response.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "arbitrary-derive", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GetCertificateStateResponse {
+    /// The state of the requested slot.
+    pub state: CertState,
+}
+make_fuzz_safe!(GetCertificateStateResponse);
+
+impl Response<'_> for GetCertificateStateResponse {
+    const TYPE: CommandType = CommandType::GetCertificateState;
+}
+
+impl<'wire> FromWire<'wire> for GetCertificateStateResponse {
+    fn from_wire<R: ReadZero<'wire> + ?Sized, A: Arena>(
+        r: &mut R,
+        a: &'wire A,
+    ) -> Result<Self, wire::Error> {
+        let state = CertState::from_wire(r, a)?;
+        Ok(Self { state })
+    }
+}
+
+impl ToWire for GetCertificateStateResponse {
+    fn to_wire<W: Write>(&self, mut w: W) -> Result<(), wire::Error> {
+        self.state.to_wire(&mut w)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    round_trip_test! {
+        request_round_trip: {
+            bytes: &[0x01],
+            value: GetCertificateStateRequest { slot: 1 },
+        },
+        response_round_trip: {
+            bytes: &[0x02],
+            value: GetCertificateStateResponse { state: CertState::InProgress },
+        },
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! `ImportSignedCert` request.
+//!
+//! This module provides a Cerberus command for provisioning a certificate
+//! chain onto a device.
+
+use core::convert::TryInto as _;
+
+use crate::io::ReadInt as _;
+use crate::io::ReadZero;
+use crate::io::Write;
+use crate::mem::Arena;
+use crate::mem::ArenaExt as _;
+use crate::protocol;
+use crate::protocol::wire;
+use crate::protocol::wire::FromWire;
+use crate::protocol::wire::ToWire;
+use crate::protocol::Command;
+use crate::protocol::CommandType;
+use crate::protocol::Request;
+
+#[cfg(feature = "arbitrary-derive")]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A command for importing a signed certificate.
+///
+/// A chain is imported one certificate at a time, starting with the root,
+/// followed by any intermediates, and ending with the Device ID certificate,
+/// which completes the chain.
+///
+/// Corresponds to [`CommandType::ImportSignedCert`].
+pub enum ImportSignedCert {}
+
+impl<'wire> Command<'wire> for ImportSignedCert {
+    type Req = ImportSignedCertRequest<'wire>;
+    type Resp = protocol::Error;
+}
+
+wire_enum! {
+    /// The position of an imported certificate within its chain.
+    #[cfg_attr(feature = "arbitrary-derive", derive(Arbitrary))]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub enum CertType: u8 {
+        /// The leaf certificate for the Device ID key; this completes a chain.
+        DeviceId = 0x00,
+        /// The root CA certificate; this starts a new chain.
+        RootCa = 0x01,
+        /// An intermediate CA certificate.
+        IntermediateCa = 0x02,
+    }
+}
+make_fuzz_safe!(CertType);
+
+make_fuzz_safe! {
+    /// The [`ImportSignedCert`] request.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct ImportSignedCertRequest<'wire> {
+        /// The slot the certificate is being imported into.
+        pub slot: u8,
+        /// The kind of certificate being imported.
+        pub cert_type: CertType,
+        /// The certificate itself.
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        pub cert: &'wire [u8],
+    }
+}
+
+impl<'wire> Request<'wire> for ImportSignedCertRequest<'wire> {
+    const TYPE: CommandType = CommandType::ImportSignedCert;
+}
+
+impl<'wire> FromWire<'wire> for ImportSignedCertRequest<'wire> {
+    fn from_wire<R: ReadZero<'wire> + ?Sized, A: Arena>(
+        r: &mut R,
+        arena: &'wire A,
+    ) -> Result<Self, wire::Error> {
+        let slot = r.read_le()?;
+        let cert_type = CertType::from_wire(r, arena)?;
+        let cert_len = r.read_le::<u16>()?;
+        let cert = arena.alloc_slice::<u8>(cert_len as usize)?;
+        r.read_bytes(cert)?;
+        Ok(Self {
+            slot,
+            cert_type,
+            cert,
+        })
+    }
+}
+
+impl ToWire for ImportSignedCertRequest<'_> {
+    fn to_wire<W: Write>(&self, mut w: W) -> Result<(), wire::Error> {
+        w.write_le(self.slot)?;
+        self.cert_type.to_wire(&mut w)?;
+        let cert_len: u16 = self
+            .cert
+            .len()
+            .try_into()
+            .map_err(|_| wire::Error::OutOfRange)?;
+        w.write_le(cert_len)?;
+        w.write_bytes(self.cert)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    round_trip_test! {
+        request_round_trip: {
+            bytes: &[0x01, 0x02, 0x05, 0x00, b'x', b'.', b'5', b'0', b'9'],
+            value: ImportSignedCertRequest {
+                slot: 1,
+                cert_type: CertType::IntermediateCa,
+                cert: b"x.509",
+            },
+        },
+    }
+}
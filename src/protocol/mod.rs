@@ -45,10 +45,13 @@
 // This is required due to the make_fuzz_safe! macro.
 #![allow(unused_parens)]
 
+use crate::crypto::sha256;
+use crate::crypto::sig;
 use crate::io::ReadInt as _;
 use crate::io::ReadZero;
 use crate::io::Write;
 use crate::mem::Arena;
+use crate::mem::OutOfMemory;
 use crate::protocol::wire::FromWire;
 use crate::protocol::wire::ToWire;
 
@@ -99,6 +102,15 @@ pub use reset_counter::ResetCounter;
 pub mod request_counter;
 pub use request_counter::RequestCounter;
 
+pub mod export_csr;
+pub use export_csr::ExportCsr;
+
+pub mod import_signed_cert;
+pub use import_signed_cert::ImportSignedCert;
+
+pub mod get_cert_state;
+pub use get_cert_state::GetCertificateState;
+
 /// A Cerberus command.
 ///
 /// A Cerberus command is identified by two types, each of which has a
@@ -176,6 +188,18 @@ wire_enum! {
         ///
         /// See [`KeyExchange`].
         KeyExchange = 0x84,
+        /// A request for a certificate signing request.
+        ///
+        /// See [`ExportCsr`].
+        ExportCsr = 0x20,
+        /// A request to import a signed certificate.
+        ///
+        /// See [`ImportSignedCert`].
+        ImportSignedCert = 0x21,
+        /// A request for the state of certificate provisioning.
+        ///
+        /// See [`GetCertificateState`].
+        GetCertificateState = 0x22,
         /// A request for the rest state of the host processor.
         ///
         /// See [`GetHostState`].
@@ -207,6 +231,21 @@ impl CommandType {
     pub fn is_manticore_extension(self) -> bool {
         matches!(self, Self::DeviceUptime)
     }
+
+    /// Returns `true` when `self` may need to perform an expensive
+    /// cryptographic operation, and is thus subject to the "crypto" timeout
+    /// rather than the "regular" one.
+    ///
+    /// See [`capabilities::Timeouts`].
+    pub fn is_crypto(self) -> bool {
+        matches!(
+            self,
+            Self::Challenge
+                | Self::KeyExchange
+                | Self::ExportCsr
+                | Self::ImportSignedCert
+        )
+    }
 }
 
 impl From<u8> for CommandType {
@@ -219,6 +258,9 @@ impl From<u8> for CommandType {
             0x81 => CommandType::GetDigests,
             0x82 => CommandType::GetCert,
             0x83 => CommandType::Challenge,
+            0x20 => CommandType::ExportCsr,
+            0x21 => CommandType::ImportSignedCert,
+            0x22 => CommandType::GetCertificateState,
             0x40 => CommandType::GetHostState,
             0x87 => CommandType::ResetCounter,
             0xa0 => CommandType::DeviceUptime,
@@ -288,19 +330,86 @@ wire_enum! {
     /// A Cerberus error.
     ///
     /// This enum represents all error types implemented by `manticore`.
-    /// Because `manticore` does not mandate running the connection over MCTP,
-    /// the MCTP-specific error codes are omited.
+    /// Some of these codes (such as [`ErrorCode::InvalidChecksum`]) only make
+    /// sense when the connection is run over MCTP; `manticore` never produces
+    /// these itself, but they are provided so that integrations that do use
+    /// MCTP can report them.
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub enum ErrorCode: u8 {
         /// Represents a successful operation; this "error" code is used to
         /// turn an [`Error`] into an ACK.
         Ok = 0x00,
+        /// Indicates that the request was malformed or referred to something
+        /// that does not exist.
+        InvalidRequest = 0x01,
         /// Indicates that the device is "busy", usually meaning that other
         /// commands are being serviced.
         Busy = 0x03,
         /// Indicates an unspecified, vendor-defined error, which may include
         /// extra data in an [`Error`].
         Unspecified = 0x04,
+        /// Indicates that a packet had a bad checksum.
+        InvalidChecksum = 0xf0,
+        /// Indicates that a packet was received out of order.
+        OutOfOrderMessage = 0xf1,
+        /// Indicates that a request required authentication that was not
+        /// provided, or that failed.
+        Authentication = 0xf2,
+        /// Indicates that a packet's sequence number was outside of the
+        /// expected window.
+        OutOfSequenceWindow = 0xf3,
+        /// Indicates that a packet had an invalid length.
+        InvalidPacketLength = 0xf4,
+        /// Indicates that a message was too large to be buffered.
+        MessageOverflow = 0xf5,
+    }
+}
+
+wire_enum! {
+    /// A `manticore`-specific refinement of an [`ErrorCode`].
+    ///
+    /// This value is carried in the first byte of [`Error::data`], and allows
+    /// a host to tell apart, e.g., a request for a missing certificate slot
+    /// from an internal failure, both of which Cerberus would otherwise
+    /// report with the same code. The remaining bytes of `data` are
+    /// sub-code-specific; see each variant for details.
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub enum ErrorSubcode: u8 {
+        /// No further information is available.
+        None = 0x00,
+        /// An internal invariant was violated; this is probably a bug in the
+        /// device.
+        Internal = 0x01,
+        /// The requested operation is not supported.
+        ///
+        /// This sub-code is always reported with
+        /// [`ErrorCode::InvalidRequest`]. If the command itself is
+        /// unsupported, the second byte of `data` is the offending
+        /// [`CommandType`].
+        Unsupported = 0x02,
+        /// The device ran out of memory while processing the request.
+        OutOfMemory = 0x03,
+        /// A field in the request was outside of its valid range.
+        OutOfRange = 0x04,
+        /// The request could not be parsed.
+        Malformed = 0x05,
+        /// The request referred to a certificate slot that does not exist.
+        ///
+        /// The second byte of `data` is the offending slot number.
+        BadSlot = 0x06,
+        /// A cryptographic operation failed.
+        Crypto = 0x07,
+        /// A certificate could not be parsed or verified.
+        BadCert = 0x08,
+        /// A manifest could not be parsed or verified.
+        BadManifest = 0x09,
+        /// A signature failed to verify.
+        BadSignature = 0x0a,
+        /// The request is not permitted over the session it arrived over.
+        ///
+        /// The second byte of `data` contains the
+        /// [`Session`](crate::net::Session) flags that were missing.
+        Unauthorized = 0x0b,
     }
 }
 
@@ -329,6 +438,45 @@ impl Error {
             data: [0; 4],
         }
     }
+
+    /// Creates a new `Error` with the given code and sub-code.
+    pub fn new(code: ErrorCode, subcode: ErrorSubcode) -> Self {
+        Self::with_arg(code, subcode, 0)
+    }
+
+    /// Creates a new `Error` with the given code and sub-code, and an
+    /// additional sub-code-specific argument byte.
+    pub fn with_arg(code: ErrorCode, subcode: ErrorSubcode, arg: u8) -> Self {
+        Self {
+            code,
+            data: [subcode as u8, arg, 0, 0],
+        }
+    }
+
+    /// Returns the [`ErrorSubcode`] this error carries, if it is one that
+    /// `manticore` understands.
+    pub fn subcode(&self) -> Option<ErrorSubcode> {
+        use crate::protocol::wire::WireEnum as _;
+        ErrorSubcode::from_wire_value(self.data[0])
+    }
+}
+
+impl From<OutOfMemory> for Error {
+    fn from(_: OutOfMemory) -> Self {
+        Self::new(ErrorCode::Unspecified, ErrorSubcode::OutOfMemory)
+    }
+}
+
+impl From<sha256::Error> for Error {
+    fn from(_: sha256::Error) -> Self {
+        Self::new(ErrorCode::Unspecified, ErrorSubcode::Crypto)
+    }
+}
+
+impl From<sig::Error> for Error {
+    fn from(_: sig::Error) -> Self {
+        Self::new(ErrorCode::Unspecified, ErrorSubcode::Crypto)
+    }
 }
 
 impl Response<'_> for Error {
@@ -355,3 +503,38 @@ impl ToWire for Error {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::Cursor;
+    use crate::mem::BumpArena;
+
+    #[test]
+    fn error_round_trip() {
+        let err = Error::with_arg(
+            ErrorCode::InvalidRequest,
+            ErrorSubcode::BadSlot,
+            3,
+        );
+
+        let mut buf = [0; 5];
+        let mut cursor = Cursor::new(&mut buf);
+        err.to_wire(&mut cursor).unwrap();
+        assert_eq!(cursor.consumed_bytes(), &[0x01, 0x06, 0x03, 0x00, 0x00]);
+
+        let arena = BumpArena::new([0; 8]);
+        let err2 = Error::from_wire(&mut &buf[..], &arena).unwrap();
+        assert_eq!(err, err2);
+        assert_eq!(err2.subcode(), Some(ErrorSubcode::BadSlot));
+    }
+
+    #[test]
+    fn unknown_subcode() {
+        let err = Error {
+            code: ErrorCode::Unspecified,
+            data: [0xff; 4],
+        };
+        assert_eq!(err.subcode(), None);
+    }
+}
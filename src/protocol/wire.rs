@@ -17,6 +17,7 @@ use crate::io::ReadZero;
 use crate::io::Write;
 use crate::mem::Arena;
 use crate::mem::OutOfMemory;
+use crate::protocol;
 
 /// A type which can be deserialized from the Cerberus wire format.
 ///
@@ -57,6 +58,24 @@ impl From<OutOfMemory> for Error {
     }
 }
 
+impl From<Error> for protocol::Error {
+    fn from(e: Error) -> Self {
+        use protocol::ErrorCode;
+        use protocol::ErrorSubcode;
+        match e {
+            Error::Io(_) => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::Malformed)
+            }
+            Error::OutOfMemory => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::OutOfMemory)
+            }
+            Error::OutOfRange => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::OutOfRange)
+            }
+        }
+    }
+}
+
 /// A type which can be serialized into the Cerberus wire format.
 pub trait ToWire: Sized {
     /// Serializes `self` into `w`.
@@ -21,12 +21,16 @@
 //!     Ok(response)
 //!   })
 //!   // ...
-//!   .run(server, req, resp)
+//!   .run_with_middleware(server, req, resp, &policy, &mut middleware)
 //! ```
-//! This defines a request handler; nothing happens until `run()` is called.
-//! When called, `run()` performs the following steps:
+//! This defines a request handler; nothing happens until
+//! `run_with_middleware()` is called. When called, it performs the following
+//! steps:
 //! - It parses a `protocol::Header` out of `req`, asserting that the header
 //!   has the request bit set.
+//! - It calls `middleware`'s `before()` hook, and then checks that the
+//!   session the request arrived over satisfies `policy` for the header's
+//!   command type, replying with an error if either rejects the request.
 //! - It selects a `.handle<MyCommand, _>()` call, such that
 //!   `MyCommand::Req::TYPE` matches the header's command type (if multiple
 //!   handlers could match, an unspecified one is chosen).
@@ -36,6 +40,7 @@
 //!   `Result<MyCommand::Resp, protocol::Error>`.
 //! - The resulting response or error is sent using `resp`.
 //! - If no handler is chosen, an error is returned.
+//! - It calls `middleware`'s `after()` hook with the outcome.
 //!
 //! This module is not part of `manticore`'s API.
 //!
@@ -53,7 +58,7 @@
 //! type. The resulting type of this expression is something like
 //! `Cons<Cons<Cons<Handler<Server>, A, ?0>, B, ?1> C, ?2>`,
 //! where the `?n` types denote anonymous closure types. When we go ahead and
-//! call `.run()`, this triggers parsing of the header, which is fed to the
+//! call `.run_with_middleware()`, this triggers parsing of the header, which is fed to the
 //! outermost `Cons`. If the command type doesn't match `C::REQ_TYPE`, it
 //! recurses into the inner `Cons`'s `run_with_handler()`.
 //!
@@ -79,12 +84,15 @@ use crate::protocol::CommandType;
 use crate::protocol::Header;
 use crate::protocol::Request as _;
 use crate::protocol::Response as _;
+use crate::server::policy::Policy;
 
 /// A `*`-importable prelude that pulls in only the names that are necessary
 /// to make `Handler` work.
 pub mod prelude {
     pub use super::Handler;
     pub use super::HandlerMethods;
+    pub use super::Middleware;
+    pub use super::Outcome;
 }
 
 /// An error returned by a request handler.
@@ -120,6 +128,126 @@ impl From<net::Error> for Error {
     }
 }
 
+impl From<Error> for protocol::Error {
+    fn from(e: Error) -> Self {
+        use protocol::ErrorCode;
+        use protocol::ErrorSubcode;
+        match e {
+            Error::Network(_) => {
+                Self::new(ErrorCode::Unspecified, ErrorSubcode::Internal)
+            }
+            Error::Wire(e) => e.into(),
+            Error::ReqTooLong(_) => {
+                Self::new(ErrorCode::InvalidRequest, ErrorSubcode::Malformed)
+            }
+            Error::UnhandledCommand(c) => Self::with_arg(
+                ErrorCode::InvalidRequest,
+                ErrorSubcode::Unsupported,
+                c as u8,
+            ),
+        }
+    }
+}
+
+/// Replies to `request` with an error message carrying `err`.
+fn reply_with_error(
+    request: &mut dyn net::HostRequest,
+    err: protocol::Error,
+) -> Result<(), Error> {
+    let header = Header {
+        is_request: false,
+        command: CommandType::Error,
+    };
+
+    let reply = request.reply(header)?;
+    err.to_wire(reply.sink()?)?;
+    reply.finish()?;
+    Ok(())
+}
+
+/// Reports `err` to the host before it propagates, since the host would
+/// otherwise never learn that its request was rejected.
+///
+/// This must only be called before any reply has been started.
+fn reject(request: &mut dyn net::HostRequest, err: Error) -> Error {
+    // This is best-effort: the original error is more interesting to the
+    // caller than a failure to report it.
+    let _ = reply_with_error(request, err.into());
+    err
+}
+
+/// The result of processing a single request, as observed by a
+/// [`Middleware`].
+#[derive(Copy, Clone, Debug)]
+pub enum Outcome {
+    /// The request was handled, and a response was sent.
+    Ok,
+    /// The request was rejected, either by its handler or by a
+    /// [`Middleware`], and the given error was sent to the host.
+    Rejected(protocol::Error),
+    /// The request could not be processed.
+    ///
+    /// If possible, an error was still sent to the host.
+    Failed(Error),
+}
+
+/// Cross-cutting logic that runs around every request processed by a
+/// handler, such as logging, metrics, or access policy.
+///
+/// See [`PaRot::process_request_with()`].
+///
+/// [`PaRot::process_request_with()`]:
+///   crate::server::pa_rot::PaRot::process_request_with
+pub trait Middleware {
+    /// Called once a request's header has been received, before it is
+    /// checked against the access policy and dispatched to a handler.
+    ///
+    /// Returning an error rejects the request: the error is sent to the host
+    /// in lieu of a response, and no handler is run.
+    fn before(&mut self, header: Header) -> Result<(), protocol::Error> {
+        let _ = header;
+        Ok(())
+    }
+
+    /// Called once a request has been processed, with the header of the
+    /// request and the result of processing it.
+    ///
+    /// This is called exactly once for every call to `before()`, even if
+    /// the request was rejected.
+    fn after(&mut self, header: Header, outcome: Outcome) {
+        let _ = (header, outcome);
+    }
+}
+
+/// A no-op [`Middleware`].
+impl Middleware for () {}
+
+impl<M: Middleware + ?Sized> Middleware for &mut M {
+    fn before(&mut self, header: Header) -> Result<(), protocol::Error> {
+        M::before(self, header)
+    }
+
+    fn after(&mut self, header: Header, outcome: Outcome) {
+        M::after(self, header, outcome)
+    }
+}
+
+/// Runs two [`Middleware`]s in sequence.
+///
+/// `before()` hooks run first-to-last, stopping at the first rejection, while
+/// `after()` hooks run last-to-first.
+impl<A: Middleware, B: Middleware> Middleware for (A, B) {
+    fn before(&mut self, header: Header) -> Result<(), protocol::Error> {
+        self.0.before(header)?;
+        self.1.before(header)
+    }
+
+    fn after(&mut self, header: Header, outcome: Outcome) {
+        self.1.after(header, outcome);
+        self.0.after(header, outcome);
+    }
+}
+
 /// A request handler builder.
 ///
 /// See the module documentation for more information.
@@ -141,7 +269,7 @@ impl<Server, Arena> Handler<Server, Arena> {
 /// A handler for a specific command type.
 ///
 /// The name "handler cons" comes from the fact that the type that
-/// `run()` is eventually called on looks like a linked list of
+/// `run_with_middleware()` is eventually called on looks like a linked list of
 /// `HandlerMethods` implementations.
 ///
 /// If `REQ_BUFFER` is true, this represents the output of
@@ -261,24 +389,49 @@ pub trait HandlerMethods<'req, 'srv, Server: 'srv, Arena: 'req>:
         header: Header,
         request: &mut dyn net::HostRequest<'req>,
         arena: &'req Arena,
-    ) -> Result<(), Error>;
+    ) -> Result<Result<(), protocol::Error>, Error>;
 
-    /// Executes a `Handler` with the given context.
+    /// Executes a `Handler` with the given context, calling into
+    /// `middleware` before and after the request is dispatched.
     ///
-    /// See the module-level documentation for more information.
+    /// Requests that do not satisfy `policy` are rejected with an
+    /// authentication error without being dispatched. `middleware` observes
+    /// every request, including ones that `policy` rejects; messages that are
+    /// not requests are dropped without calling it.
+    ///
+    /// See the module-level documentation and [`Middleware`] for more
+    /// information.
     #[inline]
-    fn run(
+    fn run_with_middleware(
         self,
         server: Server,
         host_port: &mut dyn net::HostPort<'req>,
         arena: &'req Arena,
+        policy: &Policy,
+        middleware: &mut dyn Middleware,
     ) -> Result<(), Error> {
         let request = host_port.receive()?;
         let header = request.header()?;
         if !header.is_request {
             return Err(wire::Error::OutOfRange.into());
         }
-        self.run_with_header(server, header, request, arena)
+
+        let admitted = middleware
+            .before(header)
+            .and_then(|_| policy.check(header.command, request.session()));
+        let result = match admitted {
+            Ok(()) => self.run_with_header(server, header, request, arena),
+            Err(err) => reply_with_error(request, err).map(|_| Err(err)),
+        };
+
+        let outcome = match result {
+            Ok(Ok(())) => Outcome::Ok,
+            Ok(Err(err)) => Outcome::Rejected(err),
+            Err(err) => Outcome::Failed(err),
+        };
+        middleware.after(header, outcome);
+
+        result.map(|_| ())
     }
 }
 
@@ -291,7 +444,7 @@ where
         self,
         request: &mut dyn net::HostRequest,
         ctx: Ctx,
-    ) -> Result<(), Error>
+    ) -> Result<Result<(), protocol::Error>, Error>
     where
         F: FnOnce(Ctx) -> Result<RespOf<'out, Command>, protocol::Error>,
     {
@@ -305,19 +458,9 @@ where
                 let reply = request.reply(header)?;
                 msg.to_wire(reply.sink()?)?;
                 reply.finish()?;
-                Ok(())
-            }
-            Err(err) => {
-                let header = Header {
-                    is_request: false,
-                    command: CommandType::Error,
-                };
-
-                let reply = request.reply(header)?;
-                err.to_wire(reply.sink()?)?;
-                reply.finish()?;
-                Ok(())
+                Ok(Ok(()))
             }
+            Err(err) => reply_with_error(request, err).map(|_| Err(err)),
         }
     }
 }
@@ -342,14 +485,15 @@ where
         header: Header,
         request: &mut dyn net::HostRequest<'req>,
         arena: &'req Arena,
-    ) -> Result<(), Error> {
+    ) -> Result<Result<(), protocol::Error>, Error> {
         if header.command != ReqOf::<'req, Command>::TYPE {
             // Recurse into the next handler case. Note that this cannot be
             // `run`, since that would re-parse the header incorrectly.
             return self.prev.run_with_header(server, header, request, arena);
         }
 
-        let req = FromWire::from_wire(request.payload()?, arena)?;
+        let req = FromWire::from_wire(request.payload()?, arena)
+            .map_err(|e| reject(request, Error::Wire(e)))?;
 
         let ctx = Context {
             req_buf: (),
@@ -381,7 +525,7 @@ where
         header: Header,
         request: &mut dyn net::HostRequest<'req>,
         arena: &'req Arena,
-    ) -> Result<(), Error> {
+    ) -> Result<Result<(), protocol::Error>, Error> {
         if header.command != ReqOf::<'req, Command>::TYPE {
             // Recurse into the next handler case. Note that this cannot be
             // `run`, since that would re-parse the header incorrectly.
@@ -391,16 +535,20 @@ where
         // Buffer the entire request payload; from_wire below will zero-copy
         // read it.
         let r = request.payload()?;
-        let req_buf = arena
-            .alloc_slice::<u8>(r.remaining_data())
-            .map_err(wire::Error::from)?;
-        r.read_bytes(req_buf).map_err(wire::Error::from)?;
+        let req_buf = match arena.alloc_slice::<u8>(r.remaining_data()) {
+            Ok(buf) => buf,
+            Err(e) => return Err(reject(request, wire::Error::from(e).into())),
+        };
+        if let Err(e) = r.read_bytes(req_buf) {
+            return Err(reject(request, wire::Error::from(e).into()));
+        }
 
         // Note: `{ req_buf }` produces a copy of req_buf, so that the from_wire
         // argument becomes an rvalue. Thus, `from_wire` does not mutate the
         // original `req_buf` that gets passed to `run_inner()`.
         let req_buf: &'req [u8] = req_buf;
-        let req = FromWire::from_wire(&mut { req_buf }, arena)?;
+        let req = FromWire::from_wire(&mut { req_buf }, arena)
+            .map_err(|e| reject(request, Error::Wire(e)))?;
 
         let ctx = Context {
             req_buf,
@@ -420,10 +568,10 @@ impl<'req, 'srv, Server: 'srv, Arena: 'req>
         self,
         _: Server,
         header: Header,
-        _: &mut dyn net::HostRequest<'req>,
+        request: &mut dyn net::HostRequest<'req>,
         _: &'req Arena,
-    ) -> Result<(), Error> {
-        Err(Error::UnhandledCommand(header.command))
+    ) -> Result<Result<(), protocol::Error>, Error> {
+        Err(reject(request, Error::UnhandledCommand(header.command)))
     }
 }
 
@@ -470,7 +618,13 @@ mod test {
             request_bytes,
         );
 
-        server.0.run(server.1, port, arena)?;
+        server.0.run_with_middleware(
+            server.1,
+            port,
+            arena,
+            &Policy::OPEN,
+            &mut (),
+        )?;
 
         let (header, mut resp) = port.response().unwrap();
         assert!(!header.is_request);
@@ -505,6 +659,257 @@ mod test {
         ));
     }
 
+    #[test]
+    fn unhandled_reply() {
+        let handler = Handler::<(), _>::new();
+
+        let mut scratch = [0; 64];
+        let mut arena = [0; 64];
+        let arena = BumpArena::new(&mut arena);
+        let mut port = net::InMemHost::new(&mut scratch);
+        port.request(
+            Header {
+                is_request: true,
+                command: CommandType::DeviceId,
+            },
+            &[],
+        );
+
+        assert!(matches!(
+            handler.run_with_middleware(
+                (),
+                &mut port,
+                &arena,
+                &Policy::OPEN,
+                &mut ()
+            ),
+            Err(Error::UnhandledCommand(CommandType::DeviceId))
+        ));
+
+        let (header, mut resp) = port.response().unwrap();
+        assert!(!header.is_request);
+        assert_eq!(header.command, CommandType::Error);
+
+        let err = protocol::Error::from_wire(&mut resp, &arena)
+            .expect("failed to read response");
+        assert_eq!(err.code, protocol::ErrorCode::InvalidRequest);
+        assert_eq!(err.subcode(), Some(protocol::ErrorSubcode::Unsupported));
+        assert_eq!(err.data[1], CommandType::DeviceId as u8);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        before: Vec<CommandType>,
+        after: Vec<(CommandType, Outcome)>,
+    }
+
+    impl Middleware for Recorder {
+        fn before(&mut self, header: Header) -> Result<(), protocol::Error> {
+            self.before.push(header.command);
+            if header.command == CommandType::DeviceId {
+                return Err(protocol::Error::new(
+                    protocol::ErrorCode::Authentication,
+                    protocol::ErrorSubcode::None,
+                ));
+            }
+            Ok(())
+        }
+
+        fn after(&mut self, header: Header, outcome: Outcome) {
+            self.after.push((header.command, outcome));
+        }
+    }
+
+    #[test]
+    fn middleware() {
+        let mut recorder = Recorder::default();
+        let mut scratch = [0; 64];
+        let mut arena = [0; 64];
+        let arena = BumpArena::new(&mut arena);
+
+        let mut port = net::InMemHost::new(&mut scratch);
+        port.request(
+            Header {
+                is_request: true,
+                command: CommandType::FirmwareVersion,
+            },
+            &[0],
+        );
+        Handler::<(), _>::new()
+            .handle::<protocol::FirmwareVersion, _>(|_| {
+                Ok(protocol::firmware_version::FirmwareVersionResponse {
+                    version: VERSION1,
+                })
+            })
+            .handle::<protocol::DeviceId, _>(|_| {
+                panic!("called a rejected handler")
+            })
+            .run_with_middleware(
+                (),
+                &mut port,
+                &arena,
+                &Policy::OPEN,
+                &mut recorder,
+            )
+            .expect("run failed");
+        let (header, _) = port.response().unwrap();
+        assert_eq!(header.command, CommandType::FirmwareVersion);
+
+        port.request(
+            Header {
+                is_request: true,
+                command: CommandType::DeviceId,
+            },
+            &[],
+        );
+        Handler::<(), _>::new()
+            .handle::<protocol::FirmwareVersion, _>(|_| {
+                panic!("called the wrong handler")
+            })
+            .handle::<protocol::DeviceId, _>(|_| {
+                panic!("called a rejected handler")
+            })
+            .run_with_middleware(
+                (),
+                &mut port,
+                &arena,
+                &Policy::OPEN,
+                &mut recorder,
+            )
+            .expect("run failed");
+        let (header, mut resp) = port.response().unwrap();
+        assert_eq!(header.command, CommandType::Error);
+        let err = protocol::Error::from_wire(&mut resp, &arena)
+            .expect("failed to read response");
+        assert_eq!(err.code, protocol::ErrorCode::Authentication);
+
+        assert_eq!(
+            recorder.before,
+            &[CommandType::FirmwareVersion, CommandType::DeviceId]
+        );
+        assert!(matches!(
+            recorder.after.as_slice(),
+            &[
+                (CommandType::FirmwareVersion, Outcome::Ok),
+                (
+                    CommandType::DeviceId,
+                    Outcome::Rejected(protocol::Error {
+                        code: protocol::ErrorCode::Authentication,
+                        ..
+                    })
+                ),
+            ]
+        ));
+    }
+
+    #[test]
+    fn middleware_sees_policy_rejections() {
+        const POLICY: Policy = Policy::new(&[(
+            CommandType::FirmwareVersion,
+            net::Session::ENCRYPTED,
+        )]);
+
+        let mut recorder = Recorder::default();
+        let mut scratch = [0; 64];
+        let mut arena = [0; 64];
+        let arena = BumpArena::new(&mut arena);
+        let mut port = net::InMemHost::new(&mut scratch);
+
+        port.request(
+            Header {
+                is_request: true,
+                command: CommandType::FirmwareVersion,
+            },
+            &[0],
+        );
+        Handler::<(), _>::new()
+            .handle::<protocol::FirmwareVersion, _>(|_| {
+                panic!("called a rejected handler")
+            })
+            .run_with_middleware((), &mut port, &arena, &POLICY, &mut recorder)
+            .expect("run failed");
+        let (header, _) = port.response().unwrap();
+        assert_eq!(header.command, CommandType::Error);
+
+        // Messages that are not requests never reach the middleware.
+        port.request(
+            Header {
+                is_request: false,
+                command: CommandType::FirmwareVersion,
+            },
+            &[0],
+        );
+        assert!(Handler::<(), _>::new()
+            .run_with_middleware((), &mut port, &arena, &POLICY, &mut recorder)
+            .is_err());
+
+        assert_eq!(recorder.before, &[CommandType::FirmwareVersion]);
+        assert_eq!(recorder.after.len(), recorder.before.len());
+        assert!(matches!(
+            recorder.after.as_slice(),
+            &[(
+                CommandType::FirmwareVersion,
+                Outcome::Rejected(protocol::Error {
+                    code: protocol::ErrorCode::Authentication,
+                    ..
+                })
+            )]
+        ));
+    }
+
+    #[test]
+    fn policy() {
+        const POLICY: Policy = Policy::new(&[(
+            CommandType::FirmwareVersion,
+            net::Session::ENCRYPTED,
+        )]);
+
+        let mut scratch = [0; 64];
+        let mut arena = [0; 64];
+        let arena = BumpArena::new(&mut arena);
+        let mut port = net::InMemHost::new(&mut scratch);
+
+        port.request(
+            Header {
+                is_request: true,
+                command: CommandType::FirmwareVersion,
+            },
+            &[0],
+        );
+        Handler::<(), _>::new()
+            .handle::<protocol::FirmwareVersion, _>(|_| {
+                panic!("called a rejected handler")
+            })
+            .run_with_middleware((), &mut port, &arena, &POLICY, &mut ())
+            .expect("run failed");
+        let (header, mut resp) = port.response().unwrap();
+        assert_eq!(header.command, CommandType::Error);
+        let err = protocol::Error::from_wire(&mut resp, &arena)
+            .expect("failed to read response");
+        assert_eq!(err.code, protocol::ErrorCode::Authentication);
+        assert_eq!(err.subcode(), Some(protocol::ErrorSubcode::Unauthorized));
+        assert_eq!(err.data[1], net::Session::ENCRYPTED.bits());
+
+        port.set_session(net::Session::ESTABLISHED | net::Session::ENCRYPTED);
+        port.request(
+            Header {
+                is_request: true,
+                command: CommandType::FirmwareVersion,
+            },
+            &[0],
+        );
+        Handler::<(), _>::new()
+            .handle::<protocol::FirmwareVersion, _>(|_| {
+                Ok(protocol::firmware_version::FirmwareVersionResponse {
+                    version: VERSION1,
+                })
+            })
+            .run_with_middleware((), &mut port, &arena, &POLICY, &mut ())
+            .expect("run failed");
+        let (header, _) = port.response().unwrap();
+        assert_eq!(header.command, CommandType::FirmwareVersion);
+    }
+
     #[test]
     fn single_handler() {
         let mut handler_called = false;
@@ -11,5 +11,10 @@
 
 mod handler;
 pub use handler::Error;
+pub use handler::Middleware;
+pub use handler::Outcome;
 
 pub mod pa_rot;
+pub mod pending;
+pub mod policy;
+pub mod provisioning;
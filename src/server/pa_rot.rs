@@ -7,22 +7,36 @@
 //! This module provides structures for serving responses to a host making
 //! requests to a PA-RoT.
 
+use core::time::Duration;
+
 use crate::cert;
+use crate::crypto::aead;
 use crate::crypto::sha256;
 use crate::crypto::sig;
 use crate::hardware;
+use crate::io::Cursor;
 use crate::mem::Arena;
 use crate::mem::ArenaExt as _;
 use crate::net;
 use crate::protocol;
 use crate::protocol::capabilities;
 use crate::protocol::device_id;
+use crate::protocol::wire::FromWire;
+use crate::protocol::wire::ToWire;
+use crate::protocol::CommandType;
+use crate::protocol::ErrorCode;
+use crate::protocol::ErrorSubcode;
+use crate::protocol::Header;
+use crate::server::pending::Pending;
+use crate::server::pending::Poll;
+use crate::server::policy::Policy;
+use crate::server::provisioning::Provisioning;
 use crate::server::Error;
 
 use crate::server::handler::prelude::*;
 
 /// Options struct for initializing a [`PaRot`].
-pub struct Options<'a, Identity, Reset, Sha, Ciphers, TrustChain> {
+pub struct Options<'a, Identity, Reset, Sha, Ciphers, Aead, TrustChain> {
     /// A handle to the "hardware identity" of the device.
     pub identity: &'a Identity,
     /// A handle for looking up reset-related information for the current
@@ -33,6 +47,12 @@ pub struct Options<'a, Identity, Reset, Sha, Ciphers, TrustChain> {
     pub sha: &'a Sha,
     /// A handle to a signature verification engine,
     pub ciphers: &'a mut Ciphers,
+    /// A handle to an AEAD engine, for encrypted sessions, if the device
+    /// supports them.
+    ///
+    /// If present, the AES key strengths it supports are advertised during
+    /// capabilities negotiation; otherwise, AES is reported as unsupported.
+    pub aead: Option<&'a mut Aead>,
     /// The trust chain to use for the challenge.
     pub trust_chain: &'a mut TrustChain,
 
@@ -49,6 +69,39 @@ pub struct Options<'a, Identity, Reset, Sha, Ciphers, TrustChain> {
     pub networking: capabilities::Networking,
     /// Integration-provided "acceptable timeout" lengths.
     pub timeouts: capabilities::Timeouts,
+
+    /// Scratch space for deferring slow requests.
+    ///
+    /// If provided, slow requests are not processed immediately; instead,
+    /// the host is told that the device is busy, and the request is
+    /// processed by a later call to [`PaRot::poll()`]. The result is
+    /// delivered when the host retries the request, so long as it does so
+    /// within `timeouts.crypto`.
+    ///
+    /// The requests deferred are `Challenge` and `ExportCsr`, which sign;
+    /// `GetDigests`, when some certificate is not already hashed; and the
+    /// `ImportSignedCert` that completes a chain, which verifies it and
+    /// writes it to flash.
+    ///
+    /// This buffer must be large enough to hold a request, its response, and,
+    /// for `Challenge`, the to-be-signed portion of the response. If `None`,
+    /// all requests are processed synchronously.
+    pub pending: Option<&'a mut [u8]>,
+
+    /// The authorization policy to enforce on incoming requests.
+    ///
+    /// Integrations should start from [`Policy::RECOMMENDED`];
+    /// [`Policy::OPEN`] is only suitable for testing. See [`Policy`].
+    pub policy: Policy<'a>,
+
+    /// State for in-field certificate provisioning.
+    ///
+    /// If `None`, `ImportSignedCert` requests are rejected. Since these
+    /// requests overwrite persistent state, [`Options::policy`] should
+    /// require a secure session for them, as [`Policy::RECOMMENDED`] does.
+    ///
+    /// See [`Provisioning`].
+    pub provisioning: Option<Provisioning<'a>>,
 }
 
 /// A PA-RoT, or "Platform Root of Trust", server.
@@ -56,29 +109,57 @@ pub struct Options<'a, Identity, Reset, Sha, Ciphers, TrustChain> {
 /// This type implements the request -> response "business logic" of the
 /// host <-> PA-RoT interaction. That is, it accepts input and output buffers,
 /// and from those, parses incoming requests and processes them into responses.
-pub struct PaRot<'a, Identity, Reset, Sha, Ciphers, TrustChain> {
-    opts: Options<'a, Identity, Reset, Sha, Ciphers, TrustChain>,
+pub struct PaRot<'a, Identity, Reset, Sha, Ciphers, Aead, TrustChain> {
+    opts: Options<'a, Identity, Reset, Sha, Ciphers, Aead, TrustChain>,
+    pending: Option<Pending<'a>>,
+    provisioning: Option<Provisioning<'a>>,
+    counters: Counters,
+}
+
+/// Request counters, as reported by `RequestCounter`.
+#[derive(Copy, Clone, Default)]
+struct Counters {
     ok_count: u16,
     err_count: u16,
 }
 
-impl<'a, Identity, Reset, Sha, Ciphers, TrustChain>
-    PaRot<'a, Identity, Reset, Sha, Ciphers, TrustChain>
+impl Middleware for Counters {
+    fn after(&mut self, _: Header, outcome: Outcome) {
+        match outcome {
+            Outcome::Failed(_) => {
+                self.err_count = self.err_count.wrapping_add(1)
+            }
+            // A deferred request that is not done yet has not succeeded; the
+            // host only learns that it should try again later.
+            Outcome::Rejected(e) if e.code == ErrorCode::Busy => {
+                self.err_count = self.err_count.wrapping_add(1)
+            }
+            _ => self.ok_count = self.ok_count.wrapping_add(1),
+        }
+    }
+}
+
+impl<'a, Identity, Reset, Sha, Ciphers, Aead, TrustChain>
+    PaRot<'a, Identity, Reset, Sha, Ciphers, Aead, TrustChain>
 where
     Identity: hardware::Identity,
     Reset: hardware::Reset,
     Sha: sha256::Builder,
     Ciphers: sig::Ciphers,
+    Aead: aead::Ciphers,
     TrustChain: cert::TrustChain,
 {
     /// Create a new `PaRot` with the given `Options`.
     pub fn new(
-        opts: Options<'a, Identity, Reset, Sha, Ciphers, TrustChain>,
+        mut opts: Options<'a, Identity, Reset, Sha, Ciphers, Aead, TrustChain>,
     ) -> Self {
+        let pending = opts.pending.take().map(Pending::new);
+        let provisioning = opts.provisioning.take();
         Self {
             opts,
-            ok_count: 0,
-            err_count: 0,
+            pending,
+            provisioning,
+            counters: Counters::default(),
         }
     }
 
@@ -92,6 +173,25 @@ where
         host_port: &mut dyn net::HostPort<'req>,
         arena: &'req A,
     ) -> Result<(), Error> {
+        self.process_request_with(host_port, arena, &mut ())
+    }
+
+    /// Process a single incoming request, calling into `middleware` before
+    /// and after it is handled.
+    ///
+    /// See [`process_request()`](Self::process_request) and
+    /// [`Middleware`].
+    pub fn process_request_with<'req, A: Arena>(
+        &mut self,
+        host_port: &mut dyn net::HostPort<'req>,
+        arena: &'req A,
+        middleware: &mut dyn Middleware,
+    ) -> Result<(), Error> {
+        // The counters are copied out of `self`, since `self` is also the
+        // server context for the handlers; `RequestCounter` sees the counts
+        // as of the start of this request.
+        let mut counters = self.counters;
+        let policy = self.opts.policy;
         let result = Handler::<&mut Self, A>::new()
             .handle::<protocol::FirmwareVersion, _>(|ctx| {
                 use protocol::firmware_version::FirmwareVersionResponse;
@@ -106,7 +206,12 @@ where
                     .opts
                     .identity
                     .vendor_firmware_version(ctx.req.index)
-                    .ok_or(UNSPECIFIED)?;
+                    .ok_or_else(|| {
+                        protocol::Error::new(
+                            ErrorCode::InvalidRequest,
+                            ErrorSubcode::OutOfRange,
+                        )
+                    })?;
                 Ok(FirmwareVersionResponse { version })
             })
             .handle::<protocol::DeviceCapabilities, _>(|ctx| {
@@ -116,6 +221,9 @@ where
                 ctx.server.opts.ciphers.negotiate(&mut crypto);
                 crypto.has_aes = false;
                 crypto.aes_strength = AesKeyStrength::empty();
+                if let Some(aead) = &ctx.server.opts.aead {
+                    aead.negotiate(&mut crypto);
+                }
 
                 let capabilities = Capabilities {
                     networking: ctx.server.opts.networking,
@@ -143,83 +251,196 @@ where
                     info: ctx.server.opts.identity.unique_device_identity(),
                 })
             })
-            .handle::<protocol::GetDigests, _>(|ctx| {
-                let digests_len = ctx
-                    .server
-                    .opts
-                    .trust_chain
-                    .chain_len(ctx.req.slot)
-                    .ok_or(UNSPECIFIED)?
-                    .get();
-                let digests = ctx
-                    .arena
-                    .alloc_slice::<sha256::Digest>(digests_len)
-                    .map_err(|_| UNSPECIFIED)?;
-                for (i, digest) in digests.iter_mut().enumerate() {
-                    let cert = ctx
-                        .server
-                        .opts
-                        .trust_chain
-                        .cert(ctx.req.slot, i)
-                        .ok_or(UNSPECIFIED)?;
-                    ctx.server
-                        .opts
-                        .sha
-                        .hash_contiguous(cert.raw(), digest)
-                        .map_err(|_| UNSPECIFIED)?;
+            .handle_buffered::<protocol::GetDigests, _>(|ctx| {
+                use protocol::get_digests::*;
+                let server = ctx.server;
+                let slot = ctx.req.slot;
+                let chain = &*server.opts.trust_chain;
+                let chain_len =
+                    chain.chain_len(slot).ok_or_else(|| bad_slot(slot))?;
+                // Only hashing certificates is slow; if every digest is
+                // cached, there is nothing to defer.
+                let is_cached = (0..chain_len.get())
+                    .all(|i| chain.cert_digest(slot, i).is_some());
+
+                let now = server.opts.reset.uptime();
+                let timeout = server.opts.timeouts.crypto;
+                match &mut server.pending {
+                    Some(pending) if !is_cached => defer(
+                        pending,
+                        CommandType::GetDigests,
+                        ctx.req_buf,
+                        now,
+                        timeout,
+                        ctx.arena,
+                        |_| Ok(0),
+                    ),
+                    _ => {
+                        let digests = chain_digests(
+                            &*server.opts.trust_chain,
+                            server.opts.sha,
+                            slot,
+                            ctx.arena,
+                        )?;
+                        Ok(GetDigestsResponse { digests })
+                    }
                 }
-                Ok(protocol::get_digests::GetDigestsResponse { digests })
             })
             .handle::<protocol::GetCert, _>(|ctx| {
-                let cert = ctx
+                let data = ctx
                     .server
                     .opts
                     .trust_chain
-                    .cert(ctx.req.slot, ctx.req.cert_number as usize)
-                    .ok_or(UNSPECIFIED)?;
-
-                let start = cert.raw().len().min(ctx.req.offset as usize);
-                let end = cert
-                    .raw()
-                    .len()
-                    .min((ctx.req.len as usize).saturating_add(start));
+                    .read_cert(
+                        ctx.req.slot,
+                        ctx.req.cert_number as usize,
+                        ctx.req.offset as usize,
+                        ctx.req.len as usize,
+                        ctx.arena,
+                    )?
+                    .ok_or_else(|| {
+                        if ctx.req.cert_number == 0 {
+                            bad_slot(ctx.req.slot)
+                        } else {
+                            protocol::Error::new(
+                                ErrorCode::InvalidRequest,
+                                ErrorSubcode::OutOfRange,
+                            )
+                        }
+                    })?;
+
                 Ok(protocol::get_cert::GetCertResponse {
                     slot: ctx.req.slot,
                     cert_number: ctx.req.cert_number,
-                    data: &cert.raw()[start..end],
+                    data,
                 })
             })
             .handle_buffered::<protocol::Challenge, _>(|ctx| {
                 use protocol::challenge::*;
-                let signer = ctx
-                    .server
+                let server = ctx.server;
+                let slot = ctx.req.slot;
+                let slot_mask = server.opts.trust_chain.slot_mask();
+                let signer = server
                     .opts
                     .trust_chain
-                    .signer(ctx.req.slot)
-                    .ok_or(UNSPECIFIED)?;
-                let tbs = ChallengeResponseTbs {
-                    slot: ctx.req.slot,
-                    slot_mask: 0, // Currently unspecified?
-                    protocol_range: (0, 0),
-                    // TODO: Wire through a CSRNG to generate the nonce.
-                    nonce: &[0xaa; 32],
-                    pmr0_components: 0,
-                    pmr0: ctx.server.opts.pmr0,
+                    .signer(slot)
+                    .ok_or_else(|| bad_slot(slot))?;
+                let tbs = challenge_tbs(slot, slot_mask, server.opts.pmr0);
+
+                let pending = match &mut server.pending {
+                    Some(pending) => pending,
+                    None => {
+                        let signature =
+                            ctx.arena.alloc_slice::<u8>(signer.sig_bytes())?;
+                        let sig_len = sign_challenge(
+                            signer,
+                            ctx.req_buf,
+                            &tbs,
+                            signature,
+                        )?;
+                        let signature = &signature[..sig_len];
+                        return Ok(ChallengeResponse { tbs, signature });
+                    }
                 };
 
-                let req_buf = ctx.req_buf;
-                let signature = ctx
-                    .arena
-                    .alloc_slice::<u8>(signer.sig_bytes())
-                    .map_err(|_| UNSPECIFIED)?;
-                let sig_len = tbs
-                    .as_iovec_with(|[a, b, c, d]| {
-                        signer.sign(&[req_buf, a, b, c, d], signature)
-                    })
-                    .map_err(|_| UNSPECIFIED)?;
-                let signature = &signature[..sig_len];
+                // The response describes the device as of when the challenge
+                // arrived, so the TBS is captured now, rather than rebuilt
+                // once the signature is ready.
+                let now = server.opts.reset.uptime();
+                let timeout = server.opts.timeouts.crypto;
+                defer(
+                    pending,
+                    CommandType::Challenge,
+                    ctx.req_buf,
+                    now,
+                    timeout,
+                    ctx.arena,
+                    |buf| encode(&tbs, buf),
+                )
+            })
+            .handle_buffered::<protocol::ExportCsr, _>(|ctx| {
+                use protocol::export_csr::*;
+                let server = ctx.server;
+                let pending = match &mut server.pending {
+                    Some(pending) => pending,
+                    None => {
+                        let csr = export_csr(
+                            &mut *server.opts.trust_chain,
+                            ctx.req.slot,
+                            ctx.arena,
+                        )?;
+                        return Ok(ExportCsrResponse { csr });
+                    }
+                };
 
-                Ok(ChallengeResponse { tbs, signature })
+                let now = server.opts.reset.uptime();
+                let timeout = server.opts.timeouts.crypto;
+                defer(
+                    pending,
+                    CommandType::ExportCsr,
+                    ctx.req_buf,
+                    now,
+                    timeout,
+                    ctx.arena,
+                    |_| Ok(0),
+                )
+            })
+            .handle_buffered::<protocol::ImportSignedCert, _>(|ctx| {
+                use protocol::import_signed_cert::CertType;
+                let server = ctx.server;
+                let provisioning =
+                    provisioning_for(&mut server.provisioning, ctx.req.slot)?;
+
+                // Only the final certificate of a chain is slow to import,
+                // since that is when the chain is verified and written to
+                // flash.
+                let pending = match &mut server.pending {
+                    Some(pending)
+                        if ctx.req.cert_type == CertType::DeviceId =>
+                    {
+                        pending
+                    }
+                    _ => {
+                        provisioning.import(
+                            ctx.req.cert_type,
+                            ctx.req.cert,
+                            &*server.opts.trust_chain,
+                            &mut *server.opts.ciphers,
+                            server.opts.sha,
+                        )?;
+                        return Ok(protocol::Error::new_ack());
+                    }
+                };
+
+                let now = server.opts.reset.uptime();
+                let timeout = server.opts.timeouts.crypto;
+                defer(
+                    pending,
+                    CommandType::ImportSignedCert,
+                    ctx.req_buf,
+                    now,
+                    timeout,
+                    ctx.arena,
+                    |_| Ok(0),
+                )
+            })
+            .handle::<protocol::GetCertificateState, _>(|ctx| {
+                use protocol::get_cert_state::*;
+                let slot = ctx.req.slot;
+                let state = ctx
+                    .server
+                    .provisioning
+                    .as_ref()
+                    .filter(|p| p.slot() == slot)
+                    .and_then(Provisioning::state);
+                let state = match state {
+                    Some(state) => state,
+                    None => match ctx.server.opts.trust_chain.chain_len(slot) {
+                        Some(_) => CertState::Provisioned,
+                        None => CertState::NotProvisioned,
+                    },
+                };
+                Ok(GetCertificateStateResponse { state })
             })
             .handle::<protocol::ResetCounter, _>(|ctx| {
                 use protocol::reset_counter::*;
@@ -228,7 +449,10 @@ where
                 if ctx.req.reset_type != ResetType::Local
                     || ctx.req.port_id != 0
                 {
-                    return Err(UNSPECIFIED);
+                    return Err(protocol::Error::new(
+                        ErrorCode::InvalidRequest,
+                        ErrorSubcode::Unsupported,
+                    ));
                 }
 
                 Ok(ResetCounterResponse {
@@ -239,7 +463,10 @@ where
                 use protocol::device_uptime::*;
                 // NOTE: CUrrently, we only handle port 0, the "self" port.
                 if ctx.req.port_id != 0 {
-                    return Err(UNSPECIFIED);
+                    return Err(protocol::Error::new(
+                        ErrorCode::InvalidRequest,
+                        ErrorSubcode::Unsupported,
+                    ));
                 }
                 Ok(DeviceUptimeResponse {
                     uptime: ctx.server.opts.reset.uptime(),
@@ -248,19 +475,124 @@ where
             .handle::<protocol::RequestCounter, _>(|ctx| {
                 use protocol::request_counter::*;
                 Ok(RequestCounterResponse {
-                    ok_count: ctx.server.ok_count,
-                    err_count: ctx.server.err_count,
+                    ok_count: ctx.server.counters.ok_count,
+                    err_count: ctx.server.counters.err_count,
                 })
             })
-            .run(self, host_port, arena);
+            .run_with_middleware(
+                self,
+                host_port,
+                arena,
+                &policy,
+                &mut (&mut counters, middleware),
+            );
 
-        match result {
-            Ok(_) => self.ok_count += 1,
-            Err(_) => self.err_count += 1,
-        }
+        self.counters = counters;
         result
     }
 
+    /// Performs work for a request that was deferred by
+    /// [`PaRot::process_request()`], if there is any.
+    ///
+    /// Integrations that provide [`Options::pending`] should call this
+    /// function whenever the device is not busy servicing the bus. Each call
+    /// performs at most one deferred operation; the result is delivered when
+    /// the host retries the request.
+    ///
+    /// Returns whether any work was done.
+    pub fn poll<A: Arena>(&mut self, arena: &A) -> bool {
+        let pending = match &mut self.pending {
+            Some(pending) => pending,
+            None => return false,
+        };
+        let job = match pending.take_job(self.opts.reset.uptime()) {
+            Some(job) => job,
+            None => return false,
+        };
+        let opts = &mut self.opts;
+        let provisioning = &mut self.provisioning;
+
+        let result = match job.command {
+            CommandType::GetDigests => {
+                use protocol::get_digests::*;
+                GetDigestsRequest::from_wire(&mut { job.request }, arena)
+                    .map_err(protocol::Error::from)
+                    .and_then(|req| {
+                        let digests = chain_digests(
+                            &*opts.trust_chain,
+                            opts.sha,
+                            req.slot,
+                            arena,
+                        )?;
+                        encode(&GetDigestsResponse { digests }, job.out)
+                    })
+            }
+            CommandType::Challenge => {
+                use protocol::challenge::*;
+                let trust_chain = &mut *opts.trust_chain;
+                ChallengeRequest::from_wire(&mut { job.request }, arena)
+                    .and_then(|req| {
+                        let tbs = ChallengeResponseTbs::from_wire(
+                            &mut { job.context },
+                            arena,
+                        )?;
+                        Ok((req, tbs))
+                    })
+                    .map_err(protocol::Error::from)
+                    .and_then(|(req, tbs)| {
+                        let signer = trust_chain
+                            .signer(req.slot)
+                            .ok_or_else(|| bad_slot(req.slot))?;
+                        let signature =
+                            arena.alloc_slice::<u8>(signer.sig_bytes())?;
+                        let sig_len = sign_challenge(
+                            signer,
+                            job.request,
+                            &tbs,
+                            signature,
+                        )?;
+                        let signature = &signature[..sig_len];
+                        encode(&ChallengeResponse { tbs, signature }, job.out)
+                    })
+            }
+            CommandType::ExportCsr => {
+                use protocol::export_csr::*;
+                ExportCsrRequest::from_wire(&mut { job.request }, arena)
+                    .map_err(protocol::Error::from)
+                    .and_then(|req| {
+                        let csr = export_csr(
+                            &mut *opts.trust_chain,
+                            req.slot,
+                            arena,
+                        )?;
+                        encode(&ExportCsrResponse { csr }, job.out)
+                    })
+            }
+            CommandType::ImportSignedCert => {
+                use protocol::import_signed_cert::*;
+                ImportSignedCertRequest::from_wire(&mut { job.request }, arena)
+                    .map_err(protocol::Error::from)
+                    .and_then(|req| {
+                        provisioning_for(provisioning, req.slot)?.import(
+                            req.cert_type,
+                            req.cert,
+                            &*opts.trust_chain,
+                            &mut *opts.ciphers,
+                            opts.sha,
+                        )?;
+                        encode(&protocol::Error::new_ack(), job.out)
+                    })
+            }
+            c => Err(protocol::Error::with_arg(
+                ErrorCode::InvalidRequest,
+                ErrorSubcode::Unsupported,
+                c as u8,
+            )),
+        };
+        pending.complete(result);
+        true
+    }
+
     /// Start and process a outgoing request.
     ///
     /// The request message will be read from `req`, while the response
@@ -275,11 +607,151 @@ where
     }
 }
 
-/// Stopgap error code until we have richer errors for Manticore and Cerberus.
-const UNSPECIFIED: protocol::Error = protocol::Error {
-    code: protocol::ErrorCode::Unspecified,
-    data: [0; 4],
-};
+/// Builds the to-be-signed portion of a `Challenge` response.
+fn challenge_tbs(
+    slot: u8,
+    slot_mask: u8,
+    pmr0: &[u8],
+) -> protocol::challenge::ChallengeResponseTbs<'_> {
+    protocol::challenge::ChallengeResponseTbs {
+        slot,
+        slot_mask,
+        protocol_range: (0, 0),
+        // TODO: Wire through a CSRNG to generate the nonce.
+        nonce: &[0xaa; 32],
+        pmr0_components: 0,
+        pmr0,
+    }
+}
+
+/// Signs a `Challenge` response, writing the signature to `out` and
+/// returning its length.
+fn sign_challenge(
+    signer: &mut dyn sig::Sign,
+    req_buf: &[u8],
+    tbs: &protocol::challenge::ChallengeResponseTbs,
+    out: &mut [u8],
+) -> Result<usize, protocol::Error> {
+    if out.len() < signer.sig_bytes() {
+        return Err(protocol::Error::new(
+            ErrorCode::Unspecified,
+            ErrorSubcode::OutOfMemory,
+        ));
+    }
+    let out = &mut out[..signer.sig_bytes()];
+    let sig_len = tbs.as_iovec_with(|[a, b, c, d]| {
+        signer.sign(&[req_buf, a, b, c, d], out)
+    })?;
+    Ok(sig_len)
+}
+
+/// Computes the digest of every certificate in `slot`, for `GetDigests`.
+fn chain_digests<'out, A: Arena>(
+    trust_chain: &impl cert::TrustChain,
+    sha: &impl sha256::Builder,
+    slot: u8,
+    arena: &'out A,
+) -> Result<&'out [sha256::Digest], protocol::Error> {
+    let digests_len = trust_chain
+        .chain_len(slot)
+        .ok_or_else(|| bad_slot(slot))?
+        .get();
+    let digests = arena.alloc_slice::<sha256::Digest>(digests_len)?;
+    for (i, digest) in digests.iter_mut().enumerate() {
+        if let Some(cached) = trust_chain.cert_digest(slot, i) {
+            *digest = *cached;
+            continue;
+        }
+        let cert = trust_chain.cert(slot, i).ok_or_else(|| bad_slot(slot))?;
+        sha.hash_contiguous(cert.raw(), digest)?;
+    }
+    Ok(digests)
+}
+
+/// Builds and signs a CSR for the leaf key of `slot`, for `ExportCsr`.
+fn export_csr<'out, A: Arena>(
+    trust_chain: &mut impl cert::TrustChain,
+    slot: u8,
+    arena: &'out A,
+) -> Result<&'out [u8], protocol::Error> {
+    let chain_len =
+        trust_chain.chain_len(slot).ok_or_else(|| bad_slot(slot))?;
+    let leaf = trust_chain
+        .cert(slot, chain_len.get() - 1)
+        .ok_or_else(|| bad_slot(slot))?;
+    if leaf.format() != cert::CertFormat::RiotX509 {
+        return Err(protocol::Error::new(
+            ErrorCode::InvalidRequest,
+            ErrorSubcode::Unsupported,
+        ));
+    }
+    let csr =
+        cert::UnsignedCsr::new(leaf.subject(), leaf.subject_key(), arena)?;
+
+    let signer = trust_chain.signer(slot).ok_or_else(|| bad_slot(slot))?;
+    Ok(csr.sign(signer, arena)?)
+}
+
+/// Returns the [`Provisioning`] that handles `ImportSignedCert` requests for
+/// `slot`.
+fn provisioning_for<'p, 'a>(
+    provisioning: &'p mut Option<Provisioning<'a>>,
+    slot: u8,
+) -> Result<&'p mut Provisioning<'a>, protocol::Error> {
+    let provisioning = provisioning.as_mut().ok_or_else(|| {
+        protocol::Error::new(
+            ErrorCode::InvalidRequest,
+            ErrorSubcode::Unsupported,
+        )
+    })?;
+    if slot != provisioning.slot() {
+        return Err(bad_slot(slot));
+    }
+    Ok(provisioning)
+}
+
+/// Hands the request in `req_buf` off to [`PaRot::poll()`], decoding the
+/// response it produced if it is ready.
+///
+/// `capture` is called if the request is newly queued; see
+/// [`Pending::submit_with()`].
+fn defer<'out, R, A>(
+    pending: &'out mut Pending,
+    command: CommandType,
+    req_buf: &[u8],
+    now: Duration,
+    timeout: Duration,
+    arena: &'out A,
+    capture: impl FnOnce(&mut [u8]) -> Result<usize, protocol::Error>,
+) -> Result<R, protocol::Error>
+where
+    R: FromWire<'out>,
+    A: Arena,
+{
+    match pending.submit_with(command, req_buf, now, timeout, capture)? {
+        Poll::Busy => {
+            Err(protocol::Error::new(ErrorCode::Busy, ErrorSubcode::None))
+        }
+        Poll::Ready(resp) => Ok(R::from_wire(&mut { resp }, arena)?),
+    }
+}
+
+/// Encodes `msg` into `out`, returning the number of bytes written.
+fn encode(msg: &impl ToWire, out: &mut [u8]) -> Result<usize, protocol::Error> {
+    let mut cursor = Cursor::new(out);
+    msg.to_wire(&mut cursor)?;
+    Ok(cursor.consumed_len())
+}
+
+/// Builds the error returned when a request names an unprovisioned
+/// certificate slot.
+fn bad_slot(slot: u8) -> protocol::Error {
+    protocol::Error::with_arg(
+        ErrorCode::InvalidRequest,
+        ErrorSubcode::BadSlot,
+        slot,
+    )
+}
 
 #[cfg(test)]
 mod test {
@@ -297,6 +769,8 @@ mod test {
     use crate::protocol::wire::ToWire;
     use crate::protocol::Header;
 
+    use testutil::data::keys;
+
     const NETWORKING: Networking = Networking {
         max_message_size: 1024,
         max_packet_size: 256,
@@ -317,7 +791,12 @@ mod test {
             subsys_id: 4,
         };
 
-    fn simulate_request<'a, C: protocol::Command<'a>, A: Arena>(
+    fn simulate_request<
+        'a,
+        C: protocol::Command<'a>,
+        A: Arena,
+        T: cert::TrustChain,
+    >(
         scratch_space: &'a mut [u8],
         port_out: &'a mut Option<net::InMemHost<'a>>,
         arena: &'a mut A,
@@ -326,7 +805,8 @@ mod test {
             fake::Reset,
             ring::sha256::Builder,
             ring::sig::Ciphers,
-            cert::SimpleChain<0>,
+            ring::aead::Ciphers,
+            T,
         >,
         request: C::Req,
     ) -> Result<Result<C::Resp, protocol::Error>, Error> {
@@ -422,12 +902,16 @@ mod test {
         );
         let reset = fake::Reset::new(0, Duration::from_millis(1));
         let mut ciphers = ring::sig::Ciphers::new();
+        let mut aead = ring::aead::Ciphers::new();
         let sha = ring::sha256::Builder::new();
-        let mut trust_chain = cert::SimpleChain::parse(
+        let mut trust_chain = cert::SimpleChain::<0>::parse(
             &[],
             cert::CertFormat::RiotX509,
             &mut ciphers,
+            &sha,
+            None,
             None,
+            &[],
         )
         .unwrap();
         let mut server = PaRot::new(Options {
@@ -435,11 +919,15 @@ mod test {
             reset: &reset,
             sha: &sha,
             ciphers: &mut ciphers,
+            aead: Some(&mut aead),
             trust_chain: &mut trust_chain,
             pmr0: "not important".as_bytes(),
             device_id: DEVICE_ID,
             networking: NETWORKING,
             timeouts: TIMEOUTS,
+            pending: None,
+            policy: Policy::OPEN,
+            provisioning: None,
         });
 
         let mut scratch = [0; 1024];
@@ -449,7 +937,7 @@ mod test {
         let mut port = None;
         let req =
             protocol::firmware_version::FirmwareVersionRequest { index: 0 };
-        let resp = simulate_request::<protocol::FirmwareVersion, _>(
+        let resp = simulate_request::<protocol::FirmwareVersion, _, _>(
             &mut scratch,
             &mut port,
             &mut arena,
@@ -465,7 +953,7 @@ mod test {
         let mut port = None;
         let req =
             protocol::firmware_version::FirmwareVersionRequest { index: 1 };
-        let resp = simulate_request::<protocol::FirmwareVersion, _>(
+        let resp = simulate_request::<protocol::FirmwareVersion, _, _>(
             &mut scratch,
             &mut port,
             &mut arena,
@@ -481,7 +969,7 @@ mod test {
         let mut port = None;
         let req =
             protocol::firmware_version::FirmwareVersionRequest { index: 2 };
-        let resp = simulate_request::<protocol::FirmwareVersion, _>(
+        let resp = simulate_request::<protocol::FirmwareVersion, _, _>(
             &mut scratch,
             &mut port,
             &mut arena,
@@ -490,13 +978,14 @@ mod test {
         )
         .expect("got error from server")
         .expect_err("got non-error message from server");
-        assert_eq!(resp.code, protocol::ErrorCode::Unspecified);
+        assert_eq!(resp.code, protocol::ErrorCode::InvalidRequest);
+        assert_eq!(resp.subcode(), Some(protocol::ErrorSubcode::OutOfRange));
 
         arena.reset();
 
         let mut port = None;
         let req = protocol::device_id::DeviceIdRequest;
-        let resp = simulate_request::<protocol::DeviceId, _>(
+        let resp = simulate_request::<protocol::DeviceId, _, _>(
             &mut scratch,
             &mut port,
             &mut arena,
@@ -509,6 +998,22 @@ mod test {
 
         arena.reset();
 
+        let mut port = None;
+        let req = protocol::request_counter::RequestCounterRequest;
+        let resp = simulate_request::<protocol::RequestCounter, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            req,
+        )
+        .expect("got error from server")
+        .expect("got error message from server");
+        assert_eq!(resp.ok_count, 4);
+        assert_eq!(resp.err_count, 0);
+
+        arena.reset();
+
         let mut port = None;
         let req = protocol::device_id::DeviceIdRequest;
         let resp = simulate_response::<protocol::DeviceId, _>(
@@ -520,4 +1025,618 @@ mod test {
         .expect("got error from client");
         assert_eq!(resp.id, DEVICE_ID);
     }
+
+    #[test]
+    fn device_capabilities() {
+        let identity = fake::Identity::new(b"test version", &[], b"random");
+        let reset = fake::Reset::new(0, Duration::from_millis(1));
+        let sha = ring::sha256::Builder::new();
+
+        for &with_aead in &[true, false] {
+            let mut ciphers = ring::sig::Ciphers::new();
+            let mut aead = ring::aead::Ciphers::new();
+            let mut trust_chain = cert::SimpleChain::<0>::parse(
+                &[],
+                cert::CertFormat::RiotX509,
+                &mut ciphers,
+                &sha,
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+            let mut server = PaRot::new(Options {
+                identity: &identity,
+                reset: &reset,
+                sha: &sha,
+                ciphers: &mut ciphers,
+                aead: if with_aead { Some(&mut aead) } else { None },
+                trust_chain: &mut trust_chain,
+                pmr0: &[],
+                device_id: DEVICE_ID,
+                networking: NETWORKING,
+                timeouts: TIMEOUTS,
+                pending: None,
+                policy: Policy::OPEN,
+                provisioning: None,
+            });
+
+            let mut scratch = [0; 1024];
+            let mut arena = [0; 64];
+            let mut arena = BumpArena::new(&mut arena);
+
+            let mut port = None;
+            let req = DeviceCapabilitiesRequest {
+                capabilities: Capabilities {
+                    networking: NETWORKING,
+                    security: Security::empty(),
+                    has_pfm_support: false,
+                    has_policy_support: false,
+                    has_firmware_protection: false,
+                    crypto: Crypto {
+                        has_ecdsa: true,
+                        has_ecc: true,
+                        has_rsa: true,
+                        has_aes: true,
+                        ecc_strength: EccKeyStrength::BITS_256,
+                        rsa_strength: RsaKeyStrength::BITS_2048,
+                        aes_strength: AesKeyStrength::BITS_256,
+                    },
+                },
+            };
+            let resp = simulate_request::<protocol::DeviceCapabilities, _, _>(
+                &mut scratch,
+                &mut port,
+                &mut arena,
+                &mut server,
+                req,
+            )
+            .expect("got error from server")
+            .expect("got error message from server");
+
+            // There is no AES bit on the wire; support is implied by the
+            // reported key strengths.
+            let expected = if with_aead {
+                AesKeyStrength::BITS_128 | AesKeyStrength::BITS_256
+            } else {
+                AesKeyStrength::empty()
+            };
+            assert_eq!(resp.capabilities.crypto.aes_strength, expected);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn deferred() {
+        use crate::crypto::sig::Verify as _;
+        use protocol::challenge::*;
+        use protocol::export_csr::*;
+        use protocol::get_digests::*;
+        use protocol::request_counter::*;
+        use testutil::data::x509;
+
+        let identity = fake::Identity::new(b"test version", &[], b"bits");
+        let reset = fake::Reset::new(0, Duration::from_millis(1));
+        let mut ciphers = ring::sig::Ciphers::new();
+        let mut aead = ring::aead::Ciphers::new();
+        let sha = ring::sha256::Builder::new();
+        let (mut verifier, mut signer) =
+            ring::rsa::from_keypair(keys::KEY3_RSA_KEYPAIR);
+        let mut trust_chain = cert::SimpleChain::<3>::parse(
+            &[x509::CHAIN1, x509::CHAIN2, x509::CHAIN3],
+            cert::CertFormat::RiotX509,
+            &mut ciphers,
+            &sha,
+            Some(&mut signer as &mut dyn sig::Sign),
+            None,
+            &[],
+        )
+        .unwrap();
+        let mut pending = [0; 2048];
+        let mut server = PaRot::new(Options {
+            identity: &identity,
+            reset: &reset,
+            sha: &sha,
+            ciphers: &mut ciphers,
+            aead: Some(&mut aead),
+            trust_chain: &mut trust_chain,
+            pmr0: b"pmr0",
+            device_id: DEVICE_ID,
+            networking: NETWORKING,
+            timeouts: TIMEOUTS,
+            pending: Some(&mut pending[..]),
+            policy: Policy::OPEN,
+            provisioning: None,
+        });
+
+        let mut scratch = [0; 4096];
+        let mut arena = [0; 4096];
+        let mut arena = BumpArena::new(&mut arena);
+
+        let req = ChallengeRequest {
+            slot: 0,
+            nonce: &[0x55; 32],
+        };
+        let mut req_bytes = [0; 34];
+        req.to_wire(&mut req_bytes[..])
+            .expect("failed to write request");
+
+        // Nothing has been queued yet.
+        assert!(!server.poll(&arena));
+
+        let mut port = None;
+        let resp = simulate_request::<protocol::Challenge, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            req,
+        )
+        .expect("got error from server")
+        .expect_err("got non-error message from server");
+        assert_eq!(resp.code, protocol::ErrorCode::Busy);
+        arena.reset();
+
+        assert!(server.poll(&arena));
+        assert!(!server.poll(&arena));
+        arena.reset();
+
+        let mut port = None;
+        let resp = simulate_request::<protocol::Challenge, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            req,
+        )
+        .expect("got error from server")
+        .expect("got error message from server");
+        assert_eq!(resp.tbs.pmr0, b"pmr0");
+        assert_eq!(resp.tbs.slot_mask, 0b1);
+        resp.tbs
+            .as_iovec_with(|[a, b, c, d]| {
+                verifier.verify(&[&req_bytes, a, b, c, d], resp.signature)
+            })
+            .expect("bad signature");
+        arena.reset();
+
+        // Every digest in a `SimpleChain` is cached, so there is nothing to
+        // defer.
+        let mut port = None;
+        let resp = simulate_request::<protocol::GetDigests, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            GetDigestsRequest {
+                slot: 0,
+                key_exchange: KeyExchangeAlgo::Ecdh,
+            },
+        )
+        .expect("got error from server")
+        .expect("got error message from server");
+        assert_eq!(resp.digests.len(), 3);
+        arena.reset();
+
+        let req = ExportCsrRequest { slot: 0 };
+        let mut port = None;
+        let resp = simulate_request::<protocol::ExportCsr, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            req,
+        )
+        .expect("got error from server")
+        .expect_err("got non-error message from server");
+        assert_eq!(resp.code, protocol::ErrorCode::Busy);
+        arena.reset();
+
+        assert!(server.poll(&arena));
+        arena.reset();
+
+        let mut port = None;
+        let resp = simulate_request::<protocol::ExportCsr, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            req,
+        )
+        .expect("got error from server")
+        .expect("got error message from server");
+        assert!(!resp.csr.is_empty());
+        arena.reset();
+
+        // `Busy` replies count as errors.
+        let mut port = None;
+        let resp = simulate_request::<protocol::RequestCounter, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            RequestCounterRequest,
+        )
+        .expect("got error from server")
+        .expect("got error message from server");
+        assert_eq!(resp.ok_count, 3);
+        assert_eq!(resp.err_count, 2);
+    }
+
+    /// Imports `cert` into slot 1, returning the server's reply.
+    fn import_cert<T: cert::TrustChain>(
+        server: &mut PaRot<
+            fake::Identity,
+            fake::Reset,
+            ring::sha256::Builder,
+            ring::sig::Ciphers,
+            ring::aead::Ciphers,
+            T,
+        >,
+        cert_type: protocol::import_signed_cert::CertType,
+        cert: &[u8],
+    ) -> protocol::Error {
+        use protocol::import_signed_cert::*;
+
+        let mut scratch = [0; 4096];
+        let mut arena = [0; 2048];
+        let mut arena = BumpArena::new(&mut arena);
+        let mut port = None;
+        let req = ImportSignedCertRequest {
+            slot: 1,
+            cert_type,
+            cert,
+        };
+        simulate_request::<ImportSignedCert, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            server,
+            req,
+        )
+        .expect("got error from server")
+        .expect_err("got non-error message from server")
+    }
+
+    /// Queries the provisioning state of `slot`.
+    fn cert_state<T: cert::TrustChain>(
+        server: &mut PaRot<
+            fake::Identity,
+            fake::Reset,
+            ring::sha256::Builder,
+            ring::sig::Ciphers,
+            ring::aead::Ciphers,
+            T,
+        >,
+        slot: u8,
+    ) -> protocol::get_cert_state::CertState {
+        use protocol::get_cert_state::*;
+
+        let mut scratch = [0; 1024];
+        let mut arena = [0; 64];
+        let mut arena = BumpArena::new(&mut arena);
+        let mut port = None;
+        let req = GetCertificateStateRequest { slot };
+        simulate_request::<GetCertificateState, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            server,
+            req,
+        )
+        .expect("got error from server")
+        .expect("got error message from server")
+        .state
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn provisioning() {
+        use protocol::export_csr::*;
+        use protocol::get_cert_state::CertState;
+        use protocol::import_signed_cert::CertType;
+        use testutil::data::x509;
+
+        let identity = fake::Identity::new(b"test version", &[], b"bits");
+        let reset = fake::Reset::new(0, Duration::from_millis(1));
+        let mut ciphers = ring::sig::Ciphers::new();
+        let mut aead = ring::aead::Ciphers::new();
+        let sha = ring::sha256::Builder::new();
+        let (_, mut signer) = ring::rsa::from_keypair(keys::KEY3_RSA_KEYPAIR);
+        let mut trust_chain = cert::SimpleChain::<3>::parse(
+            &[x509::CHAIN1, x509::CHAIN2, x509::CHAIN3],
+            cert::CertFormat::RiotX509,
+            &mut ciphers,
+            &sha,
+            Some(&mut signer as &mut dyn sig::Sign),
+            None,
+            &[],
+        )
+        .unwrap();
+
+        // RSA signatures are deterministic, so we can compute the expected
+        // CSR up front.
+        let mut csr_arena = [0; 2048];
+        let csr_arena = BumpArena::new(&mut csr_arena);
+        let leaf = cert::TrustChain::cert(&trust_chain, 0, 2).unwrap();
+        let (_, mut csr_signer) =
+            ring::rsa::from_keypair(keys::KEY3_RSA_KEYPAIR);
+        let expected_csr = cert::UnsignedCsr::new(
+            leaf.subject(),
+            leaf.subject_key(),
+            &csr_arena,
+        )
+        .unwrap()
+        .sign(&mut csr_signer, &csr_arena)
+        .unwrap();
+
+        let mut flash = hardware::flash::RamMut(vec![0xff; 4096]);
+        let mut staging = [0; 4096];
+        let mut server = PaRot::new(Options {
+            identity: &identity,
+            reset: &reset,
+            sha: &sha,
+            ciphers: &mut ciphers,
+            aead: Some(&mut aead),
+            trust_chain: &mut trust_chain,
+            pmr0: b"pmr0",
+            device_id: DEVICE_ID,
+            networking: NETWORKING,
+            timeouts: TIMEOUTS,
+            pending: None,
+            policy: Policy::OPEN,
+            provisioning: Some(Provisioning::new(
+                1,
+                0,
+                cert::CertFormat::RiotX509,
+                x509::CHAIN1,
+                None,
+                &[],
+                &mut staging,
+                &mut flash,
+            )),
+        });
+
+        assert_eq!(cert_state(&mut server, 0), CertState::Provisioned);
+        assert_eq!(cert_state(&mut server, 1), CertState::NotProvisioned);
+
+        let mut scratch = [0; 4096];
+        let mut arena = [0; 4096];
+        let mut arena = BumpArena::new(&mut arena);
+        let mut port = None;
+        let resp = simulate_request::<ExportCsr, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            ExportCsrRequest { slot: 0 },
+        )
+        .expect("got error from server")
+        .expect("got error message from server");
+        assert_eq!(resp.csr, expected_csr);
+        arena.reset();
+
+        let mut port = None;
+        let resp = simulate_request::<ExportCsr, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            ExportCsrRequest { slot: 1 },
+        )
+        .expect("got error from server")
+        .expect_err("got non-error message from server");
+        assert_eq!(resp.subcode(), Some(ErrorSubcode::BadSlot));
+
+        // A chain must start with its root.
+        let resp =
+            import_cert(&mut server, CertType::IntermediateCa, x509::CHAIN2);
+        assert_eq!(resp.code, ErrorCode::InvalidRequest);
+        assert_eq!(cert_state(&mut server, 1), CertState::NotProvisioned);
+
+        // The leaf must certify the identity key.
+        let resp = import_cert(&mut server, CertType::RootCa, x509::CHAIN1);
+        assert_eq!(resp.code, ErrorCode::Ok);
+        assert_eq!(cert_state(&mut server, 1), CertState::InProgress);
+        let resp = import_cert(&mut server, CertType::DeviceId, x509::CHAIN2);
+        assert_eq!(resp.subcode(), Some(ErrorSubcode::BadCert));
+        assert_eq!(cert_state(&mut server, 1), CertState::Invalid);
+
+        for (cert_type, cert) in &[
+            (CertType::RootCa, x509::CHAIN1),
+            (CertType::IntermediateCa, x509::CHAIN2),
+            (CertType::DeviceId, x509::CHAIN3),
+        ] {
+            let resp = import_cert(&mut server, *cert_type, cert);
+            assert_eq!(resp.code, ErrorCode::Ok);
+        }
+        assert_eq!(cert_state(&mut server, 1), CertState::Provisioned);
+        drop(server);
+
+        let flash = hardware::flash::Ram(flash.0);
+        let mut chain = cert::FlashChain::<2, 3>::new();
+        let loaded = chain
+            .load(
+                1,
+                &flash,
+                &crate::mem::OutOfMemory,
+                cert::CertFormat::RiotX509,
+                &mut ring::sig::Ciphers::new(),
+                &ring::sha256::Builder::new(),
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+        assert!(loaded);
+        assert_eq!(cert::TrustChain::chain_len(&chain, 1).unwrap().get(), 3);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn provisioning_untrusted() {
+        use protocol::get_cert_state::CertState;
+        use protocol::import_signed_cert::CertType;
+
+        let mut cert_arena = [0; 2048];
+        let cert_arena = BumpArena::new(&mut cert_arena);
+        let mut ca_signer =
+            ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR)
+                .unwrap();
+        let ca_key = sig::PublicKeyParams::Okp {
+            curve: sig::Curve::Ed25519,
+            x: keys::KEY1_ED25519_RAW,
+        };
+        let leaf_key = sig::PublicKeyParams::Ecc {
+            curve: sig::Curve::NistP256,
+            x: keys::KEY1_ECDSA_P256_X,
+            y: keys::KEY1_ECDSA_P256_Y,
+        };
+        let ca_name = cert::Name::x509_common_name("CA", &cert_arena).unwrap();
+        let leaf_name =
+            cert::Name::x509_common_name("Leaf", &cert_arena).unwrap();
+        let ca = cert::CertBuilder::new(&[1], ca_name, ca_name, &ca_key)
+            .ca(None)
+            .sign(&ca_key, &mut ca_signer, &cert_arena)
+            .unwrap();
+        let leaf = cert::CertBuilder::new(&[2], ca_name, leaf_name, &leaf_key)
+            .validity(Some(Duration::from_secs(1609459200)), None) // 2021.
+            .sign(&ca_key, &mut ca_signer, &cert_arena)
+            .unwrap();
+
+        // The Unix epoch is before `leaf` is valid.
+        let epoch = fake::Clock::new();
+        for &(anchor, clock, state) in &[
+            (leaf, None, CertState::Invalid),
+            (
+                ca,
+                Some(&epoch as &dyn hardware::WallClock),
+                CertState::Invalid,
+            ),
+            (ca, None, CertState::Provisioned),
+        ] {
+            let identity = fake::Identity::new(b"test version", &[], b"bits");
+            let reset = fake::Reset::new(0, Duration::from_millis(1));
+            let mut ciphers = ring::sig::Ciphers::new();
+            let mut aead = ring::aead::Ciphers::new();
+            let sha = ring::sha256::Builder::new();
+            let mut trust_chain = cert::SimpleChain::<2>::parse(
+                &[ca, leaf],
+                cert::CertFormat::RiotX509,
+                &mut ciphers,
+                &sha,
+                None,
+                None,
+                &[],
+            )
+            .unwrap();
+
+            let mut flash = hardware::flash::RamMut(vec![0xff; 4096]);
+            let mut staging = [0; 4096];
+            let mut server = PaRot::new(Options {
+                identity: &identity,
+                reset: &reset,
+                sha: &sha,
+                ciphers: &mut ciphers,
+                aead: Some(&mut aead),
+                trust_chain: &mut trust_chain,
+                pmr0: b"pmr0",
+                device_id: DEVICE_ID,
+                networking: NETWORKING,
+                timeouts: TIMEOUTS,
+                pending: None,
+                policy: Policy::OPEN,
+                provisioning: Some(Provisioning::new(
+                    1,
+                    0,
+                    cert::CertFormat::RiotX509,
+                    anchor,
+                    clock,
+                    &[],
+                    &mut staging,
+                    &mut flash,
+                )),
+            });
+
+            let resp = import_cert(&mut server, CertType::RootCa, ca);
+            assert_eq!(resp.code, ErrorCode::Ok);
+            let resp = import_cert(&mut server, CertType::DeviceId, leaf);
+            if state == CertState::Provisioned {
+                assert_eq!(resp.code, ErrorCode::Ok);
+            } else {
+                assert_eq!(resp.subcode(), Some(ErrorSubcode::BadCert));
+            }
+            assert_eq!(cert_state(&mut server, 1), state);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn export_csr_cwt() {
+        use protocol::export_csr::*;
+
+        let identity = fake::Identity::new(b"test version", &[], b"bits");
+        let reset = fake::Reset::new(0, Duration::from_millis(1));
+        let mut ciphers = ring::sig::Ciphers::new();
+        let mut aead = ring::aead::Ciphers::new();
+        let sha = ring::sha256::Builder::new();
+
+        let mut cert_arena = [0; 1024];
+        let cert_arena = BumpArena::new(&mut cert_arena);
+        let mut signer =
+            ring::ed25519::Sign::from_pkcs8(keys::KEY1_ED25519_KEYPAIR)
+                .unwrap();
+        let key = sig::PublicKeyParams::Okp {
+            curve: sig::Curve::Ed25519,
+            x: keys::KEY1_ED25519_RAW,
+        };
+        let name = cert::Name::cwt("my cool device");
+        let raw_cert = cert::CwtBuilder::new(name, name, &key)
+            .ca()
+            .sign(&key, &mut signer, &cert_arena)
+            .unwrap();
+        let mut trust_chain = cert::SimpleChain::<1>::parse(
+            &[raw_cert],
+            cert::CertFormat::OpenDiceCwt,
+            &mut ciphers,
+            &sha,
+            Some(&mut signer as &mut dyn sig::Sign),
+            None,
+            &[],
+        )
+        .unwrap();
+
+        let mut server = PaRot::new(Options {
+            identity: &identity,
+            reset: &reset,
+            sha: &sha,
+            ciphers: &mut ciphers,
+            aead: Some(&mut aead),
+            trust_chain: &mut trust_chain,
+            pmr0: b"pmr0",
+            device_id: DEVICE_ID,
+            networking: NETWORKING,
+            timeouts: TIMEOUTS,
+            pending: None,
+            policy: Policy::OPEN,
+            provisioning: None,
+        });
+
+        // CSRs are X.509-only.
+        let mut scratch = [0; 1024];
+        let mut arena = [0; 1024];
+        let mut arena = BumpArena::new(&mut arena);
+        let mut port = None;
+        let resp = simulate_request::<ExportCsr, _, _>(
+            &mut scratch,
+            &mut port,
+            &mut arena,
+            &mut server,
+            ExportCsrRequest { slot: 0 },
+        )
+        .expect("got error from server")
+        .expect_err("got non-error message from server");
+        assert_eq!(resp.code, ErrorCode::InvalidRequest);
+        assert_eq!(resp.subcode(), Some(ErrorSubcode::Unsupported));
+    }
 }
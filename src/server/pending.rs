@@ -0,0 +1,397 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deferred request processing.
+//!
+//! Some requests, such as `Challenge`, require cryptographic operations that
+//! can take far longer than a bus controller is willing to hold a transaction
+//! open for. Rather than blocking, a server can use [`Pending`] to defer such
+//! a request: the request is copied into a scratch buffer and the host is
+//! told that the device is `Busy`. The integration later calls into the
+//! server outside of a bus transaction to actually perform the work, and the
+//! result is delivered when the host retries the same request.
+//!
+//! Along with the request, a server may capture some *context* when the
+//! request is first queued, for any state that the eventual response must
+//! reflect as of the time the request arrived.
+//!
+//! A deferred request moves through the following states:
+//! ```text
+//!   Idle --submit--> Queued --take_job/complete--> Ready --submit--> Idle
+//! ```
+//! A request that is not collected by the host within its deadline (usually
+//! the negotiated cryptographic timeout) is discarded; a later retry is then
+//! treated as a brand new request.
+
+use core::time::Duration;
+
+use crate::protocol;
+use crate::protocol::CommandType;
+use crate::protocol::ErrorCode;
+use crate::protocol::ErrorSubcode;
+
+/// The state of a [`Pending`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum State {
+    /// No request is in flight.
+    Idle,
+    /// A request has been accepted, but no work has been done for it yet.
+    Queued {
+        command: CommandType,
+        req_len: usize,
+        ctx_len: usize,
+        deadline: Duration,
+    },
+    /// The work for a request has been completed, and is waiting for the host
+    /// to collect it.
+    Ready {
+        command: CommandType,
+        req_len: usize,
+        ctx_len: usize,
+        result: Result<usize, protocol::Error>,
+        deadline: Duration,
+    },
+}
+
+/// The outcome of submitting a request to a [`Pending`].
+#[derive(Debug)]
+pub enum Poll<'a> {
+    /// The request is still being processed; the host should be told to try
+    /// again later.
+    Busy,
+    /// The request has been processed, producing the given bytes.
+    ///
+    /// The meaning of these bytes is up to the server that produced them.
+    Ready(&'a [u8]),
+}
+
+/// A queued request that is ready to be worked on.
+///
+/// See [`Pending::take_job()`].
+pub struct Job<'a> {
+    /// The command of the queued request.
+    pub command: CommandType,
+    /// The raw bytes of the queued request.
+    pub request: &'a [u8],
+    /// The context captured when the request was queued.
+    ///
+    /// See [`Pending::submit_with()`].
+    pub context: &'a [u8],
+    /// Scratch space to write the result of the request into.
+    pub out: &'a mut [u8],
+}
+
+/// A single-entry queue of deferred requests.
+///
+/// See the module documentation for more information.
+pub struct Pending<'buf> {
+    buf: &'buf mut [u8],
+    state: State,
+}
+
+impl<'buf> Pending<'buf> {
+    /// Creates a new `Pending` that uses `buf` as storage for both deferred
+    /// requests and their results.
+    pub fn new(buf: &'buf mut [u8]) -> Self {
+        Self {
+            buf,
+            state: State::Idle,
+        }
+    }
+
+    /// Discards any request whose deadline has passed.
+    fn expire(&mut self, now: Duration) {
+        match self.state {
+            State::Queued { deadline, .. } | State::Ready { deadline, .. }
+                if now >= deadline =>
+            {
+                self.state = State::Idle
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks whether the in-flight request (if any) is `req`.
+    fn is_in_flight(&self, command: CommandType, req: &[u8]) -> bool {
+        match self.state {
+            State::Queued {
+                command: c,
+                req_len,
+                ..
+            }
+            | State::Ready {
+                command: c,
+                req_len,
+                ..
+            } => c == command && &self.buf[..req_len] == req,
+            State::Idle => false,
+        }
+    }
+
+    /// Submits a request for deferred processing.
+    ///
+    /// If `req` is already in flight, this returns its status, and, if its
+    /// result is ready, hands the result back and returns to the idle state.
+    /// Otherwise, `req` replaces whatever request was in flight, and will
+    /// need to be processed within `timeout` of `now`.
+    pub fn submit(
+        &mut self,
+        command: CommandType,
+        req: &[u8],
+        now: Duration,
+        timeout: Duration,
+    ) -> Result<Poll<'_>, protocol::Error> {
+        self.submit_with(command, req, now, timeout, |_| Ok(0))
+    }
+
+    /// Like [`Pending::submit()`], but also captures context for the
+    /// request.
+    ///
+    /// If `req` is not already in flight, `capture` is called with the
+    /// space remaining after the request, and returns how many bytes of
+    /// context it wrote; these are handed back in [`Job::context`].
+    pub fn submit_with(
+        &mut self,
+        command: CommandType,
+        req: &[u8],
+        now: Duration,
+        timeout: Duration,
+        capture: impl FnOnce(&mut [u8]) -> Result<usize, protocol::Error>,
+    ) -> Result<Poll<'_>, protocol::Error> {
+        self.expire(now);
+        if self.is_in_flight(command, req) {
+            return match self.state {
+                State::Ready {
+                    req_len,
+                    ctx_len,
+                    result,
+                    ..
+                } => {
+                    self.state = State::Idle;
+                    let len = result?;
+                    let start = req_len + ctx_len;
+                    Ok(Poll::Ready(&self.buf[start..start + len]))
+                }
+                _ => Ok(Poll::Busy),
+            };
+        }
+
+        self.state = State::Idle;
+        if req.len() > self.buf.len() {
+            return Err(protocol::Error::new(
+                ErrorCode::MessageOverflow,
+                ErrorSubcode::OutOfMemory,
+            ));
+        }
+        let (req_buf, ctx_buf) = self.buf.split_at_mut(req.len());
+        req_buf.copy_from_slice(req);
+        let ctx_len = capture(ctx_buf)?;
+        if ctx_len > ctx_buf.len() {
+            return Err(protocol::Error::new(
+                ErrorCode::Unspecified,
+                ErrorSubcode::Internal,
+            ));
+        }
+        self.state = State::Queued {
+            command,
+            req_len: req.len(),
+            ctx_len,
+            deadline: now + timeout,
+        };
+        Ok(Poll::Busy)
+    }
+
+    /// Returns the queued request that needs to be worked on, if there is
+    /// one.
+    ///
+    /// Once the work is done, [`Pending::complete()`] must be called to
+    /// record the result.
+    pub fn take_job(&mut self, now: Duration) -> Option<Job<'_>> {
+        self.expire(now);
+        match self.state {
+            State::Queued {
+                command,
+                req_len,
+                ctx_len,
+                ..
+            } => {
+                let (request, rest) = self.buf.split_at_mut(req_len);
+                let (context, out) = rest.split_at_mut(ctx_len);
+                Some(Job {
+                    command,
+                    request,
+                    context,
+                    out,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Records the result of the job returned by [`Pending::take_job()`].
+    ///
+    /// `result` is either the number of bytes written to [`Job::out`], or an
+    /// error to deliver to the host. If there is no queued job, this function
+    /// does nothing.
+    pub fn complete(&mut self, result: Result<usize, protocol::Error>) {
+        if let State::Queued {
+            command,
+            req_len,
+            ctx_len,
+            deadline,
+        } = self.state
+        {
+            let result = result.and_then(|len| {
+                if len > self.buf.len() - req_len - ctx_len {
+                    return Err(protocol::Error::new(
+                        ErrorCode::Unspecified,
+                        ErrorSubcode::Internal,
+                    ));
+                }
+                Ok(len)
+            });
+            self.state = State::Ready {
+                command,
+                req_len,
+                ctx_len,
+                result,
+                deadline,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut buf = [0; 16];
+        let mut pending = Pending::new(&mut buf);
+        let req = &[1, 2, 3, 4];
+
+        let poll = pending.submit(CommandType::Challenge, req, ms(0), TIMEOUT);
+        assert!(matches!(poll, Ok(Poll::Busy)));
+        let poll = pending.submit(CommandType::Challenge, req, ms(5), TIMEOUT);
+        assert!(matches!(poll, Ok(Poll::Busy)));
+
+        let job = pending.take_job(ms(10)).unwrap();
+        assert_eq!(job.command, CommandType::Challenge);
+        assert_eq!(job.request, req);
+        job.out[..2].copy_from_slice(&[0xaa, 0xbb]);
+        pending.complete(Ok(2));
+        assert!(pending.take_job(ms(15)).is_none());
+
+        let poll = pending.submit(CommandType::Challenge, req, ms(20), TIMEOUT);
+        assert!(matches!(poll, Ok(Poll::Ready(&[0xaa, 0xbb]))));
+
+        // The result is only delivered once.
+        let poll = pending.submit(CommandType::Challenge, req, ms(25), TIMEOUT);
+        assert!(matches!(poll, Ok(Poll::Busy)));
+    }
+
+    #[test]
+    fn context() {
+        let mut buf = [0; 16];
+        let mut pending = Pending::new(&mut buf);
+        let req = &[1, 2, 3, 4];
+
+        let poll = pending.submit_with(
+            CommandType::Challenge,
+            req,
+            ms(0),
+            TIMEOUT,
+            |ctx| {
+                ctx[..2].copy_from_slice(&[0xcc, 0xdd]);
+                Ok(2)
+            },
+        );
+        assert!(matches!(poll, Ok(Poll::Busy)));
+
+        // Context is only captured when a request is first queued.
+        let poll = pending.submit_with(
+            CommandType::Challenge,
+            req,
+            ms(5),
+            TIMEOUT,
+            |_| panic!("captured context twice"),
+        );
+        assert!(matches!(poll, Ok(Poll::Busy)));
+
+        let job = pending.take_job(ms(10)).unwrap();
+        assert_eq!(job.request, req);
+        assert_eq!(job.context, &[0xcc, 0xdd]);
+        assert_eq!(job.out.len(), 10);
+        job.out[..2].copy_from_slice(&[0xaa, 0xbb]);
+        pending.complete(Ok(2));
+
+        let poll = pending.submit(CommandType::Challenge, req, ms(20), TIMEOUT);
+        assert!(matches!(poll, Ok(Poll::Ready(&[0xaa, 0xbb]))));
+    }
+
+    #[test]
+    fn failure() {
+        let mut buf = [0; 16];
+        let mut pending = Pending::new(&mut buf);
+        let req = &[1, 2, 3, 4];
+
+        let _ = pending.submit(CommandType::Challenge, req, ms(0), TIMEOUT);
+        assert!(pending.take_job(ms(10)).is_some());
+        pending.complete(Err(protocol::Error::new(
+            ErrorCode::Unspecified,
+            ErrorSubcode::Crypto,
+        )));
+
+        let poll = pending.submit(CommandType::Challenge, req, ms(20), TIMEOUT);
+        let err = poll.unwrap_err();
+        assert_eq!(err.subcode(), Some(ErrorSubcode::Crypto));
+    }
+
+    #[test]
+    fn replaced() {
+        let mut buf = [0; 16];
+        let mut pending = Pending::new(&mut buf);
+
+        let _ = pending.submit(CommandType::Challenge, &[1], ms(0), TIMEOUT);
+        let _ = pending.submit(CommandType::Challenge, &[2], ms(1), TIMEOUT);
+        let job = pending.take_job(ms(2)).unwrap();
+        assert_eq!(job.request, &[2]);
+    }
+
+    #[test]
+    fn expired() {
+        let mut buf = [0; 16];
+        let mut pending = Pending::new(&mut buf);
+        let req = &[1, 2, 3, 4];
+
+        let _ = pending.submit(CommandType::Challenge, req, ms(0), TIMEOUT);
+        assert!(pending.take_job(ms(300)).is_none());
+
+        let _ = pending.submit(CommandType::Challenge, req, ms(300), TIMEOUT);
+        assert!(pending.take_job(ms(310)).is_some());
+        pending.complete(Ok(0));
+        let poll =
+            pending.submit(CommandType::Challenge, req, ms(600), TIMEOUT);
+        assert!(matches!(poll, Ok(Poll::Busy)));
+        assert!(pending.take_job(ms(610)).is_some());
+    }
+
+    #[test]
+    fn too_big() {
+        let mut buf = [0; 4];
+        let mut pending = Pending::new(&mut buf);
+
+        let poll =
+            pending.submit(CommandType::Challenge, &[0; 8], ms(0), TIMEOUT);
+        assert_eq!(poll.unwrap_err().code, ErrorCode::MessageOverflow);
+        assert!(pending.take_job(ms(1)).is_none());
+    }
+}
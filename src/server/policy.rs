@@ -0,0 +1,162 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-command authorization policies.
+//!
+//! Some commands must only be accepted from a host that has set up a
+//! suitably secure session with the device. A [`Policy`] is a declarative
+//! table mapping each [`CommandType`] to the [`Session`] properties a request
+//! must arrive with; it is enforced by the request handler before a command's
+//! handler ever runs.
+//!
+//! ```
+//! # use manticore::net::Session;
+//! # use manticore::protocol::CommandType;
+//! # use manticore::server::policy::Policy;
+//! const POLICY: Policy = Policy::new(&[
+//!     (CommandType::GetHostState, Session::ESTABLISHED),
+//! ]);
+//!
+//! assert!(POLICY.check(CommandType::GetHostState, Session::empty()).is_err());
+//! assert!(POLICY.check(CommandType::DeviceId, Session::empty()).is_ok());
+//! ```
+//!
+//! [`Policy::RECOMMENDED`] is a reasonable starting point for integrations
+//! that do not need anything more specific.
+
+use crate::net::Session;
+use crate::protocol;
+use crate::protocol::CommandType;
+use crate::protocol::ErrorCode;
+use crate::protocol::ErrorSubcode;
+
+/// A per-command authorization policy.
+///
+/// See the module documentation for more information.
+#[derive(Copy, Clone, Debug)]
+pub struct Policy<'a> {
+    rules: &'a [(CommandType, Session)],
+}
+
+impl<'a> Policy<'a> {
+    /// A policy that places no requirements on any command.
+    ///
+    /// This policy is intended for testing only: it allows any host on the
+    /// bus to provision the device.
+    pub const OPEN: Policy<'static> = Policy::new(&[]);
+
+    /// The recommended policy, which requires an established session for
+    /// every command that changes persistent state.
+    ///
+    /// These are the certificate provisioning commands, `ExportCsr` and
+    /// `ImportSignedCert`. Cerberus's reset commands, such as `ResetConfig`,
+    /// are not implemented; they belong here as well once they are.
+    pub const RECOMMENDED: Policy<'static> = Policy::new(&[
+        (CommandType::ExportCsr, Session::ESTABLISHED),
+        (CommandType::ImportSignedCert, Session::ESTABLISHED),
+    ]);
+
+    /// Creates a new `Policy` out of a table of rules.
+    ///
+    /// Each rule requires that requests for its command arrive over a session
+    /// with (at least) the given properties. Commands without a rule have no
+    /// requirements; if a command has several rules, all of them apply.
+    pub const fn new(rules: &'a [(CommandType, Session)]) -> Self {
+        Self { rules }
+    }
+
+    /// Returns the session properties required for `command`.
+    pub fn requirements(&self, command: CommandType) -> Session {
+        self.rules
+            .iter()
+            .filter(|(c, _)| *c == command)
+            .fold(Session::empty(), |acc, (_, s)| acc | *s)
+    }
+
+    /// Checks whether a request for `command` may be processed over a session
+    /// with properties `session`.
+    ///
+    /// If not, this returns the error to reply to the host with.
+    pub fn check(
+        &self,
+        command: CommandType,
+        session: Session,
+    ) -> Result<(), protocol::Error> {
+        let missing = self.requirements(command) - session;
+        if !missing.is_empty() {
+            return Err(protocol::Error::with_arg(
+                ErrorCode::Authentication,
+                ErrorSubcode::Unauthorized,
+                missing.bits(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Policy<'_> {
+    fn default() -> Self {
+        Policy::RECOMMENDED
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const POLICY: Policy = Policy::new(&[
+        (CommandType::GetHostState, Session::ESTABLISHED),
+        (CommandType::ResetCounter, Session::ENCRYPTED),
+        (CommandType::ResetCounter, Session::PAIRED),
+    ]);
+
+    #[test]
+    fn open() {
+        assert!(Policy::OPEN
+            .check(CommandType::GetHostState, Session::empty())
+            .is_ok());
+    }
+
+    #[test]
+    fn recommended() {
+        for &c in &[CommandType::ExportCsr, CommandType::ImportSignedCert] {
+            let err =
+                Policy::RECOMMENDED.check(c, Session::empty()).unwrap_err();
+            assert_eq!(err.code, ErrorCode::Authentication);
+            assert!(Policy::RECOMMENDED.check(c, Session::ESTABLISHED).is_ok());
+        }
+        assert!(Policy::RECOMMENDED
+            .check(CommandType::Challenge, Session::empty())
+            .is_ok());
+    }
+
+    #[test]
+    fn requirements() {
+        assert_eq!(
+            POLICY.requirements(CommandType::ResetCounter),
+            Session::ENCRYPTED | Session::PAIRED
+        );
+        assert_eq!(
+            POLICY.requirements(CommandType::DeviceId),
+            Session::empty()
+        );
+    }
+
+    #[test]
+    fn check() {
+        assert!(POLICY
+            .check(CommandType::GetHostState, Session::ESTABLISHED)
+            .is_ok());
+        assert!(POLICY
+            .check(CommandType::DeviceId, Session::empty())
+            .is_ok());
+
+        let err = POLICY
+            .check(CommandType::ResetCounter, Session::ENCRYPTED)
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Authentication);
+        assert_eq!(err.subcode(), Some(ErrorSubcode::Unauthorized));
+        assert_eq!(err.data[1], Session::PAIRED.bits());
+    }
+}
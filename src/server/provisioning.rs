@@ -0,0 +1,244 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-field certificate provisioning.
+//!
+//! A host provisions a certificate chain onto a device by first requesting a
+//! CSR for the device's identity key with `ExportCsr`, having it signed by a
+//! CA, and then importing the resulting chain one certificate at a time with
+//! `ImportSignedCert`: first the root, then any intermediates, and finally
+//! the Device ID certificate itself.
+//!
+//! [`Provisioning`] stages imported certificates in a scratch buffer until
+//! the chain is complete, at which point it is verified and persisted to
+//! flash, in the format read by [`FlashChain::load()`]. A chain is only
+//! accepted if its root is the trust anchor the integration configured, so a
+//! host cannot provision a chain issued by a CA of its own choosing.
+//!
+//! The running [`TrustChain`] does not pick up a newly provisioned chain by
+//! itself; integrations are expected to reload the slot (for example, at the
+//! next boot).
+//!
+//! [`FlashChain::load()`]: crate::cert::FlashChain::load
+//! [`TrustChain`]: crate::cert::TrustChain
+
+use arrayvec::ArrayVec;
+
+use crate::cert;
+use crate::cert::CertFormat;
+use crate::cert::Crl;
+use crate::cert::SimpleChain;
+use crate::crypto::sha256;
+use crate::crypto::sig;
+use crate::hardware::flash::Flash;
+use crate::hardware::WallClock;
+use crate::protocol;
+use crate::protocol::get_cert_state::CertState;
+use crate::protocol::import_signed_cert::CertType;
+use crate::protocol::ErrorCode;
+use crate::protocol::ErrorSubcode;
+
+/// The longest chain that may be provisioned, including the root.
+pub const MAX_CHAIN_LEN: usize = 8;
+
+/// The progress of a [`Provisioning`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum State {
+    /// Nothing has been imported yet.
+    Idle,
+    /// Some, but not all, of a chain has been imported.
+    Staging,
+    /// A chain was imported and persisted.
+    Persisted,
+    /// The most recently completed chain failed verification.
+    Invalid,
+}
+
+/// Staging area for a certificate chain being provisioned.
+///
+/// See the module documentation for more information.
+pub struct Provisioning<'a> {
+    slot: u8,
+    identity_slot: u8,
+    format: CertFormat,
+    anchor: &'a [u8],
+    clock: Option<&'a dyn WallClock>,
+    crls: &'a [Crl<'a>],
+    staging: &'a mut [u8],
+    flash: &'a mut dyn Flash,
+
+    lens: ArrayVec<usize, MAX_CHAIN_LEN>,
+    state: State,
+}
+
+impl<'a> Provisioning<'a> {
+    /// Creates a new `Provisioning`.
+    ///
+    /// Chains are imported into `slot`, and their leaf must certify the same
+    /// key as the leaf of the chain already in `identity_slot`. Their root
+    /// must be `anchor`, byte for byte. Certificates are staged in `staging`,
+    /// which must be large enough to hold an entire chain, and are written to
+    /// `flash` once the chain is complete; `flash` must be erased beforehand.
+    ///
+    /// `clock` and `crls` are used to verify a completed chain, as in
+    /// [`SimpleChain::parse()`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        slot: u8,
+        identity_slot: u8,
+        format: CertFormat,
+        anchor: &'a [u8],
+        clock: Option<&'a dyn WallClock>,
+        crls: &'a [Crl<'a>],
+        staging: &'a mut [u8],
+        flash: &'a mut dyn Flash,
+    ) -> Self {
+        Self {
+            slot,
+            identity_slot,
+            format,
+            anchor,
+            clock,
+            crls,
+            staging,
+            flash,
+            lens: ArrayVec::new(),
+            state: State::Idle,
+        }
+    }
+
+    /// Returns the slot that chains are provisioned into.
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    /// Returns the slot holding the identity key being provisioned.
+    pub fn identity_slot(&self) -> u8 {
+        self.identity_slot
+    }
+
+    /// Returns the state of provisioning, as reported by
+    /// `GetCertificateState`.
+    ///
+    /// Returns `None` if nothing has been imported yet, in which case the
+    /// state depends on whether the slot has been provisioned previously.
+    pub fn state(&self) -> Option<CertState> {
+        match self.state {
+            State::Idle => None,
+            State::Staging => Some(CertState::InProgress),
+            State::Persisted => Some(CertState::Provisioned),
+            State::Invalid => Some(CertState::Invalid),
+        }
+    }
+
+    /// Imports a single certificate.
+    ///
+    /// A [`CertType::RootCa`] starts a new chain, discarding any partially
+    /// imported one; a [`CertType::DeviceId`] completes it. Once complete,
+    /// the chain is verified using `ciphers` and `sha`, its root is checked
+    /// against the trust anchor and its leaf against the leaf of the chain in
+    /// the identity slot of `trust_chain`, and it is written to flash.
+    pub fn import(
+        &mut self,
+        cert_type: CertType,
+        cert: &[u8],
+        trust_chain: &impl cert::TrustChain,
+        ciphers: &mut impl sig::Ciphers,
+        sha: &impl sha256::Builder,
+    ) -> Result<(), protocol::Error> {
+        match (cert_type, self.state) {
+            (CertType::RootCa, _) => {
+                self.lens.clear();
+                self.state = State::Idle;
+            }
+            (_, State::Staging) => {}
+            _ => {
+                return Err(protocol::Error::new(
+                    ErrorCode::InvalidRequest,
+                    ErrorSubcode::None,
+                ))
+            }
+        }
+
+        let out_of_memory = || {
+            protocol::Error::new(
+                ErrorCode::Unspecified,
+                ErrorSubcode::OutOfMemory,
+            )
+        };
+        if self.lens.is_full() {
+            return Err(out_of_memory());
+        }
+        let start = self.lens.iter().sum::<usize>();
+        self.staging
+            .get_mut(start..start + cert.len())
+            .ok_or_else(out_of_memory)?
+            .copy_from_slice(cert);
+        self.lens.push(cert.len());
+        self.state = State::Staging;
+
+        if cert_type != CertType::DeviceId {
+            return Ok(());
+        }
+
+        let result = self.commit(trust_chain, ciphers, sha);
+        self.lens.clear();
+        self.state = match result {
+            Ok(()) => State::Persisted,
+            Err(_) => State::Invalid,
+        };
+        result
+    }
+
+    /// Verifies the staged chain and writes it to flash.
+    fn commit(
+        &mut self,
+        trust_chain: &impl cert::TrustChain,
+        ciphers: &mut impl sig::Ciphers,
+        sha: &impl sha256::Builder,
+    ) -> Result<(), protocol::Error> {
+        let mut raw_chain = ArrayVec::<&[u8], MAX_CHAIN_LEN>::new();
+        let mut start = 0;
+        for &len in &self.lens {
+            raw_chain.push(&self.staging[start..start + len]);
+            start += len;
+        }
+
+        let untrusted = || {
+            protocol::Error::new(
+                ErrorCode::Authentication,
+                ErrorSubcode::BadCert,
+            )
+        };
+        if raw_chain.first() != Some(&self.anchor) {
+            return Err(untrusted());
+        }
+
+        let chain = SimpleChain::<MAX_CHAIN_LEN>::parse(
+            &raw_chain,
+            self.format,
+            ciphers,
+            sha,
+            None,
+            self.clock,
+            self.crls,
+        )?;
+        let identity = leaf_key(trust_chain, self.identity_slot);
+        if identity.is_none() || identity != leaf_key(&chain, 0) {
+            return Err(untrusted());
+        }
+
+        cert::write_chain(&mut *self.flash, &raw_chain)?;
+        Ok(())
+    }
+}
+
+/// Returns the key certified by the leaf of the chain in `slot`.
+fn leaf_key(
+    chain: &dyn cert::TrustChain,
+    slot: u8,
+) -> Option<&sig::PublicKeyParams> {
+    let len = chain.chain_len(slot)?.get();
+    Some(chain.cert(slot, len - 1)?.subject_key())
+}
@@ -160,6 +160,39 @@ for key in $(find "$DATA_DIR/keys" -name '*.ecdsa-*.pk8' -type f | sort); do
   echo >> "$KEYS_RS"
 done
 
+# Ed25519 keypairs.
+# To generate a new *private* key, use
+#   openssl genpkey -algorithm ed25519 -outform der -out <name>.ed25519.pk8
+rm -f "$DATA_DIR"/keys/*.ed25519.pub.pk8
+for key in $(find "$DATA_DIR/keys" -name '*.ed25519.pk8' -type f | sort); do
+  echo "Processing Ed25519 key $key..." >&2
+  base="${key%.ed25519.pk8}"
+  pub="$base.ed25519.pub.pk8"
+  openssl pkey \
+    -pubout \
+    -inform der -outform der \
+    -in "$key" -out "$pub" \
+    2> /dev/null
+
+  # The raw public key is the last 32 bytes of the SPKI.
+  raw="$KEYS_GEN/$(basename "$base").ed25519.pub.raw"
+  tail -c 32 "$pub" > "$raw"
+
+  push_const \
+    "$KEYS_RS" "$key" \
+    "$(basename "$base")_ED25519_KEYPAIR" '&[u8]' \
+    "Test-only Ed25519 keypair \`$(basename "$key")\`."
+  push_const \
+    "$KEYS_RS" "$pub" \
+    "$(basename "$base")_ED25519_PUBLIC" '&[u8]' \
+    "Test-only Ed25519 public key generated from \`$(basename "$key")\`."
+  push_const \
+    "$KEYS_RS" "$raw" \
+    "$(basename "$base")_ED25519_RAW" '&[u8; 32]' \
+    "Raw public key of \`$(basename "$key")\`."
+  echo >> "$KEYS_RS"
+done
+
 ## Generate DER snippets using der-ascii.
 DER_RS="$DATA_DIR/der.rs"
 clear_rust_file "$DER_RS"
@@ -191,9 +224,9 @@ X509_RS="$DATA_DIR/x509.rs"
 clear_rust_file "$X509_RS"
 
 X509_GEN="$DATA_DIR/x509/generated"
-# This assumes signing is deterministic for idempotence.
-# Once we start doing things with ECDSA, we'll have to do something a bit
-# smarter.
+# This assumes signing is deterministic for idempotence, which is not true
+# of ECDSA; ECDSA-signed certs will change (but remain valid) every time this
+# script is run.
 rm -r "$X509_GEN"
 mkdir -p "$X509_GEN"
 
@@ -214,13 +247,16 @@ for tbs in $(find "$DATA_DIR/x509" -name '*.tbs' -type f | sort); do
     perl -pi -e 's!\Q# include:'"$include"'\E!`'"$hex_encoded"'`!g' "$SCRATCH"
   done
 
-  # Then, sign the to-be-signed portion of the cert.
-  # This currently assumes RSA; it may be worth doing a `case` on
-  # `$alg`.
+  # Then, sign the to-be-signed portion of the cert. The key type is implied
+  # by the key file itself, so we only need to pick the right digest.
+  case "$alg" in
+    *1.2.840.10045.4.3.3*) digest=-sha384 ;; # ecdsa-with-SHA384
+    *) digest=-sha256 ;;
+  esac
   ascii2der -i "$SCRATCH" | openssl dgst \
     -keyform DER \
     -sign "$key" \
-    -sha256 \
+    "$digest" \
     -out "$bin"
 
   # Now, input the contents of the signature into the real cert.
@@ -0,0 +1,15 @@
+// Copyright lowRISC contributors.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! CWT certificates, as emitted by OpenDICE.
+
+/// An OpenDICE CDI certificate for an Ed25519 subject key, signed by
+/// `key1.ed25519.pk8`.
+///
+/// This uses the exact layout of open-dice's `cbor_cert_op.c`: a tagless
+/// `COSE_Sign1` whose payload carries the code hash, an inline configuration
+/// descriptor, the authority hash, the mode (normal), a bstr-wrapped
+/// `COSE_Key` with `key_ops`, and a key usage of `keyCertSign`.
+#[rustfmt::skip]
+pub const OPEN_DICE_ED25519: &[u8] = include_bytes!("cwt/open_dice_ed25519.cbor");
@@ -10,6 +10,18 @@
 #[rustfmt::skip]
 pub const BAD_BOOL: &[u8] = include_bytes!("der/generated/bad_bool.der.bin");
 
+/// DER snippet generated from `bad_time_fraction.der`.
+#[rustfmt::skip]
+pub const BAD_TIME_FRACTION: &[u8] = include_bytes!("der/generated/bad_time_fraction.der.bin");
+
+/// DER snippet generated from `bad_time_month.der`.
+#[rustfmt::skip]
+pub const BAD_TIME_MONTH: &[u8] = include_bytes!("der/generated/bad_time_month.der.bin");
+
+/// DER snippet generated from `bad_time_offset.der`.
+#[rustfmt::skip]
+pub const BAD_TIME_OFFSET: &[u8] = include_bytes!("der/generated/bad_time_offset.der.bin");
+
 /// DER snippet generated from `bits_overflow.der`.
 #[rustfmt::skip]
 pub const BITS_OVERFLOW: &[u8] = include_bytes!("der/generated/bits_overflow.der.bin");
@@ -42,6 +54,10 @@ pub const FALSE: &[u8] = include_bytes!("der/generated/false.der.bin");
 #[rustfmt::skip]
 pub const FORTY_TWO: &[u8] = include_bytes!("der/generated/forty_two.der.bin");
 
+/// DER snippet generated from `generalized_time.der`.
+#[rustfmt::skip]
+pub const GENERALIZED_TIME: &[u8] = include_bytes!("der/generated/generalized_time.der.bin");
+
 /// DER snippet generated from `huge_int.der`.
 #[rustfmt::skip]
 pub const HUGE_INT: &[u8] = include_bytes!("der/generated/huge_int.der.bin");
@@ -70,6 +86,10 @@ pub const NINE_THOUSAND: &[u8] = include_bytes!("der/generated/nine_thousand.der
 #[rustfmt::skip]
 pub const NONEMPTY_NULL: &[u8] = include_bytes!("der/generated/nonempty_null.der.bin");
 
+/// DER snippet generated from `no_well_defined_time.der`.
+#[rustfmt::skip]
+pub const NO_WELL_DEFINED_TIME: &[u8] = include_bytes!("der/generated/no_well_defined_time.der.bin");
+
 /// DER snippet generated from `null.der`.
 #[rustfmt::skip]
 pub const NULL: &[u8] = include_bytes!("der/generated/null.der.bin");
@@ -86,6 +106,10 @@ pub const SHORT_FORM_ANY: &[u8] = include_bytes!("der/generated/short_form_any.d
 #[rustfmt::skip]
 pub const TRUE: &[u8] = include_bytes!("der/generated/true.der.bin");
 
+/// DER snippet generated from `utc_time.der`.
+#[rustfmt::skip]
+pub const UTC_TIME: &[u8] = include_bytes!("der/generated/utc_time.der.bin");
+
 /// DER snippet generated from `zero.der`.
 #[rustfmt::skip]
 pub const ZERO: &[u8] = include_bytes!("der/generated/zero.der.bin");
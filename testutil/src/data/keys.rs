@@ -57,3 +57,26 @@ pub const KEY1_ECDSA_P256_X: &[u8; 32] = include_bytes!("keys/generated/key1.ecd
 /// Y coordinate of `key1.ecdsa-p256.pk8`.
 #[rustfmt::skip]
 pub const KEY1_ECDSA_P256_Y: &[u8; 32] = include_bytes!("keys/generated/key1.ecdsa-p256.pub.y");
+
+/// Test-only ECDSA keypair `key1.ecdsa-p384.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ECDSA_P384_KEYPAIR: &[u8] = include_bytes!("keys/key1.ecdsa-p384.pk8");
+/// Test-only ECDAS public key generated from `key1.ecdsa-p384.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ECDSA_P384_PUBLIC: &[u8] = include_bytes!("keys/key1.ecdsa-p384.pub.pk8");
+/// X coordinate of `key1.ecdsa-p384.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ECDSA_P384_X: &[u8; 48] = include_bytes!("keys/generated/key1.ecdsa-p384.pub.x");
+/// Y coordinate of `key1.ecdsa-p384.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ECDSA_P384_Y: &[u8; 48] = include_bytes!("keys/generated/key1.ecdsa-p384.pub.y");
+
+/// Test-only Ed25519 keypair `key1.ed25519.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ED25519_KEYPAIR: &[u8] = include_bytes!("keys/key1.ed25519.pk8");
+/// Test-only Ed25519 public key generated from `key1.ed25519.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ED25519_PUBLIC: &[u8] = include_bytes!("keys/key1.ed25519.pub.pk8");
+/// Raw public key of `key1.ed25519.pk8`.
+#[rustfmt::skip]
+pub const KEY1_ED25519_RAW: &[u8; 32] = include_bytes!("keys/generated/key1.ed25519.pub.raw");
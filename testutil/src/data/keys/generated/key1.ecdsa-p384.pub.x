@@ -0,0 +1 @@
+~*NpH)JY՞r_pR<j$!
\ No newline at end of file
@@ -0,0 +1 @@
+T;h0nI.*KV˨<&p
\ No newline at end of file
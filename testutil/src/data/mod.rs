@@ -4,6 +4,7 @@
 
 //! Test data of all kinds, ranging from manifest, to keys, to certificates.
 
+pub mod cwt;
 pub mod der;
 pub mod keys;
 pub mod misc_crypto;
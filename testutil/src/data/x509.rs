@@ -23,6 +23,10 @@ pub const BAD_CERT_SIGN_WITHOUT_BC: &[u8] = include_bytes!("x509/generated/bad_c
 #[rustfmt::skip]
 pub const BAD_DIFFERENT_SIG_ALG: &[u8] = include_bytes!("x509/generated/bad_different_sig_alg.tbs.bin");
 
+/// X509 certificate generated from `bad_ecdsa_alg_with_rsa_key.tbs`.
+#[rustfmt::skip]
+pub const BAD_ECDSA_ALG_WITH_RSA_KEY: &[u8] = include_bytes!("x509/generated/bad_ecdsa_alg_with_rsa_key.tbs.bin");
+
 /// X509 certificate generated from `bad_missing_extns.tbs`.
 #[rustfmt::skip]
 pub const BAD_MISSING_EXTNS: &[u8] = include_bytes!("x509/generated/bad_missing_extns.tbs.bin");
@@ -75,6 +79,10 @@ pub const BAD_UNKNOWN_CRITICAL: &[u8] = include_bytes!("x509/generated/bad_unkno
 #[rustfmt::skip]
 pub const BAD_WRONG_VERSION: &[u8] = include_bytes!("x509/generated/bad_wrong_version.tbs.bin");
 
+/// X509 certificate generated from `bounded_validity.tbs`.
+#[rustfmt::skip]
+pub const BOUNDED_VALIDITY: &[u8] = include_bytes!("x509/generated/bounded_validity.tbs.bin");
+
 /// X509 certificate generated from `chain1.tbs`.
 #[rustfmt::skip]
 pub const CHAIN1: &[u8] = include_bytes!("x509/generated/chain1.tbs.bin");
@@ -87,6 +95,10 @@ pub const CHAIN2: &[u8] = include_bytes!("x509/generated/chain2.tbs.bin");
 #[rustfmt::skip]
 pub const CHAIN3: &[u8] = include_bytes!("x509/generated/chain3.tbs.bin");
 
+/// X509 certificate generated from `dice_tcb_info.tbs`.
+#[rustfmt::skip]
+pub const DICE_TCB_INFO: &[u8] = include_bytes!("x509/generated/dice_tcb_info.tbs.bin");
+
 /// X509 certificate generated from `ok_no_ca_without_bc.tbs`.
 #[rustfmt::skip]
 pub const OK_NO_CA_WITHOUT_BC: &[u8] = include_bytes!("x509/generated/ok_no_ca_without_bc.tbs.bin");
@@ -99,6 +111,14 @@ pub const OK_UNKNOWN_DEFAULT_CRITICALITY: &[u8] = include_bytes!("x509/generated
 #[rustfmt::skip]
 pub const OK_UNKNOWN_NONCRITICAL: &[u8] = include_bytes!("x509/generated/ok_unknown_noncritical.tbs.bin");
 
+/// X509 certificate generated from `self_signed_ecdsa_p256.tbs`.
+#[rustfmt::skip]
+pub const SELF_SIGNED_ECDSA_P256: &[u8] = include_bytes!("x509/generated/self_signed_ecdsa_p256.tbs.bin");
+
+/// X509 certificate generated from `self_signed_ecdsa_p384.tbs`.
+#[rustfmt::skip]
+pub const SELF_SIGNED_ECDSA_P384: &[u8] = include_bytes!("x509/generated/self_signed_ecdsa_p384.tbs.bin");
+
 /// X509 certificate generated from `self_signed.tbs`.
 #[rustfmt::skip]
 pub const SELF_SIGNED: &[u8] = include_bytes!("x509/generated/self_signed.tbs.bin");